@@ -0,0 +1,27 @@
+use std::path::Path;
+use std::process::Command;
+
+/// Records the git commit and target triple the shell is built from, for
+/// `--version` and the `version` builtin.
+fn main() {
+    let commit = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|commit| commit.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=MYSHELL_GIT_COMMIT={commit}");
+    println!(
+        "cargo:rustc-env=MYSHELL_TARGET={}",
+        std::env::var("TARGET").unwrap_or_default()
+    );
+
+    println!("cargo:rerun-if-changed=build.rs");
+    for git_file in [".git/HEAD", ".git/refs/heads"] {
+        if Path::new(git_file).exists() {
+            println!("cargo:rerun-if-changed={git_file}");
+        }
+    }
+}
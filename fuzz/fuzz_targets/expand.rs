@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: (&str, Vec<(String, String)>)| {
+    let (word, env) = input;
+    let _ = codecrafters_shell::expand(word, &env);
+});
@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// Unbalanced quotes, stray escapes and deep nesting must come back as
+// syntax errors rather than panics or stack overflows.
+fuzz_target!(|input: &str| {
+    let _ = codecrafters_shell::parser::parse(input);
+});
@@ -0,0 +1,122 @@
+use crate::Shell;
+/// ============================================
+/// ALIASES
+/// ============================================
+use crate::error::ShellError;
+use crate::parser::{AssignValue, Command, SimpleCommand, StreamType, here_doc_delimiter};
+
+/// Whether `name` can be defined as an alias: a word the lexer reads back
+/// unchanged, without quotes, expansions, `/` or `=`.
+pub(crate) fn is_valid_alias_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name
+            .chars()
+            .any(|c| c.is_whitespace() || "/=$`\\'\";&|<>()".contains(c))
+}
+
+impl Shell {
+    /// Runs `simple` with its command name replaced by the alias it names,
+    /// or gives `None` when it does not name one. Aliases being expanded
+    /// are not looked up again, so an alias may use the command it hides.
+    pub(crate) fn execute_alias(&mut self, simple: &SimpleCommand) -> Option<i32> {
+        let (source, names) = self.alias_source(simple)?;
+        let program = match self.parse(&source) {
+            Ok(program) => program,
+            Err(error) => {
+                let error = ShellError::Syntax {
+                    origin: None,
+                    line: None,
+                    error,
+                };
+                return Some(self.report(&error));
+            }
+        };
+
+        let depth = self.active_aliases.len();
+        self.active_aliases.extend(names);
+        let line_base = self.line_base;
+        self.line_base += simple.line.saturating_sub(1);
+        let status = match program.as_slice() {
+            [Command::Simple(simple)] => self.execute_simple(simple),
+            _ => match self.stage.take() {
+                Some(stage) => self.run_stage(stage, |shell| shell.execute(&program)),
+                None => self.execute(&program),
+            },
+        };
+        self.line_base = line_base;
+        self.active_aliases.truncate(depth);
+        Some(status)
+    }
+
+    /// The source `simple` stands for with its aliases expanded, and the
+    /// aliases used. When an alias ends in a blank, the word after it is
+    /// looked up too.
+    fn alias_source(&self, simple: &SimpleCommand) -> Option<(String, Vec<String>)> {
+        let mut source = String::new();
+        for assignment in &simple.assignments {
+            source.push_str(&assignment.name);
+            if let Some(index) = &assignment.index {
+                source.push_str(&format!("[{}]", index));
+            }
+            match &assignment.value {
+                AssignValue::Scalar(value) => source.push_str(&format!("={} ", value)),
+                AssignValue::Array(elements) => {
+                    source.push_str(&format!("=({}) ", elements.join(" ")))
+                }
+            }
+        }
+
+        let mut names = Vec::new();
+        let mut words = simple.words.iter();
+        for word in words.by_ref() {
+            let value = match self.aliases.get(word) {
+                Some(value) if !self.active_aliases.contains(word) && !names.contains(word) => {
+                    value
+                }
+                _ => {
+                    source.push_str(word);
+                    break;
+                }
+            };
+            names.push(word.clone());
+            source.push_str(value);
+            if !value.ends_with([' ', '\t']) {
+                break;
+            }
+        }
+        if names.is_empty() {
+            return None;
+        }
+
+        for word in words {
+            source.push(' ');
+            source.push_str(word);
+        }
+        let mut bodies = String::new();
+        for redirect in &simple.redirects {
+            let operator = match (&redirect.stream, redirect.append) {
+                (StreamType::Stdin, _) if redirect.duplicate => "<&",
+                (StreamType::Stdout, _) if redirect.duplicate => ">&",
+                (StreamType::Stderr, _) if redirect.duplicate => "2>&",
+                (StreamType::Stdin, _) if redirect.here_doc.is_some() => "<<",
+                (StreamType::Stdin, _) => "<",
+                (StreamType::Stdout, false) => ">",
+                (StreamType::Stdout, true) => ">>",
+                (StreamType::Stderr, false) => "2>",
+                (StreamType::Stderr, true) => "2>>",
+            };
+            source.push_str(&format!(" {} {}", operator, redirect.file));
+            // Here-document bodies follow the command line, each ending
+            // with its delimiter.
+            if let Some(here_doc) = &redirect.here_doc {
+                let delimiter = here_doc_delimiter(&redirect.file);
+                bodies.push_str(&format!("{}{}\n", here_doc.body, delimiter));
+            }
+        }
+        if !bodies.is_empty() {
+            source.push('\n');
+            source.push_str(&bodies);
+        }
+        Some((source, names))
+    }
+}
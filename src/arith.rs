@@ -0,0 +1,706 @@
+/// ============================================
+/// ARITHMETIC EVALUATION
+/// ============================================
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq)]
+#[error("{expr}: {message}")]
+pub struct ArithError {
+    pub expr: String,
+    pub message: String,
+}
+
+/// Variable access for the evaluator. `index` is set for `name[index]`.
+pub trait Variables {
+    fn get(&mut self, name: &str, index: Option<i64>) -> Option<String>;
+    fn set(&mut self, name: &str, index: Option<i64>, value: i64);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinOp {
+    Mul,
+    Div,
+    Rem,
+    Pow,
+    Add,
+    Sub,
+    Shl,
+    Shr,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    BitAnd,
+    BitXor,
+    BitOr,
+    And,
+    Or,
+    Comma,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum UnaryOp {
+    Neg,
+    Plus,
+    Not,
+    BitNot,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Num(i64),
+    /// Only produced for `evaluate_float`.
+    Float(f64),
+    Var(String, Option<Box<Expr>>),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+    Ternary(Box<Expr>, Box<Expr>, Box<Expr>),
+    /// `name = value` or a compound assignment such as `name += value`.
+    Assign(String, Option<Box<Expr>>, Option<BinOp>, Box<Expr>),
+    /// `++name`, `--name` (prefix) and `name++`, `name--` (postfix).
+    Step(String, Option<Box<Expr>>, i64, bool),
+}
+
+// ============================================
+// TOKENIZER
+// ============================================
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(i64),
+    Float(f64),
+    Name(String),
+    Op(&'static str),
+}
+
+const OPERATORS: &[&str] = &[
+    "<<=", ">>=", "**", "++", "--", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "+=", "-=",
+    "*=", "/=", "%=", "&=", "^=", "|=", "+", "-", "*", "/", "%", "<", ">", "&", "^", "|", "!", "~",
+    "?", ":", "=", ",", "(", ")", "[", "]",
+];
+
+fn parse_number(text: &str) -> Option<i64> {
+    if let Some((base, digits)) = text.split_once('#') {
+        let base: u32 = base.parse().ok()?;
+        if !(2..=64).contains(&base) {
+            return None;
+        }
+        let mut value: i64 = 0;
+        for c in digits.chars() {
+            let digit = match c {
+                '0'..='9' => c as u32 - '0' as u32,
+                'a'..='z' => c as u32 - 'a' as u32 + 10,
+                'A'..='Z' if base <= 36 => c as u32 - 'A' as u32 + 10,
+                'A'..='Z' => c as u32 - 'A' as u32 + 36,
+                '@' => 62,
+                '_' => 63,
+                _ => return None,
+            };
+            if digit >= base {
+                return None;
+            }
+            value = value.wrapping_mul(base as i64).wrapping_add(digit as i64);
+        }
+        return Some(value);
+    }
+
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        return i64::from_str_radix(hex, 16).ok();
+    }
+    if text.len() > 1 && text.starts_with('0') {
+        return i64::from_str_radix(&text[1..], 8).ok();
+    }
+    text.parse().ok()
+}
+
+/// The length of the decimal number such as `1.5`, `.5` or `2e-3` at the
+/// start of `text`, if it is one rather than an integer.
+fn float_length(text: &str) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let digits = |from: usize| {
+        from + bytes[from..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count()
+    };
+
+    let mut len = digits(0);
+    let mut is_float = false;
+    if bytes.get(len) == Some(&b'.') {
+        len = digits(len + 1);
+        is_float = true;
+    }
+    if matches!(bytes.get(len), Some(b'e' | b'E')) {
+        let sign = usize::from(matches!(bytes.get(len + 1), Some(b'+' | b'-')));
+        let end = digits(len + 1 + sign);
+        if end > len + 1 + sign {
+            len = end;
+            is_float = true;
+        }
+    }
+    (is_float && len > 1).then_some(len)
+}
+
+/// Splits `input` into tokens. With `floats`, decimal numbers such as
+/// `1.5` are allowed as well as integers.
+fn tokenize(input: &str, floats: bool) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = input;
+
+    loop {
+        rest = rest.trim_start();
+        let Some(c) = rest.chars().next() else {
+            return Ok(tokens);
+        };
+
+        if floats
+            && (c.is_ascii_digit() || c == '.')
+            && let Some(len) = float_length(rest)
+        {
+            let value = rest[..len]
+                .parse()
+                .map_err(|_| format!("invalid number (error token is \"{}\")", &rest[..len]))?;
+            tokens.push(Token::Float(value));
+            rest = &rest[len..];
+        } else if c.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '#' || c == '@' || c == '_'))
+                .unwrap_or(rest.len());
+            let value = parse_number(&rest[..len]).ok_or_else(|| {
+                format!(
+                    "value too great for base (error token is \"{}\")",
+                    &rest[..len]
+                )
+            })?;
+            tokens.push(Token::Num(value));
+            rest = &rest[len..];
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            tokens.push(Token::Name(rest[..len].to_string()));
+            rest = &rest[len..];
+        } else if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+            tokens.push(Token::Op(op));
+            rest = &rest[op.len()..];
+        } else {
+            return Err(format!(
+                "syntax error: invalid arithmetic operator (error token is \"{}\")",
+                rest
+            ));
+        }
+    }
+}
+
+// ============================================
+// PARSER
+// ============================================
+
+/// How deeply parentheses and unary operators may nest, so that malformed
+/// input cannot exhaust the stack.
+const MAX_NESTING: usize = 256;
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    depth: usize,
+}
+
+fn binary_op(op: &str) -> Option<(BinOp, u8)> {
+    Some(match op {
+        "," => (BinOp::Comma, 1),
+        "||" => (BinOp::Or, 4),
+        "&&" => (BinOp::And, 5),
+        "|" => (BinOp::BitOr, 6),
+        "^" => (BinOp::BitXor, 7),
+        "&" => (BinOp::BitAnd, 8),
+        "==" => (BinOp::Eq, 9),
+        "!=" => (BinOp::Ne, 9),
+        "<" => (BinOp::Lt, 10),
+        "<=" => (BinOp::Le, 10),
+        ">" => (BinOp::Gt, 10),
+        ">=" => (BinOp::Ge, 10),
+        "<<" => (BinOp::Shl, 11),
+        ">>" => (BinOp::Shr, 11),
+        "+" => (BinOp::Add, 12),
+        "-" => (BinOp::Sub, 12),
+        "*" => (BinOp::Mul, 13),
+        "/" => (BinOp::Div, 13),
+        "%" => (BinOp::Rem, 13),
+        "**" => (BinOp::Pow, 14),
+        _ => return None,
+    })
+}
+
+fn assign_op(op: &str) -> Option<Option<BinOp>> {
+    Some(match op {
+        "=" => None,
+        "+=" => Some(BinOp::Add),
+        "-=" => Some(BinOp::Sub),
+        "*=" => Some(BinOp::Mul),
+        "/=" => Some(BinOp::Div),
+        "%=" => Some(BinOp::Rem),
+        "<<=" => Some(BinOp::Shl),
+        ">>=" => Some(BinOp::Shr),
+        "&=" => Some(BinOp::BitAnd),
+        "^=" => Some(BinOp::BitXor),
+        "|=" => Some(BinOp::BitOr),
+        _ => return None,
+    })
+}
+
+const ASSIGN_PRECEDENCE: u8 = 2;
+const TERNARY_PRECEDENCE: u8 = 3;
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_op(&self) -> Option<&'static str> {
+        match self.peek() {
+            Some(Token::Op(op)) => Some(op),
+            _ => None,
+        }
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, op: &str) -> Result<(), String> {
+        match self.next() {
+            Some(Token::Op(found)) if found == op => Ok(()),
+            _ => Err(format!("syntax error: `{}' expected", op)),
+        }
+    }
+
+    fn expression(&mut self, min_precedence: u8) -> Result<Expr, String> {
+        let mut left = self.unary()?;
+
+        while let Some(op) = self.peek_op() {
+            if let Some(compound) = assign_op(op) {
+                if min_precedence > ASSIGN_PRECEDENCE {
+                    break;
+                }
+                let Expr::Var(name, index) = left else {
+                    return Err("attempted assignment to non-variable".to_string());
+                };
+                self.next();
+                let value = self.expression(ASSIGN_PRECEDENCE)?;
+                left = Expr::Assign(name, index, compound, Box::new(value));
+                continue;
+            }
+
+            if op == "?" {
+                if min_precedence > TERNARY_PRECEDENCE {
+                    break;
+                }
+                self.next();
+                let then = self.expression(1)?;
+                self.expect(":")?;
+                let otherwise = self.expression(TERNARY_PRECEDENCE)?;
+                left = Expr::Ternary(Box::new(left), Box::new(then), Box::new(otherwise));
+                continue;
+            }
+
+            let Some((bin, precedence)) = binary_op(op) else {
+                break;
+            };
+            if precedence < min_precedence {
+                break;
+            }
+            self.next();
+            // `**` is right-associative; everything else is left-associative.
+            let next_min = if bin == BinOp::Pow {
+                precedence
+            } else {
+                precedence + 1
+            };
+            let right = self.expression(next_min)?;
+            left = Expr::Binary(bin, Box::new(left), Box::new(right));
+        }
+
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.depth >= MAX_NESTING {
+            return Err("expression recursion level exceeded".to_string());
+        }
+        self.depth += 1;
+        let result = self.unary_nested();
+        self.depth -= 1;
+        result
+    }
+
+    fn unary_nested(&mut self) -> Result<Expr, String> {
+        let op = self.peek_op();
+        let unary = match op {
+            Some("-") => Some(UnaryOp::Neg),
+            Some("+") => Some(UnaryOp::Plus),
+            Some("!") => Some(UnaryOp::Not),
+            Some("~") => Some(UnaryOp::BitNot),
+            _ => None,
+        };
+
+        if let Some(unary) = unary {
+            self.next();
+            return Ok(Expr::Unary(unary, Box::new(self.unary()?)));
+        }
+
+        if let Some(step @ ("++" | "--")) = op {
+            self.next();
+            let Expr::Var(name, index) = self.postfix()? else {
+                return Err("syntax error: operand expected".to_string());
+            };
+            let delta = if step == "++" { 1 } else { -1 };
+            return Ok(Expr::Step(name, index, delta, true));
+        }
+
+        self.postfix()
+    }
+
+    fn postfix(&mut self) -> Result<Expr, String> {
+        let primary = self.primary()?;
+
+        if let Expr::Var(name, index) = &primary
+            && let Some(step @ ("++" | "--")) = self.peek_op()
+        {
+            self.next();
+            let delta = if step == "++" { 1 } else { -1 };
+            return Ok(Expr::Step(name.clone(), index.clone(), delta, false));
+        }
+
+        Ok(primary)
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Num(n)) => Ok(Expr::Num(n)),
+            Some(Token::Float(n)) => Ok(Expr::Float(n)),
+            Some(Token::Name(name)) => {
+                if self.peek_op() == Some("[") {
+                    self.next();
+                    let index = self.expression(1)?;
+                    self.expect("]")?;
+                    return Ok(Expr::Var(name, Some(Box::new(index))));
+                }
+                Ok(Expr::Var(name, None))
+            }
+            Some(Token::Op("(")) => {
+                let inner = self.expression(1)?;
+                self.expect(")")?;
+                Ok(inner)
+            }
+            Some(Token::Op(op)) => Err(format!(
+                "syntax error: operand expected (error token is \"{}\")",
+                op
+            )),
+            None => Err("syntax error: operand expected".to_string()),
+        }
+    }
+}
+
+// ============================================
+// EVALUATOR
+// ============================================
+
+/// Nested variable references are evaluated recursively, up to this depth.
+const MAX_DEPTH: usize = 64;
+
+struct Evaluator<'a> {
+    vars: &'a mut dyn Variables,
+    depth: usize,
+}
+
+impl Evaluator<'_> {
+    fn index(&mut self, index: &Option<Box<Expr>>) -> Result<Option<i64>, String> {
+        index.as_ref().map(|index| self.eval(index)).transpose()
+    }
+
+    fn variable(&mut self, name: &str, index: Option<i64>) -> Result<i64, String> {
+        let value = self.vars.get(name, index).unwrap_or_default();
+        let value = value.trim();
+        if value.is_empty() {
+            return Ok(0);
+        }
+        if let Some(n) = parse_number(value) {
+            return Ok(n);
+        }
+        if self.depth >= MAX_DEPTH {
+            return Err("expression recursion level exceeded".to_string());
+        }
+
+        self.depth += 1;
+        let result = parse(value).and_then(|expr| match expr {
+            Some(expr) => self.eval(&expr),
+            None => Ok(0),
+        });
+        self.depth -= 1;
+        result
+    }
+
+    fn binary(op: BinOp, left: i64, right: i64) -> Result<i64, String> {
+        Ok(match op {
+            BinOp::Add => left.wrapping_add(right),
+            BinOp::Sub => left.wrapping_sub(right),
+            BinOp::Mul => left.wrapping_mul(right),
+            BinOp::Div | BinOp::Rem if right == 0 => return Err("division by 0".to_string()),
+            BinOp::Div => left.wrapping_div(right),
+            BinOp::Rem => left.wrapping_rem(right),
+            BinOp::Pow if right < 0 => return Err("exponent less than 0".to_string()),
+            BinOp::Pow => left.wrapping_pow(right.min(u32::MAX as i64) as u32),
+            BinOp::Shl => left.wrapping_shl(right as u32),
+            BinOp::Shr => left.wrapping_shr(right as u32),
+            BinOp::Lt => (left < right) as i64,
+            BinOp::Le => (left <= right) as i64,
+            BinOp::Gt => (left > right) as i64,
+            BinOp::Ge => (left >= right) as i64,
+            BinOp::Eq => (left == right) as i64,
+            BinOp::Ne => (left != right) as i64,
+            BinOp::BitAnd => left & right,
+            BinOp::BitXor => left ^ right,
+            BinOp::BitOr => left | right,
+            BinOp::And => (left != 0 && right != 0) as i64,
+            BinOp::Or => (left != 0 || right != 0) as i64,
+            BinOp::Comma => right,
+        })
+    }
+
+    fn eval(&mut self, expr: &Expr) -> Result<i64, String> {
+        match expr {
+            Expr::Num(n) => Ok(*n),
+            Expr::Float(n) => Ok(*n as i64),
+            Expr::Var(name, index) => {
+                let index = self.index(index)?;
+                self.variable(name, index)
+            }
+            Expr::Unary(op, operand) => {
+                let value = self.eval(operand)?;
+                Ok(match op {
+                    UnaryOp::Neg => value.wrapping_neg(),
+                    UnaryOp::Plus => value,
+                    UnaryOp::Not => (value == 0) as i64,
+                    UnaryOp::BitNot => !value,
+                })
+            }
+            Expr::Binary(BinOp::And, left, right) => {
+                Ok((self.eval(left)? != 0 && self.eval(right)? != 0) as i64)
+            }
+            Expr::Binary(BinOp::Or, left, right) => {
+                Ok((self.eval(left)? != 0 || self.eval(right)? != 0) as i64)
+            }
+            Expr::Binary(op, left, right) => {
+                let left = self.eval(left)?;
+                let right = self.eval(right)?;
+                Self::binary(*op, left, right)
+            }
+            Expr::Ternary(condition, then, otherwise) => {
+                if self.eval(condition)? != 0 {
+                    self.eval(then)
+                } else {
+                    self.eval(otherwise)
+                }
+            }
+            Expr::Assign(name, index, compound, value) => {
+                let index = self.index(index)?;
+                let mut value = self.eval(value)?;
+                if let Some(op) = compound {
+                    let current = self.variable(name, index)?;
+                    value = Self::binary(*op, current, value)?;
+                }
+                self.vars.set(name, index, value);
+                Ok(value)
+            }
+            Expr::Step(name, index, delta, prefix) => {
+                let index = self.index(index)?;
+                let current = self.variable(name, index)?;
+                let updated = current.wrapping_add(*delta);
+                self.vars.set(name, index, updated);
+                Ok(if *prefix { updated } else { current })
+            }
+        }
+    }
+}
+
+/// Parses `input`, which is `None` if it is empty or all blanks.
+fn parse_expression(input: &str, floats: bool) -> Result<Option<Expr>, String> {
+    let tokens = tokenize(input, floats)?;
+    if tokens.is_empty() {
+        return Ok(None);
+    }
+
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        depth: 0,
+    };
+    let expr = parser.expression(1)?;
+    if let Some(token) = parser.peek() {
+        let found = match token {
+            Token::Num(n) => n.to_string(),
+            Token::Float(n) => n.to_string(),
+            Token::Name(name) => name.clone(),
+            Token::Op(op) => op.to_string(),
+        };
+        return Err(format!(
+            "syntax error in expression (error token is \"{}\")",
+            found
+        ));
+    }
+    Ok(Some(expr))
+}
+
+fn parse(input: &str) -> Result<Option<Expr>, String> {
+    parse_expression(input, false)
+}
+
+/// Evaluates an arithmetic expression such as the body of `$(( ... ))`.
+pub fn evaluate(input: &str, vars: &mut dyn Variables) -> Result<i64, ArithError> {
+    let mut evaluator = Evaluator { vars, depth: 0 };
+    parse(input)
+        .and_then(|expr| match expr {
+            Some(expr) => evaluator.eval(&expr),
+            None => Ok(0),
+        })
+        .map_err(|message| ArithError {
+            expr: input.trim().to_string(),
+            message,
+        })
+}
+
+// ============================================
+// FLOATING-POINT EVALUATOR
+// ============================================
+
+/// Evaluates expressions for `calc`, where numbers may have fractions.
+/// Variables are read but not assigned to.
+struct FloatEvaluator<'a> {
+    vars: &'a mut dyn Variables,
+    depth: usize,
+}
+
+fn truth(condition: bool) -> f64 {
+    if condition { 1.0 } else { 0.0 }
+}
+
+/// `value` as an integer, for the operators only integers have.
+fn integral(value: f64) -> Result<i64, String> {
+    if value.is_finite() && value.fract() == 0.0 {
+        Ok(value as i64)
+    } else {
+        Err(format!("{}: integer expected", value))
+    }
+}
+
+impl FloatEvaluator<'_> {
+    fn variable(&mut self, name: &str) -> Result<f64, String> {
+        let value = self.vars.get(name, None).unwrap_or_default();
+        let value = value.trim();
+        if value.is_empty() {
+            return Ok(0.0);
+        }
+        if let Some(n) = parse_number(value) {
+            return Ok(n as f64);
+        }
+        if let Ok(n) = value.parse::<f64>() {
+            return Ok(n);
+        }
+        if self.depth >= MAX_DEPTH {
+            return Err("expression recursion level exceeded".to_string());
+        }
+
+        self.depth += 1;
+        let result = parse_expression(value, true).and_then(|expr| match expr {
+            Some(expr) => self.eval(&expr),
+            None => Ok(0.0),
+        });
+        self.depth -= 1;
+        result
+    }
+
+    fn binary(op: BinOp, left: f64, right: f64) -> Result<f64, String> {
+        Ok(match op {
+            BinOp::Add => left + right,
+            BinOp::Sub => left - right,
+            BinOp::Mul => left * right,
+            BinOp::Div | BinOp::Rem if right == 0.0 => return Err("division by 0".to_string()),
+            BinOp::Div => left / right,
+            BinOp::Rem => left % right,
+            BinOp::Pow => left.powf(right),
+            BinOp::Lt => truth(left < right),
+            BinOp::Le => truth(left <= right),
+            BinOp::Gt => truth(left > right),
+            BinOp::Ge => truth(left >= right),
+            BinOp::Eq => truth(left == right),
+            BinOp::Ne => truth(left != right),
+            BinOp::And => truth(left != 0.0 && right != 0.0),
+            BinOp::Or => truth(left != 0.0 || right != 0.0),
+            BinOp::Comma => right,
+            BinOp::Shl | BinOp::Shr | BinOp::BitAnd | BinOp::BitXor | BinOp::BitOr => {
+                Evaluator::binary(op, integral(left)?, integral(right)?)? as f64
+            }
+        })
+    }
+
+    fn eval(&mut self, expr: &Expr) -> Result<f64, String> {
+        match expr {
+            Expr::Num(n) => Ok(*n as f64),
+            Expr::Float(n) => Ok(*n),
+            Expr::Var(name, None) => self.variable(name),
+            Expr::Var(_, Some(_)) => Err("array elements are not supported here".to_string()),
+            Expr::Unary(op, operand) => {
+                let value = self.eval(operand)?;
+                Ok(match op {
+                    UnaryOp::Neg => -value,
+                    UnaryOp::Plus => value,
+                    UnaryOp::Not => truth(value == 0.0),
+                    UnaryOp::BitNot => !integral(value)? as f64,
+                })
+            }
+            Expr::Binary(BinOp::And, left, right) => {
+                Ok(truth(self.eval(left)? != 0.0 && self.eval(right)? != 0.0))
+            }
+            Expr::Binary(BinOp::Or, left, right) => {
+                Ok(truth(self.eval(left)? != 0.0 || self.eval(right)? != 0.0))
+            }
+            Expr::Binary(op, left, right) => {
+                let left = self.eval(left)?;
+                let right = self.eval(right)?;
+                Self::binary(*op, left, right)
+            }
+            Expr::Ternary(condition, then, otherwise) => {
+                if self.eval(condition)? != 0.0 {
+                    self.eval(then)
+                } else {
+                    self.eval(otherwise)
+                }
+            }
+            Expr::Assign(..) | Expr::Step(..) => {
+                Err("attempted assignment in a calculation".to_string())
+            }
+        }
+    }
+}
+
+/// Evaluates an expression as `evaluate` does but in floating point, so
+/// that `7 / 2` is `3.5` and numbers such as `1.5` and `2e-3` may be used.
+/// Bitwise operators still need integer operands, and variables can only
+/// be read.
+pub fn evaluate_float(input: &str, vars: &mut dyn Variables) -> Result<f64, ArithError> {
+    let mut evaluator = FloatEvaluator { vars, depth: 0 };
+    parse_expression(input, true)
+        .and_then(|expr| match expr {
+            Some(expr) => evaluator.eval(&expr),
+            None => Ok(0.0),
+        })
+        .map_err(|message| ArithError {
+            expr: input.trim().to_string(),
+            message,
+        })
+}
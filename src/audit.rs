@@ -0,0 +1,138 @@
+/// ============================================
+/// AUDIT LOG
+/// ============================================
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::error::ShellError;
+use crate::json::Json;
+use crate::{Shell, osstr};
+
+/// The variable naming the audit log. Auditing is on while it is set and
+/// not empty.
+const AUDIT_VARIABLE: &str = "MYSHELL_AUDIT_LOG";
+
+/// A command being audited, from just before it runs.
+pub struct AuditRecord {
+    argv: Vec<String>,
+    cwd: String,
+    time: SystemTime,
+    started: Instant,
+}
+
+impl Shell {
+    /// Starts an audit record for `command args...` if auditing is on.
+    pub(crate) fn audit_start(&self, command: &str, args: &[String]) -> Option<AuditRecord> {
+        self.get_var(AUDIT_VARIABLE).filter(|log| !log.is_empty())?;
+
+        let argv = std::iter::once(command.to_string())
+            .chain(args.iter().cloned())
+            .collect();
+        let cwd = self
+            .logical_cwd()
+            .map(|cwd| osstr::from_os(cwd.as_os_str()))
+            .unwrap_or_default();
+        Some(AuditRecord {
+            argv,
+            cwd,
+            time: SystemTime::now(),
+            started: Instant::now(),
+        })
+    }
+
+    /// Appends `record` to the audit log as one line of JSON:
+    ///
+    /// `{"time":"2025-01-31T12:00:00.000Z","user":"me","cwd":"/home/me",
+    /// "argv":["ls","-l"],"status":0,"duration_ms":3}`
+    pub(crate) fn audit_finish(&self, record: AuditRecord, status: i32) {
+        let Some(log) = self.get_var(AUDIT_VARIABLE).filter(|log| !log.is_empty()) else {
+            return;
+        };
+
+        let user = self
+            .get_var("USER")
+            .or_else(|| self.get_var("USERNAME"))
+            .filter(|user| !user.is_empty())
+            .or_else(login_name)
+            .unwrap_or_default();
+        let duration = record.started.elapsed().as_millis();
+        let entry = Json::Object(vec![
+            ("time", timestamp(record.time).into()),
+            ("user", user.into()),
+            ("cwd", record.cwd.into()),
+            ("argv", record.argv.into()),
+            ("status", status.into()),
+            (
+                "duration_ms",
+                Json::Number(duration.try_into().unwrap_or(i64::MAX)),
+            ),
+        ]);
+        let line = format!("{}\n", entry);
+
+        let written = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(osstr::to_os(&log))
+            .and_then(|mut file| file.write_all(line.as_bytes()));
+        if let Err(source) = written {
+            self.report(&ShellError::Io {
+                context: format!("audit: {}", log),
+                source,
+            });
+        }
+    }
+}
+
+/// The name of the user the shell runs as, from the password database.
+#[cfg(unix)]
+fn login_name() -> Option<String> {
+    use std::ffi::{CStr, OsStr};
+    use std::os::unix::ffi::OsStrExt;
+
+    let entry = unsafe { libc::getpwuid(libc::getuid()) };
+    if entry.is_null() {
+        return None;
+    }
+    let name = unsafe { CStr::from_ptr((*entry).pw_name) };
+    Some(osstr::from_os(OsStr::from_bytes(name.to_bytes())))
+}
+
+#[cfg(windows)]
+fn login_name() -> Option<String> {
+    None
+}
+
+#[cfg(unix)]
+pub(crate) fn timestamp_now() -> String {
+    timestamp(SystemTime::now())
+}
+
+/// `time` in UTC as an ISO 8601 timestamp with milliseconds.
+fn timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (days, secs_of_day) = (secs / 86_400, secs % 86_400);
+
+    // Days since 1970-01-01 to a civil date (Howard Hinnant's algorithm).
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3_600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
@@ -0,0 +1,76 @@
+/// ============================================
+/// ABBR BUILTIN
+/// ============================================
+use super::Builtin;
+use crate::error::ShellError;
+use crate::parser::quote;
+use crate::shell_io::ShellIo;
+use crate::{Shell, write_line};
+
+/// `abbr -a name expansion...` defines an abbreviation, which the line
+/// editor replaces with its expansion when it is typed as a command name
+/// and followed by Space or Enter. Unlike an alias, history records the
+/// expanded command. `abbr -e name...` removes abbreviations, `abbr -l`
+/// lists their names and `abbr` alone shows them as commands.
+pub struct Abbr;
+
+impl Builtin for Abbr {
+    fn name(&self) -> &'static str {
+        "abbr"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "abbr [-a name expansion ...] [-e name ...] [-l]"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        let Some((option, rest)) = args.split_first() else {
+            for (name, expansion) in &shell.abbreviations {
+                write_line(io.stdout(), &definition(name, expansion));
+            }
+            return 0;
+        };
+
+        match option.as_str() {
+            "-a" | "--add" => {
+                let Some((name, words)) = rest.split_first().filter(|(_, words)| !words.is_empty())
+                else {
+                    let message = "usage: abbr -a name expansion ...";
+                    return shell.fail(ShellError::usage("abbr", message), io);
+                };
+                if name.is_empty() || name.contains(char::is_whitespace) {
+                    let message = format!("{}: invalid abbreviation name", quote(name));
+                    return shell.fail(ShellError::builtin("abbr", message), io);
+                }
+                shell.abbreviations.insert(name.clone(), words.join(" "));
+                0
+            }
+            "-e" | "--erase" => {
+                let mut status = 0;
+                for name in rest {
+                    if shell.abbreviations.remove(name).is_none() {
+                        let message = format!("{}: no such abbreviation", name);
+                        status = shell.fail(ShellError::builtin("abbr", message), io);
+                    }
+                }
+                status
+            }
+            "-l" | "--list" => {
+                for name in shell.abbreviations.keys() {
+                    write_line(io.stdout(), name);
+                }
+                0
+            }
+            _ => {
+                let message = format!("{}: invalid option", option);
+                shell.fail(ShellError::usage("abbr", message), io)
+            }
+        }
+    }
+}
+
+/// The `abbr` command that defines `name`, as `abbr` lists it and `state
+/// save` records it.
+pub(crate) fn definition(name: &str, expansion: &str) -> String {
+    format!("abbr -a {} {}", quote(name), quote(expansion))
+}
@@ -0,0 +1,115 @@
+/// ============================================
+/// ALIAS BUILTINS
+/// ============================================
+use super::Builtin;
+use crate::alias::is_valid_alias_name;
+use crate::error::ShellError;
+use crate::json::Json;
+use crate::parser::quote;
+use crate::shell_io::ShellIo;
+use crate::{Shell, write_line};
+
+/// `alias [-p] [name[=value] ...]` defines an alias for each
+/// `name=value`, which replaces `name` when it is run as a command, and
+/// shows the alias of each other name. Without names, or with `-p`, shows
+/// every alias as the command that defines it. With `--json` prints an
+/// array with an object per alias, such as `{"name":"ll","value":"ls -l"}`.
+pub struct Alias;
+
+impl Builtin for Alias {
+    fn name(&self) -> &'static str {
+        "alias"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "alias [--json] [-p] [name[=value] ...]"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        if args.first().is_some_and(|arg| arg == "--json") {
+            let aliases = shell
+                .aliases
+                .iter()
+                .map(|(name, value)| {
+                    Json::Object(vec![
+                        ("name", name.as_str().into()),
+                        ("value", value.as_str().into()),
+                    ])
+                })
+                .collect();
+            write_line(io.stdout(), &Json::Array(aliases).to_string());
+            return 0;
+        }
+
+        let (all, args) = match args.split_first() {
+            Some((option, rest)) if option == "-p" => (true, rest),
+            _ => (args.is_empty(), args),
+        };
+        if all {
+            for (name, value) in &shell.aliases {
+                write_line(io.stdout(), &definition(name, value));
+            }
+        }
+
+        let mut status = 0;
+        for arg in args {
+            match arg.split_once('=') {
+                Some((name, _)) if !is_valid_alias_name(name) => {
+                    let message = format!("`{}': invalid alias name", name);
+                    status = shell.fail(ShellError::builtin("alias", message), io);
+                }
+                Some((name, value)) => {
+                    shell.aliases.insert(name.to_string(), value.to_string());
+                }
+                None => match shell.aliases.get(arg) {
+                    Some(value) => write_line(io.stdout(), &definition(arg, value)),
+                    None => {
+                        let message = format!("{}: not found", arg);
+                        status = shell.fail(ShellError::builtin("alias", message), io);
+                    }
+                },
+            }
+        }
+        status
+    }
+}
+
+/// `unalias [-a] name ...` removes the given aliases, or every alias with
+/// `-a`.
+pub struct Unalias;
+
+impl Builtin for Unalias {
+    fn name(&self) -> &'static str {
+        "unalias"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "unalias [-a] name [name ...]"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        if args.first().is_some_and(|arg| arg == "-a") {
+            shell.aliases.clear();
+            return 0;
+        }
+        if args.is_empty() {
+            let message = "usage: unalias [-a] name [name ...]";
+            return shell.fail(ShellError::usage("unalias", message), io);
+        }
+
+        let mut status = 0;
+        for name in args {
+            if shell.aliases.remove(name).is_none() {
+                let message = format!("{}: not found", name);
+                status = shell.fail(ShellError::builtin("unalias", message), io);
+            }
+        }
+        status
+    }
+}
+
+/// The `alias` command that defines `name`, as `alias` lists it and `state
+/// save` records it.
+pub(crate) fn definition(name: &str, value: &str) -> String {
+    format!("alias {}={}", name, quote(value))
+}
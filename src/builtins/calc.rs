@@ -0,0 +1,48 @@
+/// ============================================
+/// CALC BUILTIN
+/// ============================================
+use super::Builtin;
+use crate::arith;
+use crate::error::ShellError;
+use crate::shell_io::ShellIo;
+use crate::{Shell, write_line};
+
+/// `calc expression` evaluates an arithmetic expression in floating point
+/// and prints the result, so `calc 7 / 2` prints `3.5`. An interactive
+/// line starting with `=` is run as `calc` with the rest of the line.
+pub struct Calc;
+
+impl Builtin for Calc {
+    fn name(&self) -> &'static str {
+        "calc"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "calc expression"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        let expression = args.join(" ");
+        if expression.trim().is_empty() {
+            return shell.fail(ShellError::usage("calc", "usage: calc expression"), io);
+        }
+
+        match arith::evaluate_float(&expression, shell) {
+            Ok(value) => {
+                write_line(io.stdout(), &format_number(value));
+                0
+            }
+            Err(e) => shell.fail(e.into(), io),
+        }
+    }
+}
+
+/// `value` without a fractional part when it is a whole number small
+/// enough to be exact, and otherwise in Rust's shortest round-trip form.
+fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else {
+        value.to_string()
+    }
+}
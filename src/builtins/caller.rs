@@ -0,0 +1,58 @@
+/// ============================================
+/// CALLER BUILTIN
+/// ============================================
+use super::Builtin;
+use crate::error::ShellError;
+use crate::shell_io::ShellIo;
+use crate::{Shell, write_line};
+
+/// `caller` prints the line and file the current function or sourced
+/// file was called from. `caller n` prints the line, function and file of
+/// the call `n` frames further out, so a loop over `caller 0`, `caller 1`
+/// and so on prints a stack trace; it fails once there are no more.
+pub struct Caller;
+
+impl Builtin for Caller {
+    fn name(&self) -> &'static str {
+        "caller"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "caller [n]"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        let depth = match args {
+            [] => None,
+            [n] => match n.parse::<usize>() {
+                Ok(n) => Some(n),
+                Err(_) => {
+                    let message = format!("{}: invalid number", n);
+                    return shell.fail(ShellError::usage("caller", message), io);
+                }
+            },
+            _ => return shell.fail(ShellError::usage("caller", "usage: caller [n]"), io),
+        };
+
+        // Frames from the innermost out; the one past the last is the
+        // shell's own input.
+        let frames: Vec<_> = shell.call_stack.iter().rev().collect();
+        let line = match depth {
+            None => {
+                let Some(frame) = frames.first() else {
+                    return 1;
+                };
+                let source = frames.get(1).map_or("main", |caller| &caller.source);
+                format!("{} {}", frame.line, source)
+            }
+            Some(n) => {
+                let (Some(frame), Some(caller)) = (frames.get(n), frames.get(n + 1)) else {
+                    return 1;
+                };
+                format!("{} {} {}", frame.line, caller.name, caller.source)
+            }
+        };
+        write_line(io.stdout(), &line);
+        0
+    }
+}
@@ -0,0 +1,261 @@
+/// ============================================
+/// CD BUILTIN
+/// ============================================
+use std::collections::VecDeque;
+use std::env;
+use std::path::{Component, Path, PathBuf};
+
+use super::{Builtin, jump};
+use crate::error::ShellError;
+use crate::shell_io::ShellIo;
+use crate::{Shell, osstr, paths, write_line};
+
+/// How many directories the directory history keeps.
+const HISTORY_SIZE: usize = 32;
+
+/// `cd [dir]` changes directory; `cd -` goes back to `$OLDPWD` and `cd -N`
+/// to the directory visited N changes ago, as numbered by `cdh`, and both
+/// print its name.
+pub struct Cd;
+
+impl Builtin for Cd {
+    fn name(&self) -> &'static str {
+        "cd"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "cd [dir | - | -N]"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        let arg = args.first().map(|s| s.as_str()).unwrap_or("");
+
+        let back = history_offset(arg);
+        let path = match (back, arg) {
+            (Some(back), _) => match shell.dir_history.get(back) {
+                Some(dir) => osstr::from_os(dir.as_os_str()),
+                None => {
+                    let message = format!("{}: no such entry in the directory history", arg);
+                    return shell.fail(ShellError::builtin("cd", message), io);
+                }
+            },
+            (None, "-") => match shell.get_var("OLDPWD").filter(|dir| !dir.is_empty()) {
+                Some(dir) => dir,
+                None => return shell.fail(ShellError::builtin("cd", "OLDPWD not set"), io),
+            },
+            // `~` was expanded with the other words; only a missing
+            // directory means home here.
+            (None, "") => shell.home().unwrap_or_default(),
+            (None, path) => path.to_string(),
+        };
+
+        let path = translate_foreign_path(shell, path);
+
+        // Windows accepts `/` as a separator too; use `\` throughout so UNC
+        // paths written as `//server/share` are recognised.
+        #[cfg(windows)]
+        let path = resolve_drive_relative(shell, path.replace('/', "\\"));
+
+        let mut shown = path;
+        if !Path::new(&osstr::to_os(&shown)).exists()
+            && let Some(corrected) = correct_spelling(shell, &shown)
+        {
+            shown = osstr::from_os(corrected.as_os_str());
+            write_line(io.stdout(), &shown);
+        }
+        let path = &PathBuf::from(osstr::to_os(&shown));
+
+        if !path.exists() {
+            let message = format!("{}: No such file or directory", shown);
+            return shell.fail(ShellError::builtin("cd", message), io);
+        }
+
+        let old_pwd = shell.logical_cwd().ok();
+        let logical = old_pwd
+            .as_deref()
+            .map(|cwd| paths::normalize(&cwd.join(path)));
+
+        // `..` is resolved lexically against $PWD, as bash does; when that
+        // path does not exist (e.g. `..` out of a symlinked directory into a
+        // missing one), fall back to the physical path.
+        let new_pwd = match logical {
+            Some(logical) if env::set_current_dir(&logical).is_ok() => logical,
+            _ => match env::set_current_dir(path).and_then(|()| env::current_dir()) {
+                Ok(physical) => paths::without_verbatim(physical),
+                Err(source) => {
+                    let context = format!("cd: {}", shown);
+                    return shell.fail(ShellError::Io { context, source }, io);
+                }
+            },
+        };
+
+        #[cfg(windows)]
+        if let Some(drive) = paths::drive_of(&new_pwd) {
+            shell.drive_dirs.insert(drive, new_pwd.clone());
+        }
+
+        if let Some(old_pwd) = &old_pwd {
+            shell.export_var("OLDPWD", osstr::from_os(old_pwd.as_os_str()));
+        }
+        if back.is_some() || arg == "-" {
+            write_line(io.stdout(), &osstr::from_os(new_pwd.as_os_str()));
+        }
+        shell.export_var("PWD", osstr::from_os(new_pwd.as_os_str()));
+        jump::record_visit(&new_pwd);
+        remember(&mut shell.dir_history, old_pwd, new_pwd);
+        0
+    }
+}
+
+/// `cdh` lists the directory history, most recent first, numbered for
+/// `cd -N`; `cdh N` goes to entry N.
+pub struct Cdh;
+
+impl Builtin for Cdh {
+    fn name(&self) -> &'static str {
+        "cdh"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "cdh [N]"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        match args {
+            [] => {
+                for (number, dir) in shell.dir_history.iter().enumerate().skip(1) {
+                    let line = format!("{:2}  {}", number, osstr::from_os(dir.as_os_str()));
+                    write_line(io.stdout(), &line);
+                }
+                0
+            }
+            [number] if number.parse::<usize>().is_ok() => {
+                Cd.execute(shell, &[format!("-{}", number)], io)
+            }
+            _ => shell.fail(ShellError::usage("cdh", "usage: cdh [N]"), io),
+        }
+    }
+}
+
+/// N for an argument of the form `-N` with N at least 1.
+fn history_offset(arg: &str) -> Option<usize> {
+    let digits = arg.strip_prefix('-')?;
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok().filter(|&n| n >= 1)
+}
+
+/// Records a change from `old_pwd` to `new_pwd`. The history holds each
+/// directory once, the current one first.
+fn remember(history: &mut VecDeque<PathBuf>, old_pwd: Option<PathBuf>, new_pwd: PathBuf) {
+    if history.is_empty()
+        && let Some(old_pwd) = old_pwd
+    {
+        history.push_front(old_pwd);
+    }
+    history.retain(|dir| *dir != new_pwd);
+    history.push_front(new_pwd);
+    history.truncate(HISTORY_SIZE);
+}
+
+/// Resolves `D:` to the directory last visited on drive D (the current
+/// directory if that is the current drive, else its root) and `D:dir`
+/// relative to it, as cmd.exe does.
+#[cfg(windows)]
+fn resolve_drive_relative(shell: &Shell, path: String) -> String {
+    let Some((drive, rest)) = paths::split_drive_relative(&path) else {
+        return path;
+    };
+
+    let cwd = shell.logical_cwd().ok();
+    let base = match cwd {
+        Some(cwd) if paths::drive_of(&cwd) == Some(drive) => cwd,
+        _ => shell
+            .drive_dirs
+            .get(&drive)
+            .cloned()
+            .unwrap_or_else(|| PathBuf::from(format!("{}:\\", drive))),
+    };
+    osstr::from_os(base.join(osstr::to_os(rest)).as_os_str())
+}
+
+/// With `set -o cdspell`, corrects small spelling mistakes in the
+/// components of a directory that does not exist, as bash's `cdspell`
+/// does: a wrong, missing, extra or transposed character, or the wrong
+/// case. `None` if a component has no single-mistake match.
+fn correct_spelling(shell: &Shell, path: &str) -> Option<PathBuf> {
+    if !shell.cdspell {
+        return None;
+    }
+
+    let mut corrected = PathBuf::new();
+    for component in Path::new(&osstr::to_os(path)).components() {
+        let Component::Normal(name) = component else {
+            corrected.push(component);
+            continue;
+        };
+        if corrected.join(name).exists() {
+            corrected.push(name);
+            continue;
+        }
+
+        let dir = if corrected.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            corrected.as_path()
+        };
+        let name = osstr::from_os(name);
+        let mut candidates: Vec<String> = std::fs::read_dir(dir)
+            .ok()?
+            .flatten()
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| osstr::from_os(&entry.file_name()))
+            .filter(|candidate| one_mistake_apart(candidate, &name))
+            .collect();
+        candidates.sort();
+        corrected.push(osstr::to_os(candidates.first()?));
+    }
+    Some(corrected)
+}
+
+/// Whether `a` and `b` differ by case, or by one substituted, inserted,
+/// deleted or transposed character.
+fn one_mistake_apart(a: &str, b: &str) -> bool {
+    if a.to_lowercase() == b.to_lowercase() {
+        return true;
+    }
+
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let (a, b) = (&a[prefix..], &b[prefix..]);
+
+    match (a.len(), b.len()) {
+        (x, y) if x == y => {
+            a[1..] == b[1..] || (x >= 2 && a[0] == b[1] && a[1] == b[0] && a[2..] == b[2..])
+        }
+        (x, y) if x == y + 1 => a[1..] == *b,
+        (x, y) if x + 1 == y => *a == b[1..],
+        _ => false,
+    }
+}
+
+/// With `MYSHELL_PATHCONV` set, lets `cd` take paths written for the
+/// other side of the WSL/Windows boundary: `/c/dir` and `/mnt/c/dir` on
+/// Windows, `C:\\dir` under WSL.
+fn translate_foreign_path(shell: &Shell, path: String) -> String {
+    if shell
+        .get_var("MYSHELL_PATHCONV")
+        .is_none_or(|value| value.is_empty())
+    {
+        return path;
+    }
+
+    #[cfg(windows)]
+    let translated = paths::unix_to_windows(&path, '\\');
+    #[cfg(unix)]
+    let translated = paths::windows_to_unix(&path);
+
+    translated.unwrap_or(path)
+}
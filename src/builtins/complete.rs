@@ -0,0 +1,121 @@
+/// ============================================
+/// COMPLETE BUILTIN
+/// ============================================
+use super::Builtin;
+use crate::error::ShellError;
+use crate::parser::quote;
+use crate::shell_io::ShellIo;
+use crate::{Shell, write_line};
+
+/// How Tab completes the arguments of a command.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct CompletionSpec {
+    /// `-W`: words to offer, separated by whitespace.
+    pub(crate) words: Option<String>,
+    /// `-F`: a function that leaves the words to offer in `COMPREPLY`.
+    pub(crate) function: Option<String>,
+}
+
+/// `complete -W words name...` and `complete -F function name...` set how
+/// Tab completes the arguments of the named commands; without either
+/// option, or with `-p`, the named specs or all of them are printed as
+/// commands. `complete -r [name...]` removes them.
+///
+/// Specs are usually kept in `~/.myshell_completions/name`, which is
+/// sourced the first time Tab completes an argument of `name`.
+pub struct Complete;
+
+impl Builtin for Complete {
+    fn name(&self) -> &'static str {
+        "complete"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "complete [-p] [-r] [-W words] [-F function] [name ...]"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        let mut spec = CompletionSpec::default();
+        let mut remove = false;
+        let mut rest = args;
+        while let Some((option, after)) = rest.split_first()
+            && option.starts_with('-')
+        {
+            rest = after;
+            match option.as_str() {
+                "-p" => {}
+                "-r" => remove = true,
+                "-W" | "-F" => {
+                    let Some((value, after)) = rest.split_first() else {
+                        let message = format!("{}: option requires an argument", option);
+                        return shell.fail(ShellError::usage("complete", message), io);
+                    };
+                    rest = after;
+                    if option == "-W" {
+                        spec.words = Some(value.clone());
+                    } else {
+                        spec.function = Some(value.clone());
+                    }
+                }
+                "--" => break,
+                _ => {
+                    let message = format!("{}: invalid option", option);
+                    return shell.fail(ShellError::usage("complete", message), io);
+                }
+            }
+        }
+
+        if remove {
+            if rest.is_empty() {
+                shell.completion_specs.clear();
+            }
+            for name in rest {
+                shell.completion_specs.remove(name);
+            }
+            return 0;
+        }
+
+        if spec != CompletionSpec::default() {
+            if rest.is_empty() {
+                let message = "usage: complete [-W words] [-F function] name ...";
+                return shell.fail(ShellError::usage("complete", message), io);
+            }
+            for name in rest {
+                shell.completion_specs.insert(name.clone(), spec.clone());
+            }
+            return 0;
+        }
+
+        if rest.is_empty() {
+            for (name, spec) in &shell.completion_specs {
+                write_line(io.stdout(), &definition(name, spec));
+            }
+            return 0;
+        }
+
+        let mut status = 0;
+        for name in rest {
+            match shell.completion_specs.get(name) {
+                Some(spec) => write_line(io.stdout(), &definition(name, spec)),
+                None => {
+                    let message = format!("{}: no completion specification", name);
+                    status = shell.fail(ShellError::builtin("complete", message), io);
+                }
+            }
+        }
+        status
+    }
+}
+
+/// The `complete` command that sets `spec` for `name`, as `complete -p`
+/// prints it and `state save` records it.
+pub(crate) fn definition(name: &str, spec: &CompletionSpec) -> String {
+    let mut command = String::from("complete");
+    if let Some(words) = &spec.words {
+        command.push_str(&format!(" -W {}", quote(words)));
+    }
+    if let Some(function) = &spec.function {
+        command.push_str(&format!(" -F {}", quote(function)));
+    }
+    format!("{} {}", command, quote(name))
+}
@@ -0,0 +1,124 @@
+/// ============================================
+/// DECLARE AND LOCAL BUILTINS
+/// ============================================
+use super::Builtin;
+use crate::error::ShellError;
+use crate::shell_io::ShellIo;
+use crate::variables::Value;
+use crate::{Shell, parser};
+
+pub struct Declare;
+
+impl Builtin for Declare {
+    fn name(&self) -> &'static str {
+        "declare"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "declare [-agnx] [name[=value] ...]"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        declare(shell, "declare", args, io)
+    }
+}
+
+pub struct Local;
+
+impl Builtin for Local {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "local [-anx] [name[=value] ...]"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        declare(shell, "local", args, io)
+    }
+}
+
+/// `declare [-agnx] name[=value]...` and `local`. Inside a function the
+/// names become local to the call unless `-g` is given.
+fn declare(shell: &mut Shell, builtin: &str, args: &[String], io: &mut dyn ShellIo) -> i32 {
+    let (mut array, mut global, mut nameref, mut export) = (false, false, false, false);
+
+    let mut args = args.iter().peekable();
+    while let Some(flags) = args.next_if(|arg| arg.len() > 1 && arg.starts_with('-')) {
+        if flags == "--" {
+            break;
+        }
+        for flag in flags[1..].chars() {
+            match flag {
+                'a' => array = true,
+                'g' if builtin == "declare" => global = true,
+                'n' => nameref = true,
+                'x' => export = true,
+                _ => {
+                    let message = format!("-{}: invalid option", flag);
+                    return shell.fail(ShellError::usage(builtin, message), io);
+                }
+            }
+        }
+    }
+
+    let local = !global && shell.function_depth > 0;
+    if builtin == "local" && !local {
+        let message = "can only be used in a function";
+        return shell.fail(ShellError::builtin("local", message), io);
+    }
+
+    let mut status = 0;
+    for arg in args {
+        let (target, value) = match arg.split_once('=') {
+            Some((target, value)) => (target, Some(value)),
+            None => (arg.as_str(), None),
+        };
+        let Some((name, index)) = parser::parse_assignment_target(target) else {
+            let message = format!("`{}': not a valid identifier", arg);
+            status = shell.fail(ShellError::builtin(builtin, message), io);
+            continue;
+        };
+
+        if nameref && let Some(target) = value {
+            let error = if !parser::is_valid_name(target) || index.is_some() {
+                Some(format!(
+                    "`{}': invalid variable name for name reference",
+                    target
+                ))
+            } else if target == name {
+                Some(format!(
+                    "{}: nameref variable self references not allowed",
+                    name
+                ))
+            } else {
+                None
+            };
+            if let Some(message) = error {
+                status = shell.fail(ShellError::builtin(builtin, message), io);
+                continue;
+            }
+        }
+
+        if local {
+            shell.make_local(&name);
+        }
+
+        match value {
+            Some(target) if nameref => shell.set_ref(&name, target),
+            Some(value) => shell.assign_scalar(&name, index.as_deref(), value.to_string()),
+            None if array && shell.get_value(&name).is_none() => {
+                shell.set_array(&name, Value::Array(Default::default()));
+            }
+            None => {}
+        }
+
+        if export {
+            let name = shell.resolve_name(&name).to_string();
+            shell.exported.insert(name);
+        }
+    }
+
+    status
+}
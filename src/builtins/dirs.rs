@@ -0,0 +1,224 @@
+/// ============================================
+/// DIRECTORY STACK BUILTINS
+/// ============================================
+use std::path::{Path, PathBuf};
+
+use super::Builtin;
+use super::cd::Cd;
+use crate::error::ShellError;
+use crate::shell_io::ShellIo;
+use crate::{Shell, osstr, paths, write_line};
+
+/// `dirs [-c] [-l] [-p | -v] [+N | -N]` shows the directory stack, the
+/// working directory first, with the home directory written as `~` unless
+/// `-l` is given. `-p` puts each directory on its own line and `-v` numbers
+/// them; `+N` and `-N` show only the Nth directory from the top or the
+/// bottom. `-c` empties the stack.
+pub struct Dirs;
+
+impl Builtin for Dirs {
+    fn name(&self) -> &'static str {
+        "dirs"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "dirs [-c] [-l] [-p | -v] [+N | -N]"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        let stack = stack(shell);
+        let (mut long, mut format, mut index) = (false, "", None);
+        for arg in args {
+            match arg.as_str() {
+                "-c" => {
+                    shell.dir_stack.clear();
+                    return 0;
+                }
+                "-l" => long = true,
+                "-p" | "-v" => format = arg.as_str(),
+                _ => match stack_index(arg, stack.len()) {
+                    Some(Ok(n)) => index = Some(n),
+                    Some(Err(message)) => {
+                        return shell.fail(ShellError::builtin("dirs", message), io);
+                    }
+                    None => {
+                        let message = format!("{}: invalid option", arg);
+                        return shell.fail(ShellError::usage("dirs", message), io);
+                    }
+                },
+            }
+        }
+
+        let names = stack.iter().map(|dir| display(shell, dir, long));
+        if let Some(index) = index {
+            let name = display(shell, &stack[index], long);
+            write_line(io.stdout(), &name);
+            return 0;
+        }
+        match format {
+            "-p" => names.for_each(|name| write_line(io.stdout(), &name)),
+            "-v" => {
+                for (number, name) in names.enumerate() {
+                    write_line(io.stdout(), &format!("{:2}  {}", number, name));
+                }
+            }
+            _ => write_line(io.stdout(), &names.collect::<Vec<_>>().join(" ")),
+        }
+        0
+    }
+}
+
+/// `pushd [dir]` saves the working directory on the directory stack and
+/// changes to `dir`; with no argument it swaps the top two directories,
+/// and `pushd +N` or `-N` rotates the stack so that the Nth directory from
+/// the top or the bottom is on top. `pushd -n dir` adds `dir` below the
+/// top without changing directory. Shows the stack after each change.
+pub struct Pushd;
+
+impl Builtin for Pushd {
+    fn name(&self) -> &'static str {
+        "pushd"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "pushd [-n] [dir | +N | -N]"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        let stack = stack(shell);
+        let rotated = match args {
+            [] if stack.len() < 2 => {
+                return shell.fail(ShellError::builtin("pushd", "no other directory"), io);
+            }
+            [] => {
+                let mut rotated = stack;
+                rotated.swap(0, 1);
+                rotated
+            }
+            [option, dir] if option == "-n" => {
+                let dir = paths::normalize(&stack[0].join(osstr::to_os(dir)));
+                shell.dir_stack.insert(0, dir);
+                show_stack(shell, io);
+                return 0;
+            }
+            [arg] => match stack_index(arg, stack.len()) {
+                Some(Ok(n)) => [&stack[n..], &stack[..n]].concat(),
+                Some(Err(message)) => {
+                    return shell.fail(ShellError::builtin("pushd", message), io);
+                }
+                None => {
+                    let status = Cd.execute(shell, args, io);
+                    if status == 0 {
+                        shell.dir_stack.insert(0, stack[0].clone());
+                        show_stack(shell, io);
+                    }
+                    return status;
+                }
+            },
+            _ => {
+                let message = "usage: pushd [-n] [dir | +N | -N]";
+                return shell.fail(ShellError::usage("pushd", message), io);
+            }
+        };
+
+        let status = change_dir(shell, &rotated[0], io);
+        if status == 0 {
+            shell.dir_stack = rotated[1..].to_vec();
+            show_stack(shell, io);
+        }
+        status
+    }
+}
+
+/// `popd` removes the top directory from the directory stack and changes
+/// to the one below it; `popd +N` or `-N` instead removes the Nth
+/// directory from the top or the bottom. Shows the stack after each change.
+pub struct Popd;
+
+impl Builtin for Popd {
+    fn name(&self) -> &'static str {
+        "popd"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "popd [+N | -N]"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        let stack = stack(shell);
+        let index = match args {
+            [] => 0,
+            [arg] => match stack_index(arg, stack.len()) {
+                Some(Ok(n)) => n,
+                Some(Err(message)) => {
+                    return shell.fail(ShellError::builtin("popd", message), io);
+                }
+                None => {
+                    let message = format!("{}: invalid argument", arg);
+                    return shell.fail(ShellError::usage("popd", message), io);
+                }
+            },
+            _ => return shell.fail(ShellError::usage("popd", "usage: popd [+N | -N]"), io),
+        };
+        if shell.dir_stack.is_empty() {
+            return shell.fail(ShellError::builtin("popd", "directory stack empty"), io);
+        }
+
+        if index == 0 {
+            let status = change_dir(shell, &stack[1], io);
+            if status != 0 {
+                return status;
+            }
+        }
+        shell.dir_stack.remove(index.saturating_sub(1));
+        show_stack(shell, io);
+        0
+    }
+}
+
+/// The directory stack: the working directory, then the directories saved
+/// by `pushd`, most recent first.
+fn stack(shell: &Shell) -> Vec<PathBuf> {
+    let cwd = shell.logical_cwd().unwrap_or_default();
+    std::iter::once(cwd)
+        .chain(shell.dir_stack.iter().cloned())
+        .collect()
+}
+
+/// The position in a stack of `len` directories that `+N` or `-N` names,
+/// counting from the top or the bottom; `None` if `arg` is neither.
+fn stack_index(arg: &str, len: usize) -> Option<Result<usize, String>> {
+    let (from_top, digits) = match arg.split_at_checked(1)? {
+        ("+", digits) => (true, digits),
+        ("-", digits) => (false, digits),
+        _ => return None,
+    };
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    match digits.parse::<usize>() {
+        Ok(n) if n < len && from_top => Some(Ok(n)),
+        Ok(n) if n < len => Some(Ok(len - 1 - n)),
+        _ => Some(Err(format!("{}: directory stack index out of range", arg))),
+    }
+}
+
+/// Changes to `dir` as `cd` does.
+fn change_dir(shell: &mut Shell, dir: &Path, io: &mut dyn ShellIo) -> i32 {
+    Cd.execute(shell, &[osstr::from_os(dir.as_os_str())], io)
+}
+
+/// Prints the directory stack on one line, as `dirs` does.
+fn show_stack(shell: &Shell, io: &mut dyn ShellIo) {
+    let names: Vec<String> = stack(shell)
+        .iter()
+        .map(|dir| display(shell, dir, false))
+        .collect();
+    write_line(io.stdout(), &names.join(" "));
+}
+
+/// How `dirs` shows `dir`: with the home directory as `~` unless `long`.
+fn display(shell: &Shell, dir: &Path, long: bool) -> String {
+    let path = osstr::from_os(dir.as_os_str());
+    if long { path } else { shell.tilde_path(&path) }
+}
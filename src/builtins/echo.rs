@@ -0,0 +1,24 @@
+/// ============================================
+/// ECHO BUILTIN
+/// ============================================
+use super::Builtin;
+use crate::shell_io::ShellIo;
+use crate::{Shell, write_line};
+
+pub struct Echo;
+
+impl Builtin for Echo {
+    fn name(&self) -> &'static str {
+        "echo"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "echo [arg ...]"
+    }
+
+    fn execute(&self, _shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        let output = args.join(" ");
+        write_line(io.stdout(), &output);
+        0
+    }
+}
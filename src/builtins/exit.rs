@@ -0,0 +1,39 @@
+/// ============================================
+/// EXIT BUILTIN
+/// ============================================
+use super::Builtin;
+use crate::error::ShellError;
+use crate::shell_io::ShellIo;
+use crate::{Flow, Shell};
+
+pub struct Exit;
+
+impl Builtin for Exit {
+    fn name(&self) -> &'static str {
+        "exit"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "exit [n]"
+    }
+
+    /// Without `n`, exits with the status of the last command run.
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        let code = match args.first() {
+            None => shell.last_status,
+            Some(arg) => match arg.parse::<i32>() {
+                Ok(code) => code & 0xff,
+                Err(_) => {
+                    let message = format!("{}: numeric argument required", arg);
+                    shell.fail(ShellError::usage("exit", message), io)
+                }
+            },
+        };
+
+        if shell.substitution_depth > 0 || shell.embedded {
+            shell.flow = Flow::Exit;
+            return code;
+        }
+        shell.exit(code);
+    }
+}
@@ -0,0 +1,109 @@
+/// ============================================
+/// EXPORT AND UNSET BUILTINS
+/// ============================================
+use std::collections::BTreeMap;
+
+use super::Builtin;
+use crate::error::ShellError;
+use crate::parser::{is_valid_name, quote};
+use crate::shell_io::ShellIo;
+use crate::{Shell, osstr, write_line};
+
+/// `export name[=value]...` marks variables to be passed to the programs
+/// the shell runs, assigning them first when a value is given. With no
+/// names, or with `-p`, lists the environment those programs get.
+pub struct Export;
+
+impl Builtin for Export {
+    fn name(&self) -> &'static str {
+        "export"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "export [-p] [name[=value] ...]"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        let names = match args.split_first() {
+            Some((option, rest)) if option == "-p" => rest,
+            _ => args,
+        };
+        if names.is_empty() {
+            for (name, value) in environment(shell) {
+                write_line(io.stdout(), &format!("export {}={}", name, quote(&value)));
+            }
+            return 0;
+        }
+
+        let mut status = 0;
+        for arg in names {
+            let (name, value) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (arg.as_str(), None),
+            };
+            if !is_valid_name(name) {
+                let message = format!("`{}': not a valid identifier", arg);
+                status = shell.fail(ShellError::builtin("export", message), io);
+                continue;
+            }
+
+            match value {
+                Some(value) => shell.export_var(name, value.to_string()),
+                None => {
+                    let name = shell.resolve_name(name).to_string();
+                    shell.exported.insert(name);
+                }
+            }
+        }
+        status
+    }
+}
+
+/// The environment a program started now would get, sorted by name.
+fn environment(shell: &Shell) -> BTreeMap<String, String> {
+    let mut environment: BTreeMap<String, String> = std::env::vars_os()
+        .map(|(name, value)| (osstr::from_os(&name), osstr::from_os(&value)))
+        .filter(|(name, _)| !shell.unset_env.contains(name))
+        .collect();
+    environment.extend(shell.exported_env());
+    environment
+}
+
+/// `unset [-f | -v] name...` removes variables, including ones the shell
+/// got from its environment. A name that is not a variable is taken as a
+/// function, unless `-v` is given; `-f` removes only functions.
+pub struct Unset;
+
+impl Builtin for Unset {
+    fn name(&self) -> &'static str {
+        "unset"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "unset [-f | -v] name ..."
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        let (functions, variables, names) = match args.split_first() {
+            Some((option, rest)) if option == "-f" => (true, false, rest),
+            Some((option, rest)) if option == "-v" => (false, true, rest),
+            _ => (true, true, args),
+        };
+
+        let mut status = 0;
+        for name in names {
+            if !is_valid_name(name) {
+                let message = format!("`{}': not a valid identifier", name);
+                status = shell.fail(ShellError::builtin("unset", message), io);
+                continue;
+            }
+
+            if variables && shell.get_value(name).is_some() {
+                shell.unset_var(name);
+            } else if functions {
+                shell.functions.remove(name);
+            }
+        }
+        status
+    }
+}
@@ -0,0 +1,113 @@
+/// ============================================
+/// RETURN, BREAK AND CONTINUE BUILTINS
+/// ============================================
+use super::Builtin;
+use crate::error::ShellError;
+use crate::shell_io::ShellIo;
+use crate::{Flow, Shell};
+
+pub struct Return;
+
+impl Builtin for Return {
+    fn name(&self) -> &'static str {
+        "return"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "return [n]"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        if shell.function_depth == 0 && shell.source_depth == 0 {
+            let message = "can only `return' from a function or sourced script";
+            return shell.fail(ShellError::builtin("return", message), io);
+        }
+
+        let status = match args.first() {
+            None => shell.last_status,
+            Some(arg) => match arg.parse::<i64>() {
+                Ok(n) => (n & 0xff) as i32,
+                Err(_) => {
+                    let message = format!("{}: numeric argument required", arg);
+                    shell.fail(ShellError::usage("return", message), io)
+                }
+            },
+        };
+
+        shell.flow = Flow::Return;
+        status
+    }
+}
+
+pub struct Break;
+
+impl Builtin for Break {
+    fn name(&self) -> &'static str {
+        "break"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "break [n]"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        loop_control(shell, "break", args, io, Flow::Break)
+    }
+}
+
+pub struct Continue;
+
+impl Builtin for Continue {
+    fn name(&self) -> &'static str {
+        "continue"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "continue [n]"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        loop_control(shell, "continue", args, io, Flow::Continue)
+    }
+}
+
+/// Implements `break [n]` and `continue [n]`.
+fn loop_control(
+    shell: &mut Shell,
+    name: &str,
+    args: &[String],
+    io: &mut dyn ShellIo,
+    flow: fn(usize) -> Flow,
+) -> i32 {
+    let count = match args.first() {
+        None => 1,
+        Some(arg) => match arg.parse::<i64>() {
+            Ok(n) if n > 0 => n as usize,
+            Ok(_) => {
+                let message = format!("{}: loop count out of range", arg);
+                return shell.fail(ShellError::builtin(name, message), io);
+            }
+            Err(_) => {
+                let error = ShellError::Builtin {
+                    name: name.to_string(),
+                    message: format!("{}: numeric argument required", arg),
+                    status: 128,
+                };
+                return shell.fail(error, io);
+            }
+        },
+    };
+
+    if shell.loop_depth == 0 {
+        // bash complains but still succeeds.
+        let error = ShellError::Builtin {
+            name: name.to_string(),
+            message: "only meaningful in a `for', `while', or `until' loop".to_string(),
+            status: 0,
+        };
+        return shell.fail(error, io);
+    }
+
+    shell.flow = flow(count.min(shell.loop_depth));
+    0
+}
@@ -0,0 +1,53 @@
+/// ============================================
+/// HASH BUILTIN
+/// ============================================
+use super::Builtin;
+use crate::error::ShellError;
+use crate::shell_io::ShellIo;
+use crate::{Shell, write_line};
+
+/// `hash [-r] [name...]`: with no arguments lists the remembered command
+/// locations; `-r` forgets them all; names are looked up and remembered.
+pub struct Hash;
+
+impl Builtin for Hash {
+    fn name(&self) -> &'static str {
+        "hash"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "hash [-r] [name ...]"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        let mut names = args;
+        if names.first().is_some_and(|arg| arg == "-r") {
+            shell.commands.borrow_mut().clear();
+            names = &names[1..];
+        } else if names.is_empty() {
+            let commands = shell.commands.borrow();
+            let list = commands.list();
+            if list.is_empty() {
+                write_line(io.stdout(), "hash: hash table empty");
+            } else {
+                write_line(io.stdout(), "hits\tcommand");
+                for (hits, _, location) in list {
+                    write_line(io.stdout(), &format!("{:4}\t{}", hits, location));
+                }
+            }
+            return 0;
+        }
+
+        let mut status = 0;
+        for name in names {
+            if shell.builtins.contains(name) {
+                continue;
+            }
+            if shell.find_executable(name, None).is_none() {
+                let message = format!("{}: not found", name);
+                status = shell.fail(ShellError::builtin("hash", message), io);
+            }
+        }
+        status
+    }
+}
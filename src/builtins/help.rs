@@ -0,0 +1,43 @@
+/// ============================================
+/// HELP BUILTIN
+/// ============================================
+use super::Builtin;
+use crate::error::ShellError;
+use crate::shell_io::ShellIo;
+use crate::{Shell, write_line};
+
+/// `help [name...]`: prints the usage of the named builtins, or of all of
+/// them.
+pub struct Help;
+
+impl Builtin for Help {
+    fn name(&self) -> &'static str {
+        "help"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "help [name ...]"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        if args.is_empty() {
+            let synopses: Vec<_> = shell.builtins.iter().map(|b| b.synopsis()).collect();
+            for synopsis in synopses {
+                write_line(io.stdout(), synopsis);
+            }
+            return 0;
+        }
+
+        let mut status = 0;
+        for name in args {
+            match shell.builtins.get(name) {
+                Some(builtin) => write_line(io.stdout(), builtin.synopsis()),
+                None => {
+                    let message = format!("no help topics match `{}'", name);
+                    status = shell.fail(ShellError::builtin("help", message), io);
+                }
+            }
+        }
+        status
+    }
+}
@@ -0,0 +1,73 @@
+/// ============================================
+/// HISTORY BUILTIN
+/// ============================================
+use super::Builtin;
+use crate::error::ShellError;
+use crate::json::Json;
+use crate::shell_io::ShellIo;
+use crate::{Shell, write_line};
+
+/// `history [n]` prints the lines typed at the prompt, numbered from 1,
+/// or only the last `n` of them, reading no more of the history file than
+/// those need. With `--json` prints an array with an
+/// object per line, such as `{"number":1,"command":"ls"}`.
+pub struct History;
+
+impl Builtin for History {
+    fn name(&self) -> &'static str {
+        "history"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "history [--json] [n]"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        let (json, args) = match args.split_first() {
+            Some((option, rest)) if option == "--json" => (true, rest),
+            _ => (false, args),
+        };
+
+        let wanted = match args {
+            [] => None,
+            [n] => match n.parse::<usize>() {
+                Ok(n) => Some(n),
+                Err(_) => {
+                    let message = format!("{}: numeric argument required", n);
+                    return shell.fail(ShellError::usage("history", message), io);
+                }
+            },
+            _ => return shell.fail(ShellError::usage("history", "too many arguments"), io),
+        };
+
+        let history = &mut shell.history;
+        let loaded = history.entries().len();
+        history.load_older(wanted.map_or(usize::MAX, |n| n.saturating_sub(loaded)));
+        let unread = history.unread_count();
+        let entries = history.entries();
+        let count = wanted.map_or(entries.len(), |n| n.min(entries.len()));
+
+        let first = entries.len() - count;
+        let numbered = entries[first..]
+            .iter()
+            .enumerate()
+            .map(|(offset, command)| (unread + first + offset + 1, command));
+
+        if json {
+            let items = numbered
+                .map(|(number, command)| {
+                    Json::Object(vec![
+                        ("number", Json::Number(number as i64)),
+                        ("command", command.as_str().into()),
+                    ])
+                })
+                .collect();
+            write_line(io.stdout(), &Json::Array(items).to_string());
+        } else {
+            for (number, command) in numbered {
+                write_line(io.stdout(), &format!("{:>5}  {}", number, command));
+            }
+        }
+        0
+    }
+}
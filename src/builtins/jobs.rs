@@ -0,0 +1,412 @@
+/// ============================================
+/// JOB CONTROL BUILTINS
+/// ============================================
+#[cfg(feature = "line-editor")]
+use std::io::IsTerminal;
+
+use super::Builtin;
+use crate::error::ShellError;
+use crate::jobs::JobState;
+use crate::json::Json;
+use crate::shell_io::ShellIo;
+use crate::signals::{SIGNALS, signal_number};
+use crate::{Shell, write_line};
+
+/// Resolves the job specifier `spec` for `name`, or the current job when
+/// there is none. `fg` and `bg` also take a bare job number.
+fn resolve_job(
+    shell: &Shell,
+    name: &str,
+    spec: Option<&String>,
+    io: &mut dyn ShellIo,
+) -> Result<usize, i32> {
+    let result = match spec {
+        Some(spec) if spec.bytes().all(|b| b.is_ascii_digit()) && name != "disown" => {
+            shell.jobs.resolve(&format!("%{}", spec))
+        }
+        Some(spec) => shell.jobs.resolve(spec),
+        None => shell
+            .jobs
+            .resolve("%+")
+            .map_err(|_| "current: no such job".to_string()),
+    };
+    result.map_err(|message| shell.fail(ShellError::builtin(name, message), io))
+}
+
+/// `jobs [-l | -p] [jobspec ...]` lists the jobs started with `&`, or
+/// only those given, with their state. `-l` adds process ids and `-p`
+/// prints only the process id of each job. With `--json` prints an array
+/// with an object per job, such as
+/// `{"id":1,"state":"running","status":null,"pids":[42],"command":"sleep 9"}`.
+/// Finished jobs are forgotten once listed. `jobs --watch` shows the jobs
+/// full-screen as they change instead, and can bring one to the
+/// foreground or end it with a key press (see `Shell::watch_jobs`).
+pub struct Jobs;
+
+impl Builtin for Jobs {
+    fn name(&self) -> &'static str {
+        "jobs"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "jobs [--json] [-l | -p] [jobspec ...] or jobs --watch"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        if args.first().is_some_and(|option| option == "--watch") {
+            return watch(shell, io);
+        }
+        let (json, args) = match args.split_first() {
+            Some((option, rest)) if option == "--json" => (true, rest),
+            _ => (false, args),
+        };
+        let (format, specs) = match args.split_first() {
+            Some((option, rest)) if option == "-l" || option == "-p" => (option.as_str(), rest),
+            _ => ("", args),
+        };
+
+        shell.update_jobs();
+        let ids: Vec<usize> = if specs.is_empty() {
+            shell.jobs.iter().map(|job| job.id).collect()
+        } else {
+            let mut ids = Vec::new();
+            for spec in specs {
+                match shell.jobs.resolve(spec) {
+                    Ok(id) => ids.push(id),
+                    Err(message) => return shell.fail(ShellError::builtin("jobs", message), io),
+                }
+            }
+            ids
+        };
+
+        let mut items = Vec::new();
+        for &id in &ids {
+            let Some(job) = shell.jobs.get(id) else {
+                continue;
+            };
+            let pids: Vec<u32> = job.pids().collect();
+            if json {
+                let status = match job.state {
+                    JobState::Done(status) => Json::Number(status.into()),
+                    _ => Json::Null,
+                };
+                items.push(Json::Object(vec![
+                    ("id", Json::Number(id as i64)),
+                    ("state", job.state.describe().to_lowercase().into()),
+                    ("status", status),
+                    (
+                        "pids",
+                        Json::Array(pids.iter().map(|&pid| Json::Number(pid.into())).collect()),
+                    ),
+                    ("command", job.command.as_str().into()),
+                ]));
+                continue;
+            }
+
+            let line = match format {
+                "-p" => pids[0].to_string(),
+                "-l" => {
+                    let line = shell.jobs.describe(id).unwrap_or_default();
+                    let (head, tail) = line.split_at(line.find(' ').unwrap_or(0));
+                    format!("{} {:>5}{}", head, pids[0], tail)
+                }
+                _ => shell.jobs.describe(id).unwrap_or_default(),
+            };
+            write_line(io.stdout(), &line);
+        }
+        if json {
+            write_line(io.stdout(), &Json::Array(items).to_string());
+        }
+
+        for id in ids {
+            if let Some(JobState::Done(_)) = shell.jobs.get(id).map(|job| job.state) {
+                shell.jobs.remove(id);
+            }
+        }
+        0
+    }
+}
+
+/// Runs `jobs --watch`, then brings the job picked in it to the
+/// foreground as `fg` does. It needs the line editor's terminal handling.
+fn watch(shell: &mut Shell, io: &mut dyn ShellIo) -> i32 {
+    #[cfg(feature = "line-editor")]
+    if std::io::stdin().is_terminal() {
+        return match shell.watch_jobs() {
+            Ok(Some(id)) => {
+                if let Some(job) = shell.jobs.get(id) {
+                    write_line(io.stdout(), &job.command);
+                }
+                shell.foreground(id)
+            }
+            Ok(None) => 0,
+            Err(e) => shell.fail(ShellError::builtin("jobs", e.to_string()), io),
+        };
+    }
+    #[cfg(feature = "line-editor")]
+    let message = "--watch: stdin is not a terminal";
+    #[cfg(not(feature = "line-editor"))]
+    let message = "--watch: the shell was built without the line editor";
+    shell.fail(ShellError::builtin("jobs", message), io)
+}
+
+/// `fg [jobspec]` continues a job in the foreground, the current job if
+/// none is given, and waits for it.
+pub struct Fg;
+
+impl Builtin for Fg {
+    fn name(&self) -> &'static str {
+        "fg"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "fg [jobspec]"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        let id = match resolve_job(shell, "fg", args.first(), io) {
+            Ok(id) => id,
+            Err(status) => return status,
+        };
+        if let Some(job) = shell.jobs.get(id) {
+            write_line(io.stdout(), &job.command);
+        }
+        shell.foreground(id)
+    }
+}
+
+/// `bg [jobspec ...]` continues stopped jobs in the background, the
+/// current job if none is given.
+pub struct Bg;
+
+impl Builtin for Bg {
+    fn name(&self) -> &'static str {
+        "bg"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "bg [jobspec ...]"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        let specs: Vec<Option<&String>> = if args.is_empty() {
+            vec![None]
+        } else {
+            args.iter().map(Some).collect()
+        };
+
+        let mut status = 0;
+        for spec in specs {
+            let id = match resolve_job(shell, "bg", spec, io) {
+                Ok(id) => id,
+                Err(error) => {
+                    status = error;
+                    continue;
+                }
+            };
+            let Some(job) = shell.jobs.get_mut(id) else {
+                continue;
+            };
+            let resumed = match job.state {
+                JobState::Stopped => job.resume().map_err(|e| e.to_string()),
+                _ => Err(format!("job {} already in background", id)),
+            };
+            let command = job.command.clone();
+            if let Err(message) = resumed {
+                shell.fail(ShellError::builtin("bg", message), io);
+                continue;
+            }
+            shell.jobs.make_current(id);
+            let line = format!("[{}]{} {} &", id, shell.jobs.mark(id), command);
+            write_line(io.stdout(), &line);
+        }
+        status
+    }
+}
+
+/// `wait [-n] [jobspec | pid ...]` waits for the given jobs, or every job,
+/// to finish, and gives the status of the last one given. With `-n` it
+/// waits for only the first of them to finish and gives its status.
+pub struct Wait;
+
+impl Builtin for Wait {
+    fn name(&self) -> &'static str {
+        "wait"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "wait [-n] [jobspec | pid ...]"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        let (any, specs) = match args.split_first() {
+            Some((option, rest)) if option == "-n" => (true, rest),
+            _ => (false, args),
+        };
+
+        let mut ids = Vec::new();
+        let mut status = 0;
+        for spec in specs {
+            let id = if spec.starts_with('%') {
+                shell.jobs.resolve(spec)
+            } else {
+                match spec.parse() {
+                    Ok(pid) => shell
+                        .jobs
+                        .find_pid(pid)
+                        .ok_or_else(|| format!("pid {} is not a child of this shell", pid)),
+                    Err(_) => Err(format!("`{}': not a pid or valid job spec", spec)),
+                }
+            };
+            match id {
+                Ok(id) => ids.push(id),
+                Err(message) => {
+                    shell.fail(ShellError::builtin("wait", message), io);
+                    status = 127;
+                }
+            }
+        }
+
+        if specs.is_empty() {
+            ids = shell.jobs.iter().map(|job| job.id).collect();
+            if ids.is_empty() {
+                return if any { 127 } else { 0 };
+            }
+        } else if ids.is_empty() {
+            return status;
+        }
+
+        if any {
+            return match shell.wait_for_jobs(&ids, true) {
+                Some((_, status)) => status,
+                None => 130,
+            };
+        }
+
+        for id in ids {
+            status = match shell.wait_for_jobs(&[id], false) {
+                Some((_, status)) => status,
+                None => return 130,
+            };
+        }
+        if specs.is_empty() { 0 } else { status }
+    }
+}
+
+/// `kill [-s sig | -sig] pid | jobspec ...` sends a signal, SIGTERM unless
+/// another is given, to processes or to every process of a job. `kill -l`
+/// lists the signal names.
+pub struct Kill;
+
+impl Builtin for Kill {
+    fn name(&self) -> &'static str {
+        "kill"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "kill [-s sig | -sig] pid | jobspec ... or kill -l"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        let (signal, targets) = match args {
+            [option, ..] if option == "-l" => {
+                for (name, number) in SIGNALS {
+                    write_line(io.stdout(), &format!("{:>2}) SIG{}", number, name));
+                }
+                return 0;
+            }
+            [option, name, rest @ ..] if option == "-s" || option == "-n" => (name.as_str(), rest),
+            [option, rest @ ..] if option.len() > 1 && option.starts_with('-') => {
+                (&option[1..], rest)
+            }
+            _ => ("TERM", args),
+        };
+
+        let Some(signal) = signal_number(signal) else {
+            let message = format!("{}: invalid signal specification", signal);
+            return shell.fail(ShellError::builtin("kill", message), io);
+        };
+        if targets.is_empty() {
+            return shell.fail(ShellError::usage("kill", "pid or job spec required"), io);
+        }
+
+        let mut status = 0;
+        for target in targets {
+            if let Err(message) = send_signal(shell, target, signal) {
+                status = shell.fail(ShellError::builtin("kill", message), io);
+            }
+        }
+        status
+    }
+}
+
+/// Sends `signal` to the job or process `target` names.
+fn send_signal(shell: &mut Shell, target: &str, signal: i32) -> Result<(), String> {
+    if target.starts_with('%') {
+        let id = shell.jobs.resolve(target)?;
+        return signal_job(shell, id, target, signal);
+    }
+
+    let pid: i32 = target
+        .parse()
+        .map_err(|_| format!("{}: arguments must be process or job IDs", target))?;
+    #[cfg(unix)]
+    if unsafe { libc::kill(pid, signal) } != 0 {
+        let error = std::io::Error::last_os_error();
+        return Err(format!("({}) - {}", pid, error));
+    }
+    // Windows can only end the processes the shell started.
+    #[cfg(windows)]
+    match shell.jobs.find_pid(pid as u32) {
+        Some(id) => signal_job(shell, id, target, signal)?,
+        None => return Err(format!("({}) - not a job of this shell", pid)),
+    }
+    Ok(())
+}
+
+fn signal_job(shell: &mut Shell, id: usize, target: &str, signal: i32) -> Result<(), String> {
+    match shell.jobs.get_mut(id) {
+        Some(job) => job.signal(signal).map_err(|e| format!("{}: {}", target, e)),
+        None => Err(format!("{}: no such job", target)),
+    }
+}
+
+/// `disown [-a] [jobspec ...]` forgets jobs without ending them, the
+/// current job if none is given and every job with `-a`.
+pub struct Disown;
+
+impl Builtin for Disown {
+    fn name(&self) -> &'static str {
+        "disown"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "disown [-a] [jobspec ...]"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        if args.first().is_some_and(|option| option == "-a") {
+            let ids: Vec<usize> = shell.jobs.iter().map(|job| job.id).collect();
+            for id in ids {
+                shell.jobs.remove(id);
+            }
+            return 0;
+        }
+
+        let specs: Vec<Option<&String>> = if args.is_empty() {
+            vec![None]
+        } else {
+            args.iter().map(Some).collect()
+        };
+        let mut status = 0;
+        for spec in specs {
+            match resolve_job(shell, "disown", spec, io) {
+                Ok(id) => {
+                    shell.jobs.remove(id);
+                }
+                Err(error) => status = error,
+            }
+        }
+        status
+    }
+}
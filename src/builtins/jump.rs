@@ -0,0 +1,184 @@
+/// ============================================
+/// J BUILTIN
+/// ============================================
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::Builtin;
+use super::cd::Cd;
+use crate::error::ShellError;
+use crate::shell_io::ShellIo;
+use crate::startup::home_dir;
+use crate::{Shell, osstr, write_line};
+
+/// The database of visited directories, in the home directory next to
+/// the shell's other files.
+const DATABASE_FILE: &str = ".myshell_dirs";
+
+/// Once the ranks add up to more than this, they are all scaled down so
+/// that directories no longer visited are eventually forgotten.
+const MAX_TOTAL_RANK: f64 = 9000.0;
+
+/// `j query...` goes to the visited directory that best matches the
+/// query, ranking directories by how often and how recently they were
+/// visited. A directory matches when its path contains each query word in
+/// order; case is ignored unless a word has capitals. `j -l query...` lists
+/// the matches with their scores instead, and `j -l` lists everything.
+pub struct Jump;
+
+impl Builtin for Jump {
+    fn name(&self) -> &'static str {
+        "j"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "j [-l] query ..."
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        let (list, query) = match args.split_first() {
+            Some((option, query)) if option == "-l" => (true, query),
+            _ => (false, args),
+        };
+        if !list && query.is_empty() {
+            return shell.fail(ShellError::usage("j", "usage: j [-l] query ..."), io);
+        }
+
+        let now = now();
+        let mut matches: Vec<(f64, PathBuf)> = load()
+            .into_iter()
+            .filter(|entry| matches_query(&entry.path, query))
+            .map(|entry| (entry.frecency(now), entry.path))
+            .collect();
+        matches.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        if list {
+            for (score, path) in matches.iter().rev() {
+                let line = format!("{:<10.1} {}", score, osstr::from_os(path.as_os_str()));
+                write_line(io.stdout(), &line);
+            }
+            return 0;
+        }
+
+        match matches.into_iter().find(|(_, path)| path.is_dir()) {
+            Some((_, path)) => Cd.execute(shell, &[osstr::from_os(path.as_os_str())], io),
+            None => {
+                let message = format!("{}: no matching directory", query.join(" "));
+                shell.fail(ShellError::builtin("j", message), io)
+            }
+        }
+    }
+}
+
+/// A directory in the database.
+struct Entry {
+    path: PathBuf,
+    rank: f64,
+    /// When it was last visited, in seconds since the Unix epoch.
+    time: u64,
+}
+
+impl Entry {
+    /// The rank weighted by how long ago the directory was last visited.
+    fn frecency(&self, now: u64) -> f64 {
+        let age = now.saturating_sub(self.time);
+        let weight = match age {
+            0..3_600 => 4.0,
+            3_600..86_400 => 2.0,
+            86_400..604_800 => 0.5,
+            _ => 0.25,
+        };
+        self.rank * weight
+    }
+}
+
+/// Records a visit to `dir`. Errors are ignored: the database is only a
+/// convenience and must not get in the way of `cd`.
+pub(crate) fn record_visit(dir: &Path) {
+    let Some(database) = database_path() else {
+        return;
+    };
+
+    let now = now();
+    let mut entries = load();
+    match entries.iter_mut().find(|entry| entry.path == dir) {
+        Some(entry) => {
+            entry.rank += 1.0;
+            entry.time = now;
+        }
+        None => entries.push(Entry {
+            path: dir.to_path_buf(),
+            rank: 1.0,
+            time: now,
+        }),
+    }
+
+    if entries.iter().map(|entry| entry.rank).sum::<f64>() > MAX_TOTAL_RANK {
+        for entry in &mut entries {
+            entry.rank *= 0.99;
+        }
+        entries.retain(|entry| entry.rank >= 1.0);
+    }
+
+    let contents: String = entries
+        .iter()
+        .map(|entry| {
+            let path = osstr::from_os(entry.path.as_os_str());
+            format!("{}|{}|{}\n", path, entry.rank, entry.time)
+        })
+        .collect();
+    let _ = fs::write(database, contents);
+}
+
+fn database_path() -> Option<PathBuf> {
+    home_dir().map(|home| home.join(DATABASE_FILE))
+}
+
+/// The database's entries, one `path|rank|time` line each. The path may
+/// itself contain `|`, so lines are split from the right.
+fn load() -> Vec<Entry> {
+    let Some(contents) = database_path().and_then(|path| fs::read_to_string(path).ok()) else {
+        return Vec::new();
+    };
+
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.rsplitn(3, '|');
+            let time = fields.next()?.parse().ok()?;
+            let rank = fields.next()?.parse().ok()?;
+            let path = PathBuf::from(osstr::to_os(fields.next()?));
+            Some(Entry { path, rank, time })
+        })
+        .collect()
+}
+
+/// Whether `path` contains each word of `query` in order.
+fn matches_query(path: &Path, query: &[String]) -> bool {
+    let ignore_case = query
+        .iter()
+        .all(|word| !word.chars().any(char::is_uppercase));
+    let fold = |text: &str| {
+        if ignore_case {
+            text.to_lowercase()
+        } else {
+            text.to_string()
+        }
+    };
+
+    let path = fold(&osstr::from_os(path.as_os_str()));
+    let mut rest = path.as_str();
+    query.iter().all(|word| {
+        let word = fold(word);
+        rest.find(&word)
+            .map(|at| rest = &rest[at + word.len()..])
+            .is_some()
+    })
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
@@ -0,0 +1,138 @@
+/// ============================================
+/// BUILTIN COMMANDS
+/// ============================================
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use crate::Shell;
+use crate::shell_io::ShellIo;
+
+mod abbr;
+mod alias;
+mod calc;
+mod caller;
+mod cd;
+mod complete;
+mod declare;
+mod dirs;
+mod echo;
+mod exit;
+mod export;
+mod flow;
+mod hash;
+mod help;
+#[cfg(feature = "history")]
+mod history;
+mod jobs;
+mod jump;
+mod pathconv;
+mod printf;
+mod pwd;
+mod record;
+mod set;
+mod source;
+mod state;
+#[cfg(feature = "line-editor")]
+mod stty;
+mod type_;
+mod version;
+
+pub(crate) use complete::CompletionSpec;
+
+/// The special builtins of POSIX, whose errors make a non-interactive
+/// shell in POSIX mode exit.
+const SPECIAL_BUILTINS: &[&str] = &[
+    "break", ":", ".", "continue", "eval", "exec", "exit", "export", "readonly", "return", "set",
+    "shift", "times", "trap", "unset",
+];
+
+/// Whether `name` is a POSIX special builtin.
+pub(crate) fn is_special(name: &str) -> bool {
+    SPECIAL_BUILTINS.contains(&name)
+}
+
+/// A command the shell runs itself instead of starting a program.
+pub trait Builtin {
+    fn name(&self) -> &'static str;
+
+    /// The usage line `help` shows, such as `cd [dir]`.
+    fn synopsis(&self) -> &'static str;
+
+    /// Runs the builtin and returns its exit status.
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32;
+}
+
+/// The builtins a shell knows, by name.
+#[derive(Clone, Default)]
+pub struct Registry {
+    builtins: BTreeMap<&'static str, Rc<dyn Builtin>>,
+}
+
+impl Registry {
+    /// A registry holding every builtin this shell provides.
+    pub fn standard() -> Self {
+        let mut registry = Self::default();
+        registry.register(Rc::new(source::Dot));
+        registry.register(Rc::new(abbr::Abbr));
+        registry.register(Rc::new(alias::Alias));
+        registry.register(Rc::new(jobs::Bg));
+        registry.register(Rc::new(calc::Calc));
+        registry.register(Rc::new(caller::Caller));
+        registry.register(Rc::new(cd::Cd));
+        registry.register(Rc::new(cd::Cdh));
+        registry.register(Rc::new(complete::Complete));
+        registry.register(Rc::new(declare::Declare));
+        registry.register(Rc::new(declare::Local));
+        registry.register(Rc::new(dirs::Dirs));
+        registry.register(Rc::new(jobs::Disown));
+        registry.register(Rc::new(echo::Echo));
+        registry.register(Rc::new(exit::Exit));
+        registry.register(Rc::new(export::Export));
+        registry.register(Rc::new(jobs::Fg));
+        registry.register(Rc::new(flow::Break));
+        registry.register(Rc::new(flow::Continue));
+        registry.register(Rc::new(flow::Return));
+        registry.register(Rc::new(hash::Hash));
+        registry.register(Rc::new(help::Help));
+        #[cfg(feature = "history")]
+        registry.register(Rc::new(history::History));
+        registry.register(Rc::new(jobs::Jobs));
+        registry.register(Rc::new(jump::Jump));
+        registry.register(Rc::new(jobs::Kill));
+        registry.register(Rc::new(pathconv::Pathconv));
+        registry.register(Rc::new(dirs::Popd));
+        registry.register(Rc::new(printf::Printf));
+        registry.register(Rc::new(dirs::Pushd));
+        registry.register(Rc::new(pwd::Pwd));
+        registry.register(Rc::new(record::Record));
+        registry.register(Rc::new(set::Set));
+        registry.register(Rc::new(source::Source));
+        registry.register(Rc::new(state::State));
+        #[cfg(feature = "line-editor")]
+        registry.register(Rc::new(stty::Stty));
+        registry.register(Rc::new(type_::Type));
+        registry.register(Rc::new(alias::Unalias));
+        registry.register(Rc::new(export::Unset));
+        registry.register(Rc::new(version::Version));
+        registry.register(Rc::new(jobs::Wait));
+        registry
+    }
+
+    /// Adds `builtin`, replacing any builtin of the same name.
+    pub fn register(&mut self, builtin: Rc<dyn Builtin>) {
+        self.builtins.insert(builtin.name(), builtin);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Rc<dyn Builtin>> {
+        self.builtins.get(name).cloned()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.builtins.contains_key(name)
+    }
+
+    /// Every builtin, sorted by name.
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Builtin> {
+        self.builtins.values().map(|builtin| builtin.as_ref())
+    }
+}
@@ -0,0 +1,57 @@
+/// ============================================
+/// PATHCONV BUILTIN
+/// ============================================
+use super::Builtin;
+use crate::error::ShellError;
+use crate::shell_io::ShellIo;
+use crate::{Shell, paths, write_line};
+
+/// `pathconv [-u|-w|-m] path...`, in the manner of `wslpath`: converts
+/// to a WSL path (`-u`, the default), a Windows path (`-w`) or a Windows
+/// path with forward slashes (`-m`). Paths that are already in the
+/// requested form, or have no equivalent, are printed unchanged.
+pub struct Pathconv;
+
+impl Builtin for Pathconv {
+    fn name(&self) -> &'static str {
+        "pathconv"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "pathconv [-u|-w|-m] path ..."
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        let mut args = args;
+        let mode = match args.first().map(String::as_str) {
+            Some(flag @ ("-u" | "-w" | "-m")) => {
+                args = &args[1..];
+                flag
+            }
+            Some(flag) if flag.starts_with('-') && flag.len() > 1 => {
+                let message = format!("{}: invalid option", flag);
+                return shell.fail(ShellError::usage("pathconv", message), io);
+            }
+            _ => "-u",
+        };
+
+        if args.is_empty() {
+            let message = "usage: pathconv [-u|-w|-m] path...";
+            return shell.fail(ShellError::usage("pathconv", message), io);
+        }
+
+        for path in args {
+            // A Windows path asked for in Windows form only has its
+            // separators normalized.
+            let converted = match mode {
+                "-u" => paths::windows_to_unix(path),
+                "-w" => paths::unix_to_windows(path, '\\')
+                    .or_else(|| paths::windows_to_unix(path).map(|_| path.replace('/', "\\"))),
+                _ => paths::unix_to_windows(path, '/')
+                    .or_else(|| paths::windows_to_unix(path).map(|_| path.replace('\\', "/"))),
+            };
+            write_line(io.stdout(), converted.as_deref().unwrap_or(path));
+        }
+        0
+    }
+}
@@ -0,0 +1,530 @@
+/// ============================================
+/// PRINTF BUILTIN
+/// ============================================
+use super::Builtin;
+use crate::error::ShellError;
+use crate::parser::quote;
+use crate::shell_io::ShellIo;
+use crate::{Shell, osstr};
+
+/// `printf format [arg...]` writes the arguments as `format` says, reusing
+/// it until they are all used. Besides the usual conversions (`%s`, `%c`,
+/// `%d`, `%i`, `%o`, `%u`, `%x`, `%X`, `%e`, `%f`, `%g` and their upper-case
+/// forms), `%b` expands backslash escapes in its argument and `%q` quotes
+/// it so the shell reads it back as the same word.
+pub struct Printf;
+
+impl Builtin for Printf {
+    fn name(&self) -> &'static str {
+        "printf"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "printf format [arg ...]"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        let args = match args.split_first() {
+            Some((first, rest)) if first == "--" => rest,
+            _ => args,
+        };
+        let Some((format, args)) = args.split_first() else {
+            return shell.fail(
+                ShellError::usage("printf", "usage: printf format [arg ...]"),
+                io,
+            );
+        };
+
+        let mut formatter = Formatter {
+            args,
+            next: 0,
+            output: Vec::new(),
+            errors: Vec::new(),
+        };
+        // The format is used again while arguments remain, as long as it
+        // consumes some.
+        while formatter.run(format) && formatter.next > 0 && formatter.next < args.len() {}
+
+        let _ = io.stdout().write_all(&formatter.output);
+        let mut status = 0;
+        for message in formatter.errors {
+            status = shell.fail(ShellError::builtin("printf", message), io);
+        }
+        status
+    }
+}
+
+/// The largest field width or precision accepted. Padding is built in
+/// memory, so anything larger is refused rather than allowed to exhaust
+/// it and abort the shell.
+const MAX_FIELD: u64 = 1 << 24;
+
+/// A conversion specification: `%`, flags, width, precision and the
+/// conversion character.
+#[derive(Default)]
+struct Spec {
+    left: bool,
+    plus: bool,
+    space: bool,
+    alternate: bool,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+struct Formatter<'a> {
+    args: &'a [String],
+    /// The index of the next argument to use.
+    next: usize,
+    output: Vec<u8>,
+    errors: Vec<String>,
+}
+
+impl Formatter<'_> {
+    /// The next argument, or the empty string once they are used up.
+    fn next_arg(&mut self) -> &str {
+        let arg = self.args.get(self.next).map_or("", String::as_str);
+        self.next += 1;
+        arg
+    }
+
+    /// Formats the arguments the format uses once. Returns false if `\c`
+    /// in a `%b` argument ended the output.
+    fn run(&mut self, format: &str) -> bool {
+        let mut chars = format.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => {
+                    push_escape(&mut chars, &mut self.output, false);
+                }
+                '%' if chars.peek() == Some(&'%') => {
+                    chars.next();
+                    self.output.push(b'%');
+                }
+                '%' => {
+                    let mut spec = Spec::default();
+                    while let Some(&flag) = chars.peek() {
+                        match flag {
+                            '-' => spec.left = true,
+                            '+' => spec.plus = true,
+                            ' ' => spec.space = true,
+                            '#' => spec.alternate = true,
+                            '0' => spec.zero = true,
+                            _ => break,
+                        }
+                        chars.next();
+                    }
+                    if let Some(width) = self.number_in_format(&mut chars) {
+                        if width.unsigned_abs() > MAX_FIELD {
+                            self.errors.push(format!("{}: invalid field width", width));
+                            return false;
+                        }
+                        spec.left |= width < 0;
+                        spec.width = width.unsigned_abs() as usize;
+                    }
+                    if chars.peek() == Some(&'.') {
+                        chars.next();
+                        let precision = self.number_in_format(&mut chars).unwrap_or(0);
+                        if precision > MAX_FIELD as i64 {
+                            self.errors
+                                .push(format!("{}: invalid precision", precision));
+                            return false;
+                        }
+                        spec.precision = usize::try_from(precision).ok();
+                    }
+
+                    let Some(conversion) = chars.next() else {
+                        self.errors
+                            .push("`%': missing format character".to_string());
+                        return true;
+                    };
+                    if !self.convert(conversion, &spec) {
+                        return false;
+                    }
+                }
+                c => push_text(&mut self.output, c.encode_utf8(&mut [0; 4])),
+            }
+        }
+        true
+    }
+
+    /// Reads a width or precision: digits, or `*` to take it from the
+    /// next argument.
+    fn number_in_format(
+        &mut self,
+        chars: &mut std::iter::Peekable<std::str::Chars>,
+    ) -> Option<i64> {
+        if chars.peek() == Some(&'*') {
+            chars.next();
+            let arg = self.next_arg().to_string();
+            return Some(self.integer(&arg));
+        }
+        let mut digits = String::new();
+        while let Some(&c) = chars.peek()
+            && c.is_ascii_digit()
+        {
+            digits.push(c);
+            chars.next();
+        }
+        (!digits.is_empty()).then(|| digits.parse().unwrap_or(i64::MAX))
+    }
+
+    /// Formats the next argument for `conversion`. Returns false if `\c`
+    /// ended the output.
+    fn convert(&mut self, conversion: char, spec: &Spec) -> bool {
+        let arg = self.next_arg().to_string();
+        let text = match conversion {
+            's' => truncate(&arg, spec.precision).to_string(),
+            'c' => arg.chars().next().map(String::from).unwrap_or_default(),
+            'q' => shell_quote(&arg),
+            'b' => {
+                let mut bytes = Vec::new();
+                let mut chars = arg.chars().peekable();
+                let mut stopped = false;
+                while let Some(c) = chars.next() {
+                    if c != '\\' {
+                        push_text(&mut bytes, c.encode_utf8(&mut [0; 4]));
+                    } else if !push_escape(&mut chars, &mut bytes, true) {
+                        stopped = true;
+                        break;
+                    }
+                }
+                let bytes = truncate_bytes(&bytes, spec.precision);
+                self.pad(bytes, spec);
+                return !stopped;
+            }
+            'd' | 'i' => {
+                let n = self.integer(&arg);
+                let digits = n.unsigned_abs().to_string();
+                let sign = sign(n < 0, spec);
+                integer_digits(sign, "", digits, spec)
+            }
+            'o' | 'u' | 'x' | 'X' => {
+                let n = self.integer(&arg) as u64;
+                let (digits, prefix) = match conversion {
+                    'o' => (format!("{:o}", n), if spec.alternate { "0" } else { "" }),
+                    'u' => (n.to_string(), ""),
+                    'x' => (
+                        format!("{:x}", n),
+                        if spec.alternate && n != 0 { "0x" } else { "" },
+                    ),
+                    _ => (
+                        format!("{:X}", n),
+                        if spec.alternate && n != 0 { "0X" } else { "" },
+                    ),
+                };
+                integer_digits("", prefix, digits, spec)
+            }
+            'e' | 'E' | 'f' | 'F' | 'g' | 'G' => {
+                let n = self.float(&arg);
+                let body = format_float(n.abs(), conversion, spec);
+                let sign = sign(n.is_sign_negative() && !n.is_nan(), spec);
+                if spec.zero && !spec.left && n.is_finite() {
+                    let width = spec.width.saturating_sub(sign.len());
+                    format!("{}{:0>width$}", sign, body)
+                } else {
+                    format!("{}{}", sign, body)
+                }
+            }
+            _ => {
+                self.errors
+                    .push(format!("`{}': invalid format character", conversion));
+                return false;
+            }
+        };
+        self.pad(&osstr::to_bytes(&text), spec);
+        true
+    }
+
+    /// Writes `bytes` padded with spaces to the width, measured in
+    /// characters.
+    fn pad(&mut self, bytes: &[u8], spec: &Spec) {
+        let len = String::from_utf8_lossy(bytes).chars().count();
+        let padding = " ".repeat(spec.width.saturating_sub(len));
+        if spec.left {
+            self.output.extend_from_slice(bytes);
+            self.output.extend_from_slice(padding.as_bytes());
+        } else {
+            self.output.extend_from_slice(padding.as_bytes());
+            self.output.extend_from_slice(bytes);
+        }
+    }
+
+    /// Reads an integer argument: decimal, octal with a leading `0`, hex
+    /// with `0x`, or the code of the character after a leading quote.
+    fn integer(&mut self, arg: &str) -> i64 {
+        if let Some(c) = char_code(arg) {
+            return c as i64;
+        }
+        let trimmed = arg.trim_start();
+        let (negative, digits) = match trimmed.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+        };
+        let parsed = if let Some(hex) = digits
+            .strip_prefix("0x")
+            .or_else(|| digits.strip_prefix("0X"))
+        {
+            i64::from_str_radix(hex, 16)
+        } else if digits.len() > 1
+            && let Some(octal) = digits.strip_prefix('0')
+        {
+            i64::from_str_radix(octal, 8)
+        } else {
+            digits.parse()
+        };
+        match parsed {
+            Ok(n) if negative => n.wrapping_neg(),
+            Ok(n) => n,
+            Err(_) => {
+                if !arg.is_empty() {
+                    self.errors.push(format!("{}: invalid number", arg));
+                }
+                0
+            }
+        }
+    }
+
+    fn float(&mut self, arg: &str) -> f64 {
+        if let Some(c) = char_code(arg) {
+            return c as f64;
+        }
+        match arg.trim().parse() {
+            Ok(n) => n,
+            Err(_) => {
+                if !arg.is_empty() {
+                    self.errors.push(format!("{}: invalid number", arg));
+                }
+                0.0
+            }
+        }
+    }
+}
+
+/// The code of the character after a leading `'` or `"`, as in
+/// `printf %d "'A"`.
+fn char_code(arg: &str) -> Option<u32> {
+    let rest = arg.strip_prefix(['\'', '"'])?;
+    rest.chars().next().map(u32::from)
+}
+
+fn sign(negative: bool, spec: &Spec) -> &'static str {
+    match (negative, spec.plus, spec.space) {
+        (true, _, _) => "-",
+        (false, true, _) => "+",
+        (false, false, true) => " ",
+        _ => "",
+    }
+}
+
+/// An integer with its sign and prefix, padded with zeros to the
+/// precision or, with the `0` flag, to the width.
+fn integer_digits(sign: &str, prefix: &str, digits: String, spec: &Spec) -> String {
+    let digits = match spec.precision {
+        Some(0) if digits == "0" => String::new(),
+        Some(precision) => format!("{:0>precision$}", digits),
+        None if spec.zero && !spec.left => {
+            let width = spec.width.saturating_sub(sign.len() + prefix.len());
+            format!("{:0>width$}", digits)
+        }
+        None => digits,
+    };
+    format!("{}{}{}", sign, prefix, digits)
+}
+
+/// Formats a non-negative float as C's `%e`, `%f` or `%g` would.
+fn format_float(n: f64, conversion: char, spec: &Spec) -> String {
+    let upper = conversion.is_ascii_uppercase();
+    if !n.is_finite() {
+        let text = if n.is_nan() { "nan" } else { "inf" };
+        return if upper {
+            text.to_uppercase()
+        } else {
+            text.to_string()
+        };
+    }
+
+    let precision = spec.precision.unwrap_or(6);
+    let text = match conversion.to_ascii_lowercase() {
+        'e' => exponential(n, precision),
+        'f' => format!("{:.precision$}", n),
+        _ => {
+            let precision = precision.max(1);
+            let exponent = if n == 0.0 {
+                0
+            } else {
+                // The exponent after rounding to the precision.
+                let rounded = exponential(n, precision - 1);
+                rounded[rounded.find('e').unwrap_or(0) + 1..]
+                    .parse::<i32>()
+                    .unwrap_or(0)
+            };
+            let text = if exponent < -4 || exponent >= precision as i32 {
+                exponential(n, precision - 1)
+            } else {
+                let decimals = (precision as i32 - 1 - exponent) as usize;
+                format!("{:.decimals$}", n)
+            };
+            if spec.alternate {
+                text
+            } else {
+                strip_trailing_zeros(&text)
+            }
+        }
+    };
+    if upper { text.to_uppercase() } else { text }
+}
+
+/// `n` as `d.ddde+XX`, with at least two exponent digits as in C.
+fn exponential(n: f64, precision: usize) -> String {
+    let text = format!("{:.precision$e}", n);
+    let (mantissa, exponent) = text.split_once('e').unwrap_or((&text, "0"));
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+    let sign = if exponent < 0 { '-' } else { '+' };
+    format!("{}e{}{:02}", mantissa, sign, exponent.unsigned_abs())
+}
+
+/// Removes the zeros at the end of the fraction, and the point if none
+/// of it is left.
+fn strip_trailing_zeros(text: &str) -> String {
+    let (number, exponent) = match text.find('e') {
+        Some(at) => text.split_at(at),
+        None => (text, ""),
+    };
+    let number = if number.contains('.') {
+        number.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        number
+    };
+    format!("{}{}", number, exponent)
+}
+
+/// The first `precision` characters of `text`, or all of it.
+fn truncate(text: &str, precision: Option<usize>) -> &str {
+    match precision.and_then(|precision| text.char_indices().nth(precision)) {
+        Some((at, _)) => &text[..at],
+        None => text,
+    }
+}
+
+fn truncate_bytes(bytes: &[u8], precision: Option<usize>) -> &[u8] {
+    &bytes[..precision.map_or(bytes.len(), |precision| precision.min(bytes.len()))]
+}
+
+fn push_text(output: &mut Vec<u8>, text: &str) {
+    output.extend_from_slice(&osstr::to_bytes(text));
+}
+
+/// Writes the escape that follows a backslash. In a `%b` argument octal
+/// escapes are written `\0nnn` and `\c` ends the output, in which case
+/// this returns false.
+fn push_escape(
+    chars: &mut std::iter::Peekable<std::str::Chars>,
+    output: &mut Vec<u8>,
+    in_argument: bool,
+) -> bool {
+    let Some(c) = chars.next() else {
+        output.push(b'\\');
+        return true;
+    };
+    let byte = match c {
+        'a' => 0x07,
+        'b' => 0x08,
+        'e' | 'E' => 0x1b,
+        'f' => 0x0c,
+        'n' => b'\n',
+        'r' => b'\r',
+        't' => b'\t',
+        'v' => 0x0b,
+        '\\' => b'\\',
+        '"' if !in_argument => b'"',
+        '\'' if !in_argument => b'\'',
+        'c' if in_argument => return false,
+        '0'..='7' => {
+            let max_digits = if in_argument && c == '0' { 3 } else { 2 };
+            let mut value = if in_argument && c == '0' {
+                0
+            } else {
+                c.to_digit(8).unwrap_or(0)
+            };
+            for _ in 0..max_digits {
+                match chars.peek().and_then(|c| c.to_digit(8)) {
+                    Some(digit) => {
+                        value = value * 8 + digit;
+                        chars.next();
+                    }
+                    None => break,
+                }
+            }
+            value as u8
+        }
+        'x' => {
+            let mut value = None;
+            for _ in 0..2 {
+                match chars.peek().and_then(|c| c.to_digit(16)) {
+                    Some(digit) => {
+                        value = Some(value.unwrap_or(0) * 16 + digit);
+                        chars.next();
+                    }
+                    None => break,
+                }
+            }
+            match value {
+                Some(value) => value as u8,
+                None => {
+                    output.extend_from_slice(b"\\x");
+                    return true;
+                }
+            }
+        }
+        c => {
+            output.push(b'\\');
+            push_text(output, c.encode_utf8(&mut [0; 4]));
+            return true;
+        }
+    };
+    output.push(byte);
+    true
+}
+
+/// Quotes `word` for `%q` as bash does: special characters get a
+/// backslash, and a word that cannot be written that way, such as one
+/// holding a newline, is put in single quotes.
+fn shell_quote(word: &str) -> String {
+    if word.is_empty() || word.chars().any(|c| c.is_control()) {
+        return quote(word);
+    }
+    let mut quoted = String::with_capacity(word.len());
+    for (i, c) in word.chars().enumerate() {
+        let special = matches!(
+            c,
+            ' ' | '\t'
+                | '\''
+                | '"'
+                | '\\'
+                | '$'
+                | '`'
+                | '!'
+                | '&'
+                | '|'
+                | ';'
+                | '('
+                | ')'
+                | '<'
+                | '>'
+                | '*'
+                | '?'
+                | '['
+                | ']'
+                | '{'
+                | '}'
+                | '^'
+                | ','
+        ) || (i == 0 && matches!(c, '#' | '~'));
+        if special {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted
+}
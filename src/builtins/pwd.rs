@@ -0,0 +1,56 @@
+/// ============================================
+/// PWD BUILTIN
+/// ============================================
+use std::{env, fs};
+
+use super::Builtin;
+use crate::error::ShellError;
+use crate::shell_io::ShellIo;
+use crate::{Shell, osstr, paths, write_line};
+
+/// `pwd [-L | -P]` prints the working directory: the logical path, as
+/// `$PWD` records it through symbolic links, or with `-P` the physical one
+/// with every link resolved. The last option given wins.
+pub struct Pwd;
+
+impl Builtin for Pwd {
+    fn name(&self) -> &'static str {
+        "pwd"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "pwd [-L | -P]"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        let mut physical = false;
+        for arg in args {
+            match arg.as_str() {
+                "-L" => physical = false,
+                "-P" => physical = true,
+                _ => {
+                    let message = format!("{}: invalid option", arg);
+                    return shell.fail(ShellError::usage("pwd", message), io);
+                }
+            }
+        }
+
+        let cwd = if physical {
+            env::current_dir()
+                .and_then(fs::canonicalize)
+                .map(paths::without_verbatim)
+        } else {
+            shell.logical_cwd()
+        };
+        match cwd {
+            Ok(path) => {
+                write_line(io.stdout(), &osstr::from_os(path.as_os_str()));
+                0
+            }
+            Err(source) => {
+                let context = "pwd".to_string();
+                shell.fail(ShellError::Io { context, source }, io)
+            }
+        }
+    }
+}
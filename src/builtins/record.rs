@@ -0,0 +1,70 @@
+/// ============================================
+/// RECORD BUILTIN
+/// ============================================
+use std::path::Path;
+
+use super::Builtin;
+use crate::error::ShellError;
+use crate::record::Recording;
+use crate::shell_io::ShellIo;
+use crate::{Shell, osstr};
+
+/// `record start [-a] file` copies the session's terminal output to `file`
+/// until `record stop`; `-a` adds to the file instead of replacing it.
+pub struct Record;
+
+impl Builtin for Record {
+    fn name(&self) -> &'static str {
+        "record"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "record start [-a] file | record stop"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        match args.as_slice() {
+            ["start", rest @ ..] => {
+                let (append, file) = match rest {
+                    ["-a", file] => (true, file),
+                    [file] => (false, file),
+                    _ => return usage(shell, io),
+                };
+                if shell.recording.is_some() {
+                    let message = "already recording; use `record stop' first";
+                    return shell.fail(ShellError::builtin("record", message), io);
+                }
+
+                // Output still buffered belongs before the recording starts.
+                let _ = io.stdout().flush();
+                shell.flush_output();
+                match Recording::start(Path::new(&osstr::to_os(file)), append) {
+                    Ok(recording) => {
+                        shell.recording = Some(recording);
+                        0
+                    }
+                    Err(source) => {
+                        let context = format!("record: {}", file);
+                        shell.fail(ShellError::Io { context, source }, io)
+                    }
+                }
+            }
+            ["stop"] => match shell.recording.take() {
+                Some(recording) => {
+                    let _ = io.stdout().flush();
+                    shell.flush_output();
+                    recording.stop();
+                    0
+                }
+                None => shell.fail(ShellError::builtin("record", "not recording"), io),
+            },
+            _ => usage(shell, io),
+        }
+    }
+}
+
+fn usage(shell: &Shell, io: &mut dyn ShellIo) -> i32 {
+    let message = "usage: record start [-a] file | record stop";
+    shell.fail(ShellError::usage("record", message), io)
+}
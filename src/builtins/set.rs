@@ -0,0 +1,219 @@
+/// ============================================
+/// SET BUILTIN
+/// ============================================
+use std::env;
+
+use super::Builtin;
+use crate::error::ShellError;
+use crate::json::Json;
+use crate::notify::NotifyStyle;
+use crate::parser::{is_valid_name, quote};
+use crate::shell_io::ShellIo;
+use crate::variables::Value;
+use crate::{Shell, osstr, write_line};
+
+/// The options `set -o` knows.
+const OPTIONS: &[&str] = &["cdspell", "notifyafter", "notifystyle", "posix"];
+
+/// The state of an option: on or off, or the value of those set with
+/// `set -o name=value`.
+enum OptionState {
+    Flag(bool),
+    Value(String),
+}
+
+/// `set -o name` turns a shell option on and `set +o name` turns it off;
+/// `set -o name=value` gives an option that takes a value its value, and
+/// `set +o name` puts it back to its default. `set -o` alone lists the
+/// options and their state, and `set +o` lists them as the commands that
+/// restore it; `set --json -o` prints them as an array of objects such as
+/// `{"name":"posix","value":false}`. `set -- arg...`, or `set arg...`
+/// with an `arg` that is not an option, makes the args the positional
+/// parameters. `set` alone lists every variable as an assignment; with
+/// `--json` prints an array with an object per variable, such as
+/// `{"name":"HOME","value":"/root"}`, an array value being a JSON array.
+pub struct Set;
+
+impl Builtin for Set {
+    fn name(&self) -> &'static str {
+        "set"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "set [--json] [-o name[=value]] [+o name] [--] [arg ...]"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        match args {
+            [] => {
+                for (name, value) in variables(shell) {
+                    write_line(io.stdout(), &assignment(&name, &value));
+                }
+                return 0;
+            }
+            [option] if option == "--json" => {
+                let variables = variables(shell)
+                    .into_iter()
+                    .map(|(name, value)| {
+                        let value = match value {
+                            Value::Array(_) => {
+                                Json::Array(value.elements().into_iter().map(Json::from).collect())
+                            }
+                            Value::Scalar(value) | Value::Ref(value) => value.into(),
+                        };
+                        Json::Object(vec![("name", name.into()), ("value", value)])
+                    })
+                    .collect();
+                write_line(io.stdout(), &Json::Array(variables).to_string());
+                return 0;
+            }
+            [json, flag] if json == "--json" && flag == "-o" => {
+                let options = OPTIONS
+                    .iter()
+                    .map(|name| {
+                        let value = match option(shell, name) {
+                            OptionState::Flag(on) => on.into(),
+                            OptionState::Value(value) => value.into(),
+                        };
+                        Json::Object(vec![("name", (*name).into()), ("value", value)])
+                    })
+                    .collect();
+                write_line(io.stdout(), &Json::Array(options).to_string());
+                return 0;
+            }
+            _ => {}
+        }
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let on = match arg.as_str() {
+                "-o" => true,
+                "+o" => false,
+                "--" => {
+                    shell.positional = args.cloned().collect();
+                    return 0;
+                }
+                _ if !arg.starts_with(['-', '+']) => {
+                    shell.positional = std::iter::once(arg).chain(args).cloned().collect();
+                    return 0;
+                }
+                _ => {
+                    let message = format!("{}: invalid option", arg);
+                    return shell.fail(ShellError::usage("set", message), io);
+                }
+            };
+            let Some(name) = args.next() else {
+                for name in OPTIONS {
+                    let line = if on {
+                        let state = match option(shell, name) {
+                            OptionState::Flag(on) => (if on { "on" } else { "off" }).to_string(),
+                            OptionState::Value(value) => value,
+                        };
+                        format!("{:<15}\t{}", name, state)
+                    } else {
+                        restore_option(shell, name)
+                    };
+                    write_line(io.stdout(), &line);
+                }
+                return 0;
+            };
+            if let Err(message) = set_option(shell, name, on) {
+                return shell.fail(ShellError::usage("set", message), io);
+            }
+        }
+        0
+    }
+}
+
+/// Turns option `name` on or off, or, for `name=value`, gives it a value.
+fn set_option(shell: &mut Shell, name: &str, on: bool) -> Result<(), String> {
+    let (name, value) = match name.split_once('=') {
+        Some((name, value)) => (name, Some(value)),
+        None => (name, None),
+    };
+    match (name, value) {
+        ("cdspell", None) => shell.cdspell = on,
+        ("posix", None) => shell.set_posix(on),
+        ("notifyafter" | "notifystyle", _) if !on => match name {
+            "notifyafter" => shell.notify_after = 0,
+            _ => shell.notify_style = NotifyStyle::default(),
+        },
+        ("notifyafter" | "notifystyle", None) => {
+            return Err(format!("{}: option requires a value", name));
+        }
+        ("notifyafter", Some(value)) => {
+            shell.notify_after = value
+                .parse()
+                .map_err(|_| format!("{}: invalid number of seconds", value))?;
+        }
+        ("notifystyle", Some(value)) => {
+            shell.notify_style = NotifyStyle::from_name(value)
+                .ok_or_else(|| format!("{}: invalid style (bell or desktop)", value))?;
+        }
+        _ => return Err(format!("{}: invalid option name", name)),
+    }
+    Ok(())
+}
+
+fn option(shell: &Shell, name: &str) -> OptionState {
+    match name {
+        "cdspell" => OptionState::Flag(shell.cdspell),
+        "notifyafter" => OptionState::Value(shell.notify_after.to_string()),
+        "notifystyle" => OptionState::Value(shell.notify_style.name().to_string()),
+        "posix" => OptionState::Flag(shell.posix),
+        _ => OptionState::Flag(false),
+    }
+}
+
+/// The `set` command that gives option `name` its current state, as
+/// `set +o` lists it.
+fn restore_option(shell: &Shell, name: &str) -> String {
+    match option(shell, name) {
+        OptionState::Flag(on) => format!("set {}o {}", if on { '-' } else { '+' }, name),
+        OptionState::Value(value) => format!("set -o {}={}", name, value),
+    }
+}
+
+/// The commands that restore every option, as `state save` records them.
+pub(crate) fn option_definitions(shell: &Shell) -> Vec<String> {
+    OPTIONS
+        .iter()
+        .map(|name| restore_option(shell, name))
+        .collect()
+}
+
+/// Every variable the shell sees, with those of the environment it
+/// started with, sorted by name.
+fn variables(shell: &Shell) -> Vec<(String, Value)> {
+    let mut variables: Vec<(String, Value)> = shell
+        .vars
+        .iter()
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+    for (name, _) in env::vars_os() {
+        let name = osstr::from_os(&name);
+        if is_valid_name(&name)
+            && !shell.vars.contains_key(&name)
+            && let Some(value) = shell.env_var(&name)
+        {
+            variables.push((name, Value::Scalar(value)));
+        }
+    }
+    variables.sort_by(|a, b| a.0.cmp(&b.0));
+    variables
+}
+
+/// The assignment that gives `name` its value, as `set` lists it and
+/// `state save` records it: `name=([0]=a [1]=b)` for an array.
+pub(crate) fn assignment(name: &str, value: &Value) -> String {
+    match value {
+        Value::Scalar(value) | Value::Ref(value) => format!("{}={}", name, quote(value)),
+        Value::Array(items) => {
+            let elements: Vec<String> = items
+                .iter()
+                .map(|(index, value)| format!("[{}]={}", index, quote(value)))
+                .collect();
+            format!("{}=({})", name, elements.join(" "))
+        }
+    }
+}
@@ -0,0 +1,80 @@
+/// ============================================
+/// SOURCE BUILTIN
+/// ============================================
+use std::path::{Path, PathBuf};
+
+use super::Builtin;
+use crate::error::ShellError;
+use crate::shell_io::ShellIo;
+use crate::{Shell, osstr};
+
+/// `source file [arg ...]` reads and runs the commands in `file` in this
+/// shell, so the variables, functions, aliases and working directory they
+/// set remain afterwards, and gives the status of the last one. A name
+/// without a `/` is looked for in `PATH`, then in the working directory.
+/// Arguments become the positional parameters while the file runs.
+pub struct Source;
+
+impl Builtin for Source {
+    fn name(&self) -> &'static str {
+        "source"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "source file [arg ...]"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        source(shell, "source", args, io)
+    }
+}
+
+/// `. file [arg ...]`, the POSIX name for `source`.
+pub struct Dot;
+
+impl Builtin for Dot {
+    fn name(&self) -> &'static str {
+        "."
+    }
+
+    fn synopsis(&self) -> &'static str {
+        ". file [arg ...]"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        source(shell, ".", args, io)
+    }
+}
+
+fn source(shell: &mut Shell, name: &str, args: &[String], io: &mut dyn ShellIo) -> i32 {
+    let Some((file, args)) = args.split_first() else {
+        return shell.fail(ShellError::usage(name, "filename argument required"), io);
+    };
+    let path = find_file(shell, file);
+
+    let saved = (!args.is_empty()).then(|| std::mem::replace(&mut shell.positional, args.to_vec()));
+    let result = shell.source_file(&path);
+    if let Some(positional) = saved {
+        shell.positional = positional;
+    }
+
+    match result {
+        Ok(status) => status,
+        Err(e) => shell.fail(e, io),
+    }
+}
+
+/// Where `file` is: in the first `PATH` directory holding it when it has no
+/// `/`, otherwise, or when no directory does, as given.
+fn find_file(shell: &Shell, file: &str) -> PathBuf {
+    let path = PathBuf::from(osstr::to_os(file));
+    if file.contains('/') || (cfg!(windows) && file.contains('\\')) {
+        return path;
+    }
+    shell
+        .path_dirs()
+        .iter()
+        .map(|dir| Path::new(&osstr::to_os(dir)).join(&path))
+        .find(|candidate| candidate.is_file())
+        .unwrap_or(path)
+}
@@ -0,0 +1,132 @@
+/// ============================================
+/// STATE BUILTIN
+/// ============================================
+use std::fs;
+use std::path::Path;
+
+use super::Builtin;
+use super::{abbr, alias, complete, set};
+use crate::error::ShellError;
+use crate::parser::quote;
+use crate::shell_io::ShellIo;
+use crate::variables::Value;
+use crate::{Shell, osstr};
+
+/// Variables that describe the running process or the last command rather
+/// than the session, and so are not saved.
+const PROCESS_VARIABLES: &[&str] = &[
+    "BASH_LINENO",
+    "BASH_SOURCE",
+    "FUNCNAME",
+    "OLDPWD",
+    "PIPESTATUS",
+    "PWD",
+    "SHLVL",
+];
+
+/// `state save file` writes the session's variables, aliases, abbreviations,
+/// completion specs, working directory, directory stack and options to
+/// `file` as a script; `state load file` runs such a script to restore
+/// them, in this shell or a new one.
+pub struct State;
+
+impl Builtin for State {
+    fn name(&self) -> &'static str {
+        "state"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "state save|load file"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        let [action, file] = args else {
+            let message = "usage: state save|load file";
+            return shell.fail(ShellError::usage("state", message), io);
+        };
+        let path = Path::new(&osstr::to_os(file)).to_path_buf();
+
+        let result = match action.as_str() {
+            "save" => fs::write(&path, snapshot(shell)).map_err(|source| ShellError::Io {
+                context: format!("state: {}", file),
+                source,
+            }),
+            "load" => shell.source_file(&path).map(|_| ()),
+            _ => Err(ShellError::usage(
+                "state",
+                format!("{}: invalid action", action),
+            )),
+        };
+
+        match result {
+            Ok(()) => 0,
+            Err(e) => shell.fail(e, io),
+        }
+    }
+}
+
+/// The script that recreates the session: assignments for each variable,
+/// namerefs once their targets exist, export flags, environment variables
+/// that were unset, aliases, abbreviations and completion specs, then the working
+/// directory, the directory stack and the shell options.
+fn snapshot(shell: &Shell) -> String {
+    let mut names: Vec<&String> = shell
+        .vars
+        .keys()
+        .filter(|name| !PROCESS_VARIABLES.contains(&name.as_str()))
+        .collect();
+    names.sort();
+
+    let mut script = String::from("# Shell session state, written by `state save'.\n");
+    let mut refs = String::new();
+    for name in &names {
+        match &shell.vars[*name] {
+            Value::Ref(target) => {
+                refs.push_str(&format!("declare -n {}={}\n", name, quote(target)))
+            }
+            value => script.push_str(&format!("{}\n", set::assignment(name, value))),
+        }
+    }
+    script.push_str(&refs);
+
+    for name in names {
+        if shell.exported.contains(name) {
+            script.push_str(&format!("declare -x {}\n", name));
+        }
+    }
+    let mut unset: Vec<&String> = shell
+        .unset_env
+        .iter()
+        .filter(|name| !shell.vars.contains_key(*name))
+        .collect();
+    unset.sort();
+    for name in unset {
+        script.push_str(&format!("unset {}\n", name));
+    }
+
+    for (name, value) in &shell.aliases {
+        script.push_str(&format!("{}\n", alias::definition(name, value)));
+    }
+    for (name, expansion) in &shell.abbreviations {
+        script.push_str(&format!("{}\n", abbr::definition(name, expansion)));
+    }
+    for (name, spec) in &shell.completion_specs {
+        script.push_str(&format!("{}\n", complete::definition(name, spec)));
+    }
+
+    if let Ok(cwd) = shell.logical_cwd() {
+        script.push_str(&format!("cd {}\n", quote(&osstr::from_os(cwd.as_os_str()))));
+    }
+    // `pushd -n` adds each directory below the top, so the bottom goes
+    // first.
+    script.push_str("dirs -c\n");
+    for dir in shell.dir_stack.iter().rev() {
+        let dir = quote(&osstr::from_os(dir.as_os_str()));
+        script.push_str(&format!("pushd -n {} >/dev/null\n", dir));
+    }
+    // Options come last, as POSIX mode would reject the array assignments.
+    for line in set::option_definitions(shell) {
+        script.push_str(&format!("{}\n", line));
+    }
+    script
+}
@@ -0,0 +1,108 @@
+/// ============================================
+/// STTY BUILTIN
+/// ============================================
+use super::Builtin;
+use crate::error::ShellError;
+use crate::shell_io::ShellIo;
+use crate::terminal::Settings;
+use crate::{Shell, write_line};
+
+/// `stty` shows the terminal settings the shell looks after, in a form
+/// `stty` accepts back; `stty setting...` changes them. The settings are
+/// `[-]ixon` and `[-]ixoff` for flow control (`stty -ixon` keeps Ctrl-S
+/// from freezing the terminal), `[-]echo`, and `erase char`, where `char`
+/// is a character, `^X` or `^?`.
+pub struct Stty;
+
+impl Builtin for Stty {
+    fn name(&self) -> &'static str {
+        "stty"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "stty [[-]ixon] [[-]ixoff] [[-]echo] [erase char]"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        let io_error = |source| ShellError::Io {
+            context: "stty: standard input".to_string(),
+            source,
+        };
+        let mut settings = match Settings::get() {
+            Ok(settings) => settings,
+            Err(e) => return shell.fail(io_error(e), io),
+        };
+
+        if args.is_empty() {
+            write_line(io.stdout(), &describe(&settings));
+            return 0;
+        }
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let (name, on) = match arg.strip_prefix('-') {
+                Some(name) => (name, false),
+                None => (arg.as_str(), true),
+            };
+            match (name, on) {
+                ("ixon", _) => settings.ixon = on,
+                ("ixoff", _) => settings.ixoff = on,
+                ("echo", _) => settings.echo = on,
+                ("erase", true) => {
+                    let Some(value) = args.next() else {
+                        let message = "erase: missing argument";
+                        return shell.fail(ShellError::usage("stty", message), io);
+                    };
+                    match parse_char(value) {
+                        Some(erase) => settings.erase = erase,
+                        None => {
+                            let message = format!("{}: invalid erase character", value);
+                            return shell.fail(ShellError::usage("stty", message), io);
+                        }
+                    }
+                }
+                _ => {
+                    let message = format!("{}: invalid argument", arg);
+                    return shell.fail(ShellError::usage("stty", message), io);
+                }
+            }
+        }
+
+        match settings.set() {
+            Ok(()) => 0,
+            Err(e) => shell.fail(io_error(e), io),
+        }
+    }
+}
+
+/// The settings as `stty` arguments, such as `ixon -ixoff echo erase ^?`.
+fn describe(settings: &Settings) -> String {
+    let flag = |name: &str, on: bool| format!("{}{}", if on { "" } else { "-" }, name);
+    format!(
+        "{} {} {} erase {}",
+        flag("ixon", settings.ixon),
+        flag("ixoff", settings.ixoff),
+        flag("echo", settings.echo),
+        show_char(settings.erase)
+    )
+}
+
+/// A control character as `^X`, or `^?` for DEL.
+fn show_char(c: u8) -> String {
+    match c {
+        0x7f => "^?".to_string(),
+        0..0x20 => format!("^{}", (c + b'@') as char),
+        _ => (c as char).to_string(),
+    }
+}
+
+fn parse_char(text: &str) -> Option<u8> {
+    match text.as_bytes() {
+        [b'^', b'?'] => Some(0x7f),
+        [b'^', c] if c.is_ascii_alphabetic() || b"@[\\]^_".contains(c) => {
+            Some(c.to_ascii_uppercase() - b'@')
+        }
+        [c] if c.is_ascii() => Some(*c),
+        _ => None,
+    }
+}
@@ -0,0 +1,81 @@
+/// ============================================
+/// TYPE BUILTIN
+/// ============================================
+use super::Builtin;
+use crate::error::ShellError;
+use crate::json::Json;
+use crate::shell_io::ShellIo;
+use crate::{Shell, write_line};
+
+/// `type [--json] name...`: says how each name would be run. With
+/// `--json` prints an array with an object per name, such as
+/// `{"name":"ls","type":"file","path":"/bin/ls"}`, or `"value"` instead of
+/// `"path"` for an alias; `type` is `null` for names that were not found.
+pub struct Type;
+
+impl Builtin for Type {
+    fn name(&self) -> &'static str {
+        "type"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "type [--json] name [name ...]"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        if args.first().is_some_and(|arg| arg == "--json") {
+            return type_json(shell, &args[1..], io);
+        }
+
+        let mut status = 0;
+
+        for cmd in args {
+            if cmd.is_empty() {
+                continue;
+            }
+
+            if let Some(value) = shell.aliases.get(cmd) {
+                write_line(io.stdout(), &format!("{} is aliased to `{}'", cmd, value));
+            } else if shell.functions.contains_key(cmd) {
+                write_line(io.stdout(), &format!("{} is a function", cmd));
+            } else if shell.builtins.contains(cmd) {
+                write_line(io.stdout(), &format!("{} is a shell builtin", cmd));
+            } else if let Some(path) = shell.find_executable(cmd, None) {
+                write_line(io.stdout(), &format!("{} is {}", cmd, path));
+            } else {
+                status = shell.fail(ShellError::Unknown(cmd.clone()), io);
+            }
+        }
+
+        status
+    }
+}
+
+fn type_json(shell: &Shell, names: &[String], io: &mut dyn ShellIo) -> i32 {
+    let mut status = 0;
+    let mut entries = Vec::new();
+
+    for name in names {
+        let (kind, detail) = if let Some(value) = shell.aliases.get(name) {
+            (Some("alias"), Some(("value", value.clone())))
+        } else if shell.functions.contains_key(name) {
+            (Some("function"), None)
+        } else if shell.builtins.contains(name) {
+            (Some("builtin"), None)
+        } else if let Some(path) = shell.find_executable(name, None) {
+            (Some("file"), Some(("path", path)))
+        } else {
+            status = 1;
+            (None, None)
+        };
+
+        let mut entry = vec![("name", name.as_str().into()), ("type", kind.into())];
+        if let Some((key, detail)) = detail {
+            entry.push((key, detail.into()));
+        }
+        entries.push(Json::Object(entry));
+    }
+
+    write_line(io.stdout(), &Json::Array(entries).to_string());
+    status
+}
@@ -0,0 +1,33 @@
+/// ============================================
+/// VERSION BUILTIN
+/// ============================================
+use super::Builtin;
+use crate::error::ShellError;
+use crate::shell_io::ShellIo;
+use crate::{Shell, version, write_line};
+
+/// `version [--json]`: prints the shell's version, the git commit and
+/// target it was built for and its enabled cargo features.
+pub struct Version;
+
+impl Builtin for Version {
+    fn name(&self) -> &'static str {
+        "version"
+    }
+
+    fn synopsis(&self) -> &'static str {
+        "version [--json]"
+    }
+
+    fn execute(&self, shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        match args {
+            [] => write_line(io.stdout(), &version::describe()),
+            [flag] if flag == "--json" => write_line(io.stdout(), &version::to_json().to_string()),
+            _ => {
+                let message = "usage: version [--json]";
+                return shell.fail(ShellError::usage("version", message), io);
+            }
+        }
+        0
+    }
+}
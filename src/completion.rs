@@ -0,0 +1,328 @@
+/// ============================================
+/// TAB COMPLETION
+/// ============================================
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::builtins::CompletionSpec;
+use crate::editor::starts_command;
+use crate::lscolors::{FileKind, LsColors};
+use crate::startup::home_dir;
+use crate::variables::Value;
+use crate::{Shell, osstr, paths};
+
+/// The directory of per-command completion files, in the home directory.
+/// `name` in it is sourced the first time an argument of `name` is
+/// completed, and is expected to run `complete` for it.
+const COMPLETIONS_DIR: &str = ".myshell_completions";
+
+/// Variables set for a `complete -F` function while it runs.
+const COMPLETION_VARIABLES: &[&str] = &["COMP_WORDS", "COMP_CWORD", "COMP_LINE", "COMP_POINT"];
+
+impl Shell {
+    /// Names of the executables in `dirs`, without their PATHEXT extension
+    /// on Windows. Runs on the background scan thread as well as the shell's.
+    pub(crate) fn scan_commands(dirs: &[String]) -> Vec<String> {
+        #[cfg(windows)]
+        let extensions = Self::path_extensions();
+
+        let mut names = Vec::new();
+        for dir in dirs {
+            if let Ok(entries) = std::fs::read_dir(dir) {
+                for entry in entries.flatten() {
+                    let file_name = osstr::from_os(&entry.file_name());
+                    #[cfg(windows)]
+                    let name = Self::strip_executable_extension(&file_name, &extensions)
+                        .map(String::from)
+                        .unwrap_or(file_name);
+                    #[cfg(unix)]
+                    let name = file_name;
+
+                    if Self::is_executable(&entry.path()) {
+                        names.push(name);
+                    }
+                }
+            }
+        }
+        names
+    }
+
+    /// Completions for `partial`, the word starting at byte `start` of the
+    /// edit buffer: command names in command position, file names after
+    /// it or when the word is a path, unless `complete` says otherwise for
+    /// the command.
+    fn find_completions(&mut self, start: usize, partial: &str) -> Vec<Completion> {
+        let before = &self.editor.buffer[..start];
+        let command_position = starts_command(before);
+        let words = command_words(before);
+
+        let mut completions = if command_position && !partial.contains(is_separator) {
+            self.find_commands(partial)
+        } else if let Some(spec) = words.first().and_then(|name| self.completion_spec(name)) {
+            self.find_arguments(&spec, &words, partial)
+        } else {
+            find_files(partial)
+        };
+
+        completions
+            .sort_by_cached_key(|completion| paths::fold_case(&completion.text).into_owned());
+        completions.dedup_by(|a, b| paths::fold_case(&a.text) == paths::fold_case(&b.text));
+        completions
+    }
+
+    fn find_commands(&self, partial: &str) -> Vec<Completion> {
+        if partial.is_empty() {
+            return Vec::new();
+        }
+
+        let mut completions = Vec::new();
+
+        for builtin in self.builtins.iter().map(|builtin| builtin.name()) {
+            if builtin.starts_with(partial) {
+                completions.push(Completion::word(builtin));
+            }
+        }
+
+        // Until the background scan of this PATH has finished, list the
+        // directories here rather than offer nothing.
+        let path = self.get_var("PATH").unwrap_or_default();
+        let names = self
+            .command_index
+            .names(&path)
+            .unwrap_or_else(|| Arc::new(Self::scan_commands(&self.path_dirs())));
+        for name in names.iter() {
+            if paths::name_starts_with(name, partial) {
+                completions.push(Completion::word(name));
+            }
+        }
+        completions
+    }
+
+    /// How the arguments of `name` are completed, sourcing its completion
+    /// file the first time if no spec is set for it yet.
+    fn completion_spec(&mut self, name: &str) -> Option<CompletionSpec> {
+        if !self.completion_specs.contains_key(name)
+            && self.completion_files_tried.insert(name.to_string())
+            && Path::new(name).file_name().is_some_and(|file| file == name)
+            && let Some(path) = home_dir().map(|home| home.join(COMPLETIONS_DIR).join(name))
+            && path.is_file()
+            && let Err(e) = self.source_file(&path)
+        {
+            println!();
+            self.report(&e);
+            self.reprint_line();
+        }
+        self.completion_specs.get(name).cloned()
+    }
+
+    /// The words `spec` offers for `partial`, an argument of the command
+    /// whose earlier words are `words`.
+    fn find_arguments(
+        &mut self,
+        spec: &CompletionSpec,
+        words: &[String],
+        partial: &str,
+    ) -> Vec<Completion> {
+        let mut candidates: Vec<String> = Vec::new();
+        if let Some(list) = &spec.words {
+            candidates.extend(list.split_whitespace().map(String::from));
+        }
+        if let Some(function) = &spec.function {
+            candidates.extend(self.call_completion_function(function, words, partial));
+        }
+        candidates
+            .iter()
+            .filter(|candidate| candidate.starts_with(partial))
+            .map(|candidate| Completion::word(candidate))
+            .collect()
+    }
+
+    /// Runs a `complete -F` function as bash does: with the command name,
+    /// the word being completed and the word before it as arguments, and
+    /// `COMP_WORDS`, `COMP_CWORD`, `COMP_LINE` and `COMP_POINT` describing
+    /// the line. Returns the words it leaves in `COMPREPLY`.
+    fn call_completion_function(
+        &mut self,
+        function: &str,
+        words: &[String],
+        partial: &str,
+    ) -> Vec<String> {
+        let comp_words: BTreeMap<usize, String> = words
+            .iter()
+            .cloned()
+            .chain([partial.to_string()])
+            .enumerate()
+            .collect();
+        self.set_array("COMP_WORDS", Value::Array(comp_words));
+        self.set_var("COMP_CWORD", words.len().to_string());
+        self.set_var("COMP_LINE", self.editor.buffer.clone());
+        self.set_var("COMP_POINT", self.editor.cursor.to_string());
+        self.vars.remove("COMPREPLY");
+
+        let previous = words.last().cloned().unwrap_or_default();
+        let args = vec![words[0].clone(), partial.to_string(), previous];
+        self.call_hook_function(function, args);
+
+        let reply = self.vars.remove("COMPREPLY");
+        for name in COMPLETION_VARIABLES {
+            self.vars.remove(*name);
+        }
+        reply.map(|value| value.elements()).unwrap_or_default()
+    }
+
+    /// Lists `completions` below the edit line, coloring file names with
+    /// `LS_COLORS` and marking their type as `ls -F` does.
+    fn show_completions(&mut self, completions: &[Completion]) {
+        let colors = LsColors::parse(self.get_var("LS_COLORS").as_deref());
+        let listing: Vec<String> = completions
+            .iter()
+            .map(|completion| match completion.kind {
+                Some(kind) => colors.paint(&completion.label, kind),
+                None => completion.label.clone(),
+            })
+            .collect();
+
+        println!();
+        println!("{}", listing.join(" "));
+        self.reprint_line();
+        let _ = io::stdout().flush();
+    }
+
+    pub(crate) fn handle_double_tab(&mut self) {
+        if let Some((start, _, word)) = self.editor.get_word_at_cursor() {
+            let word = word.to_string();
+            let completions = self.find_completions(start, &word);
+            self.show_completions(&completions);
+        }
+    }
+
+    fn longest_common_prefix(strings: &[String]) -> String {
+        if strings.is_empty() {
+            return String::new();
+        }
+
+        if strings.len() == 1 {
+            return strings[0].clone();
+        }
+
+        let first = &strings[0];
+        let mut prefix_len = first.len();
+
+        for s in &strings[1..] {
+            prefix_len = first
+                .chars()
+                .zip(s.chars())
+                .take(prefix_len)
+                .take_while(|(a, b)| paths::chars_equal(*a, *b))
+                .count();
+
+            if prefix_len == 0 {
+                break;
+            }
+        }
+
+        first.chars().take(prefix_len).collect()
+    }
+
+    pub(crate) fn handle_tab(&mut self) {
+        if let Some((start, end, word)) = self.editor.get_word_at_cursor() {
+            let word = word.to_string();
+            let completions = self.find_completions(start, &word);
+            let texts: Vec<String> = completions.into_iter().map(|c| c.text).collect();
+
+            match texts.len() {
+                0 => {
+                    print!("\x07");
+                    let _ = io::stdout().flush();
+                }
+                1 => {
+                    self.editor.replace_word(start, end, &texts[0]);
+                    self.redraw_line();
+                }
+                _ => {
+                    let lcp = Self::longest_common_prefix(&texts);
+
+                    if lcp.len() > word.len() {
+                        self.editor.replace_word(start, end, &lcp);
+                        self.redraw_line();
+                    }
+
+                    print!("\x07");
+                    let _ = io::stdout().flush();
+                }
+            }
+        }
+    }
+}
+
+/// A word completion can put in place of the one being typed.
+struct Completion {
+    /// The text that replaces the word, ending in a space or, for a
+    /// directory, a separator.
+    text: String,
+    /// What is shown when completions are listed.
+    label: String,
+    /// Set for file names, which are listed in color.
+    kind: Option<FileKind>,
+}
+
+impl Completion {
+    /// A command name or argument, followed by a space.
+    fn word(word: &str) -> Self {
+        Completion {
+            text: format!("{word} "),
+            label: word.to_string(),
+            kind: None,
+        }
+    }
+}
+
+/// The words of the command `before` ends in, the command name first.
+fn command_words(before: &str) -> Vec<String> {
+    let command = before
+        .rfind([';', '|', '&', '('])
+        .map_or(before, |at| &before[at + 1..]);
+    command.split_whitespace().map(String::from).collect()
+}
+
+fn is_separator(c: char) -> bool {
+    c == '/' || (cfg!(windows) && c == '\\')
+}
+
+/// The files whose path begins with `partial`. Hidden files are offered
+/// only when the name being typed starts with a dot.
+fn find_files(partial: &str) -> Vec<Completion> {
+    let (dir, prefix) = match partial.rfind(is_separator) {
+        Some(at) => partial.split_at(at + 1),
+        None => ("", partial),
+    };
+    let Ok(entries) = std::fs::read_dir(if dir.is_empty() { "." } else { dir }) else {
+        return Vec::new();
+    };
+
+    let mut completions = Vec::new();
+    for entry in entries.flatten() {
+        let name = osstr::from_os(&entry.file_name());
+        if !paths::name_starts_with(&name, prefix)
+            || (name.starts_with('.') && !prefix.starts_with('.'))
+        {
+            continue;
+        }
+        let Some(kind) = FileKind::of(&entry.path()) else {
+            continue;
+        };
+        let suffix = if entry.path().is_dir() {
+            std::path::MAIN_SEPARATOR_STR
+        } else {
+            " "
+        };
+        completions.push(Completion {
+            text: format!("{dir}{name}{suffix}"),
+            label: name,
+            kind: Some(kind),
+        });
+    }
+    completions
+}
@@ -0,0 +1,993 @@
+/// ============================================
+/// LINE EDITOR
+/// ============================================
+use std::io::{self, Write};
+
+#[cfg(unix)]
+use std::io::Read;
+
+#[cfg(feature = "history")]
+use crate::history::HISTORY_CHUNK;
+use crate::jobs::JobState;
+use crate::{ReadStatus, Shell, signals};
+
+// ============================================
+// KEY CODES
+// ============================================
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Key {
+    Char(char),
+    Backspace,
+    Delete,
+    Enter,
+    Tab,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    CtrlC,
+    CtrlD,
+    CtrlA,
+    CtrlE,
+    CtrlG,
+    CtrlN,
+    CtrlP,
+    CtrlQ,
+    CtrlU,
+    CtrlW,
+    AltQ,
+    AltR,
+    /// Escape on its own, with nothing following it.
+    Escape,
+    /// The terminal gaining or losing focus, reported once focus reporting
+    /// is on.
+    FocusIn,
+    FocusOut,
+    Unknown,
+}
+
+// ============================================
+// KEY READER
+// ============================================
+
+/// Decodes the rest of an escape sequence after ESC, reading one byte at a
+/// time with `next`.
+fn parse_escape(mut next: impl FnMut() -> io::Result<Option<u8>>) -> io::Result<Key> {
+    match next()? {
+        Some(b'[') => {}
+        Some(b'q') => return Ok(Key::AltQ),
+        Some(b'r') => return Ok(Key::AltR),
+        None => return Ok(Key::Escape),
+        _ => return Ok(Key::Unknown),
+    }
+    Ok(match next()? {
+        Some(b'A') => Key::Up,
+        Some(b'B') => Key::Down,
+        Some(b'C') => Key::Right,
+        Some(b'D') => Key::Left,
+        Some(b'H') => Key::Home,
+        Some(b'F') => Key::End,
+        Some(b'I') => Key::FocusIn,
+        Some(b'O') => Key::FocusOut,
+        Some(b'3') => {
+            let _tilde = next()?;
+            Key::Delete
+        }
+        _ => Key::Unknown,
+    })
+}
+
+#[cfg(unix)]
+thread_local! {
+    /// A byte read while decoding a key that turned out to start the next one.
+    static PENDING_BYTE: std::cell::Cell<Option<u8>> = const { std::cell::Cell::new(None) };
+}
+
+/// Reads the next key, taking `erase` as Backspace as well as DEL and ^H.
+#[cfg(unix)]
+fn read_key(erase: u8) -> io::Result<Option<Key>> {
+    let mut stdin = io::stdin();
+    let mut buf = [0u8; 1];
+
+    if let Some(byte) = PENDING_BYTE.take() {
+        buf[0] = byte;
+    } else if stdin.read(&mut buf)? == 0 {
+        return Ok(None);
+    }
+
+    let key = match buf[0] {
+        byte if byte == erase => Key::Backspace,
+        b'\n' | b'\r' => Key::Enter,
+        b'\t' => Key::Tab,
+        0x7f | 0x08 => Key::Backspace,
+        0x03 => Key::CtrlC,
+        0x04 => Key::CtrlD,
+        0x01 => Key::CtrlA,
+        0x05 => Key::CtrlE,
+        0x07 => Key::CtrlG,
+        0x0e => Key::CtrlN,
+        0x10 => Key::CtrlP,
+        0x11 => Key::CtrlQ,
+        0x15 => Key::CtrlU,
+        0x17 => Key::CtrlW,
+        0x1b => parse_escape(|| {
+            let mut byte = [0u8; 1];
+            Ok((stdin.read(&mut byte)? > 0).then_some(byte[0]))
+        })?,
+        ch if (32..127).contains(&ch) => Key::Char(ch as char),
+        lead @ 0xc2..=0xf4 => {
+            // The remaining bytes of a UTF-8 encoded character.
+            let len = match lead {
+                0xc2..=0xdf => 2,
+                0xe0..=0xef => 3,
+                _ => 4,
+            };
+            let mut bytes = [lead, 0, 0, 0];
+            let mut read = 1;
+            while read < len && stdin.read(&mut bytes[read..read + 1])? == 1 {
+                if bytes[read] & 0xc0 != 0x80 {
+                    PENDING_BYTE.set(Some(bytes[read]));
+                    break;
+                }
+                read += 1;
+            }
+            match std::str::from_utf8(&bytes[..read]) {
+                Ok(text) => text.chars().next().map_or(Key::Unknown, Key::Char),
+                Err(_) => Key::Unknown,
+            }
+        }
+        _ => Key::Unknown,
+    };
+
+    Ok(Some(key))
+}
+
+/// Reads the next key. The console's erase key is always Backspace.
+#[cfg(windows)]
+fn read_key(_erase: u8) -> io::Result<Option<Key>> {
+    use windows::Win32::System::Console::{
+        FOCUS_EVENT, GetStdHandle, INPUT_RECORD, KEY_EVENT, ReadConsoleInputW, STD_INPUT_HANDLE,
+    };
+    use windows::Win32::UI::Input::KeyboardAndMouse::{
+        VIRTUAL_KEY, VK_BACK, VK_DELETE, VK_DOWN, VK_END, VK_HOME, VK_LEFT, VK_RETURN, VK_RIGHT,
+        VK_TAB, VK_UP,
+    };
+
+    unsafe {
+        let handle = GetStdHandle(STD_INPUT_HANDLE).map_err(io::Error::other)?;
+
+        let mut buffer = [INPUT_RECORD::default()];
+        let mut read = 0u32;
+
+        ReadConsoleInputW(handle, &mut buffer, &mut read).map_err(io::Error::other)?;
+
+        if buffer[0].EventType == KEY_EVENT as u16 {
+            let event = buffer[0].Event.KeyEvent;
+
+            if !event.bKeyDown.as_bool() {
+                return Ok(None);
+            }
+
+            let key_code = VIRTUAL_KEY(event.wVirtualKeyCode);
+            let char_code = event.uChar.UnicodeChar;
+            // Alt counts too, so Alt+letter is not typed as the letter.
+            let ctrl_pressed = event.dwControlKeyState & 0x000F != 0;
+            let alt_pressed = event.dwControlKeyState & 0x0003 != 0;
+
+            // With virtual terminal input enabled, special keys arrive as
+            // escape sequences rather than virtual key codes.
+            if char_code == 0x1b {
+                return parse_escape(|| read_console_byte(handle)).map(Some);
+            }
+
+            let key = match key_code {
+                VK_RETURN => Key::Enter,
+                VK_TAB => Key::Tab,
+                VK_BACK => Key::Backspace,
+                VK_DELETE => Key::Delete,
+                VK_LEFT => Key::Left,
+                VK_RIGHT => Key::Right,
+                VK_UP => Key::Up,
+                VK_DOWN => Key::Down,
+                VK_HOME => Key::Home,
+                VK_END => Key::End,
+                _ if ctrl_pressed => match char_code as u8 {
+                    3 => Key::CtrlC,
+                    4 => Key::CtrlD,
+                    1 => Key::CtrlA,
+                    5 => Key::CtrlE,
+                    7 => Key::CtrlG,
+                    14 => Key::CtrlN,
+                    16 => Key::CtrlP,
+                    17 => Key::CtrlQ,
+                    21 => Key::CtrlU,
+                    23 => Key::CtrlW,
+                    b'q' if alt_pressed => Key::AltQ,
+                    b'r' if alt_pressed => Key::AltR,
+                    _ => Key::Unknown,
+                },
+                _ => {
+                    if char_code > 0 && char_code < 128 {
+                        let ch = char::from_u32(char_code as u32).unwrap_or('\0');
+                        if ch.is_ascii_graphic() || ch == ' ' {
+                            Key::Char(ch)
+                        } else {
+                            Key::Unknown
+                        }
+                    } else {
+                        Key::Unknown
+                    }
+                }
+            };
+
+            Ok(Some(key))
+        } else if buffer[0].EventType == FOCUS_EVENT as u16 {
+            let focused = buffer[0].Event.FocusEvent.bSetFocus.as_bool();
+            Ok(Some(if focused { Key::FocusIn } else { Key::FocusOut }))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Reads the character of the next key press from the console, for the
+/// remainder of an escape sequence.
+#[cfg(windows)]
+fn read_console_byte(handle: windows::Win32::Foundation::HANDLE) -> io::Result<Option<u8>> {
+    use windows::Win32::System::Console::{INPUT_RECORD, KEY_EVENT, ReadConsoleInputW};
+
+    loop {
+        let mut buffer = [INPUT_RECORD::default()];
+        let mut read = 0u32;
+        unsafe {
+            ReadConsoleInputW(handle, &mut buffer, &mut read).map_err(io::Error::other)?;
+            if read == 0 {
+                return Ok(None);
+            }
+            if buffer[0].EventType == KEY_EVENT as u16 {
+                let event = buffer[0].Event.KeyEvent;
+                if event.bKeyDown.as_bool() {
+                    return Ok(u8::try_from(event.uChar.UnicodeChar).ok());
+                }
+            }
+        }
+    }
+}
+
+// ============================================
+// EDITING BUFFER
+// ============================================
+
+pub struct LineEditor {
+    pub buffer: String,
+    pub(crate) cursor: usize,
+    prompt: String,
+    /// The buffer as it is currently shown after the prompt, and the column
+    /// (in characters from the end of the prompt) the terminal cursor is on.
+    shown: String,
+    shown_column: usize,
+    /// Lines set aside with push-line, the most recent last.
+    pushed: Vec<String>,
+    /// The pushed line the next line edited starts with.
+    restored: Option<String>,
+    /// The history entry shown with Up and Down, and the line that was
+    /// being typed before the first Up, which Down past the newest entry
+    /// brings back.
+    #[cfg(feature = "history")]
+    history_index: Option<usize>,
+    #[cfg(feature = "history")]
+    draft: String,
+    /// Whether the line is shown as it is typed; not after `stty -echo`.
+    echo: bool,
+    /// Whether the terminal has been asked to report focus changes, and
+    /// whether, as far as its reports tell, it is focused.
+    focus_reporting: bool,
+    pub(crate) focused: bool,
+    /// The notification of a slow command, sent once the focus reports
+    /// queued while it ran have been read.
+    pub(crate) notification: Option<String>,
+}
+
+impl LineEditor {
+    pub fn new() -> Self {
+        Self {
+            buffer: String::new(),
+            cursor: 0,
+            prompt: String::new(),
+            shown: String::new(),
+            shown_column: 0,
+            pushed: Vec::new(),
+            restored: None,
+            #[cfg(feature = "history")]
+            history_index: None,
+            #[cfg(feature = "history")]
+            draft: String::new(),
+            echo: true,
+            focus_reporting: false,
+            focused: true,
+            notification: None,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.buffer.clear();
+        self.cursor = 0;
+        self.shown.clear();
+        self.shown_column = 0;
+        #[cfg(feature = "history")]
+        {
+            self.history_index = None;
+        }
+    }
+
+    /// Sets the line aside and empties the buffer, as zsh's push-line
+    /// does. It comes back at the next primary prompt.
+    fn push_line(&mut self) {
+        if !self.buffer.is_empty() {
+            self.pushed.push(std::mem::take(&mut self.buffer));
+            self.cursor = 0;
+        }
+    }
+
+    /// Makes the next line edited start with the line pushed last, if any.
+    pub(crate) fn restore_pushed(&mut self) {
+        self.restored = self.pushed.pop();
+    }
+
+    /// Records that the whole buffer has just been printed after the prompt,
+    /// leaving the terminal cursor at its end.
+    pub fn mark_shown(&mut self) {
+        self.shown.clone_from(&self.buffer);
+        self.shown_column = self.buffer.chars().count();
+    }
+
+    /// The terminal output that turns the shown line into the current one.
+    /// Only the text after the first difference is rewritten, so typing at
+    /// the end of the line prints just the new character.
+    fn render(&mut self) -> String {
+        let mut out = String::new();
+
+        let (same_bytes, same_chars) = self
+            .shown
+            .char_indices()
+            .zip(self.buffer.chars())
+            .take_while(|((_, a), b)| a == b)
+            .fold((0, 0), |(_, count), ((at, a), _)| {
+                (at + a.len_utf8(), count + 1)
+            });
+
+        let mut column = self.shown_column;
+        if same_bytes < self.shown.len() || same_bytes < self.buffer.len() {
+            Self::move_cursor(&mut out, column, same_chars);
+            out.push_str(&self.buffer[same_bytes..]);
+            column = self.buffer.chars().count();
+            if self.shown.chars().count() > column {
+                out.push_str("\x1B[K");
+            }
+        }
+
+        let target = self.buffer[..self.cursor].chars().count();
+        Self::move_cursor(&mut out, column, target);
+
+        self.shown.clone_from(&self.buffer);
+        self.shown_column = target;
+        out
+    }
+
+    fn move_cursor(out: &mut String, from: usize, to: usize) {
+        if to < from {
+            out.push_str(&format!("\x1B[{}D", from - to));
+        } else if to > from {
+            out.push_str(&format!("\x1B[{}C", to - from));
+        }
+    }
+
+    /// The byte offset of the character before the cursor, if any.
+    fn prev_boundary(&self) -> Option<usize> {
+        let ch = self.buffer[..self.cursor].chars().next_back()?;
+        Some(self.cursor - ch.len_utf8())
+    }
+
+    /// The byte offset just past the character at the cursor, if any.
+    fn next_boundary(&self) -> Option<usize> {
+        let ch = self.buffer[self.cursor..].chars().next()?;
+        Some(self.cursor + ch.len_utf8())
+    }
+
+    fn insert(&mut self, ch: char) {
+        self.buffer.insert(self.cursor, ch);
+        self.cursor += ch.len_utf8();
+    }
+
+    fn backspace(&mut self) {
+        if let Some(prev) = self.prev_boundary() {
+            self.cursor = prev;
+            self.buffer.remove(self.cursor);
+        }
+    }
+
+    fn delete(&mut self) {
+        if self.cursor < self.buffer.len() {
+            self.buffer.remove(self.cursor);
+        }
+    }
+
+    fn move_left(&mut self) {
+        if let Some(prev) = self.prev_boundary() {
+            self.cursor = prev;
+        }
+    }
+
+    fn move_right(&mut self) {
+        if let Some(next) = self.next_boundary() {
+            self.cursor = next;
+        }
+    }
+
+    /// Deletes everything before the cursor.
+    fn kill_to_start(&mut self) {
+        self.buffer.drain(..self.cursor);
+        self.cursor = 0;
+    }
+
+    /// Deletes the word before the cursor, up to the whitespace in front
+    /// of it, along with any whitespace between it and the cursor.
+    fn kill_word_before(&mut self) {
+        let before = self.buffer[..self.cursor].trim_end();
+        let start = before.trim_end_matches(|c: char| !c.is_whitespace()).len();
+        self.buffer.drain(start..self.cursor);
+        self.cursor = start;
+    }
+
+    fn move_home(&mut self) {
+        self.cursor = 0;
+    }
+
+    fn move_end(&mut self) {
+        self.cursor = self.buffer.len();
+    }
+
+    /// The word that ends at the cursor, with its start, if it is in
+    /// command position.
+    fn command_word_before_cursor(&self) -> Option<(usize, &str)> {
+        let before = &self.buffer[..self.cursor];
+        let start = before
+            .rfind(|c: char| c.is_ascii_whitespace())
+            .map_or(0, |at| at + 1);
+        let after_word = self.buffer[self.cursor..].chars().next();
+        if start == self.cursor
+            || after_word.is_some_and(|c| !c.is_ascii_whitespace())
+            || !starts_command(&before[..start])
+        {
+            return None;
+        }
+        Some((start, &before[start..]))
+    }
+
+    /// The word the cursor is in or just after, as its byte range and
+    /// text. After a space this is the empty word at the cursor.
+    #[cfg(feature = "completion")]
+    pub fn get_word_at_cursor(&self) -> Option<(usize, usize, &str)> {
+        if self.buffer.is_empty() {
+            return None;
+        }
+
+        let bytes = self.buffer.as_bytes();
+        let mut start = self.cursor;
+        let mut end = self.cursor;
+
+        while start > 0 && !bytes[start - 1].is_ascii_whitespace() {
+            start -= 1;
+        }
+
+        while end < self.buffer.len() && !bytes[end].is_ascii_whitespace() {
+            end += 1;
+        }
+
+        Some((start, end, &self.buffer[start..end]))
+    }
+
+    pub fn replace_word(&mut self, start: usize, end: usize, replacement: &str) {
+        self.buffer.replace_range(start..end, replacement);
+        self.cursor = start + replacement.len();
+    }
+}
+
+/// Whether a word following `before` on the line is a command name.
+pub(crate) fn starts_command(before: &str) -> bool {
+    let before = before.trim_end();
+    before.is_empty() || before.ends_with([';', '|', '&', '('])
+}
+
+impl Shell {
+    pub(crate) fn print_prompt(&self) {
+        self.flush_output();
+        print!("{}", self.editor.prompt);
+        let _ = io::stdout().flush();
+    }
+
+    /// Prints waiting notices above the edit line, then draws the prompt
+    /// and the line again with the cursor where it was.
+    fn show_notices(&mut self) {
+        if !self.notices.has_pending() {
+            return;
+        }
+        self.clear_line();
+        self.print_notices();
+        self.reprint_line();
+    }
+
+    /// Draws the prompt again if a prompt segment has changed it.
+    #[cfg(feature = "prompt-theme")]
+    fn refresh_prompt(&mut self) {
+        if let Some(prompt) = self.updated_prompt()
+            && prompt != self.editor.prompt
+        {
+            self.clear_line();
+            self.editor.prompt = prompt;
+            self.reprint_line();
+        }
+    }
+
+    /// Replaces the line with the previous (`older`) or next history entry,
+    /// leaving the cursor at its end. Going down past the newest entry
+    /// brings back the line being typed.
+    #[cfg(feature = "history")]
+    fn recall_history(&mut self, older: bool) {
+        // Past the oldest line read, more are read from the history file.
+        if older && self.editor.history_index == Some(0) {
+            let added = self.history.load_older(HISTORY_CHUNK);
+            self.editor.history_index = Some(added);
+        }
+        let editor = &mut self.editor;
+        let entries = self.history.entries();
+        let index = match (editor.history_index, older) {
+            (None, true) if !entries.is_empty() => {
+                editor.draft = editor.buffer.clone();
+                entries.len() - 1
+            }
+            (Some(index), true) if index > 0 => index - 1,
+            (Some(index), false) if index + 1 < entries.len() => index + 1,
+            (Some(_), false) => {
+                editor.history_index = None;
+                editor.buffer = std::mem::take(&mut editor.draft);
+                editor.move_end();
+                return;
+            }
+            _ => return,
+        };
+
+        editor.history_index = Some(index);
+        editor.buffer.clone_from(&entries[index]);
+        editor.move_end();
+    }
+
+    /// Moves to the start of the prompt and clears everything after it.
+    fn clear_line(&mut self) {
+        let mut out = String::new();
+        LineEditor::move_cursor(&mut out, self.editor.shown_column, 0);
+        print!("{out}\r\x1B[J");
+    }
+
+    /// Prints the prompt and the whole line, leaving the cursor where it
+    /// was in the line.
+    pub(crate) fn reprint_line(&mut self) {
+        self.print_prompt();
+        if self.editor.echo {
+            print!("{}", self.editor.buffer);
+            self.editor.mark_shown();
+        }
+        self.redraw_line();
+    }
+
+    /// Replaces a command name just typed with the expansion of the
+    /// abbreviation it names, if it is one.
+    fn expand_abbreviation(&mut self) {
+        let Some((start, word)) = self.editor.command_word_before_cursor() else {
+            return;
+        };
+        if let Some(expansion) = self.abbreviations.get(word) {
+            let end = self.editor.cursor;
+            self.editor.replace_word(start, end, &expansion.clone());
+        }
+    }
+
+    pub(crate) fn redraw_line(&mut self) {
+        if !self.editor.echo {
+            return;
+        }
+        let update = self.editor.render();
+        if !update.is_empty() {
+            print!("{update}");
+            let _ = io::stdout().flush();
+        }
+    }
+
+    /// Asks the terminal to report focus changes (`CSI ?1004h`), which it
+    /// does with `CSI I` and `CSI O`, or to stop. They are on while
+    /// `set -o notifyafter` is, through the commands run too, since whether
+    /// the terminal is focused when a slow command ends is what matters;
+    /// programs reading the terminal then see those reports as input.
+    pub(crate) fn report_focus(&mut self, on: bool) {
+        if self.editor.focus_reporting == on {
+            return;
+        }
+        print!("{}", if on { "\x1b[?1004h" } else { "\x1b[?1004l" });
+        let _ = io::stdout().flush();
+        self.editor.focus_reporting = on;
+        self.editor.focused = true;
+    }
+
+    /// Reads a line from the terminal with the line editor.
+    // Without completion nothing reads `double_tab`.
+    #[cfg_attr(
+        not(feature = "completion"),
+        allow(unused_variables, unused_assignments)
+    )]
+    pub(crate) fn edit_line(&mut self, prompt: &str) -> io::Result<ReadStatus> {
+        use crate::terminal::RawMode;
+
+        self.editor.clear();
+        self.editor.prompt = prompt.to_string();
+        if let Some(line) = self.editor.restored.take() {
+            self.editor.buffer = line;
+            self.editor.move_end();
+        }
+
+        let raw = RawMode::enable()?;
+        self.editor.echo = raw.echo();
+        self.report_focus(self.notify_after > 0);
+        self.print_prompt();
+        self.redraw_line();
+        let mut double_tab = false;
+        loop {
+            match read_key(raw.erase())? {
+                None if signals::take_interrupt() => {
+                    println!("^C");
+                    self.editor.clear();
+                    return Ok(ReadStatus::Interrupted);
+                }
+                None => {
+                    self.send_notification();
+                    self.poll_jobs();
+                    self.show_notices();
+                    #[cfg(feature = "prompt-theme")]
+                    self.refresh_prompt();
+                }
+                Some(Key::FocusIn) => self.editor.focused = true,
+                Some(Key::FocusOut) => self.editor.focused = false,
+                Some(Key::Enter) => {
+                    self.expand_abbreviation();
+                    self.redraw_line();
+                    println!();
+                    return Ok(ReadStatus::Line(self.editor.buffer.clone()));
+                }
+                #[cfg(feature = "completion")]
+                Some(Key::Tab) => {
+                    if !double_tab {
+                        self.handle_tab();
+                        double_tab = true;
+                    } else {
+                        self.handle_double_tab();
+                        double_tab = false;
+                    }
+                }
+                Some(Key::Backspace) => {
+                    double_tab = false;
+                    self.editor.backspace();
+                    self.redraw_line();
+                }
+                Some(Key::Delete) => {
+                    double_tab = false;
+                    self.editor.delete();
+                    self.redraw_line();
+                }
+                Some(Key::Left) => {
+                    double_tab = false;
+                    self.editor.move_left();
+                    self.redraw_line();
+                }
+                Some(Key::Right) => {
+                    double_tab = false;
+                    self.editor.move_right();
+                    self.redraw_line();
+                }
+                Some(Key::Home) | Some(Key::CtrlA) => {
+                    double_tab = false;
+                    self.editor.move_home();
+                    self.redraw_line();
+                }
+                Some(Key::End) | Some(Key::CtrlE) => {
+                    double_tab = false;
+                    self.editor.move_end();
+                    self.redraw_line();
+                }
+                Some(Key::CtrlC) => {
+                    println!("^C");
+                    self.editor.clear();
+                    return Ok(ReadStatus::Interrupted);
+                }
+                Some(Key::CtrlD) => {
+                    double_tab = false;
+                    if self.editor.buffer.is_empty() {
+                        println!();
+                        return Ok(ReadStatus::Eof);
+                    }
+                }
+                Some(Key::Char(ch)) => {
+                    double_tab = false;
+                    if ch == ' ' {
+                        self.expand_abbreviation();
+                    }
+                    self.editor.insert(ch);
+                    self.redraw_line();
+                }
+                Some(Key::CtrlU) => {
+                    double_tab = false;
+                    self.editor.kill_to_start();
+                    self.redraw_line();
+                }
+                Some(Key::CtrlW) => {
+                    double_tab = false;
+                    self.editor.kill_word_before();
+                    self.redraw_line();
+                }
+                Some(Key::CtrlQ | Key::AltQ) => {
+                    double_tab = false;
+                    self.editor.push_line();
+                    self.redraw_line();
+                }
+                #[cfg(feature = "history")]
+                Some(key @ (Key::Up | Key::Down | Key::CtrlP | Key::CtrlN)) => {
+                    double_tab = false;
+                    self.recall_history(matches!(key, Key::Up | Key::CtrlP));
+                    self.redraw_line();
+                }
+                #[cfg(feature = "history")]
+                Some(Key::AltR) => {
+                    double_tab = false;
+                    if let Some(line) = self.pick_history(&raw)? {
+                        let cursor = self.editor.cursor;
+                        self.editor.replace_word(cursor, cursor, &line);
+                    }
+                    self.redraw_line();
+                }
+                #[cfg(not(feature = "history"))]
+                Some(Key::Up | Key::Down | Key::CtrlP | Key::CtrlN | Key::AltR) => {}
+                #[cfg(not(feature = "completion"))]
+                Some(Key::Tab) => {}
+                Some(Key::CtrlG | Key::Escape | Key::Unknown) => {
+                    double_tab = false;
+                }
+            }
+        }
+    }
+}
+
+// ============================================
+// HISTORY PICKER
+// ============================================
+
+/// What the history picker shows before the query.
+#[cfg(feature = "history")]
+const PICKER_PROMPT: &str = "history> ";
+
+/// Whether the characters of `query` all appear in `line`, in the same
+/// order, ignoring case.
+#[cfg(feature = "history")]
+fn fuzzy_match(line: &str, query: &str) -> bool {
+    let mut chars = line.chars().flat_map(char::to_lowercase);
+    query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .all(|wanted| chars.any(|c| c == wanted))
+}
+
+/// Draws the history picker: the query on the top row and the lines
+/// matching it below, the selected one highlighted and scrolled into view.
+#[cfg(feature = "history")]
+fn draw_picker(query: &str, matches: &[&str], selected: usize, (rows, columns): (usize, usize)) {
+    let mut out = format!("\x1B[H\x1B[2J{}{}", PICKER_PROMPT, query);
+    let visible = rows.saturating_sub(1).max(1);
+    let top = (selected + 1).saturating_sub(visible);
+    for (index, line) in matches.iter().enumerate().skip(top).take(visible) {
+        let line: String = line.chars().take(columns.saturating_sub(2)).collect();
+        if index == selected {
+            out.push_str(&format!("\r\n\x1B[7m> {}\x1B[m", line));
+        } else {
+            out.push_str(&format!("\r\n  {}", line));
+        }
+    }
+    let column = PICKER_PROMPT.len() + query.chars().count() + 1;
+    out.push_str(&format!("\x1B[1;{}H", column));
+    print!("{out}");
+    let _ = io::stdout().flush();
+}
+
+/// Runs the history picker over `lines` until a line is picked or the
+/// picker is closed.
+#[cfg(feature = "history")]
+fn pick(lines: &[&str], raw: &crate::terminal::RawMode) -> io::Result<Option<String>> {
+    let filter = |query: &str| -> Vec<&str> {
+        lines
+            .iter()
+            .copied()
+            .filter(|line| fuzzy_match(line, query))
+            .collect()
+    };
+    let mut query = String::new();
+    let mut matches = filter(&query);
+    let mut selected = 0;
+    loop {
+        draw_picker(&query, &matches, selected, raw.size());
+        let key = loop {
+            if let Some(key) = read_key(raw.erase())? {
+                break key;
+            }
+        };
+        let previous = query.clone();
+        match key {
+            Key::Enter => return Ok(matches.get(selected).map(|line| line.to_string())),
+            Key::CtrlC | Key::CtrlG | Key::Escape => return Ok(None),
+            Key::Up | Key::CtrlP => selected = selected.saturating_sub(1),
+            Key::Down | Key::CtrlN => {
+                selected = (selected + 1).min(matches.len().saturating_sub(1));
+            }
+            Key::Backspace => {
+                query.pop();
+            }
+            Key::CtrlU => query.clear(),
+            Key::Char(c) => query.push(c),
+            _ => {}
+        }
+        if query != previous {
+            matches = filter(&query);
+            selected = 0;
+        }
+    }
+}
+
+#[cfg(feature = "history")]
+impl Shell {
+    /// Shows the history full-screen, newest first and each line once.
+    /// Typing narrows it to the lines holding the typed characters in
+    /// order; Up and Down or Ctrl-P and Ctrl-N move the selection, Enter
+    /// returns the selected line and Ctrl-C, Ctrl-G or Escape nothing.
+    fn pick_history(&mut self, raw: &crate::terminal::RawMode) -> io::Result<Option<String>> {
+        self.history.load_older(usize::MAX);
+        let mut seen = std::collections::HashSet::new();
+        let lines: Vec<&str> = self
+            .history
+            .entries()
+            .iter()
+            .rev()
+            .map(String::as_str)
+            .filter(|line| seen.insert(*line))
+            .collect();
+
+        print!("\x1B[?1049h");
+        let picked = pick(&lines, raw);
+        print!("\x1B[?1049l");
+        let _ = io::stdout().flush();
+        picked
+    }
+}
+
+// ============================================
+// JOB MONITOR
+// ============================================
+
+/// The top row of the job monitor.
+const MONITOR_TITLE: &str = "Jobs  (f: foreground, k: kill, q: quit)";
+
+impl Shell {
+    /// The job monitor as drawn: the title, then a row per job with the
+    /// CPU time it has used, the selected one highlighted.
+    fn monitor_screen(
+        &self,
+        ids: &[usize],
+        selected: usize,
+        (rows, columns): (usize, usize),
+    ) -> String {
+        let mut out = format!("\x1B[H\x1B[2J{}", MONITOR_TITLE);
+        if ids.is_empty() {
+            out.push_str("\r\n  No jobs");
+        }
+        let visible = rows.saturating_sub(1).max(1);
+        let top = (selected + 1).saturating_sub(visible);
+        for (index, &id) in ids.iter().enumerate().skip(top).take(visible) {
+            let Some(job) = self.jobs.get(id) else {
+                continue;
+            };
+            let cpu = match job.cpu_time() {
+                Some(time) => format!("{:>7.1}s  ", time.as_secs_f64()),
+                // Kept blank so the commands line up.
+                None if cfg!(target_os = "linux") => " ".repeat(10),
+                None => String::new(),
+            };
+            let row = format!(
+                "[{}]{}  {:<24}{}{}",
+                id,
+                self.jobs.mark(id),
+                job.state.describe(),
+                cpu,
+                job.command
+            );
+            let row: String = row.chars().take(columns.saturating_sub(2)).collect();
+            if index == selected {
+                out.push_str(&format!("\r\n\x1B[7m> {}\x1B[m", row));
+            } else {
+                out.push_str(&format!("\r\n  {}", row));
+            }
+        }
+        out
+    }
+
+    /// `jobs --watch`: shows the jobs full-screen, updated as they change,
+    /// with the CPU time their processes have used where the system tells.
+    /// Up and Down or Ctrl-P and Ctrl-N move the selection; `k` ends the
+    /// selected job as `kill` does, and `f` or Enter returns it to be
+    /// brought to the foreground. `q`, Escape or Ctrl-C closes the view.
+    /// Finished jobs are forgotten once shown.
+    pub(crate) fn watch_jobs(&mut self) -> io::Result<Option<usize>> {
+        use crate::terminal::RawMode;
+
+        let raw = RawMode::enable()?;
+        print!("\x1B[?1049h");
+        let mut selected = None;
+        let mut drawn = String::new();
+        let chosen = loop {
+            self.update_jobs();
+            let ids: Vec<usize> = self.jobs.iter().map(|job| job.id).collect();
+            let index = selected
+                .and_then(|id| ids.iter().position(|&job| job == id))
+                .unwrap_or(0);
+            let screen = self.monitor_screen(&ids, index, raw.size());
+            if screen != drawn {
+                print!("{screen}");
+                let _ = io::stdout().flush();
+                drawn = screen;
+            }
+
+            // No key within the read timeout means it is time to refresh.
+            let Some(key) = read_key(raw.erase())? else {
+                continue;
+            };
+            match key {
+                Key::Char('q') | Key::Escape | Key::CtrlC => break None,
+                Key::Up | Key::CtrlP => selected = ids.get(index.saturating_sub(1)).copied(),
+                Key::Down | Key::CtrlN => {
+                    selected = ids
+                        .get((index + 1).min(ids.len().saturating_sub(1)))
+                        .copied();
+                }
+                Key::Char('f') | Key::Enter if !ids.is_empty() => break Some(ids[index]),
+                Key::Char('k') => {
+                    let terminate = signals::signal_number("TERM").unwrap_or(15);
+                    if let Some(job) = ids.get(index).and_then(|&id| self.jobs.get_mut(id)) {
+                        let _ = job.signal(terminate);
+                    }
+                }
+                _ => {}
+            }
+        };
+        print!("\x1B[?1049l");
+        let _ = io::stdout().flush();
+        drop(raw);
+
+        self.update_jobs();
+        let finished: Vec<usize> = self
+            .jobs
+            .iter()
+            .filter(|job| matches!(job.state, JobState::Done(_)))
+            .map(|job| job.id)
+            .collect();
+        for id in finished {
+            self.jobs.remove(id);
+        }
+        Ok(chosen.filter(|&id| self.jobs.get(id).is_some()))
+    }
+}
@@ -0,0 +1,90 @@
+/// ============================================
+/// EMBEDDING API
+/// ============================================
+use std::path::Path;
+
+use crate::error::ShellError;
+use crate::shell_io::Capture;
+use crate::{Flow, Shell, osstr};
+
+/// The outcome of `Shell::run_line` or `Shell::run_script`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandResult {
+    /// The exit status of the last command run, as `$?` would show it.
+    pub status: i32,
+    /// What the commands wrote to stdout, when output is being captured
+    /// (see `Shell::set_capture_output`).
+    pub output: Option<String>,
+}
+
+impl Shell {
+    /// Makes `run_line` and `run_script` collect what commands write to
+    /// stdout and return it in `CommandResult::output` instead of writing it
+    /// to the terminal. stderr is never captured.
+    pub fn set_capture_output(&mut self, capture: bool) {
+        self.capture_output = capture;
+    }
+
+    /// Runs `line` as if it had been typed at the prompt, without the
+    /// prompt hooks. A syntax error, including input that ends mid-command,
+    /// is reported on stderr and gives status 2 without running anything.
+    pub fn run_line(&mut self, line: &str) -> CommandResult {
+        self.run_captured(|shell| match shell.parse(line) {
+            Ok(program) => shell.execute(&program),
+            Err(error) => shell.report(&ShellError::Syntax {
+                origin: None,
+                line: None,
+                error,
+            }),
+        })
+    }
+
+    /// Runs the script at `path` in this shell with `$0` set to `path` and
+    /// `$1`, `$2`, ... to `args`, as `sh path args...` would except that
+    /// variables and functions it defines remain afterwards.
+    pub fn run_script(&mut self, path: &Path, args: &[String]) -> CommandResult {
+        let name = osstr::from_os(path.as_os_str());
+        let saved_name = std::mem::replace(&mut self.shell_name, name);
+        let saved_positional = std::mem::replace(&mut self.positional, args.to_vec());
+
+        let result =
+            self.run_captured(|shell| shell.source_file(path).unwrap_or_else(|e| shell.report(&e)));
+
+        self.shell_name = saved_name;
+        self.positional = saved_positional;
+        result
+    }
+
+    /// Runs `run`, capturing its output if `set_capture_output` asked for
+    /// it, and records its status in `$?`. `exit` ends `run` with its
+    /// status rather than ending the process.
+    fn run_captured(&mut self, run: impl FnOnce(&mut Self) -> i32) -> CommandResult {
+        let embedded = std::mem::replace(&mut self.embedded, true);
+        let (status, output) = if self.capture_output {
+            let (status, output) = self.with_capture(run);
+            (status, Some(String::from_utf8_lossy(&output).into_owned()))
+        } else {
+            let status = run(self);
+            self.flush_output();
+            (status, None)
+        };
+        self.embedded = embedded;
+
+        // `exit`, or `break` or `continue` outside a loop, must not leak
+        // into the next line.
+        self.flow = Flow::Normal;
+        self.last_status = status;
+        CommandResult { status, output }
+    }
+
+    /// Runs `run` with stdout going to a buffer rather than the terminal and
+    /// returns what was written. Captures nest: output inside an inner
+    /// capture goes only to that capture.
+    pub(crate) fn with_capture<T>(&mut self, run: impl FnOnce(&mut Self) -> T) -> (T, Vec<u8>) {
+        let capture = Capture::default();
+        let saved = self.capture.replace(capture.clone());
+        let result = run(self);
+        self.capture = saved;
+        (result, capture.take())
+    }
+}
@@ -0,0 +1,116 @@
+/// ============================================
+/// SHELL ERRORS
+/// ============================================
+use std::io;
+
+use thiserror::Error;
+
+use crate::arith::ArithError;
+use crate::parser::ParseError;
+
+/// Everything that can make a command fail. Each error prints as the
+/// message bash would give and knows the exit status it results in.
+#[derive(Debug, Error)]
+pub enum ShellError {
+    #[error("{0}: command not found")]
+    NotFound(String),
+    /// A name `type` could not find; unlike running it, this is status 1.
+    #[error("{0}: not found")]
+    Unknown(String),
+    /// The program was found but could not be started.
+    #[error("{command}: {}", describe(.source))]
+    NotExecutable { command: String, source: io::Error },
+    /// A syntax error, at `line` of the file named by `origin` if it came
+    /// from one.
+    #[error("{}{error}", locate(.origin, .line))]
+    Syntax {
+        origin: Option<String>,
+        line: Option<usize>,
+        error: ParseError,
+    },
+    #[error("{context}: {}", describe(.source))]
+    Io { context: String, source: io::Error },
+    /// A word could not be expanded, such as a bad `${...}` substitution.
+    #[error("{0}")]
+    Expansion(String),
+    #[error(transparent)]
+    Arith(#[from] ArithError),
+    /// A plugin library could not be loaded.
+    #[error("{path}: {message}")]
+    Plugin { path: String, message: String },
+    /// A builtin could not do what it was asked.
+    #[error("{name}: {message}")]
+    Builtin {
+        name: String,
+        message: String,
+        status: i32,
+    },
+}
+
+/// The `file: line N: ` prefix of a syntax error.
+fn locate(origin: &Option<String>, line: &Option<usize>) -> String {
+    match (origin, line) {
+        (Some(origin), Some(line)) => format!("{origin}: line {line}: "),
+        (Some(origin), None) => format!("{origin}: "),
+        (None, _) => String::new(),
+    }
+}
+
+/// An I/O error as bash words it: `No such file or directory`, without the
+/// `(os error 2)` that Rust appends.
+fn describe(error: &io::Error) -> String {
+    let message = error.to_string();
+    match message.rfind(" (os error ") {
+        Some(at) => message[..at].to_string(),
+        None => message,
+    }
+}
+
+impl ShellError {
+    /// A builtin failure with exit status 1.
+    pub fn builtin(name: &str, message: impl Into<String>) -> Self {
+        Self::Builtin {
+            name: name.to_string(),
+            message: message.into(),
+            status: 1,
+        }
+    }
+
+    /// A builtin given bad options or arguments, with exit status 2.
+    pub fn usage(name: &str, message: impl Into<String>) -> Self {
+        Self::Builtin {
+            name: name.to_string(),
+            message: message.into(),
+            status: 2,
+        }
+    }
+
+    /// A syntax error in `source`, which starts after line `base` of the
+    /// file named by `origin`. It is placed at the token it was found at,
+    /// or at the last line if the input ended early.
+    pub fn syntax(origin: &str, base: usize, source: &str, error: ParseError) -> Self {
+        let line = match error.span() {
+            Some(span) => span.line,
+            None => source.matches('\n').count().max(1),
+        };
+        Self::Syntax {
+            origin: Some(origin.to_string()),
+            line: Some(base + line),
+            error,
+        }
+    }
+
+    pub fn status(&self) -> i32 {
+        match self {
+            Self::NotFound(_) => 127,
+            Self::NotExecutable { .. } => 126,
+            Self::Syntax { .. } => 2,
+            Self::Builtin { status, .. } => *status,
+            Self::Unknown(_)
+            | Self::Io { .. }
+            | Self::Expansion(_)
+            | Self::Arith(_)
+            | Self::Plugin { .. } => 1,
+        }
+    }
+}
@@ -0,0 +1,1091 @@
+/// ============================================
+/// WORD EXPANSION
+/// ============================================
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::arith::{self, ArithError};
+use crate::error::ShellError;
+use crate::glob::{self, ReplaceMode};
+use crate::osstr;
+use crate::parser::{is_valid_name, quote};
+use crate::startup::home_dir;
+use crate::variables::Value;
+use crate::{Flow, Shell};
+
+const DEFAULT_IFS: &str = " \t\n";
+
+/// What a word being expanded is, which decides how it is expanded.
+#[derive(Clone, Copy, PartialEq)]
+enum Context {
+    /// A command word, split into fields.
+    Word,
+    /// A word that stays one string, such as a `case` subject.
+    String,
+    /// The value of an assignment, in which `~` is also expanded after
+    /// each unquoted `:`.
+    Assignment,
+    /// The body of a here-document: as in double quotes, except that `"`
+    /// is an ordinary character.
+    HereDoc,
+}
+
+/// One expanded field. `pattern` holds the same text with every quoted
+/// glob character escaped, so pattern matching treats it literally.
+#[derive(Debug, Default)]
+pub(crate) struct Field {
+    pub(crate) text: String,
+    pub(crate) pattern: String,
+}
+
+impl Field {
+    fn push(&mut self, c: char, quoted: bool) {
+        self.text.push(c);
+        if quoted && matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            self.pattern.push('\\');
+        }
+        self.pattern.push(c);
+    }
+}
+
+/// Collects the fields produced while expanding a single word.
+struct Fields {
+    fields: Vec<Field>,
+    current: Field,
+    /// Whether `current` exists as a field even if it is empty (e.g. `""`).
+    started: bool,
+    /// Set when `"$@"` expanded to nothing, so the surrounding quotes must
+    /// not produce an empty field on their own.
+    empty_at: bool,
+    /// Whether unquoted expansions are split into separate fields.
+    split: bool,
+}
+
+impl Fields {
+    fn new(split: bool) -> Self {
+        Self {
+            fields: Vec::new(),
+            current: Field::default(),
+            started: false,
+            empty_at: false,
+            split,
+        }
+    }
+
+    fn push(&mut self, c: char, quoted: bool) {
+        self.current.push(c, quoted);
+        self.started = true;
+    }
+
+    fn push_str(&mut self, text: &str, quoted: bool) {
+        for c in text.chars() {
+            self.current.push(c, quoted);
+        }
+        self.started = true;
+    }
+
+    /// Appends the result of an unquoted expansion, splitting it on `ifs`.
+    fn push_split(&mut self, text: &str, ifs: &str) {
+        if !self.split {
+            for c in text.chars() {
+                self.push(c, false);
+            }
+            return;
+        }
+
+        for c in text.chars() {
+            if !ifs.contains(c) {
+                self.push(c, false);
+            } else if c.is_whitespace() {
+                if self.started {
+                    self.finish();
+                }
+            } else {
+                self.finish();
+            }
+        }
+    }
+
+    fn finish(&mut self) {
+        self.fields.push(std::mem::take(&mut self.current));
+        self.started = false;
+    }
+
+    fn into_fields(mut self) -> Vec<Field> {
+        if self.empty_at && self.current.text.is_empty() {
+            self.started = false;
+        }
+        if self.started {
+            self.finish();
+        }
+        self.fields
+    }
+}
+
+/// The result of looking up a parameter, before it is added to the fields.
+enum ParamValue {
+    Single(String),
+    /// The elements of `$@`, `$*`, `${a[@]}` or `${a[*]}`; `joined` is set
+    /// for the `*` forms, which join into one field when quoted.
+    List {
+        items: Vec<String>,
+        joined: bool,
+    },
+}
+
+/// Reads the body of `${...}` after the opening brace, consuming the
+/// closing brace.
+fn take_braced(chars: &mut Peekable<Chars>) -> String {
+    let mut inner = String::new();
+    let mut depth = 1;
+    let mut quote = None;
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"') | None, '\\') => {
+                inner.push(c);
+                if let Some(next) = chars.next() {
+                    inner.push(next);
+                }
+                continue;
+            }
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '{') => depth += 1,
+            (None, '}') => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            _ => {}
+        }
+        inner.push(c);
+    }
+
+    inner
+}
+
+/// Reads the body of `$((...))` after the opening parentheses, consuming
+/// the closing `))`.
+fn take_arith(chars: &mut Peekable<Chars>) -> String {
+    let mut expr = String::new();
+    let mut depth = 0;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => {
+                chars.next();
+                break;
+            }
+            ')' => depth -= 1,
+            _ => {}
+        }
+        expr.push(c);
+    }
+
+    expr
+}
+
+/// Reads the body of `$(...)` after the opening parenthesis, consuming
+/// the closing one.
+fn take_substitution(chars: &mut Peekable<Chars>) -> String {
+    let mut body = String::new();
+    let mut depth = 1;
+    let mut quote = None;
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"' | '`') | None, '\\') => {
+                body.push(c);
+                if let Some(next) = chars.next() {
+                    body.push(next);
+                }
+                continue;
+            }
+            (Some(_), _) => {}
+            (None, '\'' | '"' | '`') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            _ => {}
+        }
+        body.push(c);
+    }
+
+    body
+}
+
+/// Reads the body of `` `...` `` after the opening backquote, consuming the
+/// closing one. A backslash before `$`, `` ` `` or `\`, or before `"` inside
+/// double quotes, is removed.
+fn take_backquoted(chars: &mut Peekable<Chars>, in_double_quote: bool) -> String {
+    let mut body = String::new();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '`' => break,
+            '\\' => match chars.peek() {
+                Some(&next @ ('$' | '`' | '\\')) => {
+                    body.push(next);
+                    chars.next();
+                }
+                Some('"') if in_double_quote => {
+                    body.push('"');
+                    chars.next();
+                }
+                _ => body.push(c),
+            },
+            _ => body.push(c),
+        }
+    }
+
+    body
+}
+
+/// Splits the body of `${...}` into the parameter name, an optional array
+/// subscript, and whatever operator text follows them.
+fn split_param(inner: &str) -> Option<(&str, Option<&str>, &str)> {
+    let name_len = match inner.chars().next()? {
+        '@' | '*' | '#' | '?' | '$' | '!' => 1,
+        c if c.is_ascii_digit() => inner.bytes().take_while(u8::is_ascii_digit).count(),
+        c if c.is_ascii_alphabetic() || c == '_' => inner
+            .bytes()
+            .take_while(|b| b.is_ascii_alphanumeric() || *b == b'_')
+            .count(),
+        _ => return None,
+    };
+
+    let (name, rest) = inner.split_at(name_len);
+    let Some(after_bracket) = rest.strip_prefix('[') else {
+        return Some((name, None, rest));
+    };
+    if !is_valid_name(name) {
+        return None;
+    }
+
+    let close = after_bracket.find(']')?;
+    Some((
+        name,
+        Some(&after_bracket[..close]),
+        &after_bracket[close + 1..],
+    ))
+}
+
+/// Splits the operator of `${name-word}` and its relatives off the text
+/// after the parameter name: whether it starts with `:`, which of `-`,
+/// `=`, `?` and `+` it is, and the word after it.
+fn split_test(rest: &str) -> Option<(bool, char, &str)> {
+    let (colon, rest) = match rest.strip_prefix(':') {
+        Some(rest) => (true, rest),
+        None => (false, rest),
+    };
+    let op = rest
+        .chars()
+        .next()
+        .filter(|c| matches!(c, '-' | '=' | '?' | '+'))?;
+    Some((colon, op, &rest[1..]))
+}
+
+/// Splits `text` at the first `separator` that is not quoted or escaped.
+fn split_unquoted(text: &str, separator: char) -> Option<(&str, &str)> {
+    let mut quote = None;
+    let mut escaped = false;
+
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            _ if escaped => escaped = false,
+            (Some('\''), '\'') | (Some('"'), '"') => quote = None,
+            (Some('\''), _) => {}
+            (_, '\\') => escaped = true,
+            (None, '\'' | '"') => quote = Some(c),
+            (None, c) if c == separator => return Some((&text[..i], &text[i + 1..])),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+impl Shell {
+    fn ifs(&mut self) -> String {
+        self.get_var("IFS")
+            .unwrap_or_else(|| DEFAULT_IFS.to_string())
+    }
+
+    /// Expands a raw word into zero or more fields: parameter expansion,
+    /// field splitting of unquoted results, pathname expansion, then quote
+    /// removal. A field with wildcards that match no file is kept as it is.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self), ret(level = "trace"))
+    )]
+    pub(crate) fn expand_word(&mut self, word: &str) -> Vec<String> {
+        let fields = self.expand(word, Context::Word);
+
+        // Setting GLOBIGNORE also lets wildcards match dotfiles.
+        let globignore = self.get_var("GLOBIGNORE").unwrap_or_default();
+        let ignore: Vec<&str> = globignore.split(':').filter(|p| !p.is_empty()).collect();
+        let dotfiles = !ignore.is_empty();
+
+        let mut words = Vec::new();
+        for field in fields {
+            if glob::has_wildcards(&field.pattern) {
+                let paths = glob::expand_paths(&field.pattern, dotfiles, &ignore, !self.posix);
+                if !paths.is_empty() {
+                    words.extend(paths);
+                    continue;
+                }
+            }
+            words.push(field.text);
+        }
+        words
+    }
+
+    /// Expands a word without field splitting, as done for `case` subjects
+    /// and assignment values.
+    pub(crate) fn expand_string(&mut self, word: &str) -> String {
+        self.expand_joined(word, Context::String)
+    }
+
+    /// Expands the value of an assignment, as `expand_string` does but
+    /// with `~` expanded after `:` too, as in `PATH=~/bin:~/.local/bin`.
+    pub(crate) fn expand_assignment(&mut self, value: &str) -> String {
+        self.expand_joined(value, Context::Assignment)
+    }
+
+    /// Expands the body of a here-document: as in double quotes, except
+    /// that `"` is an ordinary character.
+    pub(crate) fn expand_here_doc(&mut self, body: &str) -> String {
+        self.expand_joined(body, Context::HereDoc)
+    }
+
+    fn expand_joined(&mut self, word: &str, context: Context) -> String {
+        let fields: Vec<String> = self
+            .expand(word, context)
+            .into_iter()
+            .map(|field| field.text)
+            .collect();
+        fields.join(" ")
+    }
+
+    /// Expands a word into a glob pattern in which quoted characters are
+    /// escaped and so only match themselves.
+    pub(crate) fn expand_pattern(&mut self, word: &str) -> String {
+        let fields: Vec<String> = self
+            .expand(word, Context::String)
+            .into_iter()
+            .map(|field| field.pattern)
+            .collect();
+        fields.join(" ")
+    }
+
+    fn expand(&mut self, word: &str, context: Context) -> Vec<Field> {
+        let ifs = self.ifs();
+        let here_doc = context == Context::HereDoc;
+        let mut fields = Fields::new(context == Context::Word);
+        let mut in_single_quote = false;
+        let mut in_double_quote = here_doc;
+        let mut chars = word.chars().peekable();
+        // Whether a `~` here would start a tilde prefix.
+        let mut tilde_allowed = !here_doc;
+
+        while let Some(c) = chars.next() {
+            let tilde = std::mem::replace(&mut tilde_allowed, false);
+            match c {
+                '~' if tilde => self.expand_tilde(&mut chars, &mut fields, context),
+                ':' if context == Context::Assignment && !in_single_quote && !in_double_quote => {
+                    fields.push(c, false);
+                    tilde_allowed = true;
+                }
+                '\'' if !in_double_quote => {
+                    in_single_quote = !in_single_quote;
+                    fields.started = true;
+                }
+                '"' if !in_single_quote && !here_doc => {
+                    in_double_quote = !in_double_quote;
+                    fields.started = true;
+                }
+                _ if in_single_quote => fields.push(c, true),
+                '\\' if in_double_quote => match chars.next() {
+                    Some('\n') if here_doc => {}
+                    Some(next @ ('"' | '\\' | '$' | '`')) if !(here_doc && next == '"') => {
+                        fields.push(next, true)
+                    }
+                    Some(next) => {
+                        fields.push('\\', true);
+                        fields.push(next, true);
+                    }
+                    None => fields.push('\\', true),
+                },
+                '\\' => {
+                    if let Some(next) = chars.next() {
+                        fields.push(next, true);
+                    }
+                }
+                '$' if chars.clone().take(2).eq(['(', '(']) => {
+                    chars.nth(1);
+                    let expr = take_arith(&mut chars);
+                    let value = self.eval_index(&expr).to_string();
+                    if in_double_quote {
+                        fields.push_str(&value, true);
+                    } else {
+                        fields.push_split(&value, &ifs);
+                    }
+                }
+                '$' if chars.peek() == Some(&'(') => {
+                    chars.next();
+                    let source = take_substitution(&mut chars);
+                    let output = self.substitute(&source);
+                    if in_double_quote {
+                        fields.push_str(&output, true);
+                    } else {
+                        fields.push_split(&output, &ifs);
+                    }
+                }
+                '`' => {
+                    let source = take_backquoted(&mut chars, in_double_quote);
+                    let output = self.substitute(&source);
+                    if in_double_quote {
+                        fields.push_str(&output, true);
+                    } else {
+                        fields.push_split(&output, &ifs);
+                    }
+                }
+                '$' => self.expand_parameter(&mut chars, &mut fields, in_double_quote, &ifs),
+                _ => fields.push(c, in_double_quote),
+            }
+        }
+
+        fields.into_fields()
+    }
+
+    /// Replaces the tilde prefix after a `~` at the start of a word: the
+    /// characters up to the first `/` (or `:` in an assignment). `~` alone
+    /// is the home directory, `~+` and `~-` are `$PWD` and `$OLDPWD`, and
+    /// `~user` is that user's home directory. A prefix that is quoted or
+    /// names nothing known is left as it is.
+    fn expand_tilde(&mut self, chars: &mut Peekable<Chars>, fields: &mut Fields, context: Context) {
+        let prefix: String = chars
+            .clone()
+            .take_while(|&c| c != '/' && !(c == ':' && context == Context::Assignment))
+            .collect();
+        let dir = match prefix.as_str() {
+            "" => self.home(),
+            "+" => self.get_var("PWD"),
+            "-" => self.get_var("OLDPWD"),
+            user if !user.contains(['\'', '"', '\\', '$', '`']) => user_home(user),
+            _ => None,
+        };
+
+        match dir {
+            Some(dir) => {
+                for _ in prefix.chars() {
+                    chars.next();
+                }
+                fields.push_str(&dir, true);
+            }
+            None => fields.push('~', false),
+        }
+    }
+
+    /// The home directory: `$HOME`, or the user's profile directory when
+    /// it is unset.
+    pub(crate) fn home(&self) -> Option<String> {
+        self.get_var("HOME")
+            .filter(|home| !home.is_empty())
+            .or_else(|| home_dir().map(|home| osstr::from_os(home.as_os_str())))
+    }
+
+    /// `path` with the home directory at its start written as `~`, as the
+    /// prompt and `dirs` show it.
+    pub(crate) fn tilde_path(&self, path: &str) -> String {
+        let home = self.home();
+        match home.as_deref().and_then(|home| path.strip_prefix(home)) {
+            Some("") => "~".to_string(),
+            Some(rest) if rest.starts_with(std::path::is_separator) => format!("~{}", rest),
+            _ => path.to_string(),
+        }
+    }
+
+    /// Runs `source` for a command substitution and returns what it wrote
+    /// to stdout, less trailing newlines. It runs in this shell rather than
+    /// a subshell, so variables it sets remain set; `exit` ends only the
+    /// substitution.
+    fn substitute(&mut self, source: &str) -> String {
+        if self.no_exec {
+            return String::new();
+        }
+        let program = match self.parse(source) {
+            Ok(program) => program,
+            Err(error) => {
+                let error = ShellError::Syntax {
+                    origin: None,
+                    line: None,
+                    error,
+                };
+                self.report(&error);
+                self.expansion_failed.set(true);
+                return String::new();
+            }
+        };
+
+        let stdout = self.redirected.stdout.take();
+        self.substitution_depth += 1;
+        let (status, output) = self.with_capture(|shell| shell.execute(&program));
+        self.substitution_depth -= 1;
+        self.redirected.stdout = stdout;
+        self.flow = Flow::Normal;
+        self.last_status = status;
+        self.substitution_status = Some(status);
+
+        let output = String::from_utf8_lossy(&output);
+        output.trim_end_matches('\n').to_string()
+    }
+
+    /// Expands a word that must yield exactly one field, such as a
+    /// redirection target. Returns `None` when the result is ambiguous.
+    pub(crate) fn expand_word_single(&mut self, word: &str) -> Option<String> {
+        let mut fields = self.expand_word(word);
+        if fields.len() == 1 {
+            fields.pop()
+        } else {
+            None
+        }
+    }
+
+    fn expand_parameter(
+        &mut self,
+        chars: &mut Peekable<Chars>,
+        fields: &mut Fields,
+        quoted: bool,
+        ifs: &str,
+    ) {
+        let value = match chars.peek() {
+            Some('{') => {
+                chars.next();
+                let inner = take_braced(chars);
+                match self.braced_value(&inner) {
+                    Some(value) => value,
+                    None => {
+                        let message = format!("${{{}}}: bad substitution", inner);
+                        self.report(&ShellError::Expansion(message));
+                        return;
+                    }
+                }
+            }
+            Some(&c) if c.is_ascii_digit() || matches!(c, '@' | '*' | '#' | '?' | '$' | '!') => {
+                chars.next();
+                self.param_value(&c.to_string(), None)
+            }
+            Some(&c) if c.is_ascii_alphabetic() || c == '_' => {
+                let mut name = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || c == '_') {
+                        break;
+                    }
+                    name.push(c);
+                    chars.next();
+                }
+                self.param_value(&name, None)
+            }
+            _ => {
+                fields.push('$', quoted);
+                return;
+            }
+        };
+
+        match value {
+            ParamValue::Single(text) if quoted => fields.push_str(&text, true),
+            ParamValue::Single(text) => fields.push_split(&text, ifs),
+            ParamValue::List { items, joined } if quoted && !joined => {
+                fields.empty_at |= items.is_empty();
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        fields.finish();
+                    }
+                    fields.push_str(item, true);
+                }
+            }
+            ParamValue::List { items, .. } if quoted => {
+                let separator = ifs.chars().next().map(String::from).unwrap_or_default();
+                fields.push_str(&items.join(&separator), true);
+            }
+            ParamValue::List { items, .. } => {
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 && fields.split {
+                        if fields.started {
+                            fields.finish();
+                        }
+                    } else if i > 0 {
+                        fields.push(' ', false);
+                    }
+                    fields.push_split(item, ifs);
+                }
+            }
+        }
+    }
+
+    /// Evaluates the body of a `${...}` expansion. Returns `None` for a bad
+    /// substitution.
+    fn braced_value(&mut self, inner: &str) -> Option<ParamValue> {
+        if let Some(param) = inner.strip_prefix('#')
+            && !param.is_empty()
+        {
+            let (name, subscript, rest) = split_param(param)?;
+            if !rest.is_empty() {
+                return None;
+            }
+            let length = match self.param_value(name, subscript) {
+                ParamValue::Single(text) => text.chars().count(),
+                ParamValue::List { items, .. } => items.len(),
+            };
+            return Some(ParamValue::Single(length.to_string()));
+        }
+
+        if let Some(param) = inner.strip_prefix('!')
+            && !param.is_empty()
+            && !param.starts_with([':', '-', '=', '?', '+'])
+            && !self.posix
+        {
+            return self.indirect_value(param);
+        }
+
+        let (name, subscript, rest) = split_param(inner)?;
+        if let Some(spec) = rest.strip_prefix(':')
+            && !spec.starts_with(['-', '=', '?', '+'])
+            && !self.posix
+            && let Some(entries) = self.list_entries(name, subscript)
+        {
+            let (offset, length) = match split_unquoted(spec, ':') {
+                Some((offset, length)) => (offset, Some(length)),
+                None => (spec, None),
+            };
+            return Some(ParamValue::List {
+                items: self.slice(entries, offset, length),
+                joined: name == "*" || subscript == Some("*"),
+            });
+        }
+
+        if let Some((colon, op, word)) = split_test(rest) {
+            return Some(self.test_value(name, subscript, colon, op, word));
+        }
+
+        let value = self.param_value(name, subscript);
+        if rest.is_empty() {
+            return Some(value);
+        }
+
+        Some(match value {
+            ParamValue::Single(text) => ParamValue::Single(self.apply_operator(rest, &text)?),
+            ParamValue::List { items, joined } => ParamValue::List {
+                items: items
+                    .iter()
+                    .map(|item| self.apply_operator(rest, item))
+                    .collect::<Option<_>>()?,
+                joined,
+            },
+        })
+    }
+
+    /// Evaluates `${name-word}`, `${name=word}`, `${name?word}` and
+    /// `${name+word}`, which use, assign, complain about or replace the
+    /// value depending on whether the parameter is set. With a `:` after
+    /// the name an empty value counts as unset.
+    fn test_value(
+        &mut self,
+        name: &str,
+        subscript: Option<&str>,
+        colon: bool,
+        op: char,
+        word: &str,
+    ) -> ParamValue {
+        let value = self.param_value(name, subscript);
+        let set = match &value {
+            ParamValue::List { items, .. } => !items.is_empty(),
+            ParamValue::Single(text) => {
+                !text.is_empty() || (!colon && self.param_is_set(name, subscript))
+            }
+        };
+
+        match op {
+            '+' if set => ParamValue::Single(self.expand_string(word)),
+            '+' => ParamValue::Single(String::new()),
+            _ if set => value,
+            '-' => ParamValue::Single(self.expand_string(word)),
+            '=' if subscript.is_none() && is_valid_name(name) => {
+                let word = self.expand_string(word);
+                self.set_var(name, word.clone());
+                ParamValue::Single(word)
+            }
+            '=' => {
+                let message = format!("${}: cannot assign in this way", name);
+                self.report(&ShellError::Expansion(message));
+                ParamValue::Single(String::new())
+            }
+            _ => {
+                let message = match word {
+                    "" if colon => "parameter null or not set".to_string(),
+                    "" => "parameter not set".to_string(),
+                    word => self.expand_string(word),
+                };
+                self.report(&ShellError::Expansion(format!("{}: {}", name, message)));
+                // Like a syntax error, this ends a script.
+                if !self.interactive {
+                    self.fatal_status.set(Some(1));
+                }
+                ParamValue::Single(String::new())
+            }
+        }
+    }
+
+    /// Whether a parameter whose value is empty is set all the same. An
+    /// array element counts as set only if it is not empty.
+    fn param_is_set(&self, name: &str, subscript: Option<&str>) -> bool {
+        if let Ok(index) = name.parse::<usize>() {
+            return index <= self.positional.len();
+        }
+        if name == "!" {
+            return self.last_background.is_some();
+        }
+        if !is_valid_name(name) {
+            return true;
+        }
+        subscript.is_none() && self.get_var(name).is_some()
+    }
+
+    /// Evaluates `${!prefix@}`, `${!name[@]}` and the indirect `${!ref}`.
+    fn indirect_value(&mut self, param: &str) -> Option<ParamValue> {
+        if let Some(prefix) = param.strip_suffix(['@', '*'])
+            && (prefix.is_empty() || is_valid_name(prefix))
+        {
+            return Some(ParamValue::List {
+                items: self.var_names(prefix),
+                joined: param.ends_with('*'),
+            });
+        }
+
+        let (name, subscript, rest) = split_param(param)?;
+        if let Some(sub @ ("@" | "*")) = subscript {
+            if !rest.is_empty() {
+                return None;
+            }
+            let indices = self
+                .get_value(name)
+                .map(|value| value.entries().into_iter().map(|(i, _)| i.to_string()))
+                .into_iter()
+                .flatten()
+                .collect();
+            return Some(ParamValue::List {
+                items: indices,
+                joined: sub == "*",
+            });
+        }
+
+        if subscript.is_none()
+            && rest.is_empty()
+            && let Some(Value::Ref(target)) = self.vars.get(name)
+        {
+            return Some(ParamValue::Single(target.clone()));
+        }
+
+        let target = match self.param_value(name, subscript) {
+            ParamValue::Single(target) => target,
+            ParamValue::List { .. } => return None,
+        };
+        match split_param(&target) {
+            Some((_, _, "")) => self.braced_value(&format!("{}{}", target, rest)),
+            _ => None,
+        }
+    }
+
+    /// The elements of `$@` or `${name[@]}` with their indices, for slicing.
+    /// `$0` is included at index 0 of the positional parameters.
+    fn list_entries(&self, name: &str, subscript: Option<&str>) -> Option<Vec<(usize, String)>> {
+        match (name, subscript) {
+            ("@" | "*", None) => Some(
+                std::iter::once(self.shell_name.clone())
+                    .chain(self.positional.iter().cloned())
+                    .enumerate()
+                    .collect(),
+            ),
+            (name, Some("@" | "*")) => Some(
+                self.get_value(name)
+                    .map(|value| value.entries())
+                    .unwrap_or_default(),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Implements `${name[@]:offset:length}`: the elements whose index is at
+    /// least `offset`, at most `length` of them. A negative offset counts
+    /// back from one past the highest index.
+    fn slice(
+        &mut self,
+        entries: Vec<(usize, String)>,
+        offset: &str,
+        length: Option<&str>,
+    ) -> Vec<String> {
+        let end = entries.last().map_or(0, |(i, _)| *i as i64 + 1);
+        let mut start = self.eval_index(offset);
+        if start < 0 {
+            start += end;
+        }
+        let count = match length.map(|length| self.eval_index(length)) {
+            Some(n) if n < 0 => {
+                let message = format!("{}: substring expression < 0", n);
+                self.report(&ShellError::Expansion(message));
+                return Vec::new();
+            }
+            Some(n) => n as usize,
+            None => usize::MAX,
+        };
+        if start < 0 {
+            return Vec::new();
+        }
+
+        entries
+            .into_iter()
+            .filter(|(i, _)| *i as i64 >= start)
+            .take(count)
+            .map(|(_, item)| item)
+            .collect()
+    }
+
+    /// The names of set variables starting with `prefix`, sorted.
+    fn var_names(&self, prefix: &str) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .vars
+            .keys()
+            .cloned()
+            .chain(std::env::vars_os().map(|(name, _)| osstr::from_os(&name)))
+            .filter(|name| name.starts_with(prefix) && !self.unset_env.contains(name))
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Applies the operator part of `${name<op>}` to one value. In POSIX
+    /// mode only the pattern removal operators are known.
+    fn apply_operator(&mut self, op: &str, text: &str) -> Option<String> {
+        if self.posix && !op.starts_with(['#', '%']) {
+            return None;
+        }
+
+        if let Some(pattern) = op.strip_prefix("##") {
+            return Some(glob::remove_prefix(
+                &self.expand_pattern(pattern),
+                text,
+                true,
+            ));
+        }
+        if let Some(pattern) = op.strip_prefix('#') {
+            return Some(glob::remove_prefix(
+                &self.expand_pattern(pattern),
+                text,
+                false,
+            ));
+        }
+        if let Some(pattern) = op.strip_prefix("%%") {
+            return Some(glob::remove_suffix(
+                &self.expand_pattern(pattern),
+                text,
+                true,
+            ));
+        }
+        if let Some(pattern) = op.strip_prefix('%') {
+            return Some(glob::remove_suffix(
+                &self.expand_pattern(pattern),
+                text,
+                false,
+            ));
+        }
+
+        if let Some(spec) = op.strip_prefix('/') {
+            let (mode, spec) = match spec.chars().next() {
+                Some('/') => (ReplaceMode::All, &spec[1..]),
+                Some('#') => (ReplaceMode::Prefix, &spec[1..]),
+                Some('%') => (ReplaceMode::Suffix, &spec[1..]),
+                _ => (ReplaceMode::First, spec),
+            };
+            let (pattern, replacement) = split_unquoted(spec, '/').unwrap_or((spec, ""));
+            let pattern = self.expand_pattern(pattern);
+            let replacement = self.expand_string(replacement);
+            return Some(glob::replace(&pattern, text, &replacement, mode));
+        }
+
+        if let Some(spec) = op.strip_prefix(':')
+            && !spec.starts_with(['-', '=', '?', '+'])
+        {
+            let (offset, length) = match split_unquoted(spec, ':') {
+                Some((offset, length)) => (offset, Some(length)),
+                None => (spec, None),
+            };
+            return Some(self.substring(text, offset, length));
+        }
+
+        if op == "@Q" {
+            return Some(quote(text));
+        }
+
+        for (prefix, upper, all) in [
+            ("^^", true, true),
+            ("^", true, false),
+            (",,", false, true),
+            (",", false, false),
+        ] {
+            if let Some(pattern) = op.strip_prefix(prefix) {
+                return Some(self.modify_case(text, pattern, upper, all));
+            }
+        }
+
+        None
+    }
+
+    /// Implements `${name^pattern}` and friends: converts the first (or
+    /// every) character matching `pattern` to upper or lower case. An empty
+    /// pattern matches any character.
+    fn modify_case(&mut self, text: &str, pattern: &str, upper: bool, all: bool) -> String {
+        let pattern = match pattern {
+            "" => "?".to_string(),
+            pattern => self.expand_pattern(pattern),
+        };
+
+        let mut result = String::with_capacity(text.len());
+        for (i, c) in text.chars().enumerate() {
+            if (all || i == 0) && glob::matches(&pattern, c.encode_utf8(&mut [0; 4])) {
+                if upper {
+                    result.extend(c.to_uppercase());
+                } else {
+                    result.extend(c.to_lowercase());
+                }
+            } else {
+                result.push(c);
+            }
+        }
+        result
+    }
+
+    /// Implements `${name:offset:length}`, counting in characters. Negative
+    /// offsets count from the end; a negative length is an end offset.
+    fn substring(&mut self, text: &str, offset: &str, length: Option<&str>) -> String {
+        let chars: Vec<char> = text.chars().collect();
+        let len = chars.len() as i64;
+
+        let mut start = self.eval_index(offset);
+        if start < 0 {
+            start += len;
+        }
+        if start < 0 || start > len {
+            return String::new();
+        }
+
+        let end = match length.map(|length| self.eval_index(length)) {
+            None => len,
+            Some(n) if n < 0 => len + n,
+            Some(n) => (start + n).min(len),
+        };
+        if end < start {
+            return String::new();
+        }
+
+        chars[start as usize..end as usize].iter().collect()
+    }
+
+    /// Looks up a parameter, with an optional array subscript.
+    fn param_value(&mut self, name: &str, subscript: Option<&str>) -> ParamValue {
+        match name {
+            "@" | "*" => {
+                return ParamValue::List {
+                    items: self.positional.clone(),
+                    joined: name == "*",
+                };
+            }
+            "#" => return ParamValue::Single(self.positional.len().to_string()),
+            "?" => return ParamValue::Single(self.last_status.to_string()),
+            "$" => return ParamValue::Single(std::process::id().to_string()),
+            "!" => {
+                let pid = self.last_background.map(|pid| pid.to_string());
+                return ParamValue::Single(pid.unwrap_or_default());
+            }
+            "0" => return ParamValue::Single(self.shell_name.clone()),
+            _ => {}
+        }
+
+        // `${00}` is `$0`, as in bash.
+        if let Ok(index) = name.parse::<usize>() {
+            let value = match index.checked_sub(1) {
+                Some(index) => self.positional.get(index).cloned().unwrap_or_default(),
+                None => self.shell_name.clone(),
+            };
+            return ParamValue::Single(value);
+        }
+
+        let value = self.get_value(name);
+        match subscript {
+            None => ParamValue::Single(self.get_var(name).unwrap_or_default()),
+            Some(sub @ ("@" | "*")) => ParamValue::List {
+                items: value.map(|v| v.elements()).unwrap_or_default(),
+                joined: sub == "*",
+            },
+            Some(sub) => {
+                let element = value.and_then(|v| {
+                    let index = v.resolve_index(self.eval_index(sub))?;
+                    v.element(index).map(String::from)
+                });
+                ParamValue::Single(element.unwrap_or_default())
+            }
+        }
+    }
+
+    /// Evaluates `$((...))`, an array subscript or an offset as an
+    /// arithmetic expression. An error is an expansion error, so the
+    /// command it is part of does not run.
+    pub(crate) fn eval_index(&mut self, raw: &str) -> i64 {
+        match self.eval_arith(raw) {
+            Ok(value) => value,
+            Err(e) => {
+                self.report(&ShellError::Expansion(e.to_string()));
+                0
+            }
+        }
+    }
+
+    /// Expands parameters inside an arithmetic expression, then evaluates it.
+    pub(crate) fn eval_arith(&mut self, expr: &str) -> Result<i64, ArithError> {
+        let text = self.expand_string(expr);
+        arith::evaluate(&text, self)
+    }
+}
+
+/// The home directory of `user`, from the password database.
+#[cfg(unix)]
+fn user_home(user: &str) -> Option<String> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let name = std::ffi::CString::new(user).ok()?;
+    // SAFETY: getpwnam returns null or a record valid until the next call.
+    unsafe {
+        let entry = libc::getpwnam(name.as_ptr());
+        if entry.is_null() {
+            return None;
+        }
+        let dir = std::ffi::CStr::from_ptr((*entry).pw_dir);
+        Some(osstr::from_os(std::ffi::OsStr::from_bytes(dir.to_bytes())))
+    }
+}
+
+/// Windows has no database of other users' home directories to look in.
+#[cfg(windows)]
+fn user_home(_user: &str) -> Option<String> {
+    None
+}
@@ -0,0 +1,442 @@
+// ============================================
+// GLOB PATTERN MATCHING
+// ============================================
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Condvar, Mutex};
+use std::thread;
+
+use crate::osstr;
+
+/// Matches `text` against a shell pattern: `*` matches any string, `?` any
+/// single character, `[...]` a bracket expression, and `\\c` the literal `c`.
+pub fn matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let mut p = 0;
+    let mut t = 0;
+    // Position after the last `*` and the text position it is retried from.
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        let step = match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p + 1, t));
+                p += 1;
+                continue;
+            }
+            Some('?') => Some(p + 1),
+            Some('[') => match match_bracket(&pattern, p, text[t]) {
+                Some((true, next)) => Some(next),
+                Some((false, _)) => None,
+                None => (text[t] == '[').then_some(p + 1),
+            },
+            Some('\\') if p + 1 < pattern.len() => (pattern[p + 1] == text[t]).then_some(p + 2),
+            Some(&c) => (c == text[t]).then_some(p + 1),
+            None => None,
+        };
+
+        match step {
+            Some(next) => {
+                p = next;
+                t += 1;
+            }
+            None => match backtrack {
+                Some((star_p, star_t)) => {
+                    p = star_p;
+                    t = star_t + 1;
+                    backtrack = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Matches `c` against the bracket expression starting at `pattern[start]`.
+/// Returns whether it matched and the index just past the closing `]`, or
+/// `None` if the bracket is unterminated and should be taken literally.
+fn match_bracket(pattern: &[char], start: usize, c: char) -> Option<(bool, usize)> {
+    let mut i = start + 1;
+    let negated = matches!(pattern.get(i), Some('!' | '^'));
+    if negated {
+        i += 1;
+    }
+
+    let mut matched = false;
+    let mut first = true;
+
+    loop {
+        let current = *pattern.get(i)?;
+
+        if current == ']' && !first {
+            return Some((matched != negated, i + 1));
+        }
+        first = false;
+
+        if current == '['
+            && pattern.get(i + 1) == Some(&':')
+            && let Some(end) = find_class_end(pattern, i + 2)
+        {
+            let class: String = pattern[i + 2..end].iter().collect();
+            matched |= class_matches(&class, c);
+            i = end + 2;
+            continue;
+        }
+
+        let low = if current == '\\' {
+            i += 1;
+            *pattern.get(i)?
+        } else {
+            current
+        };
+        i += 1;
+
+        if pattern.get(i) == Some(&'-') && pattern.get(i + 1).is_some_and(|&n| n != ']') {
+            let mut high = pattern[i + 1];
+            i += 2;
+            if high == '\\' {
+                high = *pattern.get(i)?;
+                i += 1;
+            }
+            matched |= low <= c && c <= high;
+        } else {
+            matched |= low == c;
+        }
+    }
+}
+
+fn find_class_end(pattern: &[char], from: usize) -> Option<usize> {
+    (from..pattern.len().saturating_sub(1)).find(|&i| pattern[i] == ':' && pattern[i + 1] == ']')
+}
+
+fn class_matches(class: &str, c: char) -> bool {
+    match class {
+        "alnum" => c.is_alphanumeric(),
+        "alpha" => c.is_alphabetic(),
+        "blank" => c == ' ' || c == '\t',
+        "cntrl" => c.is_control(),
+        "digit" => c.is_ascii_digit(),
+        "graph" => !c.is_whitespace() && !c.is_control(),
+        "lower" => c.is_lowercase(),
+        "print" => !c.is_control(),
+        "punct" => c.is_ascii_punctuation(),
+        "space" => c.is_whitespace(),
+        "upper" => c.is_uppercase(),
+        "xdigit" => c.is_ascii_hexdigit(),
+        _ => false,
+    }
+}
+
+/// Whether `pattern` has an unescaped `*`, `?` or `[`, so that as a word
+/// it names files rather than standing for itself.
+pub fn has_wildcards(pattern: &str) -> bool {
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '*' | '?' | '[' => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Removes the backslashes escaping characters in a pattern.
+fn unescape(pattern: &str) -> String {
+    let mut text = String::with_capacity(pattern.len());
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        text.push(if c == '\\' {
+            chars.next().unwrap_or(c)
+        } else {
+            c
+        });
+    }
+    text
+}
+
+/// The paths matching `pattern`, sorted. Each `/` in the pattern only
+/// matches a `/`, and a pattern ending in `/` matches only directories. A
+/// leading `.` in a file name has to be matched by a `.` in the pattern
+/// unless `dotfiles` is set. Paths matching one of `ignore` are left out.
+///
+/// With `recursive`, a `**` component matches any number of directories,
+/// or as the last component every file and directory below, as bash's
+/// `globstar` does; otherwise it is the same as `*`. It is not
+/// recognised as part of a larger component such as `a**`.
+pub fn expand_paths(
+    pattern: &str,
+    dotfiles: bool,
+    ignore: &[&str],
+    recursive: bool,
+) -> Vec<String> {
+    let (pattern, dirs_only) = match pattern.strip_suffix('/') {
+        Some(pattern) if !pattern.is_empty() => (pattern, true),
+        _ => (pattern, false),
+    };
+    let (mut paths, rest) = match pattern.strip_prefix('/') {
+        Some(rest) => (vec!["/".to_string()], rest),
+        None => (vec![String::new()], pattern),
+    };
+    let is_dir = |path: &str| Path::new(&osstr::to_os(path)).is_dir();
+
+    let components: Vec<&str> = rest.split('/').filter(|c| !c.is_empty()).collect();
+    for (i, component) in components.iter().enumerate() {
+        let last = i + 1 == components.len();
+        if recursive && *component == "**" {
+            // Followed by a `/`, it matches the directories it starts from.
+            let files = last && !dirs_only;
+            let below = walk(&paths, dotfiles, files);
+            if files {
+                paths = below;
+            } else {
+                paths.extend(below);
+            }
+            continue;
+        }
+        let mut found = Vec::new();
+
+        for path in &paths {
+            if !has_wildcards(component) {
+                let candidate = join(path, &unescape(component));
+                if fs::symlink_metadata(osstr::to_os(&candidate)).is_ok() {
+                    found.push(candidate);
+                }
+                continue;
+            }
+
+            let dir = if path.is_empty() { "." } else { path };
+            let Ok(entries) = fs::read_dir(osstr::to_os(dir)) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let name = osstr::from_os(&entry.file_name());
+                if name.starts_with('.') && !dotfiles && !component.starts_with('.') {
+                    continue;
+                }
+                if !matches(component, &name) {
+                    continue;
+                }
+                let candidate = join(path, &name);
+                if last || is_dir(&candidate) {
+                    found.push(candidate);
+                }
+            }
+        }
+        paths = found;
+    }
+
+    if components.is_empty() {
+        return Vec::new();
+    }
+    if dirs_only {
+        paths.retain(|path| is_dir(path));
+        for path in &mut paths {
+            path.push('/');
+        }
+    }
+    paths.retain(|path| !ignore.iter().any(|pattern| matches(pattern, path)));
+    paths.sort();
+    // `**/**` reaches a directory more than one way.
+    paths.dedup();
+    paths
+}
+
+/// Every directory below the directories `roots`, and with `files` every
+/// other file too, sorted. The directories are read by a thread per core
+/// taking them from a shared queue, so that a large tree is walked in
+/// parallel; each thread sorts what it found and the lists are merged.
+/// Links to directories are not followed, so a link cycle cannot make the
+/// walk endless.
+fn walk(roots: &[String], dotfiles: bool, files: bool) -> Vec<String> {
+    // The directories left to read, and how many are being read, which
+    // may add more.
+    let queue = Mutex::new((roots.to_vec(), 0usize));
+    let changed = Condvar::new();
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+
+    let lists: Vec<Vec<String>> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut found = Vec::new();
+                    while let Some(dir) = next_dir(&queue, &changed) {
+                        let mut subdirs = Vec::new();
+                        let entries =
+                            fs::read_dir(osstr::to_os(if dir.is_empty() { "." } else { &dir }));
+                        for entry in entries.into_iter().flatten().flatten() {
+                            let name = osstr::from_os(&entry.file_name());
+                            if name.starts_with('.') && !dotfiles {
+                                continue;
+                            }
+                            let path = join(&dir, &name);
+                            if entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+                                subdirs.push(path.clone());
+                            } else if !files {
+                                continue;
+                            }
+                            found.push(path);
+                        }
+
+                        let Ok(mut state) = queue.lock() else { break };
+                        state.0.extend(subdirs);
+                        state.1 -= 1;
+                        changed.notify_all();
+                    }
+                    found.sort();
+                    found
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().unwrap_or_default())
+            .collect()
+    });
+    merge(lists)
+}
+
+/// Takes a directory off the queue for `walk`, waiting while the queue is
+/// empty but other threads may still add to it. `None` once the walk is
+/// over.
+fn next_dir(queue: &Mutex<(Vec<String>, usize)>, changed: &Condvar) -> Option<String> {
+    let mut queue = queue.lock().ok()?;
+    loop {
+        if let Some(dir) = queue.0.pop() {
+            queue.1 += 1;
+            return Some(dir);
+        }
+        if queue.1 == 0 {
+            return None;
+        }
+        queue = changed.wait(queue).ok()?;
+    }
+}
+
+/// Merges lists that are each sorted into one sorted list.
+fn merge(lists: Vec<Vec<String>>) -> Vec<String> {
+    let mut merged = Vec::with_capacity(lists.iter().map(Vec::len).sum());
+    let mut lists: Vec<_> = lists.into_iter().map(Vec::into_iter).collect();
+    let mut heads = BinaryHeap::new();
+    for (i, list) in lists.iter_mut().enumerate() {
+        if let Some(path) = list.next() {
+            heads.push(Reverse((path, i)));
+        }
+    }
+    while let Some(Reverse((path, i))) = heads.pop() {
+        merged.push(path);
+        if let Some(path) = lists[i].next() {
+            heads.push(Reverse((path, i)));
+        }
+    }
+    merged
+}
+
+fn join(dir: &str, name: &str) -> String {
+    if dir.is_empty() {
+        name.to_string()
+    } else if dir.ends_with('/') {
+        format!("{}{}", dir, name)
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+/// Removes the shortest (or longest) prefix of `text` matching `pattern`.
+pub fn remove_prefix(pattern: &str, text: &str, longest: bool) -> String {
+    let mut ends: Vec<usize> = text.char_indices().map(|(i, _)| i).collect();
+    ends.push(text.len());
+    if longest {
+        ends.reverse();
+    }
+
+    match ends.into_iter().find(|&end| matches(pattern, &text[..end])) {
+        Some(end) => text[end..].to_string(),
+        None => text.to_string(),
+    }
+}
+
+/// Removes the shortest (or longest) suffix of `text` matching `pattern`.
+pub fn remove_suffix(pattern: &str, text: &str, longest: bool) -> String {
+    let mut starts: Vec<usize> = text.char_indices().map(|(i, _)| i).collect();
+    starts.push(text.len());
+    if !longest {
+        starts.reverse();
+    }
+
+    match starts
+        .into_iter()
+        .find(|&start| matches(pattern, &text[start..]))
+    {
+        Some(start) => text[..start].to_string(),
+        None => text.to_string(),
+    }
+}
+
+/// Where a replacement pattern may match, for `${var/pattern/replacement}`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplaceMode {
+    First,
+    All,
+    Prefix,
+    Suffix,
+}
+
+/// Replaces the longest matches of `pattern` in `text`.
+pub fn replace(pattern: &str, text: &str, replacement: &str, mode: ReplaceMode) -> String {
+    if pattern.is_empty() {
+        return text.to_string();
+    }
+
+    let mut bounds: Vec<usize> = text.char_indices().map(|(i, _)| i).collect();
+    bounds.push(text.len());
+
+    let mut result = String::new();
+    let mut start_index = 0;
+
+    while start_index < bounds.len() {
+        let start = bounds[start_index];
+        let anchored_elsewhere = mode == ReplaceMode::Prefix && start != 0;
+
+        let matched_end = if anchored_elsewhere {
+            None
+        } else {
+            bounds[start_index..]
+                .iter()
+                .rev()
+                .copied()
+                .filter(|&end| mode != ReplaceMode::Suffix || end == text.len())
+                .find(|&end| end > start && matches(pattern, &text[start..end]))
+        };
+
+        match matched_end {
+            Some(end) => {
+                result.push_str(replacement);
+                if mode != ReplaceMode::All {
+                    result.push_str(&text[end..]);
+                    return result;
+                }
+                start_index = bounds
+                    .iter()
+                    .position(|&b| b == end)
+                    .unwrap_or(bounds.len());
+            }
+            None => {
+                if start_index + 1 < bounds.len() {
+                    result.push_str(&text[start..bounds[start_index + 1]]);
+                }
+                start_index += 1;
+            }
+        }
+    }
+
+    result
+}
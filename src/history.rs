@@ -0,0 +1,199 @@
+/// ============================================
+/// COMMAND HISTORY
+/// ============================================
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+
+use crate::error::ShellError;
+use crate::startup::home_dir;
+use crate::{Shell, osstr};
+
+/// The history file in the home directory, unless `HISTFILE` names another.
+const HISTORY_FILE: &str = ".shell_history";
+
+/// How many lines the history file keeps; older ones are dropped on save.
+const HISTORY_SIZE: usize = 1000;
+
+/// How many lines of the history file are read at startup, and how many
+/// more each time Up goes past the oldest line read.
+pub(crate) const HISTORY_CHUNK: usize = 200;
+
+/// How much of the history file is read at a time, going back from its end.
+const READ_SIZE: u64 = 8192;
+
+/// The lines typed at the prompt, oldest first.
+#[derive(Default)]
+pub(crate) struct History {
+    entries: Vec<String>,
+    /// The history file, when lines before the ones read remain in it,
+    /// and where the lines read begin.
+    unread: Option<(PathBuf, u64)>,
+    /// Whether lines were added since the history file was read.
+    modified: bool,
+}
+
+impl History {
+    /// Records `line`, unless it is blank or the same as the line before.
+    pub(crate) fn add(&mut self, line: &str) {
+        if line.trim().is_empty() || self.entries.last().is_some_and(|last| last == line) {
+            return;
+        }
+        self.entries.push(line.to_string());
+        self.modified = true;
+    }
+
+    pub(crate) fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// Reads up to `count` more of the history file's lines, before the
+    /// ones read so far, and returns how many were added. Only the part of
+    /// the file they are in is read.
+    pub(crate) fn load_older(&mut self, count: usize) -> usize {
+        if count == 0 {
+            return 0;
+        }
+        let Some((path, end)) = self.unread.take() else {
+            return 0;
+        };
+        let Ok((start, lines)) = File::open(&path).and_then(|file| read_before(file, end, count))
+        else {
+            return 0;
+        };
+        if start > 0 {
+            self.unread = Some((path, start));
+        }
+
+        let mut older: Vec<String> = Vec::new();
+        for line in lines {
+            if !line.trim().is_empty() && older.last() != Some(&line) {
+                older.push(line);
+            }
+        }
+        if older.last().is_some() && older.last() == self.entries.first() {
+            older.pop();
+        }
+        let added = older.len();
+        older.append(&mut self.entries);
+        self.entries = older;
+        added
+    }
+
+    /// How many entries the lines of the history file not read yet make,
+    /// counted without keeping them.
+    pub(crate) fn unread_count(&self) -> usize {
+        let Some((path, end)) = &self.unread else {
+            return 0;
+        };
+        let Ok(file) = File::open(path) else {
+            return 0;
+        };
+        let mut count = 0;
+        let mut previous = Vec::new();
+        for line in BufReader::new(file.take(*end)).split(b'\n') {
+            let Ok(line) = line else {
+                break;
+            };
+            if !line.trim_ascii().is_empty() && line != previous {
+                count += 1;
+                previous = line;
+            }
+        }
+        count
+    }
+}
+
+/// Reads the last `count` lines of `file` that end before byte `end`,
+/// going back from `end` a block at a time. Returns where the first of
+/// them begins, with the lines.
+fn read_before(mut file: File, end: u64, count: usize) -> io::Result<(u64, Vec<String>)> {
+    let mut start = end;
+    let mut text = Vec::new();
+    // Where the lines begin in `text`, once `count` of them are in it.
+    let mut first = None;
+    while start > 0 && first.is_none() {
+        let size = READ_SIZE.min(start);
+        start -= size;
+        let mut block = vec![0; size as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(&mut block)?;
+        block.append(&mut text);
+        text = block;
+
+        // The newline ending the last line does not begin one.
+        let body = text.strip_suffix(b"\n").unwrap_or(&text);
+        first = body
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|&(_, &byte)| byte == b'\n')
+            .nth(count.saturating_sub(1))
+            .map(|(index, _)| index + 1);
+    }
+
+    let first = first.unwrap_or(0);
+    let lines = String::from_utf8_lossy(&text[first..])
+        .lines()
+        .map(str::to_string)
+        .collect();
+    Ok((start + first as u64, lines))
+}
+
+impl Shell {
+    /// `$HISTFILE`, or `~/.shell_history` when it is unset.
+    fn history_file(&self) -> Option<PathBuf> {
+        match self.get_var("HISTFILE") {
+            Some(file) if file.is_empty() => None,
+            Some(file) => Some(PathBuf::from(osstr::to_os(&file))),
+            None => home_dir().map(|home| home.join(HISTORY_FILE)),
+        }
+    }
+
+    /// Reads the last lines of the history file, when the shell is
+    /// interactive; the older ones are read when asked for. Call after the
+    /// rc file, so that it can set `HISTFILE`.
+    pub fn load_history(&mut self) {
+        if !self.interactive {
+            return;
+        }
+        let Some(path) = self.history_file() else {
+            return;
+        };
+        let Ok(metadata) = fs::metadata(&path) else {
+            return;
+        };
+
+        let mut history = History {
+            unread: Some((path, metadata.len())),
+            ..History::default()
+        };
+        history.load_older(HISTORY_CHUNK);
+        history.entries.append(&mut self.history.entries);
+        history.modified = self.history.modified;
+        self.history = history;
+    }
+
+    /// Writes the history to the history file if lines were added to it.
+    pub(crate) fn save_history(&mut self) {
+        if !std::mem::take(&mut self.history.modified) {
+            return;
+        }
+        let Some(path) = self.history_file() else {
+            return;
+        };
+
+        let wanted = HISTORY_SIZE.saturating_sub(self.history.entries.len());
+        self.history.load_older(wanted);
+        let entries = self.history.entries();
+        let kept = &entries[entries.len().saturating_sub(HISTORY_SIZE)..];
+        let mut contents = kept.join("\n");
+        contents.push('\n');
+        if let Err(source) = fs::write(&path, contents) {
+            self.report(&ShellError::Io {
+                context: path.display().to_string(),
+                source,
+            });
+        }
+    }
+}
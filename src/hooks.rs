@@ -0,0 +1,66 @@
+/// ============================================
+/// PROMPT AND EXECUTION HOOKS
+/// ============================================
+use std::time::Duration;
+
+use crate::Shell;
+
+type PrePrompt = Box<dyn FnMut()>;
+type PreExec = Box<dyn FnMut(&str)>;
+type PostExec = Box<dyn FnMut(i32, Duration)>;
+
+/// Callbacks registered through `Shell::on_pre_prompt`, `on_pre_exec` and
+/// `on_post_exec`, run in the order they were added.
+#[derive(Default)]
+pub struct Hooks {
+    pre_prompt: Vec<PrePrompt>,
+    pre_exec: Vec<PreExec>,
+    post_exec: Vec<PostExec>,
+}
+
+impl Hooks {
+    pub fn add_pre_prompt(&mut self, hook: impl FnMut() + 'static) {
+        self.pre_prompt.push(Box::new(hook));
+    }
+
+    pub fn add_pre_exec(&mut self, hook: impl FnMut(&str) + 'static) {
+        self.pre_exec.push(Box::new(hook));
+    }
+
+    pub fn add_post_exec(&mut self, hook: impl FnMut(i32, Duration) + 'static) {
+        self.post_exec.push(Box::new(hook));
+    }
+
+    pub fn pre_prompt(&mut self) {
+        for hook in &mut self.pre_prompt {
+            hook();
+        }
+    }
+
+    pub fn pre_exec(&mut self, command: &str) {
+        for hook in &mut self.pre_exec {
+            hook(command);
+        }
+    }
+
+    pub fn post_exec(&mut self, status: i32, duration: Duration) {
+        for hook in &mut self.post_exec {
+            hook(status, duration);
+        }
+    }
+}
+
+impl Shell {
+    /// Calls the shell function `name`, if one is defined, as a hook:
+    /// `precmd` before each prompt and `preexec` with the command line
+    /// before it runs, as zsh does. `$?` is left as it was.
+    pub(crate) fn call_hook_function(&mut self, name: &str, args: Vec<String>) {
+        let Some(function) = self.functions.get(name).cloned() else {
+            return;
+        };
+        let status = self.last_status;
+        self.call_function(name, &function, args);
+        self.flush_output();
+        self.last_status = status;
+    }
+}
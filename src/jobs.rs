@@ -0,0 +1,599 @@
+/// ============================================
+/// JOB CONTROL
+/// ============================================
+use std::io;
+#[cfg(unix)]
+use std::io::IsTerminal;
+use std::time::Duration;
+
+use crate::parser::Command;
+use crate::pipeline::Started;
+use crate::{Shell, signals};
+
+/// How often `wait` checks whether the jobs it waits for have finished.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// The status of a job stopped by Ctrl-Z, as for a process stopped by
+/// SIGTSTP.
+#[cfg(unix)]
+const STOPPED_STATUS: i32 = 128 + libc::SIGTSTP;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum JobState {
+    Running,
+    // Jobs never stop on Windows.
+    #[cfg_attr(windows, allow(dead_code))]
+    Stopped,
+    /// Every process has ended; the status is that of the last one.
+    Done(i32),
+}
+
+impl JobState {
+    pub(crate) fn describe(self) -> String {
+        match self {
+            JobState::Running => "Running".to_string(),
+            JobState::Stopped => "Stopped".to_string(),
+            JobState::Done(0) => "Done".to_string(),
+            JobState::Done(status) => format!("Exit {}", status),
+        }
+    }
+}
+
+/// What `Process::poll` saw happen to a process.
+enum Event {
+    Exited(i32),
+    #[cfg(unix)]
+    Stopped,
+}
+
+/// A process started for a job, and its status once it has ended.
+struct Process {
+    child: Started,
+    status: Option<i32>,
+}
+
+impl Process {
+    /// Checks whether the process has ended or stopped, waiting until it
+    /// does if `block` is set.
+    #[cfg(unix)]
+    fn poll(&mut self, block: bool) -> Option<Event> {
+        let flags = if block {
+            libc::WUNTRACED
+        } else {
+            libc::WUNTRACED | libc::WNOHANG
+        };
+        let mut status = 0;
+        let pid = unsafe { libc::waitpid(self.child.id() as libc::pid_t, &mut status, flags) };
+        if pid == 0 {
+            return None;
+        }
+        if pid < 0 {
+            // A copy of the shell forked for a pipeline stage sees the
+            // shell's jobs but cannot wait for them.
+            if unsafe { libc::kill(self.child.id() as libc::pid_t, 0) } == 0 {
+                return None;
+            }
+            // Nothing more can be learnt of a process that cannot be waited
+            // for.
+            return Some(Event::Exited(1));
+        }
+
+        if libc::WIFSTOPPED(status) {
+            Some(Event::Stopped)
+        } else {
+            Some(Event::Exited(exit_status(status)))
+        }
+    }
+
+    #[cfg(windows)]
+    fn poll(&mut self, block: bool) -> Option<Event> {
+        let Started::Program(child) = &mut self.child;
+        let status = if block {
+            child.wait().map(Some)
+        } else {
+            child.try_wait()
+        };
+        match status {
+            Ok(Some(status)) => Some(Event::Exited(Shell::exit_code(status))),
+            Ok(None) => None,
+            Err(_) => Some(Event::Exited(1)),
+        }
+    }
+}
+
+/// A command run with `&`, or stopped with Ctrl-Z: the programs of one
+/// pipeline.
+pub(crate) struct Job {
+    pub(crate) id: usize,
+    /// The command line, as `jobs` shows it.
+    pub(crate) command: String,
+    processes: Vec<Process>,
+    pub(crate) state: JobState,
+}
+
+impl Job {
+    fn new(command: String, children: Vec<Started>) -> Self {
+        Job {
+            id: 0,
+            command,
+            processes: children
+                .into_iter()
+                .map(|child| Process {
+                    child,
+                    status: None,
+                })
+                .collect(),
+            state: JobState::Running,
+        }
+    }
+
+    pub(crate) fn pids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.processes.iter().map(|process| process.child.id())
+    }
+
+    /// The CPU time the job's processes still running have used, with
+    /// that of the children they have waited for, where the system tells.
+    /// A finished job has none.
+    #[cfg(all(feature = "line-editor", target_os = "linux"))]
+    pub(crate) fn cpu_time(&self) -> Option<Duration> {
+        let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+        if ticks <= 0 {
+            return None;
+        }
+        if self
+            .processes
+            .iter()
+            .all(|process| process.status.is_some())
+        {
+            return None;
+        }
+        let mut total = 0;
+        for process in self
+            .processes
+            .iter()
+            .filter(|process| process.status.is_none())
+        {
+            let path = format!("/proc/{}/stat", process.child.id());
+            let Ok(stat) = std::fs::read_to_string(path) else {
+                continue;
+            };
+            // utime, stime, cutime and cstime are the 14th to 17th fields;
+            // the 2nd, the command name in parentheses, may hold spaces.
+            let Some((_, fields)) = stat.rsplit_once(')') else {
+                continue;
+            };
+            total += fields
+                .split_whitespace()
+                .skip(11)
+                .take(4)
+                .filter_map(|field| field.parse::<u64>().ok())
+                .sum::<u64>();
+        }
+        Some(Duration::from_millis(total * 1000 / ticks as u64))
+    }
+
+    #[cfg(all(feature = "line-editor", not(target_os = "linux")))]
+    pub(crate) fn cpu_time(&self) -> Option<Duration> {
+        None
+    }
+
+    /// The process group of the job, which its first process leads.
+    #[cfg(unix)]
+    pub(crate) fn group(&self) -> libc::pid_t {
+        self.processes[0].child.id() as libc::pid_t
+    }
+
+    /// Collects the processes that have ended or stopped, waiting for one
+    /// of those if `block` is set. Returns whether the state changed.
+    fn update(&mut self, block: bool) -> bool {
+        let before = self.state;
+        for process in self.processes.iter_mut() {
+            if process.status.is_some() {
+                continue;
+            }
+            match process.poll(block) {
+                Some(Event::Exited(status)) => process.status = Some(status),
+                #[cfg(unix)]
+                Some(Event::Stopped) => {
+                    self.state = JobState::Stopped;
+                    return true;
+                }
+                None => {}
+            }
+        }
+
+        if self
+            .processes
+            .iter()
+            .all(|process| process.status.is_some())
+        {
+            let status = self.processes.last().and_then(|process| process.status);
+            self.state = JobState::Done(status.unwrap_or(0));
+        }
+        self.state != before
+    }
+
+    /// Sends `signal` to every process of the job. A stopped job is also
+    /// continued for SIGTERM and SIGHUP, which it could not act on
+    /// otherwise.
+    #[cfg(unix)]
+    pub(crate) fn signal(&mut self, signal: i32) -> io::Result<()> {
+        if unsafe { libc::kill(-self.group(), signal) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if matches!(signal, libc::SIGTERM | libc::SIGHUP) && self.state == JobState::Stopped {
+            return self.resume();
+        }
+        if signal == libc::SIGCONT && self.state == JobState::Stopped {
+            self.state = JobState::Running;
+        }
+        Ok(())
+    }
+
+    /// Ends every process of the job, as Windows has no other signals.
+    /// Signal 0 does nothing.
+    #[cfg(windows)]
+    pub(crate) fn signal(&mut self, signal: i32) -> io::Result<()> {
+        if signal == 0 {
+            return Ok(());
+        }
+        for process in &mut self.processes {
+            let Started::Program(child) = &mut process.child;
+            if process.status.is_none() {
+                child.kill()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Continues a stopped job.
+    #[cfg(unix)]
+    pub(crate) fn resume(&mut self) -> io::Result<()> {
+        self.signal(libc::SIGCONT)
+    }
+
+    /// Jobs never stop on Windows, so there is nothing to continue.
+    #[cfg(windows)]
+    pub(crate) fn resume(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The jobs of a shell, numbered from 1 in the order they were started.
+#[derive(Default)]
+pub(crate) struct Jobs {
+    jobs: Vec<Job>,
+    /// Job ids, the current job (`%+`) last and the previous job (`%-`)
+    /// before it.
+    recent: Vec<usize>,
+}
+
+impl Jobs {
+    /// Adds `job` as the current job and returns its id.
+    fn add(&mut self, mut job: Job) -> usize {
+        let id = self.jobs.last().map_or(1, |job| job.id + 1);
+        job.id = id;
+        self.jobs.push(job);
+        self.recent.push(id);
+        id
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &Job> {
+        self.jobs.iter()
+    }
+
+    pub(crate) fn get(&self, id: usize) -> Option<&Job> {
+        self.jobs.iter().find(|job| job.id == id)
+    }
+
+    pub(crate) fn get_mut(&mut self, id: usize) -> Option<&mut Job> {
+        self.jobs.iter_mut().find(|job| job.id == id)
+    }
+
+    pub(crate) fn remove(&mut self, id: usize) -> Option<Job> {
+        self.recent.retain(|&recent| recent != id);
+        let index = self.jobs.iter().position(|job| job.id == id)?;
+        Some(self.jobs.remove(index))
+    }
+
+    pub(crate) fn make_current(&mut self, id: usize) {
+        self.recent.retain(|&recent| recent != id);
+        self.recent.push(id);
+    }
+
+    /// The job that `pid` is one of the processes of.
+    pub(crate) fn find_pid(&self, pid: u32) -> Option<usize> {
+        self.jobs
+            .iter()
+            .find(|job| job.pids().any(|job_pid| job_pid == pid))
+            .map(|job| job.id)
+    }
+
+    /// `+` for the current job, `-` for the previous one, as `jobs` marks
+    /// them.
+    pub(crate) fn mark(&self, id: usize) -> char {
+        match self.recent.iter().rev().position(|&recent| recent == id) {
+            Some(0) => '+',
+            Some(1) => '-',
+            _ => ' ',
+        }
+    }
+
+    /// The line `jobs` and job notices show for job `id`.
+    pub(crate) fn describe(&self, id: usize) -> Option<String> {
+        let job = self.get(id)?;
+        let suffix = if job.state == JobState::Running {
+            " &"
+        } else {
+            ""
+        };
+        Some(format!(
+            "[{}]{}  {:<24}{}{}",
+            job.id,
+            self.mark(id),
+            job.state.describe(),
+            job.command,
+            suffix
+        ))
+    }
+
+    /// Resolves a job specifier to a job id: `%%`, `%+` or `%` for the
+    /// current job, `%-` for the previous one, `%n` for job n, `%str` for
+    /// the job whose command starts with str and `%?str` for the one whose
+    /// command contains it. Errors are messages for the builtin to report.
+    pub(crate) fn resolve(&self, spec: &str) -> Result<usize, String> {
+        let no_such_job = || format!("{}: no such job", spec);
+        let Some(rest) = spec.strip_prefix('%') else {
+            return Err(no_such_job());
+        };
+
+        match rest {
+            "" | "%" | "+" => self.recent.last().copied().ok_or_else(no_such_job),
+            "-" => self
+                .recent
+                .iter()
+                .rev()
+                .nth(1)
+                .or(self.recent.last())
+                .copied()
+                .ok_or_else(no_such_job),
+            n if n.bytes().all(|b| b.is_ascii_digit()) => n
+                .parse()
+                .ok()
+                .filter(|&id| self.get(id).is_some())
+                .ok_or_else(no_such_job),
+            pattern => {
+                let mut matching = self
+                    .jobs
+                    .iter()
+                    .filter(|job| match pattern.strip_prefix('?') {
+                        Some(text) => job.command.contains(text),
+                        None => job.command.starts_with(pattern),
+                    });
+                match (matching.next(), matching.next()) {
+                    (Some(job), None) => Ok(job.id),
+                    (Some(_), Some(_)) => Err(format!("{}: ambiguous job spec", spec)),
+                    (None, _) => Err(no_such_job()),
+                }
+            }
+        }
+    }
+}
+
+impl Shell {
+    /// Runs `command &`, leaving the processes it starts running as a job.
+    /// On Windows a builtin, function or compound command, which has no
+    /// process to leave running, runs to completion first. Either way the
+    /// status is 0.
+    pub(crate) fn execute_background(&mut self, command: &Command, text: &str) -> i32 {
+        let stages = match command {
+            Command::Pipeline(stages, _) => stages.as_slice(),
+            command => std::slice::from_ref(command),
+        };
+
+        self.job_group = Some(0);
+        let started = self.start_pipeline(stages, true);
+        let children: Vec<Started> = started
+            .running
+            .into_iter()
+            .map(|(_, child)| child)
+            .collect();
+        self.job_group = None;
+        if children.is_empty() {
+            return 0;
+        }
+
+        self.last_background = children.last().map(Started::id);
+        let id = self.jobs.add(Job::new(text.to_string(), children));
+        if self.interactive
+            && let Some(pid) = self.last_background
+        {
+            self.flush_output();
+            eprintln!("[{}] {}", id, pid);
+        }
+        0
+    }
+
+    /// Collects jobs that have finished or stopped. In an interactive
+    /// shell each is announced through the notifier, and finished jobs are
+    /// then forgotten; a script keeps them for `wait`.
+    pub(crate) fn poll_jobs(&mut self) {
+        if !self.interactive {
+            return;
+        }
+
+        let mut changed = Vec::new();
+        for job in self.jobs.jobs.iter_mut() {
+            if job.update(false) {
+                changed.push(job.id);
+            }
+        }
+        for id in changed {
+            if let Some(line) = self.jobs.describe(id) {
+                self.notices.post(line);
+            }
+            if let Some(JobState::Done(_)) = self.jobs.get(id).map(|job| job.state) {
+                self.jobs.remove(id);
+            }
+        }
+    }
+
+    /// Updates the state of every job without waiting.
+    pub(crate) fn update_jobs(&mut self) {
+        for job in self.jobs.jobs.iter_mut() {
+            job.update(false);
+        }
+    }
+
+    /// Continues job `id` in the foreground and waits for it to finish or
+    /// stop. A finished job is forgotten; the status is its last process's.
+    pub(crate) fn foreground(&mut self, id: usize) -> i32 {
+        self.flush_output();
+        self.jobs.make_current(id);
+        let Some(job) = self.jobs.get_mut(id) else {
+            return 1;
+        };
+
+        #[cfg(unix)]
+        {
+            let interactive = self.interactive;
+            if interactive {
+                give_terminal(job.group());
+            }
+            if job.state == JobState::Stopped {
+                let _ = job.resume();
+            }
+            job.update(true);
+            if interactive {
+                give_terminal(unsafe { libc::getpgrp() });
+            }
+        }
+        #[cfg(windows)]
+        job.update(true);
+
+        match job.state {
+            JobState::Done(status) => {
+                self.jobs.remove(id);
+                status
+            }
+            #[cfg(unix)]
+            JobState::Stopped => {
+                self.announce_stopped(id);
+                STOPPED_STATUS
+            }
+            _ => 0,
+        }
+    }
+
+    /// Shows the line for job `id` once Ctrl-Z has stopped it, below the
+    /// `^Z` the terminal echoed.
+    #[cfg(unix)]
+    fn announce_stopped(&self, id: usize) {
+        if let Some(line) = self.jobs.describe(id) {
+            self.flush_output();
+            eprintln!();
+            eprintln!("{}", line);
+        }
+    }
+
+    /// Whether foreground commands run as jobs, in a process group of
+    /// their own that is given the terminal, so that Ctrl-Z stops them and
+    /// not the shell. That takes an interactive shell reading from a
+    /// terminal, and output that is not being captured.
+    #[cfg(unix)]
+    pub(crate) fn job_control(&self) -> bool {
+        self.interactive && self.capture.is_none() && io::stdin().is_terminal()
+    }
+
+    /// Waits for the processes of a foreground command, started in a
+    /// process group of their own, and gives that group the terminal
+    /// meanwhile. Returns the status of each process; if they stop
+    /// instead, they become a job, and the error is the status for that.
+    #[cfg(unix)]
+    pub(crate) fn wait_foreground(
+        &mut self,
+        command: &str,
+        processes: Vec<Started>,
+    ) -> Result<Vec<i32>, i32> {
+        let mut job = Job::new(command.to_string(), processes);
+        self.flush_output();
+        give_terminal(job.group());
+        job.update(true);
+        give_terminal(unsafe { libc::getpgrp() });
+
+        if job.state == JobState::Stopped {
+            let id = self.jobs.add(job);
+            self.announce_stopped(id);
+            return Err(STOPPED_STATUS);
+        }
+        let statuses: Vec<i32> = job
+            .processes
+            .iter()
+            .map(|process| process.status.unwrap_or(0))
+            .collect();
+        // Ctrl-C went to the job alone; ending it stops the shell's loops
+        // and lists too, as if the shell had seen it.
+        if statuses.contains(&(128 + libc::SIGINT)) {
+            signals::interrupt();
+        }
+        Ok(statuses)
+    }
+
+    /// Waits until every job in `ids` has finished, or with `any` until one
+    /// of them has. Finished jobs are forgotten. Returns the id and status
+    /// of the job that finished last, or `None` if Ctrl-C ended the wait.
+    pub(crate) fn wait_for_jobs(&mut self, ids: &[usize], any: bool) -> Option<(usize, i32)> {
+        let mut finished = None;
+        let mut pending = ids.to_vec();
+
+        loop {
+            for job in self.jobs.jobs.iter_mut() {
+                if pending.contains(&job.id) {
+                    job.update(false);
+                }
+            }
+            for &id in &pending {
+                if let Some(JobState::Done(status)) = self.jobs.get(id).map(|job| job.state) {
+                    self.jobs.remove(id);
+                    finished = Some((id, status));
+                }
+            }
+            pending.retain(|&id| self.jobs.get(id).is_some());
+
+            if pending.is_empty() || (any && finished.is_some()) {
+                return finished;
+            }
+            if signals::interrupted() {
+                return None;
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+/// The status of a process that ended with wait status `status`: its exit
+/// code, or 128 plus the signal that ended it.
+#[cfg(unix)]
+pub(crate) fn exit_status(status: libc::c_int) -> i32 {
+    if libc::WIFSIGNALED(status) {
+        128 + libc::WTERMSIG(status)
+    } else {
+        libc::WEXITSTATUS(status)
+    }
+}
+
+/// Makes `group` the foreground process group of the terminal. SIGTTOU is
+/// blocked meanwhile, as it stops a shell that is not in the foreground
+/// group when it takes the terminal back.
+#[cfg(unix)]
+fn give_terminal(group: libc::pid_t) {
+    unsafe {
+        let mut blocked: libc::sigset_t = std::mem::zeroed();
+        let mut saved: libc::sigset_t = std::mem::zeroed();
+        libc::sigemptyset(&mut blocked);
+        libc::sigaddset(&mut blocked, libc::SIGTTOU);
+        libc::sigprocmask(libc::SIG_BLOCK, &blocked, &mut saved);
+        libc::tcsetpgrp(libc::STDIN_FILENO, group);
+        libc::sigprocmask(libc::SIG_SETMASK, &saved, std::ptr::null_mut());
+    }
+}
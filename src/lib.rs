@@ -0,0 +1,1719 @@
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::PipeWriter;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command as ProcessCommand, ExitStatus, Stdio};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+pub use builtins::Builtin;
+use builtins::{CompletionSpec, Registry};
+#[cfg(feature = "line-editor")]
+use editor::LineEditor;
+pub use embed::CommandResult;
+use error::ShellError;
+#[cfg(feature = "history")]
+use history::History;
+use hooks::Hooks;
+use lookup::CommandCache;
+#[cfg(feature = "completion")]
+use lookup::CommandIndex;
+pub use notices::Notifier;
+use parser::{
+    ArithForClause, AssignValue, Assignment, CaseClause, CaseTerminator, Command, ForClause,
+    HereDoc, IfClause, ParseError, Redirect, SimpleCommand, StreamType, WhileClause,
+};
+use pipeline::Stage;
+use shell_io::{Capture, SharedStdout, ShellIo, Streams};
+use variables::Value;
+
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+
+/// Enters a `tracing` span lasting to the end of the enclosing block when
+/// built with the `tracing` feature, and does nothing otherwise.
+macro_rules! trace_span {
+    ($($span:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!($($span)*).entered();
+    };
+}
+
+mod alias;
+mod arith;
+mod audit;
+mod builtins;
+#[cfg(feature = "completion")]
+mod completion;
+#[cfg(feature = "line-editor")]
+mod editor;
+mod embed;
+mod error;
+mod expand;
+mod glob;
+#[cfg(feature = "history")]
+mod history;
+mod hooks;
+mod jobs;
+mod json;
+mod lookup;
+#[cfg(feature = "completion")]
+mod lscolors;
+mod notices;
+mod notify;
+pub mod options;
+mod osstr;
+pub mod parser;
+mod paths;
+mod pipeline;
+pub mod plugin;
+mod prompt;
+mod record;
+pub mod shell_io;
+mod signals;
+mod startup;
+#[cfg(feature = "line-editor")]
+mod terminal;
+#[cfg(feature = "prompt-theme")]
+mod theme;
+mod variables;
+pub mod version;
+#[cfg(windows)]
+mod winjob;
+#[cfg(windows)]
+mod winquote;
+
+/// Outcome of reading one line of input.
+#[derive(Debug, Clone, PartialEq)]
+enum ReadStatus {
+    Line(String),
+    /// Ctrl-C at the line editor.
+    #[cfg(feature = "line-editor")]
+    Interrupted,
+    Eof,
+}
+
+/// Writes `message` and a newline, turning escaped bytes back into raw ones.
+fn write_line(out: &mut (impl Write + ?Sized), message: &str) {
+    let _ = out.write_all(&osstr::to_bytes(message));
+    let _ = out.write_all(b"\n");
+}
+
+// ============================================
+// SHELL STRUCTURES
+// ============================================
+
+/// Non-local control flow requested by a builtin, unwinding the executor
+/// until the construct that handles it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Flow {
+    Normal,
+    Return,
+    /// `exit` inside a command substitution, which ends only the
+    /// substitution, or in an embedded shell, which ends only the
+    /// `run_line` or `run_script` call.
+    Exit,
+    /// `break n`: leave this many enclosing loops.
+    Break(usize),
+    /// `continue n`: resume the n-th enclosing loop.
+    Continue(usize),
+}
+
+/// A shell function, with the line numbering of the input it was
+/// defined in.
+#[derive(Clone)]
+struct Function {
+    body: Rc<Command>,
+    line_base: usize,
+    /// The file it was defined in, or `main` for the shell's own input.
+    source: String,
+}
+
+/// Where the standard streams go for every command run while a function
+/// runs with its output redirected (see `call_redirected`) or a pipeline
+/// stage runs (see `execute_pipeline`).
+#[derive(Default)]
+struct OuterRedirects {
+    stdin: Option<Target>,
+    stdout: Option<Target>,
+    stderr: Option<Target>,
+}
+
+impl OuterRedirects {
+    /// New handles on the same files.
+    fn try_clone(&self) -> Self {
+        let clone =
+            |target: &Option<Target>| target.as_ref().and_then(|target| target.try_clone().ok());
+        Self {
+            stdin: clone(&self.stdin),
+            stdout: clone(&self.stdout),
+            stderr: clone(&self.stderr),
+        }
+    }
+
+    fn target(&mut self, stream: &StreamType) -> &mut Option<Target> {
+        match stream {
+            StreamType::Stdin => &mut self.stdin,
+            StreamType::Stdout => &mut self.stdout,
+            StreamType::Stderr => &mut self.stderr,
+        }
+    }
+}
+
+/// Where a redirected stream goes: a file, or the stream a command would
+/// have without redirections, as `2>&1` leaves stderr when stdout is not
+/// redirected.
+enum Target {
+    File(File),
+    Standard(StreamType),
+}
+
+impl Target {
+    fn try_clone(&self) -> io::Result<Self> {
+        Ok(match self {
+            Self::File(file) => Self::File(file.try_clone()?),
+            Self::Standard(stream) => Self::Standard(*stream),
+        })
+    }
+}
+
+/// A function call or sourced file in progress, as `FUNCNAME`,
+/// `BASH_SOURCE`, `BASH_LINENO` and `caller` report it.
+struct Frame {
+    /// The function's name, or `source`.
+    name: String,
+    /// The file the code being run comes from.
+    source: String,
+    /// The line it was called from.
+    line: usize,
+}
+
+#[derive(Debug)]
+struct ParsedCommand {
+    args: Vec<String>,
+    redirects: Vec<Redirect>,
+    /// Assignments written before the command name, placed in the
+    /// environment of that command only.
+    env: Vec<(String, String)>,
+}
+
+/// Expands `word` as the shell would with only the variables in `env` set,
+/// without running any commands: `$(...)` and backquotes expand to
+/// nothing. Meant for fuzzing the expander.
+pub fn expand(word: &str, env: &[(String, String)]) -> Vec<String> {
+    thread_local! {
+        static SHELL: RefCell<Shell> = RefCell::new(Shell::new());
+    }
+
+    SHELL.with_borrow_mut(|shell| {
+        shell.no_exec = true;
+        shell.vars.clear();
+        for (name, value) in env {
+            if parser::is_valid_name(name) {
+                shell.set_var(name, value.clone());
+            }
+        }
+        shell.expand_word(word)
+    })
+}
+
+/// An interactive shell: its variables, functions and line editor, and the
+/// REPL that reads and runs commands.
+pub struct Shell {
+    /// Where commands were found in PATH. Lookups happen behind `&self`,
+    /// hence the `RefCell`.
+    commands: RefCell<CommandCache>,
+    /// Command names in PATH for completion, scanned in the background.
+    #[cfg(feature = "completion")]
+    command_index: CommandIndex,
+    /// Builtin output, buffered and written in one go; see `flush_output`.
+    out: SharedStdout,
+    /// Where stdout goes instead of the terminal while output is being
+    /// captured; see `with_capture`.
+    capture: Option<Capture>,
+    /// Whether `run_line` and `run_script` capture stdout.
+    capture_output: bool,
+    /// Set while `run_line` or `run_script` runs, so that `exit` ends only
+    /// that call instead of the program embedding the shell.
+    embedded: bool,
+    /// The redirections of the functions and pipeline being run.
+    redirected: OuterRedirects,
+    /// How the pipeline stage about to run is started, and the process it
+    /// started, if any; see `start_pipeline`.
+    stage: Option<pipeline::Stage>,
+    started: Option<pipeline::Started>,
+    /// The read end of the pipe the stage about to run writes to, which a
+    /// forked stage closes.
+    #[cfg(unix)]
+    pipe_reader: Option<std::os::fd::RawFd>,
+    /// Set in a copy of the shell forked for a pipeline stage, which
+    /// `exit` ends without the shell's own clean-up.
+    #[cfg(unix)]
+    subshell: bool,
+    /// While a background job is started, the process group its programs
+    /// join; 0 until the first of them has started.
+    job_group: Option<u32>,
+    jobs: jobs::Jobs,
+    /// The process id of the last background job started, for `$!`.
+    last_background: Option<u32>,
+    builtins: Registry,
+    hooks: Hooks,
+    /// Messages waiting to be shown above the prompt.
+    notices: Notifier,
+    #[cfg(feature = "history")]
+    history: History,
+    #[cfg(feature = "prompt-theme")]
+    prompt_segments: theme::PromptSegments,
+    /// `$PS1` as last expanded, shown after the prompt segments.
+    #[cfg(feature = "prompt-theme")]
+    prompt: String,
+    #[cfg(feature = "line-editor")]
+    editor: LineEditor,
+    last_status: i32,
+    vars: HashMap<String, Value>,
+    exported: HashSet<String>,
+    /// Variables of the environment removed with `unset`.
+    unset_env: HashSet<String>,
+    /// Saved values of variables shadowed by `local`, one map per call.
+    scopes: Vec<HashMap<String, Option<Value>>>,
+    login: bool,
+    /// Whether commands are read from a terminal rather than a script or
+    /// `-c`; job notices, history and the rc file are for interactive use.
+    interactive: bool,
+    /// `set -o posix`: bash extensions are turned off, and a
+    /// non-interactive shell exits on the errors POSIX deems fatal.
+    posix: bool,
+    /// `set -o cdspell`: `cd` corrects small spelling mistakes in the
+    /// directory it is given.
+    cdspell: bool,
+    /// `set -o notifyafter=N`: how many seconds an interactive command
+    /// must run before its end is announced; 0 turns that off.
+    notify_after: u64,
+    /// `set -o notifystyle`: how the end of a slow command is announced.
+    notify_style: notify::NotifyStyle,
+    /// The status to exit with once the current command returns, after an
+    /// error that is fatal in POSIX mode.
+    fatal_status: Cell<Option<i32>>,
+    /// Set when an expansion error is reported, so that the command being
+    /// expanded is not run.
+    expansion_failed: Cell<bool>,
+    /// The status of the last command substitution in the command being
+    /// expanded, which an assignment-only command returns.
+    substitution_status: Option<i32>,
+    /// The session recording started by `record start`, if any.
+    recording: Option<record::Recording>,
+    /// Directories visited with `cd`, the current one first.
+    dir_history: VecDeque<PathBuf>,
+    /// The directories saved by `pushd`, most recent first; the working
+    /// directory is the top of the stack and is not kept here.
+    dir_stack: Vec<PathBuf>,
+    /// The last directory visited on each drive, for `cd D:`.
+    #[cfg(windows)]
+    drive_dirs: HashMap<char, PathBuf>,
+    positional: Vec<String>,
+    shell_name: String,
+    functions: HashMap<String, Function>,
+    /// The state of the generator behind `$RANDOM`.
+    random: Cell<u32>,
+    /// When `$SECONDS` was last set, and the value it was set to.
+    seconds_base: (Instant, i64),
+    /// `$LINENO`: the line of the command being run.
+    lineno: usize,
+    /// The number of lines before the input being run, added to the line
+    /// numbers the parser gives.
+    line_base: usize,
+    /// The function calls and sourced files in progress, innermost last.
+    call_stack: Vec<Frame>,
+    /// Aliases defined with `alias`, expanded when a command is run.
+    aliases: BTreeMap<String, String>,
+    /// The aliases being expanded, which are not looked up again.
+    active_aliases: Vec<String>,
+    /// Abbreviations defined with `abbr`, expanded by the line editor.
+    abbreviations: BTreeMap<String, String>,
+    /// How Tab completes each command's arguments, set with `complete`.
+    completion_specs: BTreeMap<String, CompletionSpec>,
+    /// Commands whose completion file has been looked for.
+    #[cfg(feature = "completion")]
+    completion_files_tried: HashSet<String>,
+    function_depth: usize,
+    source_depth: usize,
+    loop_depth: usize,
+    /// How many command substitutions are running.
+    substitution_depth: usize,
+    /// Set for `expand`, which must not run commands: command
+    /// substitutions then expand to nothing.
+    no_exec: bool,
+    flow: Flow,
+}
+
+impl Default for Shell {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Shell {
+    pub fn new() -> Self {
+        Shell {
+            commands: RefCell::new(CommandCache::default()),
+            #[cfg(feature = "completion")]
+            command_index: CommandIndex::spawn(
+                osstr::env_var("PATH").unwrap_or_default(),
+                Self::scan_commands,
+            ),
+            out: SharedStdout::default(),
+            capture: None,
+            capture_output: false,
+            embedded: false,
+            redirected: OuterRedirects::default(),
+            stage: None,
+            started: None,
+            #[cfg(unix)]
+            pipe_reader: None,
+            #[cfg(unix)]
+            subshell: false,
+            job_group: None,
+            jobs: jobs::Jobs::default(),
+            last_background: None,
+            builtins: Registry::standard(),
+            hooks: Hooks::default(),
+            notices: Notifier::default(),
+            #[cfg(feature = "history")]
+            history: History::default(),
+            #[cfg(feature = "prompt-theme")]
+            prompt_segments: theme::PromptSegments::default(),
+            #[cfg(feature = "prompt-theme")]
+            prompt: String::new(),
+            #[cfg(feature = "line-editor")]
+            editor: LineEditor::new(),
+            last_status: 0,
+            vars: HashMap::new(),
+            exported: HashSet::new(),
+            unset_env: HashSet::new(),
+            scopes: Vec::new(),
+            login: false,
+            interactive: io::stdin().is_terminal(),
+            posix: false,
+            cdspell: false,
+            notify_after: 0,
+            notify_style: notify::NotifyStyle::default(),
+            fatal_status: Cell::new(None),
+            expansion_failed: Cell::new(false),
+            substitution_status: None,
+            recording: None,
+            dir_history: VecDeque::new(),
+            dir_stack: Vec::new(),
+            #[cfg(windows)]
+            drive_dirs: HashMap::new(),
+            positional: Vec::new(),
+            shell_name: env::args_os()
+                .next()
+                .map(|arg| osstr::from_os(&arg))
+                .unwrap_or_default(),
+            functions: HashMap::new(),
+            random: Cell::new(variables::random_seed()),
+            seconds_base: (Instant::now(), 0),
+            lineno: 0,
+            line_base: 0,
+            call_stack: Vec::new(),
+            aliases: BTreeMap::new(),
+            active_aliases: Vec::new(),
+            abbreviations: BTreeMap::new(),
+            completion_specs: BTreeMap::new(),
+            #[cfg(feature = "completion")]
+            completion_files_tried: HashSet::new(),
+            function_depth: 0,
+            source_depth: 0,
+            loop_depth: 0,
+            substitution_depth: 0,
+            no_exec: false,
+            flow: Flow::Normal,
+        }
+    }
+
+    /// The PATH directories, in search order, refreshing the command cache
+    /// if `$PATH` has changed since it was last used.
+    fn path_dirs(&self) -> Vec<String> {
+        let mut commands = self.commands.borrow_mut();
+        commands.refresh(&self.get_var("PATH").unwrap_or_default());
+        commands.dirs().to_vec()
+    }
+
+    #[cfg(unix)]
+    fn is_executable(path: &Path) -> bool {
+        use std::os::unix::fs::PermissionsExt;
+
+        std::fs::metadata(path)
+            .map(|m| m.is_file() && (m.permissions().mode() & 0o111 != 0))
+            .unwrap_or(false)
+    }
+
+    /// The executable extensions from `%PATHEXT%`, lowercased and in order,
+    /// e.g. `.com`, `.exe`, `.bat`, followed by `.ps1` for PowerShell scripts,
+    /// which are run through their interpreter.
+    #[cfg(windows)]
+    fn path_extensions() -> Vec<String> {
+        const DEFAULT_PATHEXT: &str = ".COM;.EXE;.BAT;.CMD;.VBS;.VBE;.JS;.JSE;.WSF;.WSH;.MSC";
+
+        let mut extensions: Vec<String> = env::var("PATHEXT")
+            .ok()
+            .filter(|value| !value.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_PATHEXT.to_string())
+            .split(';')
+            .map(|ext| ext.trim().to_lowercase())
+            .filter(|ext| ext.len() > 1 && ext.starts_with('.'))
+            .collect();
+        if !extensions.iter().any(|ext| ext == ".ps1") {
+            extensions.push(".ps1".to_string());
+        }
+        extensions
+    }
+
+    /// Strips an extension listed in `extensions` from `name`, ignoring case.
+    #[cfg(windows)]
+    fn strip_executable_extension<'a>(name: &'a str, extensions: &[String]) -> Option<&'a str> {
+        let dot = name.rfind('.')?;
+        let ext = name[dot..].to_lowercase();
+        extensions.contains(&ext).then(|| &name[..dot])
+    }
+
+    #[cfg(windows)]
+    fn is_executable(path: &Path) -> bool {
+        path.is_file()
+            && path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| {
+                    Self::strip_executable_extension(name, &Self::path_extensions()).is_some()
+                })
+    }
+
+    /// The file names `cmd` may resolve to in each PATH directory. On
+    /// Windows, as with cmd.exe, a name that already has an extension is
+    /// only tried as given; otherwise each `%PATHEXT%` extension is appended
+    /// in order.
+    #[cfg(windows)]
+    fn executable_candidates(cmd: &str) -> Vec<String> {
+        let has_extension = Path::new(cmd).extension().is_some();
+        if has_extension {
+            return vec![cmd.to_string()];
+        }
+        Self::path_extensions()
+            .iter()
+            .map(|ext| format!("{}{}", cmd, ext))
+            .collect()
+    }
+
+    #[cfg(unix)]
+    fn executable_candidates(cmd: &str) -> Vec<String> {
+        vec![cmd.to_string()]
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self), ret)
+    )]
+    fn find_executable(&self, cmd: &str, path: Option<&str>) -> Option<String> {
+        let candidates = Self::executable_candidates(cmd);
+
+        // A name with a directory part, including a UNC path, is run as
+        // given rather than looked up in PATH.
+        if cmd.contains('/') || (cfg!(windows) && cmd.contains('\\')) {
+            return candidates
+                .into_iter()
+                .find(|candidate| Self::is_executable(Path::new(&osstr::to_os(candidate))));
+        }
+
+        // A PATH given for this command alone, as in `PATH=/dir cmd`, is
+        // searched without the cache, which belongs to the shell's PATH.
+        if let Some(path) = path {
+            return lookup::split_path(path).iter().find_map(|dir| {
+                candidates.iter().find_map(|candidate| {
+                    let full_path = PathBuf::from(osstr::to_os(dir)).join(osstr::to_os(candidate));
+                    (full_path.exists() && Self::is_executable(&full_path))
+                        .then(|| osstr::from_os(full_path.as_os_str()))
+                })
+            });
+        }
+
+        let dirs = self.path_dirs();
+        let cached = self.commands.borrow().get(cmd).map(String::from);
+        // A remembered location costs one stat to confirm, instead of one
+        // per PATH directory; if the file is gone, search again.
+        if let Some(location) = cached {
+            if Self::is_executable(Path::new(&osstr::to_os(&location))) {
+                return Some(location);
+            }
+            self.commands.borrow_mut().remove(cmd);
+        }
+
+        for dir in &dirs {
+            for candidate in &candidates {
+                let full_path = PathBuf::from(osstr::to_os(dir)).join(osstr::to_os(candidate));
+
+                if full_path.exists() && Self::is_executable(&full_path) {
+                    let location = osstr::from_os(full_path.as_os_str());
+                    self.commands.borrow_mut().insert(cmd, location.clone());
+                    return Some(location);
+                }
+            }
+        }
+        None
+    }
+
+    fn open_redirect_file(redirect: &Redirect) -> io::Result<File> {
+        if let Some(here_doc) = &redirect.here_doc {
+            return pipeline::here_doc_file(&here_doc.body);
+        }
+        let path = paths::redirect_target(&redirect.file);
+        match redirect.stream {
+            StreamType::Stdin => File::open(path),
+            _ if redirect.append => OpenOptions::new().create(true).append(true).open(path),
+            _ => File::create(path),
+        }
+    }
+
+    fn execute(&mut self, commands: &[Command]) -> i32 {
+        for command in commands {
+            if signals::interrupted() {
+                self.last_status = 130;
+                break;
+            }
+            self.last_status = self.execute_command(command);
+            if let Some(status) = self.fatal_status.take() {
+                self.exit(status);
+            }
+            if self.flow != Flow::Normal {
+                break;
+            }
+        }
+        self.last_status
+    }
+
+    fn execute_command(&mut self, command: &Command) -> i32 {
+        match command {
+            Command::Simple(simple) => {
+                let status = self.execute_simple(simple);
+                self.set_pipestatus(&[status]);
+                status
+            }
+            Command::If(clause) => self.execute_if(clause),
+            Command::For(clause) => self.execute_for(clause),
+            Command::ArithFor(clause) => self.execute_arith_for(clause),
+            Command::Select(clause) => self.execute_select(clause),
+            Command::While(clause) => self.execute_while(clause),
+            Command::Case(clause) => self.execute_case(clause),
+            Command::Group(body) => self.execute(body),
+            Command::Not(command) => (self.execute_command(command) == 0) as i32,
+            Command::Pipeline(stages, text) => self.execute_pipeline(stages, text),
+            Command::And(left, right) => self.execute_if_status(left, right, true),
+            Command::Or(left, right) => self.execute_if_status(left, right, false),
+            Command::Background(command, text) => self.execute_background(command, text),
+            Command::Arith(expr) => {
+                let status = match self.eval_arith(expr) {
+                    Ok(value) => (value == 0) as i32,
+                    Err(e) => self.report(&e.into()),
+                };
+                self.set_pipestatus(&[status]);
+                status
+            }
+            Command::FunctionDef(def) => {
+                let function = Function {
+                    body: Rc::clone(&def.body),
+                    line_base: self.line_base,
+                    source: self.current_source().to_string(),
+                };
+                self.functions.insert(def.name.clone(), function);
+                0
+            }
+        }
+    }
+
+    /// Runs `left`, then `right` if `left` succeeded (`&&`) or failed
+    /// (`||`) as `on_success` asks.
+    fn execute_if_status(&mut self, left: &Command, right: &Command, on_success: bool) -> i32 {
+        let status = self.execute_command(left);
+        if (status == 0) != on_success || self.flow != Flow::Normal {
+            return status;
+        }
+        self.last_status = status;
+        self.execute_command(right)
+    }
+
+    fn execute_if(&mut self, clause: &IfClause) -> i32 {
+        for (condition, body) in &clause.branches {
+            if self.execute(condition) == 0 {
+                return self.execute(body);
+            }
+        }
+
+        match &clause.else_body {
+            Some(body) => self.execute(body),
+            None => 0,
+        }
+    }
+
+    fn execute_for(&mut self, clause: &ForClause) -> i32 {
+        let items: Vec<String> = match &clause.words {
+            Some(words) => words.iter().flat_map(|w| self.expand_word(w)).collect(),
+            None => self.positional.clone(),
+        };
+
+        let mut status = 0;
+        self.loop_depth += 1;
+
+        for item in items {
+            if signals::interrupted() {
+                break;
+            }
+            self.set_var(&clause.var, item);
+            status = self.execute(&clause.body);
+            if self.loop_should_exit() {
+                break;
+            }
+        }
+
+        self.loop_depth -= 1;
+        status
+    }
+
+    fn execute_arith_for(&mut self, clause: &ArithForClause) -> i32 {
+        let mut status = 0;
+
+        if let Err(e) = self.eval_arith(&clause.init) {
+            return self.report(&e.into());
+        }
+
+        self.loop_depth += 1;
+
+        loop {
+            if signals::interrupted() {
+                break;
+            }
+            if !clause.condition.trim().is_empty() {
+                match self.eval_arith(&clause.condition) {
+                    Ok(0) => break,
+                    Ok(_) => {}
+                    Err(e) => {
+                        status = self.report(&e.into());
+                        break;
+                    }
+                }
+            }
+
+            status = self.execute(&clause.body);
+            if self.loop_should_exit() {
+                break;
+            }
+
+            if let Err(e) = self.eval_arith(&clause.step) {
+                status = self.report(&e.into());
+                break;
+            }
+        }
+
+        self.loop_depth -= 1;
+        status
+    }
+
+    fn execute_select(&mut self, clause: &ForClause) -> i32 {
+        let items: Vec<String> = match &clause.words {
+            Some(words) => words.iter().flat_map(|w| self.expand_word(w)).collect(),
+            None => self.positional.clone(),
+        };
+
+        if items.is_empty() {
+            return 0;
+        }
+
+        let mut status = 0;
+        let mut show_menu = true;
+        self.loop_depth += 1;
+
+        self.flush_output();
+        loop {
+            if show_menu {
+                let width = items.len().to_string().len();
+                for (i, item) in items.iter().enumerate() {
+                    eprintln!("{:>width$}) {}", i + 1, item);
+                }
+            }
+
+            eprint!(
+                "{}",
+                self.get_var("PS3").unwrap_or_else(|| "#? ".to_string())
+            );
+            let _ = io::stderr().flush();
+
+            let mut reply = String::new();
+            match io::stdin().read_line(&mut reply) {
+                Ok(0) | Err(_) => {
+                    eprintln!();
+                    break;
+                }
+                Ok(_) => {}
+            }
+            if signals::interrupted() {
+                break;
+            }
+
+            let reply = reply.trim_end_matches(['\n', '\r']).to_string();
+            show_menu = reply.trim().is_empty();
+            if show_menu {
+                continue;
+            }
+
+            let choice = reply
+                .trim()
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| n.checked_sub(1))
+                .and_then(|i| items.get(i))
+                .cloned()
+                .unwrap_or_default();
+            self.set_var("REPLY", reply);
+            self.set_var(&clause.var, choice);
+
+            status = self.execute(&clause.body);
+            if self.loop_should_exit() {
+                break;
+            }
+        }
+
+        self.loop_depth -= 1;
+        status
+    }
+
+    fn execute_while(&mut self, clause: &WhileClause) -> i32 {
+        let mut status = 0;
+        self.loop_depth += 1;
+
+        loop {
+            let succeeded = self.execute(&clause.condition) == 0;
+            if self.flow != Flow::Normal {
+                status = self.last_status;
+                if self.loop_should_exit() {
+                    break;
+                }
+                continue;
+            }
+            if succeeded == clause.until || signals::interrupted() {
+                break;
+            }
+            status = self.execute(&clause.body);
+            if self.loop_should_exit() {
+                break;
+            }
+        }
+
+        self.loop_depth -= 1;
+        status
+    }
+
+    /// Consumes a pending `break`/`continue` at the end of a loop iteration.
+    /// Returns whether the current loop must stop.
+    fn loop_should_exit(&mut self) -> bool {
+        match self.flow {
+            Flow::Normal => false,
+            Flow::Return | Flow::Exit => true,
+            Flow::Break(n) => {
+                self.flow = if n > 1 {
+                    Flow::Break(n - 1)
+                } else {
+                    Flow::Normal
+                };
+                true
+            }
+            Flow::Continue(n) if n > 1 => {
+                self.flow = Flow::Continue(n - 1);
+                true
+            }
+            Flow::Continue(_) => {
+                self.flow = Flow::Normal;
+                false
+            }
+        }
+    }
+
+    fn execute_case(&mut self, clause: &CaseClause) -> i32 {
+        let subject = self.expand_string(&clause.word);
+        let mut status = 0;
+        let mut falling_through = false;
+
+        for item in &clause.items {
+            let selected = falling_through
+                || item
+                    .patterns
+                    .iter()
+                    .any(|pattern| glob::matches(&self.expand_pattern(pattern), &subject));
+
+            if !selected {
+                continue;
+            }
+
+            status = self.execute(&item.body);
+            if self.flow != Flow::Normal {
+                break;
+            }
+            match item.terminator {
+                CaseTerminator::Break => break,
+                CaseTerminator::FallThrough => falling_through = true,
+                CaseTerminator::Continue => falling_through = false,
+            }
+        }
+
+        status
+    }
+
+    fn assign(&mut self, assignment: &Assignment) {
+        let name = &assignment.name;
+
+        match (&assignment.value, &assignment.index) {
+            (AssignValue::Scalar(value), index) => {
+                let value = self.expand_assignment(value);
+                self.assign_scalar(name, index.as_deref(), value);
+            }
+            (AssignValue::Array(words), _) => {
+                let mut array = Value::Array(Default::default());
+                let mut next = 0;
+
+                for word in words {
+                    let explicit = word
+                        .strip_prefix('[')
+                        .and_then(|rest| rest.split_once("]="));
+                    if let Some((index, value)) = explicit {
+                        next = usize::try_from(self.eval_index(index)).unwrap_or(0);
+                        array.set_element(next, self.expand_string(value));
+                        next += 1;
+                        continue;
+                    }
+                    for field in self.expand_word(word) {
+                        array.set_element(next, field);
+                        next += 1;
+                    }
+                }
+
+                self.set_array(name, array);
+            }
+        }
+    }
+
+    /// Assigns an already expanded value to `name` or to `name[index]`.
+    fn assign_scalar(&mut self, name: &str, index: Option<&str>, value: String) {
+        let Some(index) = index else {
+            self.set_var(name, value);
+            return;
+        };
+
+        let index = self.eval_index(index);
+        let current = self
+            .get_value(name)
+            .unwrap_or(Value::Array(Default::default()));
+        match current.resolve_index(index) {
+            Some(index) => self.set_element(name, index, value),
+            None => {
+                let message = format!("{}[{}]: bad array subscript", name, index);
+                self.report(&ShellError::Expansion(message));
+            }
+        }
+    }
+
+    /// Runs `f` with shell variables temporarily set, restoring them after.
+    fn with_temporary_vars(
+        &mut self,
+        vars: Vec<(String, String)>,
+        f: impl FnOnce(&mut Self) -> i32,
+    ) -> i32 {
+        let saved: Vec<(String, Option<Value>)> = vars
+            .iter()
+            .map(|(name, _)| (name.clone(), self.vars.get(name).cloned()))
+            .collect();
+
+        for (name, value) in vars {
+            self.set_var(&name, value);
+        }
+
+        let status = f(self);
+
+        for (name, value) in saved {
+            match value {
+                Some(value) => self.vars.insert(name, value),
+                None => self.vars.remove(&name),
+            };
+        }
+
+        status
+    }
+
+    fn call_function(&mut self, name: &str, function: &Function, args: Vec<String>) -> i32 {
+        self.push_frame(name, function.source.clone());
+        let saved_positional = std::mem::replace(&mut self.positional, args);
+        let saved_line_base = std::mem::replace(&mut self.line_base, function.line_base);
+        self.function_depth += 1;
+        self.push_scope();
+
+        let status = self.execute_command(&function.body);
+
+        self.pop_scope();
+        self.function_depth -= 1;
+        self.line_base = saved_line_base;
+        self.positional = saved_positional;
+        self.pop_frame();
+        if self.flow == Flow::Return {
+            self.flow = Flow::Normal;
+        }
+
+        status
+    }
+
+    fn execute_simple(&mut self, simple: &SimpleCommand) -> i32 {
+        if let Some(status) = self.execute_alias(simple) {
+            return status;
+        }
+        let stage = self.stage.take();
+        self.lineno = self.line_base + simple.line;
+        self.expansion_failed.set(false);
+        self.substitution_status = None;
+        let mut args: Vec<String> = simple
+            .words
+            .iter()
+            .flat_map(|w| self.expand_word(w))
+            .collect();
+
+        let mut redirects = Vec::new();
+        for redirect in &simple.redirects {
+            // The delimiter of a here-document is not expanded, and its
+            // body only if the delimiter was unquoted.
+            if let Some(here_doc) = &redirect.here_doc {
+                let body = if here_doc.expand {
+                    self.expand_here_doc(&here_doc.body)
+                } else {
+                    here_doc.body.clone()
+                };
+                redirects.push(Redirect {
+                    here_doc: Some(HereDoc {
+                        body,
+                        expand: false,
+                    }),
+                    ..redirect.clone()
+                });
+                continue;
+            }
+            let Some(file) = self.expand_word_single(&redirect.file) else {
+                let message = format!("{}: ambiguous redirect", redirect.file);
+                return self.report(&ShellError::Expansion(message));
+            };
+            redirects.push(Redirect {
+                file,
+                ..redirect.clone()
+            });
+        }
+
+        for redirect in &redirects {
+            if redirect.stream != StreamType::Stdin && !redirect.duplicate {
+                let _ = Self::open_redirect_file(redirect);
+            }
+        }
+
+        if args.is_empty() {
+            let assign = |shell: &mut Self| {
+                for assignment in &simple.assignments {
+                    shell.assign(assignment);
+                }
+                if shell.expansion_failed.take() {
+                    return 1;
+                }
+                shell.substitution_status.take().unwrap_or(0)
+            };
+            return match stage {
+                Some(stage) => self.run_stage(stage, assign),
+                None => assign(self),
+            };
+        }
+
+        let command = args.remove(0);
+        let env: Vec<(String, String)> = simple
+            .assignments
+            .iter()
+            .filter_map(|a| match &a.value {
+                AssignValue::Scalar(value) if a.index.is_none() => {
+                    Some((a.name.clone(), self.expand_assignment(value)))
+                }
+                _ => None,
+            })
+            .collect();
+
+        // An expansion error keeps the command from running, and in POSIX
+        // mode ends the shell.
+        if let Some(status) = self.fatal_status.get() {
+            return status;
+        }
+        if self.expansion_failed.take() {
+            return 1;
+        }
+
+        let parsed = ParsedCommand {
+            args,
+            redirects,
+            env,
+        };
+
+        let audit = self.audit_start(&command, &parsed.args);
+
+        let is_external =
+            !self.functions.contains_key(&command) && !self.builtins.contains(&command);
+        let run = |shell: &mut Self, parsed: ParsedCommand| {
+            if is_external || parsed.env.is_empty() {
+                shell.dispatch(&command, parsed)
+            } else {
+                let vars = parsed.env.clone();
+                shell.with_temporary_vars(vars, |shell| shell.dispatch(&command, parsed))
+            }
+        };
+        let status = match stage {
+            Some(Stage::Pipe(writer)) if is_external => {
+                self.start_stage(&command, &parsed, Some(writer))
+            }
+            Some(Stage::Last) if is_external => self.start_stage(&command, &parsed, None),
+            Some(stage) => self.run_stage(stage, |shell| run(shell, parsed)),
+            None => run(self, parsed),
+        };
+
+        if let Some(record) = audit {
+            self.audit_finish(record, status);
+        }
+        status
+    }
+
+    /// Runs a command by name: a function, then a builtin, then a program.
+    fn dispatch(&mut self, command: &str, mut parsed: ParsedCommand) -> i32 {
+        if let Some(function) = self.functions.get(command).cloned() {
+            return self.call_redirected(command, &function, parsed);
+        }
+        // The commands `source` runs get its redirections, as a function's
+        // do.
+        if matches!(command, "source" | ".") && !parsed.redirects.is_empty() {
+            let redirects = std::mem::take(&mut parsed.redirects);
+            return self.with_redirects(&redirects, |shell| shell.dispatch(command, parsed));
+        }
+
+        #[cfg(windows)]
+        if parsed.args.is_empty()
+            && paths::split_drive_relative(command).is_some_and(|(_, rest)| rest.is_empty())
+        {
+            let parsed = ParsedCommand {
+                args: vec![command.to_string()],
+                ..parsed
+            };
+            return self.dispatch("cd", parsed);
+        }
+
+        let result = match self.builtins.get(command) {
+            Some(builtin) => self
+                .streams(&parsed.redirects)
+                .map(|mut io| builtin.execute(self, &parsed.args, &mut io)),
+            None => self.run_external(command, &parsed),
+        };
+        result.unwrap_or_else(|e| self.fail_command(e, &parsed.redirects))
+    }
+
+    /// Reports `error` from a command on its stderr, or the shell's if that
+    /// cannot be opened.
+    fn fail_command(&self, error: ShellError, redirects: &[Redirect]) -> i32 {
+        match self.streams(redirects) {
+            Ok(mut io) => self.fail(error, &mut io),
+            Err(_) => self.report(&error),
+        }
+    }
+
+    /// Calls a function with its redirections applied to every command
+    /// it runs.
+    fn call_redirected(&mut self, name: &str, function: &Function, parsed: ParsedCommand) -> i32 {
+        self.with_redirects(&parsed.redirects, |shell| {
+            shell.call_function(name, function, parsed.args)
+        })
+    }
+
+    /// Runs `run` with `redirects` applied to every command it runs.
+    fn with_redirects(
+        &mut self,
+        redirects: &[Redirect],
+        run: impl FnOnce(&mut Self) -> i32,
+    ) -> i32 {
+        if redirects.is_empty() {
+            return run(self);
+        }
+
+        let outer = match self.redirect_targets(redirects) {
+            Ok(outer) => outer,
+            Err(e) => return self.report(&e),
+        };
+
+        self.flush_output();
+        let saved = std::mem::replace(&mut self.redirected, outer);
+        let status = run(self);
+        self.redirected = saved;
+        status
+    }
+
+    #[cfg(unix)]
+    fn exit_code(status: ExitStatus) -> i32 {
+        use std::os::unix::process::ExitStatusExt;
+
+        status
+            .code()
+            .unwrap_or_else(|| 128 + status.signal().unwrap_or(0))
+    }
+
+    #[cfg(windows)]
+    fn exit_code(status: ExitStatus) -> i32 {
+        status.code().unwrap_or(1)
+    }
+
+    /// Where each stream goes once `redirects` are applied, in order, on
+    /// top of the outer redirections. Each file is opened once; `n>&m`
+    /// makes stream `n` go where `m` goes at that point.
+    fn redirect_targets(&self, redirects: &[Redirect]) -> Result<OuterRedirects, ShellError> {
+        let mut targets = self.redirected.try_clone();
+        for redirect in redirects {
+            let io_error = |source| ShellError::Io {
+                context: redirect.file.clone(),
+                source,
+            };
+            let target = if redirect.duplicate {
+                let Some(from) = StreamType::from_descriptor(&redirect.file) else {
+                    let message = format!("{}: bad file descriptor", redirect.file);
+                    return Err(ShellError::Expansion(message));
+                };
+                match targets.target(&from) {
+                    Some(target) => target.try_clone().map_err(io_error)?,
+                    None => Target::Standard(from),
+                }
+            } else {
+                Target::File(Self::open_redirect_file(redirect).map_err(io_error)?)
+            };
+            *targets.target(&redirect.stream) = Some(target);
+        }
+        Ok(targets)
+    }
+
+    /// The streams for a builtin: the terminal, with each redirection
+    /// opened once and swapped in for the stream it names.
+    fn streams(&self, redirects: &[Redirect]) -> Result<Streams, ShellError> {
+        let mut targets = self.redirect_targets(redirects)?;
+        let io_error = |source| ShellError::Io {
+            context: "redirection".to_string(),
+            source,
+        };
+        let mut streams = Streams::terminal(&self.out);
+        match targets.stdin.take() {
+            Some(Target::File(file)) => streams.stdin = Box::new(file),
+            Some(Target::Standard(StreamType::Stdin)) | None => {}
+            Some(Target::Standard(stream)) => {
+                streams.stdin = Box::new(pipeline::standard_file(&stream).map_err(io_error)?);
+            }
+        }
+        let writer = |target: Option<Target>, stream| -> Result<Box<dyn Write>, ShellError> {
+            Ok(match target.unwrap_or(Target::Standard(stream)) {
+                Target::File(file) => Box::new(file),
+                Target::Standard(StreamType::Stdout) => match &self.capture {
+                    Some(capture) => Box::new(capture.clone()),
+                    None => Box::new(self.out.clone()),
+                },
+                Target::Standard(StreamType::Stderr) => Streams::terminal(&self.out).stderr,
+                Target::Standard(StreamType::Stdin) => {
+                    Box::new(pipeline::standard_file(&StreamType::Stdin).map_err(io_error)?)
+                }
+            })
+        };
+        streams.stdout = writer(targets.stdout.take(), StreamType::Stdout)?;
+        streams.stderr = writer(targets.stderr.take(), StreamType::Stderr)?;
+        Ok(streams)
+    }
+
+    /// Reports `error` from a command on its stderr and returns the exit
+    /// status it results in.
+    fn fail(&self, error: ShellError, io: &mut dyn ShellIo) -> i32 {
+        write_line(io.stderr(), &error.to_string());
+        self.note_fatal(&error);
+        error.status()
+    }
+
+    /// Reports `error` on stderr, after any output it follows, and returns
+    /// the exit status it results in. All error messages go through here.
+    pub(crate) fn report(&self, error: &ShellError) -> i32 {
+        self.flush_output();
+        match self.streams(&[]) {
+            Ok(mut io) => write_line(io.stderr(), &error.to_string()),
+            Err(_) => write_line(&mut io::stderr(), &error.to_string()),
+        }
+        if let ShellError::Expansion(_) = error {
+            self.expansion_failed.set(true);
+        }
+        self.note_fatal(error);
+        error.status()
+    }
+
+    /// In POSIX mode, makes a non-interactive shell exit once the current
+    /// command returns if `error` is a syntax error, an expansion error or
+    /// an error in a special builtin.
+    fn note_fatal(&self, error: &ShellError) {
+        let fatal = match error {
+            ShellError::Syntax { .. } | ShellError::Expansion(_) => true,
+            ShellError::Builtin { name, .. } => builtins::is_special(name),
+            _ => false,
+        };
+        if fatal && self.posix && !self.interactive {
+            self.fatal_status.set(Some(error.status()));
+        }
+    }
+
+    /// Parses `source` as the shell's current mode requires.
+    fn parse(&self, source: &str) -> Result<Vec<Command>, ParseError> {
+        if self.posix {
+            parser::parse_posix(source)
+        } else {
+            parser::parse(source)
+        }
+    }
+
+    /// Parses the commands on the first line of `source` with
+    /// `parser::parse_line`.
+    fn parse_line(&self, source: &str) -> Result<(Vec<Command>, usize), ParseError> {
+        parser::parse_line(source, self.posix)
+    }
+
+    /// Writes out buffered builtin output. Needed before anything else can
+    /// write to the terminal: the prompt, error messages and child processes.
+    pub(crate) fn flush_output(&self) {
+        let _ = self.out.clone().flush();
+    }
+
+    /// The working directory as the user reached it: `$PWD` if it still
+    /// names the current directory, otherwise the physical path.
+    fn logical_cwd(&self) -> io::Result<PathBuf> {
+        let physical = paths::without_verbatim(env::current_dir()?);
+        if let Some(pwd) = self
+            .get_var("PWD")
+            .map(|pwd| PathBuf::from(osstr::to_os(&pwd)))
+            && pwd.is_absolute()
+            && fs::canonicalize(&pwd).ok() == fs::canonicalize(&physical).ok()
+        {
+            return Ok(pwd);
+        }
+        Ok(physical)
+    }
+
+    /// The process for the program `command`, with its arguments,
+    /// environment and redirections; stdout goes to `stdout` unless it is
+    /// redirected.
+    fn external_command(
+        &self,
+        command: &str,
+        parsed: &ParsedCommand,
+        stdout: Option<PipeWriter>,
+    ) -> Result<ProcessCommand, ShellError> {
+        let path_override = parsed
+            .env
+            .iter()
+            .rfind(|(name, _)| name == "PATH")
+            .map(|(_, value)| value.as_str());
+        let Some(path) = self.find_executable(command, path_override) else {
+            return Err(ShellError::NotFound(command.to_string()));
+        };
+        if path_override.is_none() {
+            self.commands.borrow_mut().count_hit(command);
+        }
+
+        #[cfg(windows)]
+        let mut cmd =
+            winquote::command(Path::new(&osstr::to_os(&path)), &parsed.args).map_err(|source| {
+                ShellError::NotExecutable {
+                    command: command.to_string(),
+                    source,
+                }
+            })?;
+        #[cfg(unix)]
+        let mut cmd = {
+            let mut cmd = ProcessCommand::new(osstr::to_os(&path));
+            cmd.arg0(osstr::to_os(command))
+                .args(parsed.args.iter().map(|arg| osstr::to_os(arg)));
+            unsafe {
+                cmd.pre_exec(|| {
+                    signals::default_job_signals();
+                    Ok(())
+                });
+            }
+            cmd
+        };
+        for name in &self.unset_env {
+            cmd.env_remove(name);
+        }
+        let env = self
+            .exported_env()
+            .into_iter()
+            .chain(parsed.env.iter().cloned());
+        cmd.envs(env.map(|(name, value)| (name, osstr::to_os(&value))));
+
+        let mut targets = self.redirect_targets(&parsed.redirects)?;
+        let io_error = |source| ShellError::Io {
+            context: "redirection".to_string(),
+            source,
+        };
+        let stdio = |target: Option<Target>, stream| -> Result<Option<Stdio>, ShellError> {
+            Ok(match target.unwrap_or(Target::Standard(stream)) {
+                Target::File(file) => Some(Stdio::from(file)),
+                Target::Standard(StreamType::Stdout) if let Some(pipe) = &stdout => {
+                    Some(Stdio::from(pipe.try_clone().map_err(io_error)?))
+                }
+                Target::Standard(standard) if standard == stream => None,
+                Target::Standard(standard) => Some(Stdio::from(
+                    pipeline::standard_file(&standard).map_err(io_error)?,
+                )),
+            })
+        };
+        if let Some(stdin) = stdio(targets.stdin.take(), StreamType::Stdin)? {
+            cmd.stdin(stdin);
+        }
+        if let Some(stdout) = stdio(targets.stdout.take(), StreamType::Stdout)? {
+            cmd.stdout(stdout);
+        }
+        if let Some(stderr) = stdio(targets.stderr.take(), StreamType::Stderr)? {
+            cmd.stderr(stderr);
+        }
+
+        Ok(cmd)
+    }
+
+    /// Runs a program found in PATH and returns its exit status. With job
+    /// control it runs as a foreground job; see `wait_foreground`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self, parsed), fields(args = ?parsed.args))
+    )]
+    fn run_external(&mut self, command: &str, parsed: &ParsedCommand) -> Result<i32, ShellError> {
+        let not_executable = |source| ShellError::NotExecutable {
+            command: command.to_string(),
+            source,
+        };
+
+        // While capturing, the child writes to a pipe drained on another
+        // thread so it cannot block on a full pipe. A stdout redirection
+        // replaces the pipe, leaving the thread nothing to read.
+        let mut captured = None;
+        let mut stdout = None;
+        if let Some(capture) = &self.capture {
+            let (writer, drain) = pipeline::drain().map_err(not_executable)?;
+            stdout = Some(writer);
+            captured = Some((capture.clone(), drain));
+        }
+
+        let mut cmd = self.external_command(command, parsed, stdout)?;
+        #[cfg(unix)]
+        let job_control = self.job_control();
+        #[cfg(unix)]
+        if job_control {
+            cmd.process_group(0);
+        }
+
+        self.flush_output();
+        #[cfg(windows)]
+        let status = winjob::run(&mut cmd).map(Self::exit_code);
+        #[cfg(unix)]
+        let status = {
+            trace_span!("spawn");
+            cmd.spawn()
+        }
+        .and_then(|mut child| {
+            if job_control {
+                let text: Vec<&str> = std::iter::once(command)
+                    .chain(parsed.args.iter().map(String::as_str))
+                    .collect();
+                let process = vec![pipeline::Started::Program(child)];
+                let status = match self.wait_foreground(&text.join(" "), process) {
+                    Ok(statuses) => statuses[0],
+                    Err(stopped) => stopped,
+                };
+                return Ok(status);
+            }
+            trace_span!("wait", pid = child.id());
+            child.wait().map(Self::exit_code)
+        });
+
+        // The write end of the pipe lives in `cmd`; closing it ends the read.
+        drop(cmd);
+        if let Some((mut capture, drain)) = captured
+            && let Ok(output) = drain.join()
+        {
+            let _ = capture.write_all(&output);
+        }
+
+        status.map_err(not_executable)
+    }
+
+    /// Reads one line of input, using the line editor when stdin is a
+    /// terminal and the shell was built with it.
+    fn read_line(&mut self, prompt: &str) -> io::Result<ReadStatus> {
+        #[cfg(feature = "line-editor")]
+        if io::stdin().is_terminal() {
+            return self.edit_line(prompt);
+        }
+
+        if io::stdin().is_terminal() {
+            self.flush_output();
+            print!("{prompt}");
+            let _ = io::stdout().flush();
+        }
+
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line)? == 0 {
+            return Ok(ReadStatus::Eof);
+        }
+        if line.ends_with('\n') {
+            line.pop();
+            if line.ends_with('\r') {
+                line.pop();
+            }
+        }
+        Ok(ReadStatus::Line(line))
+    }
+
+    /// Reads lines until they form a complete program, prompting with `> `
+    /// for continuation lines. Returns the program with its source text, or
+    /// `None` at end of input.
+    fn read_program(&mut self) -> io::Result<Option<(String, Vec<Command>)>> {
+        let mut source = String::new();
+        let mut prompt = self.primary_prompt();
+        #[cfg(feature = "line-editor")]
+        self.editor.restore_pushed();
+
+        loop {
+            match self.read_line(&prompt)? {
+                ReadStatus::Line(line) => {
+                    #[cfg(feature = "history")]
+                    if self.interactive {
+                        self.history.add(&line);
+                    }
+                    source.push_str(&line);
+                }
+                #[cfg(feature = "line-editor")]
+                ReadStatus::Interrupted => {
+                    self.last_status = 130;
+                    return Ok(Some((source, Vec::new())));
+                }
+                ReadStatus::Eof if source.is_empty() => return Ok(None),
+                ReadStatus::Eof => {
+                    self.last_status = self.report(&ShellError::Syntax {
+                        origin: None,
+                        line: None,
+                        error: ParseError::Incomplete,
+                    });
+                    return Ok(Some((source, Vec::new())));
+                }
+            }
+
+            source.push('\n');
+
+            // `= expression` is shorthand for `calc`, quoted so the
+            // expression is not taken apart as shell syntax.
+            let parsed = match source.trim_start().strip_prefix('=') {
+                Some(expression) if !self.posix => {
+                    parser::parse(&format!("calc {}", parser::quote(expression.trim())))
+                }
+                _ => self.parse(&source),
+            };
+            match parsed {
+                Ok(program) => return Ok(Some((source, program))),
+                Err(ParseError::Incomplete) => prompt = self.secondary_prompt(),
+                Err(error) => {
+                    self.last_status = self.report(&ShellError::Syntax {
+                        origin: None,
+                        line: None,
+                        error,
+                    });
+                    return Ok(Some((source, Vec::new())));
+                }
+            }
+        }
+    }
+
+    /// Installs the signal handlers an interactive shell needs. Call once,
+    /// before `run`.
+    pub fn install_signal_handlers() {
+        signals::install();
+    }
+
+    /// Sets up a login shell, which also happens when the shell was started
+    /// with a name beginning with `-`.
+    pub fn set_login(&mut self, login: bool) {
+        self.login = login || self.shell_name.starts_with('-');
+    }
+
+    /// Sets `$0` and the positional parameters, as for a script started
+    /// with arguments.
+    pub fn set_arguments(&mut self, name: String, args: Vec<String>) {
+        self.shell_name = name;
+        self.positional = args;
+    }
+
+    /// Turns POSIX mode on or off, as `set -o posix` does.
+    pub fn set_posix(&mut self, posix: bool) {
+        self.posix = posix;
+    }
+
+    /// The status of the last command run, as `$?` shows it.
+    pub fn last_status(&self) -> i32 {
+        self.last_status
+    }
+
+    /// Registers `hook` to run before each primary prompt is shown.
+    pub fn on_pre_prompt(&mut self, hook: impl FnMut() + 'static) {
+        self.hooks.add_pre_prompt(hook);
+    }
+
+    /// Registers `hook` to run with the source text of each command line
+    /// just before it is executed. Blank lines and syntax errors are skipped.
+    pub fn on_pre_exec(&mut self, hook: impl FnMut(&str) + 'static) {
+        self.hooks.add_pre_exec(hook);
+    }
+
+    /// Registers `hook` to run after each command line with its exit status
+    /// and how long it took.
+    pub fn on_post_exec(&mut self, hook: impl FnMut(i32, Duration) + 'static) {
+        self.hooks.add_post_exec(hook);
+    }
+
+    /// A handle through which other threads can post messages for the
+    /// user. They appear before the next prompt, or above the line being
+    /// edited without disturbing it.
+    pub fn notifier(&self) -> Notifier {
+        self.notices.clone()
+    }
+
+    /// Prints the messages posted through `notifier` since the last call.
+    pub(crate) fn print_notices(&self) {
+        let notices = self.notices.take();
+        if notices.is_empty() {
+            return;
+        }
+        self.flush_output();
+        let mut stderr = io::stderr().lock();
+        for notice in notices {
+            let _ = writeln!(stderr, "{notice}");
+        }
+        let _ = stderr.flush();
+    }
+
+    /// Reads and runs commands until end of input.
+    pub fn run(&mut self) -> io::Result<()> {
+        #[cfg(unix)]
+        if self.interactive {
+            signals::ignore_job_signals();
+        }
+        loop {
+            self.flush_output();
+            self.hooks.pre_prompt();
+            self.call_hook_function("precmd", Vec::new());
+            self.poll_jobs();
+            self.print_notices();
+            let Some((source, program)) = self.read_program()? else {
+                break;
+            };
+            if let Some(status) = self.fatal_status.take() {
+                self.exit(status);
+            }
+
+            if !program.is_empty() {
+                self.hooks.pre_exec(source.trim_end());
+                self.call_hook_function("preexec", vec![source.trim_end().to_string()]);
+                let started = Instant::now();
+                let status = self.execute(&program);
+                self.flush_output();
+                let elapsed = started.elapsed();
+                self.hooks.post_exec(status, elapsed);
+                self.notify_if_slow(source.trim_end(), status, elapsed);
+            }
+            self.line_base += source.matches('\n').count();
+            // The terminal echoed `^C` after the command's output; the
+            // prompt goes on the next line.
+            if signals::take_interrupt() && io::stdin().is_terminal() {
+                println!();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Runs the script at `path` as `shell path` does, without prompts,
+    /// history or job notices. A script that cannot be read gives status
+    /// 127.
+    pub fn run_file(&mut self, path: &str) {
+        self.interactive = false;
+        match fs::read(osstr::to_os(path)) {
+            Ok(source) => self.run_source(path, &osstr::from_bytes(&source)),
+            Err(source) => {
+                self.report(&ShellError::Io {
+                    context: path.to_string(),
+                    source,
+                });
+                self.last_status = 127;
+            }
+        }
+    }
+
+    /// Runs `source` as `shell -c source` does, without prompts, history or
+    /// job notices.
+    pub fn run_string(&mut self, source: &str) {
+        self.interactive = false;
+        self.run_source("-c", source);
+    }
+
+    /// Runs the commands in `source` a line at a time, each line parsed
+    /// once the line before it has run, so that a command sees the aliases,
+    /// functions and options set before it; a command that goes on over
+    /// several lines is parsed and run as a whole. A syntax error stops the
+    /// shell with status 2.
+    fn run_source(&mut self, origin: &str, source: &str) {
+        let mut source = source.to_string();
+        if !source.ends_with('\n') {
+            source.push('\n');
+        }
+        // A continuation on the last line has no line to join.
+        let backslashes = source[..source.len() - 1]
+            .bytes()
+            .rev()
+            .take_while(|&b| b == b'\\')
+            .count();
+        if backslashes % 2 == 1 {
+            source.truncate(source.len() - 2);
+            source.push('\n');
+        }
+
+        let mut rest = source.as_str();
+        while !rest.is_empty() {
+            let (program, length) = match self.parse_line(rest) {
+                Ok(parsed) => parsed,
+                Err(error) => {
+                    let error = ShellError::syntax(origin, self.line_base, rest, error);
+                    self.last_status = self.report(&error);
+                    return;
+                }
+            };
+
+            self.execute(&program);
+            self.flush_output();
+            if let Some(status) = self.fatal_status.take() {
+                self.exit(status);
+            }
+            if signals::take_interrupt() {
+                self.last_status = 130;
+                return;
+            }
+            self.line_base += rest[..length].matches('\n').count();
+            rest = &rest[length..];
+        }
+    }
+}
@@ -0,0 +1,183 @@
+/// ============================================
+/// COMMAND LOOKUP CACHE
+/// ============================================
+use std::collections::HashMap;
+#[cfg(feature = "completion")]
+use std::sync::{Arc, Condvar, Mutex};
+#[cfg(feature = "completion")]
+use std::thread;
+#[cfg(feature = "completion")]
+use std::time::Duration;
+
+use crate::paths;
+
+/// How often the background scan re-reads PATH directories to pick up
+/// newly installed commands.
+#[cfg(feature = "completion")]
+const RESCAN_INTERVAL: Duration = Duration::from_secs(30);
+
+pub fn split_path(path: &str) -> Vec<String> {
+    let separator = if cfg!(windows) { ';' } else { ':' };
+    path.split(separator).map(String::from).collect()
+}
+
+/// Remembers where commands were found in PATH, like bash's `hash` table,
+/// so each command stats PATH directories only the first time it runs.
+#[derive(Debug, Default)]
+pub struct CommandCache {
+    /// The PATH value `dirs` was split from. Any change to it empties the
+    /// cache.
+    path: Option<String>,
+    dirs: Vec<String>,
+    /// Keyed by the case-folded command name; holds the name as first
+    /// looked up, where it was found and how often it has been run.
+    entries: HashMap<String, Entry>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    name: String,
+    location: String,
+    hits: usize,
+}
+
+impl CommandCache {
+    /// Makes the cache match `path`, splitting it into directories and
+    /// forgetting every remembered command if it has changed.
+    pub fn refresh(&mut self, path: &str) {
+        if self.path.as_deref() == Some(path) {
+            return;
+        }
+
+        self.dirs = split_path(path);
+        self.path = Some(path.to_string());
+        self.entries.clear();
+    }
+
+    /// The PATH directories, in search order.
+    pub fn dirs(&self) -> &[String] {
+        &self.dirs
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.entries
+            .get(paths::fold_case(name).as_ref())
+            .map(|entry| entry.location.as_str())
+    }
+
+    pub fn insert(&mut self, name: &str, location: String) {
+        self.entries.insert(
+            paths::fold_case(name).into_owned(),
+            Entry {
+                name: name.to_string(),
+                location,
+                hits: 0,
+            },
+        );
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.entries.remove(paths::fold_case(name).as_ref());
+    }
+
+    /// Counts one run of a remembered command.
+    pub fn count_hit(&mut self, name: &str) {
+        if let Some(entry) = self.entries.get_mut(paths::fold_case(name).as_ref()) {
+            entry.hits += 1;
+        }
+    }
+
+    /// Forgets every remembered command, as `hash -r` does.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// `(hits, name, location)` for each remembered command, sorted by name.
+    pub fn list(&self) -> Vec<(usize, &str, &str)> {
+        let mut list: Vec<_> = self
+            .entries
+            .values()
+            .map(|entry| (entry.hits, entry.name.as_str(), entry.location.as_str()))
+            .collect();
+        list.sort_by(|a, b| a.1.cmp(b.1));
+        list
+    }
+}
+
+/// Names of the commands in PATH, kept up to date by a background thread so
+/// Tab completion never has to list every PATH directory itself.
+#[cfg(feature = "completion")]
+pub struct CommandIndex {
+    shared: Arc<(Mutex<IndexState>, Condvar)>,
+}
+
+#[cfg(feature = "completion")]
+struct IndexState {
+    /// The PATH the scanner should index next.
+    path: String,
+    /// The PATH `names` was scanned from, and the names found there.
+    scanned: Option<(String, Arc<Vec<String>>)>,
+}
+
+#[cfg(feature = "completion")]
+impl CommandIndex {
+    /// Starts scanning `path` on a background thread with `scan`, which
+    /// returns the command names in the given directories.
+    pub fn spawn(path: String, scan: fn(&[String]) -> Vec<String>) -> Self {
+        let shared = Arc::new((
+            Mutex::new(IndexState {
+                path,
+                scanned: None,
+            }),
+            Condvar::new(),
+        ));
+
+        let worker = Arc::clone(&shared);
+        let _ = thread::Builder::new()
+            .name("path-scan".into())
+            .spawn(move || Self::run(&worker, scan));
+
+        Self { shared }
+    }
+
+    fn run(shared: &(Mutex<IndexState>, Condvar), scan: fn(&[String]) -> Vec<String>) {
+        let (state, changed) = shared;
+        loop {
+            let Ok(guard) = state.lock() else { return };
+            let path = guard.path.clone();
+            drop(guard);
+
+            let names = Arc::new(scan(&split_path(&path)));
+
+            let Ok(mut guard) = state.lock() else { return };
+            // PATH may have changed while scanning; only publish names that
+            // still describe it.
+            if guard.path != path {
+                continue;
+            }
+            guard.scanned = Some((path.clone(), names));
+            if changed
+                .wait_timeout_while(guard, RESCAN_INTERVAL, |state| state.path == path)
+                .is_err()
+            {
+                return;
+            }
+        }
+    }
+
+    /// The indexed command names, if a scan of `path` has finished. A
+    /// different `path` from last time wakes the scanner to index it.
+    pub fn names(&self, path: &str) -> Option<Arc<Vec<String>>> {
+        let (state, changed) = &*self.shared;
+        let mut state = state.lock().ok()?;
+        if state.path != path {
+            state.path = path.to_string();
+            changed.notify_one();
+        }
+
+        match &state.scanned {
+            Some((scanned, names)) if scanned == path => Some(Arc::clone(names)),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,120 @@
+/// ============================================
+/// FILE-TYPE COLORS
+/// ============================================
+use std::path::Path;
+
+/// What a listed file is, which decides its color and `ls -F` indicator.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileKind {
+    Directory,
+    Executable,
+    Symlink,
+    File,
+}
+
+impl FileKind {
+    /// The kind of the file at `path`, without following a final symlink.
+    pub fn of(path: &Path) -> Option<Self> {
+        let metadata = std::fs::symlink_metadata(path).ok()?;
+        Some(if metadata.file_type().is_symlink() {
+            FileKind::Symlink
+        } else if metadata.is_dir() {
+            FileKind::Directory
+        } else if crate::Shell::is_executable(path) {
+            FileKind::Executable
+        } else {
+            FileKind::File
+        })
+    }
+
+    /// The suffix `ls -F` adds to names of this kind.
+    pub fn indicator(self) -> &'static str {
+        match self {
+            FileKind::Directory => "/",
+            FileKind::Executable => "*",
+            FileKind::Symlink => "@",
+            FileKind::File => "",
+        }
+    }
+}
+
+/// Used when `LS_COLORS` is unset or empty; the GNU `dircolors` defaults
+/// for the kinds completion distinguishes.
+const DEFAULT_COLORS: &str = "di=01;34:ln=01;36:ex=01;32:\
+    *.tar=01;31:*.tgz=01;31:*.gz=01;31:*.bz2=01;31:*.xz=01;31:*.zst=01;31:\
+    *.zip=01;31:*.7z=01;31:*.rar=01;31:*.jar=01;31:*.deb=01;31:*.rpm=01;31";
+
+/// Colors for file names, read from an `LS_COLORS` value.
+pub struct LsColors {
+    kinds: Vec<(String, String)>,
+    /// `*.ext` patterns as lowercase extensions with their dot.
+    extensions: Vec<(String, String)>,
+}
+
+impl LsColors {
+    /// Parses `spec`, falling back to the built-in palette when it is
+    /// missing or empty. Entries that are not SGR codes are ignored.
+    pub fn parse(spec: Option<&str>) -> Self {
+        let spec = spec
+            .filter(|spec| !spec.is_empty())
+            .unwrap_or(DEFAULT_COLORS);
+        let mut colors = LsColors {
+            kinds: Vec::new(),
+            extensions: Vec::new(),
+        };
+
+        for entry in spec.split(':') {
+            let Some((key, code)) = entry.split_once('=') else {
+                continue;
+            };
+            if code.is_empty() || !code.bytes().all(|b| b.is_ascii_digit() || b == b';') {
+                continue;
+            }
+            if let Some(extension) = key.strip_prefix('*') {
+                colors
+                    .extensions
+                    .push((extension.to_lowercase(), code.to_string()));
+            } else {
+                colors.kinds.push((key.to_string(), code.to_string()));
+            }
+        }
+        colors
+    }
+
+    /// `name` wrapped in the color for a file of `kind`, followed by its
+    /// indicator, or just the name and indicator if it has no color.
+    pub fn paint(&self, name: &str, kind: FileKind) -> String {
+        let indicator = kind.indicator();
+        match self.code(name, kind) {
+            Some(code) => format!("\x1b[{code}m{name}\x1b[0m{indicator}"),
+            None => format!("{name}{indicator}"),
+        }
+    }
+
+    fn code(&self, name: &str, kind: FileKind) -> Option<&str> {
+        let key = match kind {
+            FileKind::Directory => "di",
+            FileKind::Executable => "ex",
+            FileKind::Symlink => "ln",
+            FileKind::File => {
+                let name = name.to_lowercase();
+                return self
+                    .extensions
+                    .iter()
+                    .rev()
+                    .find(|(extension, _)| name.ends_with(extension.as_str()))
+                    .map(|(_, code)| code.as_str())
+                    .or_else(|| self.kind_code("fi"));
+            }
+        };
+        self.kind_code(key)
+    }
+
+    fn kind_code(&self, key: &str) -> Option<&str> {
+        self.kinds
+            .iter()
+            .rev()
+            .find(|(kind, _)| kind == key)
+            .map(|(_, code)| code.as_str())
+    }
+}
@@ -1,904 +1,63 @@
-use std::collections::HashSet;
-use std::env;
-use std::fs::{File, OpenOptions};
-use std::io::{self, Write};
-use std::path::Path;
-use std::process::{Command as ProcessCommand, Stdio};
+use codecrafters_shell::Shell;
+use codecrafters_shell::options::Options;
+use codecrafters_shell::version;
 
-#[cfg(unix)]
-use std::io::Read;
-
-mod terminal;
-// ============================================
-// KEY CODES
-// ============================================
-
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum Key {
-    Char(char),
-    Backspace,
-    Delete,
-    Enter,
-    Tab,
-    Left,
-    Right,
-    Up,
-    Down,
-    Home,
-    End,
-    CtrlC,
-    CtrlD,
-    CtrlA,
-    CtrlE,
-    Unknown,
-}
-
-// ============================================
-// KEY READER
-// ============================================
-
-#[cfg(unix)]
-fn read_key() -> io::Result<Option<Key>> {
-    let mut stdin = io::stdin();
-    let mut buf = [0u8; 1];
-
-    if stdin.read(&mut buf)? == 0 {
-        return Ok(None);
-    }
-
-    let key = match buf[0] {
-        b'\n' | b'\r' => Key::Enter,
-        b'\t' => Key::Tab,
-        0x7f | 0x08 => Key::Backspace,
-        0x03 => Key::CtrlC,
-        0x04 => Key::CtrlD,
-        0x01 => Key::CtrlA,
-        0x05 => Key::CtrlE,
-        0x1b => {
-            let mut seq = [0u8; 2];
-            if stdin.read(&mut seq[0..1])? > 0 && seq[0] == b'[' {
-                if stdin.read(&mut seq[1..2])? > 0 {
-                    match seq[1] {
-                        b'A' => Key::Up,
-                        b'B' => Key::Down,
-                        b'C' => Key::Right,
-                        b'D' => Key::Left,
-                        b'H' => Key::Home,
-                        b'F' => Key::End,
-                        b'3' => {
-                            let mut tilde = [0u8; 1];
-                            let _ = stdin.read(&mut tilde);
-                            Key::Delete
-                        }
-                        _ => Key::Unknown,
-                    }
-                } else {
-                    Key::Unknown
-                }
-            } else {
-                Key::Unknown
-            }
-        }
-        ch if ch >= 32 && ch < 127 => Key::Char(ch as char),
-        _ => Key::Unknown,
-    };
-
-    Ok(Some(key))
-}
-
-#[cfg(windows)]
-fn read_key() -> io::Result<Option<Key>> {
-    use windows::Win32::System::Console::{
-        GetStdHandle, INPUT_RECORD, KEY_EVENT, ReadConsoleInputW, STD_INPUT_HANDLE,
-    };
-    use windows::Win32::UI::Input::KeyboardAndMouse::{
-        VIRTUAL_KEY, VK_BACK, VK_DELETE, VK_DOWN, VK_END, VK_HOME, VK_LEFT, VK_RETURN, VK_RIGHT,
-        VK_TAB, VK_UP,
-    };
-
-    unsafe {
-        let handle = GetStdHandle(STD_INPUT_HANDLE).map_err(io::Error::other)?;
-
-        let mut buffer = [INPUT_RECORD::default()];
-        let mut read = 0u32;
-
-        ReadConsoleInputW(handle, &mut buffer, &mut read).map_err(io::Error::other)?;
-
-        if buffer[0].EventType == KEY_EVENT as u16 {
-            let event = buffer[0].Event.KeyEvent;
-
-            if !event.bKeyDown.as_bool() {
-                return Ok(None);
-            }
-
-            let key_code = VIRTUAL_KEY(event.wVirtualKeyCode);
-            let char_code = event.uChar.UnicodeChar;
-            let ctrl_pressed = event.dwControlKeyState & 0x000F != 0;
-
-            let key = match key_code {
-                VK_RETURN => Key::Enter,
-                VK_TAB => Key::Tab,
-                VK_BACK => Key::Backspace,
-                VK_DELETE => Key::Delete,
-                VK_LEFT => Key::Left,
-                VK_RIGHT => Key::Right,
-                VK_UP => Key::Up,
-                VK_DOWN => Key::Down,
-                VK_HOME => Key::Home,
-                VK_END => Key::End,
-                _ if ctrl_pressed => match char_code as u8 {
-                    3 => Key::CtrlC,
-                    4 => Key::CtrlD,
-                    1 => Key::CtrlA,
-                    5 => Key::CtrlE,
-                    _ => Key::Unknown,
-                },
-                _ => {
-                    if char_code > 0 && char_code < 128 {
-                        let ch = char::from_u32(char_code as u32).unwrap_or('\0');
-                        if ch.is_ascii_graphic() || ch == ' ' {
-                            Key::Char(ch)
-                        } else {
-                            Key::Unknown
-                        }
-                    } else {
-                        Key::Unknown
-                    }
-                }
-            };
-
-            Ok(Some(key))
-        } else {
-            Ok(None)
-        }
-    }
-}
-
-// ============================================
-// LINE EDITOR
-// ============================================
-
-struct LineEditor {
-    buffer: String,
-    cursor: usize,
-}
-
-impl LineEditor {
-    fn new() -> Self {
-        Self {
-            buffer: String::new(),
-            cursor: 0,
-        }
-    }
-
-    fn clear(&mut self) {
-        self.buffer.clear();
-        self.cursor = 0;
-    }
-
-    fn insert(&mut self, ch: char) {
-        self.buffer.insert(self.cursor, ch);
-        self.cursor += 1;
-    }
-
-    fn backspace(&mut self) {
-        if self.cursor > 0 {
-            self.cursor -= 1;
-            self.buffer.remove(self.cursor);
-        }
-    }
-
-    fn delete(&mut self) {
-        if self.cursor < self.buffer.len() {
-            self.buffer.remove(self.cursor);
-        }
-    }
-
-    fn move_left(&mut self) {
-        if self.cursor > 0 {
-            self.cursor -= 1;
-        }
-    }
-
-    fn move_right(&mut self) {
-        if self.cursor < self.buffer.len() {
-            self.cursor += 1;
-        }
-    }
-
-    fn move_home(&mut self) {
-        self.cursor = 0;
-    }
-
-    fn move_end(&mut self) {
-        self.cursor = self.buffer.len();
-    }
-
-    fn get_word_at_cursor(&self) -> Option<(usize, usize, &str)> {
-        if self.buffer.is_empty() {
-            return None;
-        }
-
-        let bytes = self.buffer.as_bytes();
-        let mut start = self.cursor.min(self.buffer.len().saturating_sub(1));
-        let mut end = self.cursor;
-
-        while start > 0 && !bytes[start - 1].is_ascii_whitespace() {
-            start -= 1;
-        }
-
-        while end < self.buffer.len() && !bytes[end].is_ascii_whitespace() {
-            end += 1;
-        }
-
-        if start < end {
-            Some((start, end, &self.buffer[start..end]))
-        } else {
-            None
-        }
-    }
-
-    fn replace_word(&mut self, start: usize, end: usize, replacement: &str) {
-        self.buffer.replace_range(start..end, replacement);
-        self.cursor = start + replacement.len();
-    }
-}
-
-// ============================================
-// SHELL STRUCTURES
-// ============================================
-
-#[derive(Debug, Clone)]
-enum StreamType {
-    Stdout,
-    Stderr,
-}
-
-#[derive(Debug, Clone)]
-struct Redirect {
-    stream: StreamType,
-    file: String,
-    append: bool,
-}
-
-#[derive(Debug)]
-struct ParsedCommand {
-    args: Vec<String>,
-    redirects: Vec<Redirect>,
-}
-
-impl ParsedCommand {
-    fn new() -> Self {
-        Self {
-            args: Vec::new(),
-            redirects: Vec::new(),
-        }
-    }
-}
-
-struct Shell {
-    paths: Vec<String>,
-    builtins: HashSet<&'static str>,
-    editor: LineEditor,
-}
-
-impl Shell {
-    fn new() -> Self {
-        Shell {
-            paths: Self::parse_path(),
-            builtins: HashSet::from(["echo", "exit", "type", "pwd", "cd"]),
-            editor: LineEditor::new(),
-        }
-    }
-
-    fn parse_path() -> Vec<String> {
-        let separator = if cfg!(windows) { ';' } else { ':' };
-
-        env::var("PATH")
-            .unwrap_or_default()
-            .split(separator)
-            .map(String::from)
-            .collect()
-    }
-
-    #[cfg(unix)]
-    fn is_executable(path: &Path) -> bool {
-        use std::os::unix::fs::PermissionsExt;
-
-        std::fs::metadata(path)
-            .map(|m| m.is_file() && (m.permissions().mode() & 0o111 != 0))
-            .unwrap_or(false)
-    }
-
-    #[cfg(windows)]
-    fn is_executable(path: &Path) -> bool {
-        path.is_file()
-            && path
-                .extension()
-                .map(|ext| {
-                    let ext = ext.to_string_lossy().to_lowercase();
-                    matches!(ext.as_str(), "exe" | "bat" | "cmd" | "com" | "ps1")
-                })
-                .unwrap_or(false)
-    }
-
-    fn find_executable(&self, cmd: &str) -> Option<String> {
-        #[cfg(windows)]
-        let candidates = [
-            cmd.to_string(),
-            format!("{}.exe", cmd),
-            format!("{}.bat", cmd),
-            format!("{}.cmd", cmd),
-            format!("{}.com", cmd),
-        ];
-
-        #[cfg(unix)]
-        let candidates = [cmd.to_string()];
-
-        for dir in &self.paths {
-            for candidate in &candidates {
-                let full_path = Path::new(dir).join(candidate);
-
-                if full_path.exists() && Self::is_executable(&full_path) {
-                    return full_path.to_str().map(String::from);
-                }
-            }
-        }
-        None
-    }
-
-    fn find_completions(&self, partial: &str) -> Vec<String> {
-        if partial.is_empty() {
-            return Vec::new();
-        }
-
-        let mut completions = Vec::new();
-
-        for builtin in &self.builtins {
-            if builtin.starts_with(partial) {
-                completions.push(format!("{builtin} "));
-            }
-        }
-
-        for dir in &self.paths {
-            if let Ok(entries) = std::fs::read_dir(dir) {
-                for entry in entries.flatten() {
-                    if let Ok(file_name) = entry.file_name().into_string() {
-                        let name_without_ext = if cfg!(windows) {
-                            file_name
-                                .strip_suffix(".exe")
-                                .or_else(|| file_name.strip_suffix(".bat"))
-                                .or_else(|| file_name.strip_suffix(".cmd"))
-                                .or_else(|| file_name.strip_suffix(".com"))
-                                .unwrap_or(&file_name)
-                        } else {
-                            &file_name
-                        };
-
-                        if name_without_ext.starts_with(partial)
-                            && Self::is_executable(&entry.path())
-                        {
-                            completions.push(format!("{name_without_ext} "));
-                        }
-                    }
-                }
-            }
-        }
-
-        completions.sort();
-        completions.dedup();
-        completions
-    }
-
-    fn print_prompt(&self) {
-        print!("$ ");
-        let _ = io::stdout().flush();
-    }
-
-    fn redraw_line(&self) {
-        print!("\r\x1B[K$ {}", self.editor.buffer);
-
-        let pos = self.editor.cursor;
-        let line_len = self.editor.buffer.len();
-        if pos < line_len {
-            print!("\r\x1B[{}C", pos + 2);
-        }
-
-        let _ = io::stdout().flush();
-    }
-
-    fn show_completions(&self, completions: &[String]) {
-        println!();
-        println!("{}", completions.join(" "));
-        self.print_prompt();
-        print!("{}", self.editor.buffer);
-        let _ = io::stdout().flush();
-    }
-
-    fn handle_double_tab(&mut self) {
-        if let Some((_, _, word)) = self.editor.get_word_at_cursor() {
-            let completions = self.find_completions(word);
-            self.show_completions(&completions);
-        }
-    }
-
-    fn longest_common_prefix(strings: &[String]) -> String {
-        if strings.is_empty() {
-            return String::new();
-        }
-
-        if strings.len() == 1 {
-            return strings[0].clone();
-        }
-
-        let first = &strings[0];
-        let mut prefix_len = first.len();
-
-        for s in &strings[1..] {
-            prefix_len = first
-                .chars()
-                .zip(s.chars())
-                .take(prefix_len)
-                .take_while(|(a, b)| a == b)
-                .count();
-
-            if prefix_len == 0 {
-                break;
-            }
-        }
-
-        first.chars().take(prefix_len).collect()
-    }
-
-    fn handle_tab(&mut self) {
-        if let Some((start, end, word)) = self.editor.get_word_at_cursor() {
-            let completions = self.find_completions(word);
-
-            match completions.len() {
-                0 => {
-                    print!("\x07");
-                    let _ = io::stdout().flush();
-                }
-                1 => {
-                    self.editor.replace_word(start, end, &completions[0]);
-                    self.redraw_line();
-                }
-                _ => {
-                    let lcp = Self::longest_common_prefix(&completions);
-
-                    if lcp.len() > word.len() {
-                        self.editor.replace_word(start, end, &lcp);
-                        self.redraw_line();
-                    }
-
-                    print!("\x07");
-                    let _ = io::stdout().flush();
-                }
-            }
-        }
-    }
-
-    fn read_line(&mut self) -> io::Result<bool> {
-        use terminal::RawMode;
-
-        self.editor.clear();
-        self.print_prompt();
-
-        let _raw = RawMode::enable()?;
-        let mut double_tab = false;
-        loop {
-            match read_key()? {
-                None => continue,
-                Some(Key::Enter) => {
-                    println!();
-                    return Ok(true);
-                }
-                Some(Key::Tab) => {
-                    if !double_tab {
-                        self.handle_tab();
-                        double_tab = true;
-                    } else {
-                        self.handle_double_tab();
-                        double_tab = false;
-                    }
-                }
-                Some(Key::Backspace) => {
-                    double_tab = false;
-                    self.editor.backspace();
-                    self.redraw_line();
-                }
-                Some(Key::Delete) => {
-                    double_tab = false;
-                    self.editor.delete();
-                    self.redraw_line();
-                }
-                Some(Key::Left) => {
-                    double_tab = false;
-                    self.editor.move_left();
-                    self.redraw_line();
-                }
-                Some(Key::Right) => {
-                    double_tab = false;
-                    self.editor.move_right();
-                    self.redraw_line();
-                }
-                Some(Key::Home) | Some(Key::CtrlA) => {
-                    double_tab = false;
-                    self.editor.move_home();
-                    self.redraw_line();
-                }
-                Some(Key::End) | Some(Key::CtrlE) => {
-                    double_tab = false;
-                    self.editor.move_end();
-                    self.redraw_line();
-                }
-                Some(Key::CtrlC) => {
-                    println!("^C");
-                    self.editor.clear();
-                    return Ok(true);
-                }
-                Some(Key::CtrlD) => {
-                    double_tab = false;
-                    if self.editor.buffer.is_empty() {
-                        println!();
-                        return Ok(false);
-                    }
-                }
-                Some(Key::Char(ch)) => {
-                    double_tab = false;
-                    self.editor.insert(ch);
-                    self.redraw_line();
-                }
-                Some(Key::Up) | Some(Key::Down) => {
-                    double_tab = false;
-                    // Could implement history here
-                }
-                Some(Key::Unknown) => {
-                    double_tab = false;
-                }
-            }
-        }
-    }
-
-    fn parse(&self) -> (String, ParsedCommand) {
-        let parsed = Self::parse_arguments(self.editor.buffer.trim());
-
-        if parsed.args.is_empty() {
-            return (String::new(), parsed);
-        }
-
-        let command = parsed.args[0].clone();
-        let remaining = ParsedCommand {
-            args: parsed.args[1..].to_vec(),
-            redirects: parsed.redirects,
-        };
-
-        (command, remaining)
-    }
-
-    fn parse_arguments(input: &str) -> ParsedCommand {
-        let mut result = ParsedCommand::new();
-        let mut current_arg = String::new();
-        let mut in_single_quote = false;
-        let mut in_double_quote = false;
-        let mut chars = input.chars().peekable();
-
-        let mut expecting_file = false;
-        let mut current_redirect: Option<Redirect> = None;
-
-        while let Some(c) = chars.next() {
-            if expecting_file && !in_single_quote && !in_double_quote {
-                match c {
-                    ' ' => {
-                        if !current_arg.is_empty()
-                            && let Some(mut redirect) = current_redirect.take()
-                        {
-                            redirect.file = current_arg.clone();
-                            result.redirects.push(redirect);
-                            current_arg.clear();
-                            expecting_file = false;
-                        }
-                        continue;
-                    }
-                    '\'' => {
-                        in_single_quote = true;
-                        continue;
-                    }
-                    '"' => {
-                        in_double_quote = true;
-                        continue;
-                    }
-                    _ => {
-                        current_arg.push(c);
-                        continue;
-                    }
-                }
-            }
-
-            match c {
-                '\\' if in_double_quote => {
-                    if let Some('"' | '\\' | '$' | '`') = chars.peek() {
-                        current_arg.push(chars.next().unwrap());
-                    } else {
-                        current_arg.push('\\');
-                    }
-                }
-
-                '\\' if !in_single_quote => {
-                    if let Some(next) = chars.next() {
-                        current_arg.push(next);
-                    }
-                }
-
-                '\'' if !in_double_quote => {
-                    in_single_quote = !in_single_quote;
-                }
-
-                '"' if !in_single_quote => {
-                    in_double_quote = !in_double_quote;
-                }
-
-                '2' if !in_single_quote && !in_double_quote => {
-                    if chars.peek() == Some(&'>') {
-                        if !current_arg.is_empty() {
-                            result.args.push(current_arg.clone());
-                            current_arg.clear();
-                        }
-
-                        chars.next();
-                        let append = chars.peek() == Some(&'>');
-                        if append {
-                            chars.next();
-                        }
-
-                        current_redirect = Some(Redirect {
-                            stream: StreamType::Stderr,
-                            file: String::new(),
-                            append,
-                        });
-                        expecting_file = true;
-                    } else {
-                        current_arg.push(c);
-                    }
-                }
-
-                '1' if !in_single_quote && !in_double_quote => {
-                    if chars.peek() == Some(&'>') {
-                        if !current_arg.is_empty() {
-                            result.args.push(current_arg.clone());
-                            current_arg.clear();
-                        }
-
-                        chars.next();
-                        let append = chars.peek() == Some(&'>');
-                        if append {
-                            chars.next();
-                        }
-
-                        current_redirect = Some(Redirect {
-                            stream: StreamType::Stdout,
-                            file: String::new(),
-                            append,
-                        });
-                        expecting_file = true;
-                    } else {
-                        current_arg.push(c);
-                    }
-                }
-
-                '>' if !in_single_quote && !in_double_quote => {
-                    if !current_arg.is_empty() {
-                        result.args.push(current_arg.clone());
-                        current_arg.clear();
-                    }
-
-                    let append = chars.peek() == Some(&'>');
-                    if append {
-                        chars.next();
-                    }
-
-                    current_redirect = Some(Redirect {
-                        stream: StreamType::Stdout,
-                        file: String::new(),
-                        append,
-                    });
-                    expecting_file = true;
-                }
-
-                ' ' if !in_single_quote && !in_double_quote => {
-                    if !current_arg.is_empty() {
-                        result.args.push(current_arg.clone());
-                        current_arg.clear();
-                    }
-                }
-
-                _ => {
-                    current_arg.push(c);
-                }
-            }
-        }
-
-        if !current_arg.is_empty() {
-            if let Some(mut redirect) = current_redirect.take() {
-                redirect.file = current_arg;
-                result.redirects.push(redirect);
-            } else {
-                result.args.push(current_arg);
-            }
-        }
-
-        result
-    }
-
-    fn open_redirect_file(redirect: &Redirect) -> io::Result<File> {
-        if redirect.append {
-            OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(&redirect.file)
-        } else {
-            File::create(&redirect.file)
-        }
-    }
-
-    fn eval(&mut self) {
-        let (command, parsed) = self.parse();
-
-        if command.is_empty() {
-            return;
-        }
-
-        for redirect in &parsed.redirects {
-            let _ = Self::open_redirect_file(redirect);
-        }
-
-        match command.as_str() {
-            "echo" => self.cmd_echo(&parsed),
-            "type" => self.cmd_type(&parsed),
-            "pwd" => self.cmd_pwd(&parsed),
-            "cd" => self.cmd_cd(&parsed),
-            "exit" => self.cmd_exit(&parsed),
-            _ => self.cmd_external(&command, &parsed),
-        }
-    }
-
-    fn write_output(&self, message: &str, parsed: &ParsedCommand) {
-        for redirect in &parsed.redirects {
-            if matches!(redirect.stream, StreamType::Stdout)
-                && let Ok(mut file) = Self::open_redirect_file(redirect)
-            {
-                let _ = writeln!(file, "{}", message);
-                return;
-            }
-        }
-        println!("{}", message);
-    }
-
-    fn write_error(&self, message: &str, parsed: &ParsedCommand) {
-        for redirect in &parsed.redirects {
-            if matches!(redirect.stream, StreamType::Stderr)
-                && let Ok(mut file) = Self::open_redirect_file(redirect)
-            {
-                let _ = writeln!(file, "{}", message);
-                return;
-            }
-        }
-        eprintln!("{}", message);
-    }
-
-    fn cmd_exit(&self, parsed: &ParsedCommand) -> ! {
-        let code: i32 = parsed
-            .args
-            .first()
-            .and_then(|s| s.parse().ok())
-            .unwrap_or(0);
-        std::process::exit(code);
-    }
-
-    fn cmd_echo(&self, parsed: &ParsedCommand) {
-        let output = parsed.args.join(" ");
-        self.write_output(&output, parsed);
-    }
-
-    fn cmd_type(&self, parsed: &ParsedCommand) {
-        for cmd in &parsed.args {
-            if cmd.is_empty() {
-                continue;
-            }
-
-            if self.builtins.contains(cmd.as_str()) {
-                self.write_output(&format!("{} is a shell builtin", cmd), parsed);
-            } else if let Some(path) = self.find_executable(cmd) {
-                self.write_output(&format!("{} is {}", cmd, path), parsed);
-            } else {
-                self.write_error(&format!("{}: not found", cmd), parsed);
-            }
-        }
-    }
-
-    fn cmd_pwd(&self, parsed: &ParsedCommand) {
-        match env::current_dir() {
-            Ok(path) => self.write_output(&path.display().to_string(), parsed),
-            Err(e) => self.write_error(&format!("pwd: {}", e), parsed),
-        }
-    }
-
-    fn cmd_cd(&self, parsed: &ParsedCommand) {
-        let arg = parsed.args.first().map(|s| s.as_str()).unwrap_or("");
-
-        let path = match arg {
-            "" | "~" => env::var("HOME")
-                .or_else(|_| env::var("USERPROFILE"))
-                .unwrap_or_default(),
-            path if path.starts_with("~/") => {
-                let home = env::var("HOME")
-                    .or_else(|_| env::var("USERPROFILE"))
-                    .unwrap_or_default();
-                format!("{}{}", home, &path[1..])
-            }
-            path => path.to_string(),
-        };
-
-        let path = Path::new(&path);
-
-        if path.exists() {
-            if let Err(e) = env::set_current_dir(path) {
-                self.write_error(&format!("cd: {}: {}", path.display(), e), parsed);
-            }
-        } else {
-            self.write_error(
-                &format!("cd: {}: No such file or directory", path.display()),
-                parsed,
+fn main() {
+    // Spans go to stderr, filtered by RUST_LOG (e.g. `RUST_LOG=debug`).
+    #[cfg(feature = "tracing")]
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_span_events(tracing_subscriber::fmt::format::FmtSpan::CLOSE)
+        .with_writer(std::io::stderr)
+        .init();
+
+    Shell::install_signal_handlers();
+
+    let options = match Options::from_env() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!(
+                "usage: {} [-l] [--posix] [--norc] [--rcfile file] [--version] \
+                 [-c command | script] [arg ...]",
+                env!("CARGO_PKG_NAME")
             );
+            std::process::exit(2);
         }
-    }
-
-    fn cmd_external(&self, command: &str, parsed: &ParsedCommand) {
-        if self.find_executable(command).is_some() {
-            let mut cmd = ProcessCommand::new(command);
-            cmd.args(&parsed.args);
-
-            for redirect in &parsed.redirects {
-                match redirect.stream {
-                    StreamType::Stdout => {
-                        if let Ok(file) = Self::open_redirect_file(redirect) {
-                            cmd.stdout(Stdio::from(file));
-                        }
-                    }
-                    StreamType::Stderr => {
-                        if let Ok(file) = Self::open_redirect_file(redirect) {
-                            cmd.stderr(Stdio::from(file));
-                        }
-                    }
-                }
-            }
-
-            match cmd.status() {
-                Ok(_) => {}
-                Err(e) => self.write_error(&format!("{}: {}", command, e), parsed),
-            }
-        } else {
-            self.write_error(&format!("{}: command not found", command), parsed);
-        }
-    }
-
-    fn run(&mut self) -> io::Result<()> {
-        loop {
-            if !self.read_line()? {
-                break;
-            }
-
-            self.eval();
-        }
+    };
 
-        Ok(())
+    if options.version {
+        println!("{}", version::describe());
+        return;
     }
-}
 
-fn main() {
     let mut shell = Shell::new();
-    if let Err(e) = shell.run() {
-        eprintln!("Shell error: {}", e);
-        std::process::exit(1);
-    }
+    shell.set_login(options.login);
+    if options.posix {
+        shell.set_posix(true);
+    }
+    shell.init_environment();
+    shell.load_plugins();
+    shell.load_env_file();
+
+    let mut operands = options.operands.into_iter();
+    if let Some(command) = options.command {
+        if let Some(name) = operands.next() {
+            shell.set_arguments(name, operands.collect());
+        }
+        shell.run_string(&command);
+    } else if let Some(script) = operands.next() {
+        shell.set_arguments(script.clone(), operands.collect());
+        shell.run_file(&script);
+    } else {
+        shell.load_rc(&options.rc_file);
+        #[cfg(feature = "history")]
+        shell.load_history();
+        if let Err(e) = shell.run() {
+            eprintln!("Shell error: {}", e);
+            std::process::exit(1);
+        }
+    }
+    let status = shell.last_status();
+    shell.exit(status);
 }
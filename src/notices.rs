@@ -0,0 +1,35 @@
+/// ============================================
+/// ASYNCHRONOUS NOTICES
+/// ============================================
+use std::sync::{Arc, Mutex};
+
+/// A queue of messages for the user, such as `[1]+ Done` for a finished
+/// background job, that may be posted from any thread. An interactive
+/// shell prints them before its next prompt or, while a line is being
+/// edited, above the edit line, which is then drawn again as it was.
+#[derive(Clone, Default)]
+pub struct Notifier {
+    pending: Arc<Mutex<Vec<String>>>,
+}
+
+impl Notifier {
+    /// Queues `message`, a single line without its newline.
+    pub fn post(&self, message: impl Into<String>) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.push(message.into());
+        }
+    }
+
+    #[cfg(feature = "line-editor")]
+    pub(crate) fn has_pending(&self) -> bool {
+        self.pending.lock().is_ok_and(|pending| !pending.is_empty())
+    }
+
+    /// Removes and returns the messages queued so far.
+    pub(crate) fn take(&self) -> Vec<String> {
+        self.pending
+            .lock()
+            .map(|mut pending| std::mem::take(&mut *pending))
+            .unwrap_or_default()
+    }
+}
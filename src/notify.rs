@@ -0,0 +1,102 @@
+/// ============================================
+/// SLOW-COMMAND NOTIFICATIONS
+/// ============================================
+use std::io::{self, IsTerminal, Write};
+use std::time::Duration;
+
+use crate::Shell;
+
+/// How the end of a slow command is announced, as `set -o notifystyle`
+/// chooses.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum NotifyStyle {
+    /// Rings the terminal bell.
+    #[default]
+    Bell,
+    /// Asks the terminal for a desktop notification with OSC 9.
+    Desktop,
+}
+
+impl NotifyStyle {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            NotifyStyle::Bell => "bell",
+            NotifyStyle::Desktop => "desktop",
+        }
+    }
+
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "bell" => Some(NotifyStyle::Bell),
+            "desktop" => Some(NotifyStyle::Desktop),
+            _ => None,
+        }
+    }
+}
+
+impl Shell {
+    /// Announces the end of an interactive command line that took at least
+    /// `set -o notifyafter` seconds, if the terminal is not focused.
+    ///
+    /// Whether it is focused is only known once the line editor has read
+    /// the focus reports the terminal queued while the command ran, so the
+    /// notification waits in the editor until then. Without the line
+    /// editor the focus is unknown and the notification is sent at once.
+    pub(crate) fn notify_if_slow(&mut self, source: &str, status: i32, elapsed: Duration) {
+        if self.notify_after == 0
+            || elapsed.as_secs() < self.notify_after
+            || !io::stderr().is_terminal()
+        {
+            return;
+        }
+
+        let message = match self.notify_style {
+            NotifyStyle::Desktop => {
+                let body = format!(
+                    "{} (status {}, {}s)",
+                    printable(source),
+                    status,
+                    elapsed.as_secs()
+                );
+                format!("\x1b]9;{}\x07", body)
+            }
+            NotifyStyle::Bell => "\x07".to_string(),
+        };
+
+        #[cfg(feature = "line-editor")]
+        if io::stdin().is_terminal() {
+            self.editor.notification = Some(message);
+            return;
+        }
+        send(&message);
+    }
+
+    /// Sends the notification `notify_if_slow` left in the editor, unless
+    /// the terminal has been focused in the meantime.
+    #[cfg(feature = "line-editor")]
+    pub(crate) fn send_notification(&mut self) {
+        if let Some(message) = self.editor.notification.take()
+            && !self.editor.focused
+        {
+            send(&message);
+        }
+    }
+}
+
+fn send(message: &str) {
+    let mut stderr = io::stderr();
+    let _ = stderr.write_all(message.as_bytes());
+    let _ = stderr.flush();
+}
+
+/// `source` on one line without control characters, which would end or
+/// corrupt the escape sequence.
+fn printable(source: &str) -> String {
+    source
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .filter(|c| !c.is_control())
+        .collect()
+}
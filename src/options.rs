@@ -0,0 +1,80 @@
+/// ============================================
+/// COMMAND-LINE OPTIONS
+/// ============================================
+use std::env;
+use std::path::PathBuf;
+
+use crate::osstr;
+
+/// Which rc file an interactive shell reads at startup.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum RcFile {
+    #[default]
+    Default,
+    Custom(PathBuf),
+    Skip,
+}
+
+/// Options given on the shell's command line.
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    pub rc_file: RcFile,
+    pub login: bool,
+    /// `--posix`: start in POSIX mode, as `set -o posix` does.
+    pub posix: bool,
+    /// `--version`: print build information and exit.
+    pub version: bool,
+    /// `-c string`: run the commands in `string` instead of reading them.
+    pub command: Option<String>,
+    /// What follows the options: a script to run and its arguments, or
+    /// with `-c` the values of `$0`, `$1`, ...
+    pub operands: Vec<String>,
+}
+
+impl Options {
+    /// Parses the arguments the shell was started with.
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(env::args_os().skip(1).map(|arg| osstr::from_os(&arg)))
+    }
+
+    /// Parses the arguments following the program name.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-l" | "--login" => options.login = true,
+                "--posix" => options.posix = true,
+                "--version" => options.version = true,
+                "-c" => {
+                    let command = args
+                        .next()
+                        .ok_or_else(|| "-c: option requires an argument".to_string())?;
+                    options.command = Some(command);
+                }
+                "--norc" => options.rc_file = RcFile::Skip,
+                "--rcfile" => {
+                    let file = args
+                        .next()
+                        .ok_or_else(|| "--rcfile: option requires an argument".to_string())?;
+                    options.rc_file = RcFile::Custom(file.into());
+                }
+                _ if arg.starts_with("--rcfile=") => {
+                    options.rc_file = RcFile::Custom(arg["--rcfile=".len()..].into());
+                }
+                "--" | "-" => break,
+                _ if arg.starts_with('-') => {
+                    return Err(format!("{}: invalid option", arg));
+                }
+                _ => {
+                    options.operands.push(arg);
+                    break;
+                }
+            }
+        }
+        options.operands.extend(args);
+
+        Ok(options)
+    }
+}
@@ -0,0 +1,107 @@
+/// ============================================
+/// OS STRING ENCODING
+/// ============================================
+use std::borrow::Cow;
+use std::ffi::{OsStr, OsString};
+
+/// The shell works on `String`s, but file names, arguments and environment
+/// values are arbitrary bytes on Unix and possibly ill-formed UTF-16 on
+/// Windows. Units that are not valid Unicode are carried through words and
+/// expansions as characters in Supplementary Private Use Area-A, at this
+/// offset, and turned back into the original units whenever a string is
+/// handed back to the OS. Valid text is unaffected.
+const ESCAPE_BASE: u32 = 0xF_0000;
+
+/// The raw unit a character stands for, if it is an escape this platform
+/// produces: a byte 0x80-0xFF on Unix, an unpaired surrogate on Windows.
+fn escaped_unit(c: char) -> Option<u32> {
+    let unit = (c as u32).checked_sub(ESCAPE_BASE)?;
+    let range = if cfg!(windows) {
+        0xD800..0xE000
+    } else {
+        0x80..0x100
+    };
+    range.contains(&unit).then_some(unit)
+}
+
+fn escape(unit: u32) -> char {
+    char::from_u32(ESCAPE_BASE + unit).expect("escaped units are valid characters")
+}
+
+/// Converts an OS string to a shell string without losing anything.
+#[cfg(unix)]
+pub fn from_os(s: &OsStr) -> String {
+    use std::os::unix::ffi::OsStrExt;
+
+    from_bytes(s.as_bytes())
+}
+
+#[cfg(windows)]
+pub fn from_os(s: &OsStr) -> String {
+    use std::os::windows::ffi::OsStrExt;
+
+    char::decode_utf16(s.encode_wide())
+        .map(|c| c.unwrap_or_else(|e| escape(e.unpaired_surrogate().into())))
+        .collect()
+}
+
+/// Converts a shell string back to the OS string it came from.
+#[cfg(unix)]
+pub fn to_os(s: &str) -> OsString {
+    use std::os::unix::ffi::OsStringExt;
+
+    OsString::from_vec(to_bytes(s).into_owned())
+}
+
+#[cfg(windows)]
+pub fn to_os(s: &str) -> OsString {
+    use std::os::windows::ffi::OsStringExt;
+
+    if !s.chars().any(|c| escaped_unit(c).is_some()) {
+        return OsString::from(s);
+    }
+    let mut wide = Vec::with_capacity(s.len());
+    for c in s.chars() {
+        match escaped_unit(c) {
+            Some(unit) => wide.push(unit as u16),
+            None => wide.extend_from_slice(c.encode_utf16(&mut [0; 2])),
+        }
+    }
+    OsString::from_wide(&wide)
+}
+
+/// The bytes a shell string stands for, for writing to files and pipes.
+/// On Windows escapes have no byte form and are written as they are.
+pub fn to_bytes(s: &str) -> Cow<'_, [u8]> {
+    if cfg!(windows) || !s.chars().any(|c| escaped_unit(c).is_some()) {
+        return Cow::Borrowed(s.as_bytes());
+    }
+    let mut bytes = Vec::with_capacity(s.len());
+    for c in s.chars() {
+        match escaped_unit(c) {
+            Some(unit) => bytes.push(unit as u8),
+            None => bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+    Cow::Owned(bytes)
+}
+
+/// Converts bytes read from a file, such as a script, to a shell string.
+/// On Unix nothing is lost; on Windows, where escapes have no byte form,
+/// invalid UTF-8 is replaced.
+pub fn from_bytes(bytes: &[u8]) -> String {
+    if cfg!(windows) {
+        return String::from_utf8_lossy(bytes).into_owned();
+    }
+    let mut text = String::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        text.push_str(chunk.valid());
+        text.extend(chunk.invalid().iter().map(|&byte| escape(byte.into())));
+    }
+    text
+}
+
+/// Reads an environment variable without dropping non-Unicode values.
+pub fn env_var(name: &str) -> Option<String> {
+    std::env::var_os(name).map(|value| from_os(&value))
+}
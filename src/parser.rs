@@ -0,0 +1,1459 @@
+/// ============================================
+/// PARSER - TOKENS, SYNTAX TREE AND GRAMMAR
+/// ============================================
+use std::borrow::Cow;
+use std::rc::Rc;
+
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StreamType {
+    Stdin,
+    Stdout,
+    Stderr,
+}
+
+impl StreamType {
+    /// The stream with file descriptor `fd`, as `2>&1` names it.
+    pub fn from_descriptor(fd: &str) -> Option<Self> {
+        match fd {
+            "0" => Some(Self::Stdin),
+            "1" => Some(Self::Stdout),
+            "2" => Some(Self::Stderr),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Redirect {
+    pub stream: StreamType,
+    /// The file, or for a here-document its delimiter as written, or for
+    /// `n>&m` the descriptor `m`.
+    pub file: String,
+    pub append: bool,
+    /// `n>&m` or `n<&m`: the stream becomes a copy of descriptor `m`.
+    pub duplicate: bool,
+    pub here_doc: Option<HereDoc>,
+}
+
+/// The lines given to a command's stdin by `<<delimiter`.
+#[derive(Debug, Clone)]
+pub struct HereDoc {
+    pub body: String,
+    /// Whether the delimiter was unquoted, so that parameters, commands
+    /// and arithmetic in the body are expanded.
+    pub expand: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum AssignValue {
+    Scalar(String),
+    /// `name=(word...)`
+    Array(Vec<String>),
+}
+
+/// `name=value`, `name[index]=value` or `name=(elements...)`.
+#[derive(Debug, Clone)]
+pub struct Assignment {
+    pub name: String,
+    pub index: Option<String>,
+    pub value: AssignValue,
+}
+
+/// A simple command as written in the source. Words are kept raw (quotes
+/// included) so that expansion can tell quoted text from unquoted text.
+#[derive(Debug, Clone, Default)]
+pub struct SimpleCommand {
+    /// The line of the input the command starts on, counting from 1.
+    pub line: usize,
+    pub assignments: Vec<Assignment>,
+    pub words: Vec<String>,
+    pub redirects: Vec<Redirect>,
+}
+
+#[derive(Debug, Clone)]
+pub struct IfClause {
+    /// `if`/`elif` conditions paired with the body run when they succeed.
+    pub branches: Vec<(Vec<Command>, Vec<Command>)>,
+    pub else_body: Option<Vec<Command>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ForClause {
+    pub var: String,
+    /// The words after `in`; `None` iterates over the positional parameters.
+    pub words: Option<Vec<String>>,
+    pub body: Vec<Command>,
+}
+
+#[derive(Debug, Clone)]
+pub struct WhileClause {
+    pub condition: Vec<Command>,
+    pub body: Vec<Command>,
+    /// `until` loops run while the condition fails.
+    pub until: bool,
+}
+
+/// How a `case` item hands control on after its body runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CaseTerminator {
+    /// `;;` ends the `case` command.
+    Break,
+    /// `;&` runs the next item's body without testing its patterns.
+    FallThrough,
+    /// `;;&` goes on testing the patterns of the following items.
+    Continue,
+}
+
+#[derive(Debug, Clone)]
+pub struct CaseItem {
+    pub patterns: Vec<String>,
+    pub body: Vec<Command>,
+    pub terminator: CaseTerminator,
+}
+
+#[derive(Debug, Clone)]
+pub struct CaseClause {
+    pub word: String,
+    pub items: Vec<CaseItem>,
+}
+
+/// `for (( init; condition; step )); do list; done`
+#[derive(Debug, Clone)]
+pub struct ArithForClause {
+    pub init: String,
+    pub condition: String,
+    pub step: String,
+    pub body: Vec<Command>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FunctionDef {
+    pub name: String,
+    /// Shared with the shell's function table, so defining and calling the
+    /// function reuse this tree instead of copying it.
+    pub body: Rc<Command>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Command {
+    Simple(SimpleCommand),
+    If(IfClause),
+    For(ForClause),
+    ArithFor(ArithForClause),
+    /// `select name in words; do list; done` shares the shape of `for`.
+    Select(ForClause),
+    While(WhileClause),
+    Case(CaseClause),
+    /// `{ list; }`
+    Group(Vec<Command>),
+    /// `(( expression ))`
+    Arith(String),
+    FunctionDef(FunctionDef),
+    /// `! pipeline`: runs the pipeline and inverts its exit status.
+    Not(Box<Command>),
+    /// `command | command...`, with at least two commands, and its text as
+    /// `jobs` shows it should it be stopped.
+    Pipeline(Vec<Command>, String),
+    /// `left && right`: runs `right` only if `left` succeeds.
+    And(Box<Command>, Box<Command>),
+    /// `left || right`: runs `right` only if `left` fails.
+    Or(Box<Command>, Box<Command>),
+    /// `command &`, with the command's text as `jobs` shows it.
+    Background(Box<Command>, String),
+}
+
+/// Where the token a syntax error was found at begins: its byte offset in
+/// the input and its line, counting from 1.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Span {
+    pub offset: usize,
+    pub line: usize,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ParseError {
+    /// The input ended in the middle of a construct; more lines are needed.
+    #[error("syntax error: unexpected end of file")]
+    Incomplete,
+    #[error("syntax error near unexpected token `{token}'")]
+    Unexpected { token: String, span: Span },
+    #[error("syntax error: commands nested too deeply")]
+    TooDeep { span: Span },
+}
+
+impl ParseError {
+    /// An unexpected `token`, placed by `parse` once parsing stops.
+    fn unexpected(token: impl Into<String>) -> Self {
+        Self::Unexpected {
+            token: token.into(),
+            span: Span::default(),
+        }
+    }
+
+    /// Where the error was found; `None` when the input ended early.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Self::Incomplete => None,
+            Self::Unexpected { span, .. } | Self::TooDeep { span } => Some(*span),
+        }
+    }
+
+    fn at(mut self, at: Span) -> Self {
+        if let Self::Unexpected { span, .. } | Self::TooDeep { span } = &mut self {
+            *span = at;
+        }
+        self
+    }
+}
+
+/// Reserved words that start a compound command.
+const OPENING_WORDS: &[&str] = &[
+    "if", "for", "select", "while", "until", "case", "{", "function",
+];
+
+/// Reserved words that continue or close a compound command; they end the
+/// command list in front of them.
+const CLOSING_WORDS: &[&str] = &["then", "elif", "else", "fi", "do", "done", "esac", "}"];
+
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Splits the target of an assignment (`name` or `name[index]`) into the
+/// variable name and optional raw subscript.
+pub fn parse_assignment_target(target: &str) -> Option<(String, Option<String>)> {
+    if is_valid_name(target) {
+        return Some((target.to_string(), None));
+    }
+
+    let (name, rest) = target.split_once('[')?;
+    let index = rest.strip_suffix(']')?;
+    (is_valid_name(name) && !index.is_empty()).then(|| (name.to_string(), Some(index.to_string())))
+}
+
+/// Quotes `word` so that the lexer reads it back as exactly that text.
+pub fn quote(word: &str) -> String {
+    format!("'{}'", word.replace('\'', r"'\''"))
+}
+
+/// The line that ends a here-document introduced with `word`: the word
+/// with its quotes removed.
+pub fn here_doc_delimiter(word: &str) -> String {
+    word.chars()
+        .filter(|c| !matches!(c, '\'' | '"' | '\\'))
+        .collect()
+}
+
+fn parse_assignment(word: &str) -> Option<Assignment> {
+    let (target, value) = word.split_once('=')?;
+    let (name, index) = parse_assignment_target(target)?;
+    Some(Assignment {
+        name,
+        index,
+        value: AssignValue::Scalar(value.to_string()),
+    })
+}
+
+// ============================================
+// LEXER
+// ============================================
+
+/// A token borrows its text from the input line; a word is only copied when
+/// line continuations have to be cut out of it.
+#[derive(Debug, Clone, PartialEq)]
+enum Token<'a> {
+    Word(Cow<'a, str>),
+    /// `name=(` ... `)`: the assignment target and the raw element words.
+    ArrayAssign(&'a str, Vec<Cow<'a, str>>),
+    /// `(( expression ))`
+    Arith(&'a str),
+    Redirect {
+        stream: StreamType,
+        append: bool,
+        duplicate: bool,
+    },
+    /// `<<`, or `<<-` to strip leading tabs from the body.
+    HereDoc {
+        strip_tabs: bool,
+    },
+    Semi,
+    Newline,
+    Pipe,
+    AndIf,
+    OrIf,
+    Amp,
+    LParen,
+    RParen,
+    CaseEnd(CaseTerminator),
+}
+
+impl Token<'_> {
+    fn describe(&self) -> String {
+        match self {
+            Token::Word(word) => word.to_string(),
+            Token::ArrayAssign(..) => "(".to_string(),
+            Token::Arith(_) => "((".to_string(),
+            Token::Redirect {
+                stream: StreamType::Stdin,
+                duplicate,
+                ..
+            } => if *duplicate { "<&" } else { "<" }.to_string(),
+            Token::Redirect {
+                stream,
+                append,
+                duplicate,
+            } => {
+                let fd = if *stream == StreamType::Stderr {
+                    "2"
+                } else {
+                    ""
+                };
+                let op = match (append, duplicate) {
+                    (true, _) => ">>",
+                    (false, true) => ">&",
+                    (false, false) => ">",
+                };
+                format!("{fd}{op}")
+            }
+            Token::HereDoc { strip_tabs: false } => "<<".to_string(),
+            Token::HereDoc { strip_tabs: true } => "<<-".to_string(),
+            Token::Semi => ";".to_string(),
+            Token::Newline => "newline".to_string(),
+            Token::Pipe => "|".to_string(),
+            Token::AndIf => "&&".to_string(),
+            Token::OrIf => "||".to_string(),
+            Token::Amp => "&".to_string(),
+            Token::LParen => "(".to_string(),
+            Token::RParen => ")".to_string(),
+            Token::CaseEnd(CaseTerminator::Break) => ";;".to_string(),
+            Token::CaseEnd(CaseTerminator::FallThrough) => ";&".to_string(),
+            Token::CaseEnd(CaseTerminator::Continue) => ";;&".to_string(),
+        }
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self, Token::Word(word) if word == keyword)
+    }
+}
+
+struct Lexer<'a> {
+    input: &'a str,
+    pos: usize,
+    /// Where the last token read began.
+    token_start: usize,
+    /// A position and its line number, from which `line_at` counts on.
+    counted: (usize, usize),
+    /// Where the here-document bodies read for the current line end; the
+    /// lexer goes on from there at the end of the line.
+    here_doc_end: Option<usize>,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(input: &'a str) -> Self {
+        Self {
+            input,
+            pos: 0,
+            token_start: 0,
+            counted: (0, 1),
+            here_doc_end: None,
+        }
+    }
+
+    /// The line number of `pos`, which must not come before a position
+    /// asked about earlier.
+    fn line_at(&mut self, pos: usize) -> usize {
+        let (from, line) = self.counted;
+        let pos = pos.max(from);
+        let line = line + self.input[from..pos].matches('\n').count();
+        self.counted = (pos, line);
+        line
+    }
+
+    fn peek_char(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn peek_second(&self) -> Option<char> {
+        self.input[self.pos..].chars().nth(1)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek_char()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    /// Skips blanks, comments and line continuations. Input that ends
+    /// right after a continuation is incomplete: the command goes on on the
+    /// next line.
+    fn skip_blanks_and_comments(&mut self) -> Result<(), ParseError> {
+        loop {
+            match self.peek_char() {
+                Some(' ' | '\t') => {
+                    self.bump();
+                }
+                Some('\\') if self.peek_second() == Some('\n') => {
+                    self.pos += 2;
+                    if self.peek_char().is_none() {
+                        return Err(ParseError::Incomplete);
+                    }
+                }
+                Some('#') => {
+                    while let Some(c) = self.peek_char() {
+                        if c == '\n' {
+                            break;
+                        }
+                        self.bump();
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn is_metachar(c: char) -> bool {
+        matches!(
+            c,
+            ' ' | '\t' | '\n' | ';' | '&' | '<' | '>' | '|' | '(' | ')'
+        )
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip(self), ret)
+    )]
+    fn next_token(&mut self) -> Result<Option<Token<'a>>, ParseError> {
+        self.skip_blanks_and_comments()?;
+        self.token_start = self.pos;
+
+        let Some(c) = self.peek_char() else {
+            return Ok(None);
+        };
+
+        match c {
+            '\n' => {
+                self.bump();
+                if let Some(end) = self.here_doc_end.take() {
+                    self.pos = end;
+                }
+                Ok(Some(Token::Newline))
+            }
+            ';' => {
+                self.bump();
+                let token = match (self.peek_char(), self.peek_second()) {
+                    (Some(';'), Some('&')) => {
+                        self.pos += 2;
+                        Token::CaseEnd(CaseTerminator::Continue)
+                    }
+                    (Some(';'), _) => {
+                        self.bump();
+                        Token::CaseEnd(CaseTerminator::Break)
+                    }
+                    (Some('&'), _) => {
+                        self.bump();
+                        Token::CaseEnd(CaseTerminator::FallThrough)
+                    }
+                    _ => Token::Semi,
+                };
+                Ok(Some(token))
+            }
+            '|' if self.peek_second() == Some('|') => {
+                self.pos += 2;
+                Ok(Some(Token::OrIf))
+            }
+            '|' => {
+                self.bump();
+                Ok(Some(Token::Pipe))
+            }
+            '&' if self.peek_second() == Some('&') => {
+                self.pos += 2;
+                Ok(Some(Token::AndIf))
+            }
+            '&' => {
+                self.bump();
+                Ok(Some(Token::Amp))
+            }
+            '(' if self.peek_second() == Some('(') => {
+                self.pos += 2;
+                Ok(Some(Token::Arith(self.arith_body()?)))
+            }
+            '(' => {
+                self.bump();
+                Ok(Some(Token::LParen))
+            }
+            ')' => {
+                self.bump();
+                Ok(Some(Token::RParen))
+            }
+            '<' if self.peek_second() == Some('<') => {
+                self.pos += 2;
+                let strip_tabs = self.peek_char() == Some('-');
+                if strip_tabs {
+                    self.bump();
+                }
+                Ok(Some(Token::HereDoc { strip_tabs }))
+            }
+            '<' => {
+                self.bump();
+                Ok(Some(self.redirect(StreamType::Stdin, '<')))
+            }
+            '>' => {
+                self.bump();
+                Ok(Some(self.redirect(StreamType::Stdout, '>')))
+            }
+            '0' | '1' | '2' if self.at_descriptor_redirect() => {
+                self.bump();
+                let operator = self.bump().unwrap_or('>');
+                let stream = match c {
+                    '0' => StreamType::Stdin,
+                    '1' => StreamType::Stdout,
+                    _ => StreamType::Stderr,
+                };
+                Ok(Some(self.redirect(stream, operator)))
+            }
+            _ => {
+                let word = self.word()?;
+                if self.peek_char() == Some('(')
+                    && let Cow::Borrowed(word) = word
+                    && let Some(target) = word.strip_suffix('=')
+                    && parse_assignment_target(target).is_some()
+                {
+                    self.bump();
+                    let elements = self.array_elements()?;
+                    return Ok(Some(Token::ArrayAssign(target, elements)));
+                }
+                Ok(Some(Token::Word(word)))
+            }
+        }
+    }
+
+    /// Reads the words of an array literal up to the closing `)`.
+    fn array_elements(&mut self) -> Result<Vec<Cow<'a, str>>, ParseError> {
+        let mut elements = Vec::new();
+
+        loop {
+            self.skip_blanks_and_comments()?;
+            match self.peek_char() {
+                None => return Err(ParseError::Incomplete),
+                Some('\n') => {
+                    self.bump();
+                }
+                Some(')') => {
+                    self.bump();
+                    return Ok(elements);
+                }
+                Some(c) if Self::is_metachar(c) => {
+                    return Err(ParseError::unexpected(c.to_string()));
+                }
+                Some(_) => elements.push(self.word()?),
+            }
+        }
+    }
+
+    /// Whether the input starts with a redirection of a numbered stream:
+    /// `0<`, `1>` or `2>`, or a duplication such as `2>&` or `0<&`.
+    fn at_descriptor_redirect(&self) -> bool {
+        let mut chars = self.input[self.pos..].chars();
+        matches!(
+            (chars.next(), chars.next(), chars.next()),
+            (Some('0'), Some('<'), _)
+                | (Some('1' | '2'), Some('>'), _)
+                | (_, Some('<' | '>'), Some('&'))
+        )
+    }
+
+    /// The redirection whose `operator` has just been read: `>>` appends,
+    /// and `>&` or `<&` duplicates a descriptor.
+    fn redirect(&mut self, stream: StreamType, operator: char) -> Token<'a> {
+        let append = operator == '>' && self.peek_char() == Some('>');
+        if append {
+            self.bump();
+        }
+        let duplicate = !append && self.peek_char() == Some('&');
+        if duplicate {
+            self.bump();
+        }
+        Token::Redirect {
+            stream,
+            append,
+            duplicate,
+        }
+    }
+
+    /// Reads the body of a here-document, up to a line holding only
+    /// `delimiter`. It starts on the line after the current one, or after
+    /// the bodies of the here-documents before it on this line.
+    fn here_doc_body(&mut self, delimiter: &str, strip_tabs: bool) -> Result<String, ParseError> {
+        let mut pos = match self.here_doc_end {
+            Some(end) => end,
+            None => {
+                self.pos
+                    + self.input[self.pos..]
+                        .find('\n')
+                        .ok_or(ParseError::Incomplete)?
+                    + 1
+            }
+        };
+
+        let mut body = String::new();
+        loop {
+            let rest = &self.input[pos..];
+            let (line, next) = match rest.find('\n') {
+                Some(end) => (&rest[..end], pos + end + 1),
+                None if !rest.is_empty() => (rest, self.input.len()),
+                None => return Err(ParseError::Incomplete),
+            };
+            pos = next;
+            let line = if strip_tabs {
+                line.trim_start_matches('\t')
+            } else {
+                line
+            };
+            if line == delimiter {
+                break;
+            }
+            body.push_str(line);
+            body.push('\n');
+        }
+
+        self.here_doc_end = Some(pos);
+        Ok(body)
+    }
+
+    /// Scans one word, keeping quotes and escapes in the returned text. The
+    /// word is a slice of the input unless it contains a line continuation.
+    fn word(&mut self) -> Result<Cow<'a, str>, ParseError> {
+        let start = self.pos;
+        // Where each backslash-newline to leave out of the word starts.
+        let mut continuations = Vec::new();
+
+        while let Some(c) = self.peek_char() {
+            match c {
+                '\\' => {
+                    let at = self.pos;
+                    self.bump();
+                    match self.bump() {
+                        Some('\n') => {
+                            if self.peek_char().is_none() {
+                                return Err(ParseError::Incomplete);
+                            }
+                            continuations.push(at);
+                        }
+                        Some(_) => {}
+                        None => return Err(ParseError::Incomplete),
+                    }
+                }
+                '\'' => {
+                    self.bump();
+                    loop {
+                        match self.bump() {
+                            Some('\'') => break,
+                            Some(_) => {}
+                            None => return Err(ParseError::Incomplete),
+                        }
+                    }
+                }
+                '"' => {
+                    self.bump();
+                    loop {
+                        let at = self.pos;
+                        match self.bump() {
+                            Some('"') => break,
+                            Some('\\') => match self.bump() {
+                                Some('\n') => continuations.push(at),
+                                Some(_) => {}
+                                None => return Err(ParseError::Incomplete),
+                            },
+                            Some('$') if self.peek_char() == Some('(') => {
+                                self.bump();
+                                self.substitution()?;
+                            }
+                            Some('`') => self.backquoted()?,
+                            Some(_) => {}
+                            None => return Err(ParseError::Incomplete),
+                        }
+                    }
+                }
+                '$' if self.input[self.pos..].starts_with("$((") => {
+                    self.pos += 3;
+                    self.arith_body()?;
+                }
+                '$' if self.peek_second() == Some('(') => {
+                    self.pos += 2;
+                    self.substitution()?;
+                }
+                '`' => {
+                    self.bump();
+                    self.backquoted()?;
+                }
+                '$' if self.peek_second() == Some('{') => {
+                    self.pos += 2;
+                    self.braced()?;
+                }
+                c if Self::is_metachar(c) => break,
+                _ => {
+                    self.bump();
+                }
+            }
+        }
+
+        let text = &self.input[start..self.pos];
+        if continuations.is_empty() {
+            return Ok(Cow::Borrowed(text));
+        }
+
+        let mut word = String::with_capacity(text.len());
+        let mut from = start;
+        for at in continuations {
+            word.push_str(&self.input[from..at]);
+            from = at + 2;
+        }
+        word.push_str(&self.input[from..self.pos]);
+        Ok(Cow::Owned(word))
+    }
+
+    /// Scans the body of an arithmetic expression up to the closing `))`,
+    /// which is consumed but not part of the returned text.
+    fn arith_body(&mut self) -> Result<&'a str, ParseError> {
+        let start = self.pos;
+        let mut depth = 0;
+
+        loop {
+            let c = self.bump().ok_or(ParseError::Incomplete)?;
+            match c {
+                '(' => depth += 1,
+                ')' if depth == 0 => {
+                    let body = &self.input[start..self.pos - 1];
+                    return match self.bump() {
+                        Some(')') => Ok(body),
+                        Some(_) => Err(ParseError::unexpected(")".to_string())),
+                        None => Err(ParseError::Incomplete),
+                    };
+                }
+                ')' => depth -= 1,
+                _ => {}
+            }
+        }
+    }
+
+    /// Skips the body of a `$(...)` command substitution, including the
+    /// closing parenthesis.
+    fn substitution(&mut self) -> Result<(), ParseError> {
+        let mut depth = 1;
+        let mut quote = None;
+
+        while depth > 0 {
+            let c = self.bump().ok_or(ParseError::Incomplete)?;
+            match (quote, c) {
+                (Some(q), c) if c == q => quote = None,
+                (Some('"' | '`') | None, '\\') => {
+                    self.bump().ok_or(ParseError::Incomplete)?;
+                }
+                (Some(_), _) => {}
+                (None, '\'' | '"' | '`') => quote = Some(c),
+                (None, '(') => depth += 1,
+                (None, ')') => depth -= 1,
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Skips the body of a `` `...` `` command substitution after the
+    /// opening backquote, including the closing one.
+    fn backquoted(&mut self) -> Result<(), ParseError> {
+        loop {
+            match self.bump().ok_or(ParseError::Incomplete)? {
+                '`' => return Ok(()),
+                '\\' => {
+                    self.bump().ok_or(ParseError::Incomplete)?;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Skips the body of a `${...}` expansion, including the closing brace,
+    /// so that blanks and metacharacters inside it stay part of the word.
+    fn braced(&mut self) -> Result<(), ParseError> {
+        let mut depth = 1;
+        let mut quote = None;
+
+        while depth > 0 {
+            let c = self.bump().ok_or(ParseError::Incomplete)?;
+            match (quote, c) {
+                (Some(q), c) if c == q => quote = None,
+                (Some('"') | None, '\\') => {
+                    self.bump().ok_or(ParseError::Incomplete)?;
+                }
+                (Some(_), _) => {}
+                (None, '\'' | '"') => quote = Some(c),
+                (None, '{') => depth += 1,
+                (None, '}') => depth -= 1,
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// ============================================
+// PARSER
+// ============================================
+
+/// How deeply compound commands may nest, so that malformed input cannot
+/// exhaust the stack.
+const MAX_NESTING: usize = 256;
+
+struct Parser<'a> {
+    lexer: Lexer<'a>,
+    peeked: Option<Option<Token<'a>>>,
+    depth: usize,
+    /// Whether bash syntax is rejected; see `parse_posix`.
+    posix: bool,
+}
+
+/// Parses `input` into commands without running anything. Never panics;
+/// input that is cut short gives `ParseError::Incomplete`.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", ret(level = "trace"), err(level = "debug"))
+)]
+pub fn parse(input: &str) -> Result<Vec<Command>, ParseError> {
+    parse_in(input, false)
+}
+
+/// Parses `input` as POSIX sh: `((...))`, `for ((...))` and array
+/// assignments are syntax errors, and `function` and `select` are not
+/// reserved words.
+pub fn parse_posix(input: &str) -> Result<Vec<Command>, ParseError> {
+    parse_in(input, true)
+}
+
+/// Parses the commands at the start of `input` up to the end of the line
+/// the first of them ends on, and gives how much of `input` they take up,
+/// here-document bodies included. A script can so be run a command at a
+/// time, each parsed once, with `set -o posix` applying from the line
+/// after it.
+pub fn parse_line(input: &str, posix: bool) -> Result<(Vec<Command>, usize), ParseError> {
+    parse_commands(input, posix, true)
+}
+
+fn parse_in(input: &str, posix: bool) -> Result<Vec<Command>, ParseError> {
+    parse_commands(input, posix, false).map(|(commands, _)| commands)
+}
+
+fn parse_commands(
+    input: &str,
+    posix: bool,
+    one_line: bool,
+) -> Result<(Vec<Command>, usize), ParseError> {
+    let mut parser = Parser {
+        lexer: Lexer::new(input),
+        peeked: None,
+        depth: 0,
+        posix,
+    };
+    // Parsing stops at the first error, so the last token read is the
+    // one it was found at.
+    match parser.program(one_line) {
+        Ok(commands) => Ok((commands, parser.lexer.pos)),
+        Err(error) => {
+            let offset = parser.lexer.token_start;
+            let line = 1 + input[..offset].matches('\n').count();
+            Err(error.at(Span { offset, line }))
+        }
+    }
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&mut self) -> Result<Option<&Token<'a>>, ParseError> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.next_token()?);
+        }
+        Ok(self.peeked.as_ref().unwrap().as_ref())
+    }
+
+    fn next(&mut self) -> Result<Option<Token<'a>>, ParseError> {
+        match self.peeked.take() {
+            Some(token) => Ok(token),
+            None => self.next_token(),
+        }
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token<'a>>, ParseError> {
+        match self.lexer.next_token()? {
+            Some(token @ (Token::Arith(_) | Token::ArrayAssign(..))) if self.posix => {
+                Err(ParseError::unexpected(token.describe()))
+            }
+            token => Ok(token),
+        }
+    }
+
+    fn unexpected(token: Option<&Token<'_>>) -> ParseError {
+        match token {
+            Some(token) => ParseError::unexpected(token.describe()),
+            None => ParseError::Incomplete,
+        }
+    }
+
+    fn skip_newlines(&mut self) -> Result<(), ParseError> {
+        while let Some(Token::Newline) = self.peek()? {
+            self.next()?;
+        }
+        Ok(())
+    }
+
+    /// Parses commands up to the end of the input, or with `one_line` up
+    /// to the end of the line the first command ends on.
+    fn program(&mut self, one_line: bool) -> Result<Vec<Command>, ParseError> {
+        let mut commands = Vec::new();
+
+        loop {
+            self.skip_newlines()?;
+            match self.peek()? {
+                None => return Ok(commands),
+                Some(Token::Word(word)) if CLOSING_WORDS.contains(&word.as_ref()) => {
+                    return Err(ParseError::unexpected(word.to_string()));
+                }
+                Some(Token::Semi) => return Err(ParseError::unexpected(";".to_string())),
+                _ => {}
+            }
+
+            let command = self.list_item()?;
+            let background = matches!(command, Command::Background(..));
+            commands.push(command);
+
+            match self.peek()? {
+                None => return Ok(commands),
+                Some(Token::Semi) => {
+                    self.next()?;
+                }
+                Some(Token::Newline) => {
+                    self.next()?;
+                    if one_line {
+                        return Ok(commands);
+                    }
+                }
+                _ if background => {}
+                token => return Err(Self::unexpected(token)),
+            }
+        }
+    }
+
+    /// Parses commands up to (but not including) one of the closing reserved
+    /// words of the enclosing compound command. The list may be empty.
+    fn command_list(&mut self) -> Result<Vec<Command>, ParseError> {
+        let mut commands = Vec::new();
+
+        loop {
+            self.skip_newlines()?;
+            match self.peek()? {
+                None => return Err(ParseError::Incomplete),
+                Some(Token::Word(word)) if CLOSING_WORDS.contains(&word.as_ref()) => {
+                    return Ok(commands);
+                }
+                Some(Token::CaseEnd(_)) => return Ok(commands),
+                Some(Token::Semi) => return Err(ParseError::unexpected(";".to_string())),
+                _ => {}
+            }
+
+            let command = self.list_item()?;
+            let background = matches!(command, Command::Background(..));
+            commands.push(command);
+
+            match self.peek()? {
+                Some(Token::Semi | Token::Newline) => {
+                    self.next()?;
+                }
+                _ if background => {}
+                Some(Token::Word(word)) if CLOSING_WORDS.contains(&word.as_ref()) => {}
+                Some(Token::CaseEnd(_)) => {}
+                token => return Err(Self::unexpected(token)),
+            }
+        }
+    }
+
+    /// Like `command_list`, but at least one command is required.
+    fn compound_list(&mut self) -> Result<Vec<Command>, ParseError> {
+        let commands = self.command_list()?;
+        if commands.is_empty() {
+            let token = self.next()?;
+            return Err(Self::unexpected(token.as_ref()));
+        }
+        Ok(commands)
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), ParseError> {
+        match self.next()? {
+            Some(token) if token.is_keyword(keyword) => Ok(()),
+            token => Err(Self::unexpected(token.as_ref())),
+        }
+    }
+
+    /// Parses an and-or list, and the `&` after it if it is to run in the
+    /// background.
+    fn list_item(&mut self) -> Result<Command, ParseError> {
+        let start = self.next_token_start()?;
+        let command = self.and_or()?;
+        let end = self.next_token_start()?;
+        if !matches!(self.peek()?, Some(Token::Amp)) {
+            return Ok(command);
+        }
+
+        self.next()?;
+        let text = self.lexer.input[start..end].trim_end().to_string();
+        Ok(Command::Background(Box::new(command), text))
+    }
+
+    /// Where the next token begins in the input.
+    fn next_token_start(&mut self) -> Result<usize, ParseError> {
+        self.peek()?;
+        Ok(self.lexer.token_start)
+    }
+
+    /// Parses pipelines joined by `&&` and `||`, which bind equally tightly
+    /// and group from the left.
+    fn and_or(&mut self) -> Result<Command, ParseError> {
+        let mut command = self.pipeline()?;
+
+        loop {
+            let and = match self.peek()? {
+                Some(Token::AndIf) => true,
+                Some(Token::OrIf) => false,
+                _ => return Ok(command),
+            };
+            self.next()?;
+            self.skip_newlines()?;
+            let right = Box::new(self.pipeline()?);
+            command = if and {
+                Command::And(Box::new(command), right)
+            } else {
+                Command::Or(Box::new(command), right)
+            };
+        }
+    }
+
+    /// Parses `[!] command [| command]...`; a lone command is returned as
+    /// it is.
+    fn pipeline(&mut self) -> Result<Command, ParseError> {
+        if let Some(Token::Word(word)) = self.peek()?
+            && word == "!"
+        {
+            self.next()?;
+            // A bare `!` negates an empty command, so its status is 1.
+            let command = match self.peek()? {
+                None | Some(Token::Semi | Token::Newline) => Command::Group(Vec::new()),
+                _ => self.pipeline()?,
+            };
+            return Ok(Command::Not(Box::new(command)));
+        }
+
+        let start = self.next_token_start()?;
+        let first = self.pipeline_stage()?;
+        if !matches!(self.peek()?, Some(Token::Pipe)) {
+            return Ok(first);
+        }
+
+        let mut stages = vec![first];
+        while let Some(Token::Pipe) = self.peek()? {
+            self.next()?;
+            self.skip_newlines()?;
+            stages.push(self.pipeline_stage()?);
+        }
+        let end = self.next_token_start()?;
+        let text = self.lexer.input[start..end].trim_end().to_string();
+        Ok(Command::Pipeline(stages, text))
+    }
+
+    fn pipeline_stage(&mut self) -> Result<Command, ParseError> {
+        match self.peek()? {
+            None => Err(ParseError::Incomplete),
+            token @ Some(
+                Token::Pipe
+                | Token::AndIf
+                | Token::OrIf
+                | Token::Amp
+                | Token::Semi
+                | Token::CaseEnd(_)
+                | Token::RParen,
+            ) => Err(Self::unexpected(token)),
+            _ => self.command(),
+        }
+    }
+
+    fn command(&mut self) -> Result<Command, ParseError> {
+        if self.depth >= MAX_NESTING {
+            return Err(ParseError::TooDeep {
+                span: Span::default(),
+            });
+        }
+        self.depth += 1;
+        let command = self.command_nested();
+        self.depth -= 1;
+        command
+    }
+
+    fn command_nested(&mut self) -> Result<Command, ParseError> {
+        if let Some(Token::Arith(_)) = self.peek()? {
+            let Some(Token::Arith(expr)) = self.next()? else {
+                unreachable!();
+            };
+            return Ok(Command::Arith(expr.to_string()));
+        }
+
+        let posix = self.posix;
+        if let Some(Token::Word(word)) = self.peek()?
+            && OPENING_WORDS.contains(&word.as_ref())
+            && !(posix && matches!(word.as_ref(), "function" | "select"))
+        {
+            return match word.as_ref() {
+                "if" => self.if_clause(),
+                "for" | "select" => self.for_clause(),
+                "case" => self.case_clause(),
+                "{" => self.brace_group(),
+                "function" => self.function_keyword_def(),
+                _ => self.while_clause(),
+            };
+        }
+
+        let simple = self.simple_command()?;
+
+        if let Some(Token::LParen) = self.peek()? {
+            match simple.words.as_slice() {
+                [name]
+                    if simple.redirects.is_empty()
+                        && simple.assignments.is_empty()
+                        && is_valid_name(name) =>
+                {
+                    let name = name.clone();
+                    self.next()?;
+                    return self.function_def(name);
+                }
+                _ => return Err(ParseError::unexpected("(".to_string())),
+            }
+        }
+
+        Ok(Command::Simple(simple))
+    }
+
+    fn brace_group(&mut self) -> Result<Command, ParseError> {
+        self.expect_keyword("{")?;
+        let body = self.compound_list()?;
+        self.expect_keyword("}")?;
+        Ok(Command::Group(body))
+    }
+
+    /// Parses `function name [()] body`.
+    fn function_keyword_def(&mut self) -> Result<Command, ParseError> {
+        self.expect_keyword("function")?;
+
+        let name = match self.next()? {
+            Some(Token::Word(name)) if is_valid_name(&name) => name.into_owned(),
+            token => return Err(Self::unexpected(token.as_ref())),
+        };
+
+        if let Some(Token::LParen) = self.peek()? {
+            self.next()?;
+            self.function_def(name)
+        } else {
+            self.function_body(name)
+        }
+    }
+
+    /// Parses the rest of `name ( ) body` once `name (` has been consumed.
+    fn function_def(&mut self, name: String) -> Result<Command, ParseError> {
+        match self.next()? {
+            Some(Token::RParen) => self.function_body(name),
+            token => Err(Self::unexpected(token.as_ref())),
+        }
+    }
+
+    fn function_body(&mut self, name: String) -> Result<Command, ParseError> {
+        self.skip_newlines()?;
+
+        match self.peek()? {
+            Some(Token::Word(word))
+                if OPENING_WORDS.contains(&word.as_ref()) && word != "function" => {}
+            token => return Err(Self::unexpected(token)),
+        }
+
+        let body = self.command()?;
+        Ok(Command::FunctionDef(FunctionDef {
+            name,
+            body: Rc::new(body),
+        }))
+    }
+
+    /// Parses `do list done`, the body shared by the loop commands.
+    fn do_group(&mut self) -> Result<Vec<Command>, ParseError> {
+        self.expect_keyword("do")?;
+        let body = self.compound_list()?;
+        self.expect_keyword("done")?;
+        Ok(body)
+    }
+
+    /// Parses `for` and `select`, which differ only in their keyword.
+    fn for_clause(&mut self) -> Result<Command, ParseError> {
+        let select = matches!(self.next()?, Some(token) if token.is_keyword("select"));
+
+        if !select && let Some(Token::Arith(_)) = self.peek()? {
+            return self.arith_for_clause();
+        }
+
+        let var = match self.next()? {
+            Some(Token::Word(name)) if is_valid_name(&name) => name.into_owned(),
+            token => return Err(Self::unexpected(token.as_ref())),
+        };
+
+        self.skip_newlines()?;
+        let mut words = None;
+
+        match self.peek()? {
+            Some(token) if token.is_keyword("in") => {
+                self.next()?;
+                let mut list = Vec::new();
+                loop {
+                    match self.next()? {
+                        Some(Token::Word(word)) => list.push(word.into_owned()),
+                        Some(Token::Semi | Token::Newline) => break,
+                        token => return Err(Self::unexpected(token.as_ref())),
+                    }
+                }
+                words = Some(list);
+            }
+            Some(Token::Semi) => {
+                self.next()?;
+            }
+            _ => {}
+        }
+
+        self.skip_newlines()?;
+        let body = self.do_group()?;
+
+        let clause = ForClause { var, words, body };
+        Ok(if select {
+            Command::Select(clause)
+        } else {
+            Command::For(clause)
+        })
+    }
+
+    fn if_clause(&mut self) -> Result<Command, ParseError> {
+        self.expect_keyword("if")?;
+        let mut branches = Vec::new();
+        let mut else_body = None;
+
+        loop {
+            let condition = self.compound_list()?;
+            self.expect_keyword("then")?;
+            let body = self.compound_list()?;
+            branches.push((condition, body));
+
+            match self.next()? {
+                Some(token) if token.is_keyword("elif") => continue,
+                Some(token) if token.is_keyword("else") => {
+                    else_body = Some(self.compound_list()?);
+                    self.expect_keyword("fi")?;
+                    break;
+                }
+                Some(token) if token.is_keyword("fi") => break,
+                token => return Err(Self::unexpected(token.as_ref())),
+            }
+        }
+
+        Ok(Command::If(IfClause {
+            branches,
+            else_body,
+        }))
+    }
+
+    fn arith_for_clause(&mut self) -> Result<Command, ParseError> {
+        let Some(Token::Arith(expr)) = self.next()? else {
+            unreachable!();
+        };
+
+        let parts: Vec<&str> = expr.split(';').collect();
+        let [init, condition, step] = parts.as_slice() else {
+            return Err(ParseError::unexpected("((".to_string()));
+        };
+
+        if let Some(Token::Semi) = self.peek()? {
+            self.next()?;
+        }
+        self.skip_newlines()?;
+        let body = self.do_group()?;
+
+        Ok(Command::ArithFor(ArithForClause {
+            init: init.to_string(),
+            condition: condition.to_string(),
+            step: step.to_string(),
+            body,
+        }))
+    }
+
+    fn case_clause(&mut self) -> Result<Command, ParseError> {
+        self.expect_keyword("case")?;
+
+        let word = match self.next()? {
+            Some(Token::Word(word)) => word.into_owned(),
+            token => return Err(Self::unexpected(token.as_ref())),
+        };
+
+        self.skip_newlines()?;
+        self.expect_keyword("in")?;
+
+        let mut items = Vec::new();
+        loop {
+            self.skip_newlines()?;
+            match self.peek()? {
+                Some(token) if token.is_keyword("esac") => {
+                    self.next()?;
+                    break;
+                }
+                Some(Token::LParen) => {
+                    self.next()?;
+                }
+                _ => {}
+            }
+
+            let mut patterns = Vec::new();
+            loop {
+                match self.next()? {
+                    Some(Token::Word(pattern)) => patterns.push(pattern.into_owned()),
+                    token => return Err(Self::unexpected(token.as_ref())),
+                }
+                match self.next()? {
+                    Some(Token::Pipe) => continue,
+                    Some(Token::RParen) => break,
+                    token => return Err(Self::unexpected(token.as_ref())),
+                }
+            }
+
+            let body = self.command_list()?;
+            let terminator = match self.peek()? {
+                Some(Token::CaseEnd(terminator)) => {
+                    let terminator = *terminator;
+                    self.next()?;
+                    terminator
+                }
+                Some(token) if token.is_keyword("esac") => CaseTerminator::Break,
+                token => return Err(Self::unexpected(token)),
+            };
+
+            items.push(CaseItem {
+                patterns,
+                body,
+                terminator,
+            });
+        }
+
+        Ok(Command::Case(CaseClause { word, items }))
+    }
+
+    fn while_clause(&mut self) -> Result<Command, ParseError> {
+        let until = matches!(self.next()?, Some(token) if token.is_keyword("until"));
+        let condition = self.compound_list()?;
+        let body = self.do_group()?;
+
+        Ok(Command::While(WhileClause {
+            condition,
+            body,
+            until,
+        }))
+    }
+
+    fn simple_command(&mut self) -> Result<SimpleCommand, ParseError> {
+        self.peek()?;
+        let mut command = SimpleCommand {
+            line: self.lexer.line_at(self.lexer.token_start),
+            ..SimpleCommand::default()
+        };
+
+        loop {
+            match self.peek()? {
+                Some(Token::Word(_)) => {
+                    let Some(Token::Word(word)) = self.next()? else {
+                        unreachable!();
+                    };
+                    match parse_assignment(&word) {
+                        Some(assignment) if command.words.is_empty() => {
+                            command.assignments.push(assignment);
+                        }
+                        _ => command.words.push(word.into_owned()),
+                    }
+                }
+                Some(Token::ArrayAssign(..)) => {
+                    if !command.words.is_empty() {
+                        return Err(ParseError::unexpected("(".to_string()));
+                    }
+                    let Some(Token::ArrayAssign(target, elements)) = self.next()? else {
+                        unreachable!();
+                    };
+                    let (name, index) = parse_assignment_target(target).unwrap_or_default();
+                    command.assignments.push(Assignment {
+                        name,
+                        index,
+                        value: AssignValue::Array(
+                            elements.into_iter().map(Cow::into_owned).collect(),
+                        ),
+                    });
+                }
+                Some(Token::Redirect { .. }) => {
+                    let Some(Token::Redirect {
+                        stream,
+                        append,
+                        duplicate,
+                    }) = self.next()?
+                    else {
+                        unreachable!();
+                    };
+                    match self.next()? {
+                        Some(Token::Word(file)) => command.redirects.push(Redirect {
+                            stream,
+                            file: file.into_owned(),
+                            append,
+                            duplicate,
+                            here_doc: None,
+                        }),
+                        Some(token) => return Err(Self::unexpected(Some(&token))),
+                        None => return Err(ParseError::unexpected("newline".to_string())),
+                    }
+                }
+                Some(Token::HereDoc { .. }) => {
+                    let Some(Token::HereDoc { strip_tabs }) = self.next()? else {
+                        unreachable!();
+                    };
+                    let word = match self.next()? {
+                        Some(Token::Word(word)) => word,
+                        Some(token) => return Err(Self::unexpected(Some(&token))),
+                        None => return Err(ParseError::unexpected("newline".to_string())),
+                    };
+                    // Quoting any part of the delimiter turns expansion off.
+                    let quoted = word.contains(['\'', '"', '\\']);
+                    let delimiter = here_doc_delimiter(&word);
+                    let body = self.lexer.here_doc_body(&delimiter, strip_tabs)?;
+                    command.redirects.push(Redirect {
+                        stream: StreamType::Stdin,
+                        file: word.into_owned(),
+                        append: false,
+                        duplicate: false,
+                        here_doc: Some(HereDoc {
+                            body,
+                            expand: !quoted,
+                        }),
+                    });
+                }
+                _ => break,
+            }
+        }
+
+        Ok(command)
+    }
+}
@@ -0,0 +1,156 @@
+/// ============================================
+/// PATH HANDLING
+/// ============================================
+use std::borrow::Cow;
+use std::path::{Component, Path, PathBuf};
+
+use crate::osstr;
+
+#[cfg(windows)]
+use std::path::Prefix;
+
+/// Whether file names are matched without regard to case, as the default
+/// filesystems of Windows and macOS do.
+pub const CASE_INSENSITIVE: bool = cfg!(any(windows, target_os = "macos"));
+
+/// Compares two characters of a file name the way this platform does.
+#[cfg(feature = "completion")]
+pub fn chars_equal(a: char, b: char) -> bool {
+    a == b || (CASE_INSENSITIVE && a.to_lowercase().eq(b.to_lowercase()))
+}
+
+/// A file name in the form in which names that this platform treats as the
+/// same file compare equal.
+pub fn fold_case(name: &str) -> Cow<'_, str> {
+    if CASE_INSENSITIVE {
+        Cow::Owned(name.to_lowercase())
+    } else {
+        Cow::Borrowed(name)
+    }
+}
+
+/// Whether file name `name` begins with `prefix` on this platform.
+#[cfg(feature = "completion")]
+pub fn name_starts_with(name: &str, prefix: &str) -> bool {
+    let mut name = name.chars();
+    prefix
+        .chars()
+        .all(|p| name.next().is_some_and(|c| chars_equal(c, p)))
+}
+
+/// Removes `.` and `..` components from an absolute path without touching
+/// the filesystem, so `..` undoes the last component the user named. On
+/// Windows this also turns every `/` into `\`.
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Rewrites a verbatim path as Windows APIs sometimes return it
+/// (`\\?\C:\dir`, `\\?\UNC\server\share`) in the ordinary form users
+/// type (`C:\dir`, `\\server\share`). Other paths are returned unchanged.
+pub fn without_verbatim(path: PathBuf) -> PathBuf {
+    #[cfg(windows)]
+    {
+        let text = path.to_string_lossy();
+        if let Some(unc) = text.strip_prefix(r"\\?\UNC\") {
+            return PathBuf::from(format!(r"\\{}", unc));
+        }
+        if let Some(rest) = text.strip_prefix(r"\\?\")
+            && rest.as_bytes().get(1) == Some(&b':')
+        {
+            return PathBuf::from(rest);
+        }
+    }
+    path
+}
+
+/// The file a redirection opens. On Windows the null device is `NUL`, so
+/// `/dev/null` maps to it, as do `nul` and `NUL:` in any case.
+pub fn redirect_target(file: &str) -> PathBuf {
+    #[cfg(windows)]
+    if file == "/dev/null" || file.trim_end_matches(':').eq_ignore_ascii_case("nul") {
+        return PathBuf::from("NUL");
+    }
+    PathBuf::from(osstr::to_os(file))
+}
+
+/// Converts a Unix-style path to a drive (`/c/dir` as in MSYS and Git
+/// Bash, `/cygdrive/c/dir`, or `/mnt/c/dir` as in WSL) into the Windows
+/// path `C:\dir`. Other paths give `None`.
+pub fn unix_to_windows(path: &str, separator: char) -> Option<String> {
+    let rest = path
+        .strip_prefix("/mnt/")
+        .or_else(|| path.strip_prefix("/cygdrive/"))
+        .or_else(|| path.strip_prefix('/'))?;
+
+    let mut chars = rest.chars();
+    let drive = chars.next().filter(char::is_ascii_alphabetic)?;
+    let tail = chars.as_str();
+    if !(tail.is_empty() || tail.starts_with('/')) {
+        return None;
+    }
+
+    let tail = tail
+        .trim_start_matches('/')
+        .replace('/', &separator.to_string());
+    Some(format!(
+        "{}:{}{}",
+        drive.to_ascii_uppercase(),
+        separator,
+        tail
+    ))
+}
+
+/// Converts a Windows path on a drive (`C:\dir` or `C:/dir`) into its WSL
+/// form `/mnt/c/dir`. Other paths give `None`.
+pub fn windows_to_unix(path: &str) -> Option<String> {
+    let mut chars = path.chars();
+    let drive = chars.next().filter(char::is_ascii_alphabetic)?;
+    let rest = chars.as_str().strip_prefix(':')?.replace('\\', "/");
+
+    let mut unix = format!("/mnt/{}", drive.to_ascii_lowercase());
+    let rest = rest.trim_start_matches('/');
+    if !rest.is_empty() {
+        unix.push('/');
+        unix.push_str(rest);
+    }
+    Some(unix)
+}
+
+/// Splits a drive-relative path such as `D:` or `D:src` into the upper-case
+/// drive letter and the rest. Paths rooted on a drive (`D:\src`) are not
+/// drive-relative and give `None`.
+#[cfg(windows)]
+pub fn split_drive_relative(path: &str) -> Option<(char, &str)> {
+    let mut chars = path.chars();
+    let letter = chars.next().filter(char::is_ascii_alphabetic)?;
+    let rest = chars.as_str().strip_prefix(':')?;
+    if rest.starts_with(['/', '\\']) {
+        return None;
+    }
+    Some((letter.to_ascii_uppercase(), rest))
+}
+
+/// The upper-case drive letter an absolute path is on, if any.
+#[cfg(windows)]
+pub fn drive_of(path: &Path) -> Option<char> {
+    match path.components().next()? {
+        Component::Prefix(prefix) => match prefix.kind() {
+            Prefix::Disk(letter) | Prefix::VerbatimDisk(letter) => {
+                Some((letter as char).to_ascii_uppercase())
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
@@ -0,0 +1,529 @@
+/// ============================================
+/// PIPELINES
+/// ============================================
+use std::fs::File;
+use std::io::{self, IsTerminal, PipeWriter, Write};
+use std::ops::{Deref, DerefMut};
+use std::process::{Child, Stdio};
+use std::thread::JoinHandle;
+
+use crate::error::ShellError;
+use crate::parser::{Command, StreamType};
+use crate::shell_io::Capture;
+use crate::{ParsedCommand, Shell, Target};
+
+#[cfg(unix)]
+use std::os::fd::{AsRawFd, RawFd};
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+#[cfg(unix)]
+use std::sync::atomic::{AtomicI32, Ordering::SeqCst};
+
+/// How the pipeline stage about to run is started; see `start_pipeline`.
+pub(crate) enum Stage {
+    /// A stage writing to the pipe the next stage reads from.
+    Pipe(PipeWriter),
+    /// The last stage of a pipeline or background job, writing where the
+    /// shell's stdout goes.
+    Last,
+}
+
+/// A process started for a pipeline stage: a program, or on Unix a copy
+/// of the shell made with fork to run a builtin, function or compound
+/// command.
+pub(crate) enum Started {
+    Program(Child),
+    #[cfg(unix)]
+    Forked(u32),
+}
+
+impl Started {
+    pub(crate) fn id(&self) -> u32 {
+        match self {
+            Started::Program(child) => child.id(),
+            #[cfg(unix)]
+            Started::Forked(pid) => *pid,
+        }
+    }
+
+    /// Waits for the process to end and returns its status.
+    #[cfg(unix)]
+    fn wait(self) -> i32 {
+        let mut status = 0;
+        loop {
+            if unsafe { libc::waitpid(self.id() as libc::pid_t, &mut status, 0) } >= 0 {
+                return crate::jobs::exit_status(status);
+            }
+            if io::Error::last_os_error().kind() != io::ErrorKind::Interrupted {
+                return 1;
+            }
+        }
+    }
+
+    #[cfg(windows)]
+    fn wait(self) -> i32 {
+        let Started::Program(mut child) = self;
+        child.wait().map(Shell::exit_code).unwrap_or(1)
+    }
+}
+
+/// The stages of a pipeline once `start_pipeline` has run them.
+pub(crate) struct StartedPipeline {
+    /// The status of each stage, 0 for processes still running.
+    statuses: Vec<i32>,
+    /// The processes still running, with the index of their stage.
+    pub(crate) running: Vec<(usize, Started)>,
+    /// While output is captured, the capture and the thread reading what
+    /// the last stage writes to it.
+    output: Option<(Capture, JoinHandle<Vec<u8>>)>,
+    /// The status to give if the pipeline could not be set up.
+    failed: Option<i32>,
+}
+
+impl Shell {
+    /// Runs `a | b | c`. The stages run side by side, each reading what the
+    /// stage before it writes as it is written. On Unix a builtin, function
+    /// or compound command runs in a copy of the shell, so that it cannot
+    /// change the shell itself; on Windows it runs to completion in the
+    /// shell, and its output is then fed to the next stage. The status is
+    /// that of the last stage; `PIPESTATUS` holds all of them. With job
+    /// control the stages share a process group of their own, and if they
+    /// stop they become a job shown as `text`.
+    pub(crate) fn execute_pipeline(
+        &mut self,
+        stages: &[Command],
+        // Jobs never stop on Windows.
+        #[cfg_attr(windows, allow(unused_variables))] text: &str,
+    ) -> i32 {
+        let outer_group = self.job_group.take();
+        #[cfg(unix)]
+        let job_control = self.job_control();
+        #[cfg(unix)]
+        if job_control {
+            self.job_group = Some(0);
+        }
+        let started = self.start_pipeline(stages, false);
+        self.job_group = outer_group;
+
+        let mut statuses = started.statuses;
+        let running = started.running;
+        #[cfg(unix)]
+        let running = if job_control && !running.is_empty() {
+            let (indices, processes): (Vec<usize>, Vec<Started>) = running.into_iter().unzip();
+            match self.wait_foreground(text, processes) {
+                Ok(ended) => {
+                    for (index, status) in indices.into_iter().zip(ended) {
+                        statuses[index] = status;
+                    }
+                }
+                Err(stopped) => {
+                    self.set_pipestatus(&[stopped]);
+                    return stopped;
+                }
+            }
+            Vec::new()
+        } else {
+            running
+        };
+        for (index, process) in running {
+            statuses[index] = process.wait();
+        }
+        if let Some((mut capture, drain)) = started.output
+            && let Ok(output) = drain.join()
+        {
+            let _ = capture.write_all(&output);
+        }
+
+        self.set_pipestatus(&statuses);
+        started
+            .failed
+            .unwrap_or(statuses.last().copied().unwrap_or(0))
+    }
+
+    /// Runs the stages of a pipeline, leaving the processes they start
+    /// running. With `background` the last stage is left running too,
+    /// instead of being waited for.
+    pub(crate) fn start_pipeline(
+        &mut self,
+        stages: &[Command],
+        background: bool,
+    ) -> StartedPipeline {
+        let Some((last, rest)) = stages.split_last() else {
+            return StartedPipeline {
+                statuses: Vec::new(),
+                running: Vec::new(),
+                output: None,
+                failed: None,
+            };
+        };
+
+        let outer = self.redirected.try_clone();
+        let mut statuses = vec![0; stages.len()];
+        let mut running = Vec::new();
+        let mut output = None;
+        let mut failed = None;
+        #[cfg(unix)]
+        let outer_reader = self.pipe_reader;
+
+        for (index, stage) in rest.iter().enumerate() {
+            let (reader, writer) = match io::pipe() {
+                Ok(pipe) => pipe,
+                Err(source) => {
+                    let error = ShellError::Io {
+                        context: "pipe".to_string(),
+                        source,
+                    };
+                    failed = Some(self.report(&error));
+                    break;
+                }
+            };
+
+            #[cfg(unix)]
+            {
+                self.pipe_reader = Some(reader.as_raw_fd());
+            }
+            self.redirected.stdout = None;
+            statuses[index] = self.execute_stage(stage, Stage::Pipe(writer));
+            if let Some(process) = self.started.take() {
+                running.push((index, process));
+            }
+            self.redirected.stdin = Some(Target::File(pipe_file(reader)));
+        }
+        #[cfg(unix)]
+        {
+            self.pipe_reader = outer_reader;
+        }
+
+        if failed.is_none() {
+            self.redirected.stdout = outer.try_clone().stdout;
+            // On Unix the last stage of a pipeline is a process of its own
+            // like the others. While output is captured it writes to a pipe
+            // read on another thread, as capturing happens in the shell.
+            let stage = if background {
+                Some(Stage::Last)
+            } else if cfg!(unix) && !rest.is_empty() {
+                match &self.capture {
+                    Some(capture) => match drain() {
+                        Ok((writer, drain)) => {
+                            output = Some((capture.clone(), drain));
+                            Some(Stage::Pipe(writer))
+                        }
+                        Err(_) => None,
+                    },
+                    None => Some(Stage::Last),
+                }
+            } else {
+                None
+            };
+            statuses[stages.len() - 1] = match stage {
+                Some(stage) => self.execute_stage(last, stage),
+                None => self.execute_command(last),
+            };
+            if let Some(process) = self.started.take() {
+                running.push((stages.len() - 1, process));
+            }
+        }
+
+        // The last stage has finished or been started; dropping the read
+        // end of its pipe lets any program still writing to it stop once it
+        // is done.
+        self.redirected = outer;
+        StartedPipeline {
+            statuses,
+            running,
+            output,
+            failed,
+        }
+    }
+
+    /// Runs `command` as a pipeline stage.
+    fn execute_stage(&mut self, command: &Command, stage: Stage) -> i32 {
+        match command {
+            Command::Simple(simple) => {
+                self.stage = Some(stage);
+                self.execute_simple(simple)
+            }
+            command => self.run_stage(stage, |shell| shell.execute_command(command)),
+        }
+    }
+
+    /// Runs `run` for a pipeline stage that is not a program, in a copy of
+    /// the shell made with fork. The copy is left running like a program
+    /// `start_stage` starts, and whatever `run` does to the shell's state
+    /// stays in it.
+    ///
+    /// Only the thread that forks is in the copy, so nothing there may wait
+    /// for another thread, or for a lock another thread may have held at
+    /// the fork. The shell's other threads are kept to what allows that:
+    /// - Prompt segment workers may take any lock, so the fork waits for
+    ///   them to finish.
+    /// - Here-document writers and capture drains only allocate, which the
+    ///   C library makes safe across fork, and use their pipe end. The copy
+    ///   closes those ends; see `HELPER_FDS`.
+    /// - The PATH scanner takes only the command index's lock, and the
+    ///   copy, not being interactive, never completes a command.
+    /// - The recording pump is not in the copy, so the copy forgets the
+    ///   recording instead of stopping it, which would wait for the pump.
+    ///
+    /// Pipe ends are all close-on-exec, as `io::pipe` makes them, so the
+    /// programs the copy runs get only those given to them.
+    #[cfg(unix)]
+    pub(crate) fn run_stage(&mut self, stage: Stage, run: impl FnOnce(&mut Self) -> i32) -> i32 {
+        self.flush_output();
+        #[cfg(feature = "prompt-theme")]
+        self.prompt_segments.wait();
+        let group = self.job_group;
+        let pid = unsafe { libc::fork() };
+        if pid < 0 {
+            let error = ShellError::Io {
+                context: "fork".to_string(),
+                source: io::Error::last_os_error(),
+            };
+            return self.report(&error);
+        }
+
+        if pid == 0 {
+            unsafe {
+                if let Some(group) = group {
+                    libc::setpgid(0, group as libc::pid_t);
+                }
+                // The next stage must be the only reader of the pipe, so
+                // that this one stops once it is gone.
+                if let Some(reader) = self.pipe_reader.take() {
+                    libc::close(reader);
+                }
+                if let Stage::Pipe(writer) = &stage {
+                    libc::dup2(writer.as_raw_fd(), libc::STDOUT_FILENO);
+                }
+            }
+            drop(stage);
+            close_helper_ends();
+            std::mem::forget(self.recording.take());
+            crate::signals::reset();
+            self.capture = None;
+            self.interactive = false;
+            self.subshell = true;
+            let status = run(self);
+            self.exit(status);
+        }
+
+        // Both sides set the group, so that it exists whichever runs first.
+        if let Some(group) = group {
+            let leader = if group == 0 {
+                pid
+            } else {
+                group as libc::pid_t
+            };
+            unsafe { libc::setpgid(pid, leader) };
+            self.job_group = Some(leader as u32);
+        }
+        self.started = Some(Started::Forked(pid as u32));
+        0
+    }
+
+    /// Runs `run` in the shell for a pipeline stage that is not a program.
+    /// Its output is fed to the pipe of a `Pipe` stage once it is done.
+    #[cfg(windows)]
+    pub(crate) fn run_stage(&mut self, stage: Stage, run: impl FnOnce(&mut Self) -> i32) -> i32 {
+        let Stage::Pipe(writer) = stage else {
+            return run(self);
+        };
+        let (status, output) = self.with_capture(run);
+        // The next stage has not started reading yet, so output larger than
+        // the pipe holds is written from another thread.
+        std::thread::spawn(move || {
+            let mut writer = writer;
+            let _ = writer.write_all(&output);
+        });
+        status
+    }
+
+    /// Starts a program as a pipeline stage writing to `stdout`, or as the
+    /// last stage, leaving it running for `execute_pipeline` to wait for or
+    /// to become a job.
+    pub(crate) fn start_stage(
+        &mut self,
+        command: &str,
+        parsed: &ParsedCommand,
+        stdout: Option<PipeWriter>,
+    ) -> i32 {
+        let started = self
+            .external_command(command, parsed, stdout)
+            .and_then(|mut cmd| {
+                #[cfg(unix)]
+                if let Some(group) = self.job_group {
+                    cmd.process_group(group as i32);
+                }
+                // Without a terminal to stop it reading, a background job
+                // must not take the shell's input.
+                if self.job_group.is_some()
+                    && self.redirected.stdin.is_none()
+                    && !parsed
+                        .redirects
+                        .iter()
+                        .any(|redirect| redirect.stream == StreamType::Stdin)
+                    && !io::stdin().is_terminal()
+                {
+                    cmd.stdin(Stdio::null());
+                }
+                self.flush_output();
+                trace_span!("spawn");
+                cmd.spawn().map_err(|source| ShellError::NotExecutable {
+                    command: command.to_string(),
+                    source,
+                })
+            });
+
+        match started {
+            Ok(child) => {
+                // The first program of a job leads its process group.
+                if self.job_group == Some(0) {
+                    self.job_group = Some(child.id());
+                }
+                self.started = Some(Started::Program(child));
+                0
+            }
+            Err(e) => self.fail_command(e, &parsed.redirects),
+        }
+    }
+}
+
+/// The read end of a pipe holding `body`, for a here-document. The body is
+/// written from another thread, as it may be larger than the pipe holds.
+pub(crate) fn here_doc_file(body: &str) -> io::Result<File> {
+    let (reader, writer) = io::pipe()?;
+    let mut writer = HelperEnd::new(writer);
+    let body = body.to_string();
+    std::thread::spawn(move || {
+        let _ = writer.write_all(body.as_bytes());
+    });
+    Ok(pipe_file(reader))
+}
+
+/// The read end of a pipe whose contents another thread collects, for the
+/// output of a command while it is captured. Joining the thread gives
+/// everything written to `writer` once it and its copies are closed.
+pub(crate) fn drain() -> io::Result<(PipeWriter, JoinHandle<Vec<u8>>)> {
+    let (reader, writer) = io::pipe()?;
+    let mut reader = HelperEnd::new(reader);
+    let drain = std::thread::spawn(move || {
+        let mut output = Vec::new();
+        let _ = io::Read::read_to_end(&mut *reader, &mut output);
+        output
+    });
+    Ok((writer, drain))
+}
+
+// ============================================
+// PIPE ENDS OF HELPER THREADS
+// ============================================
+
+/// The file descriptors of the pipe ends helper threads hold: here-document
+/// writers and capture drains. A stage forked by `run_stage` closes them,
+/// as a copy of a here-document's write end would keep its reader from
+/// ever seeing the end of it. Lock-free slots rather than a locked list,
+/// since the forked copy must not take a lock another thread may have
+/// held at the fork. An end that finds no free slot is left open in the
+/// copy.
+#[cfg(unix)]
+static HELPER_FDS: [AtomicI32; 64] = [const { AtomicI32::new(-1) }; 64];
+
+/// A pipe end a helper thread holds, listed in `HELPER_FDS` until it is
+/// dropped. It is taken off the list before it is closed, so that the list
+/// never names a descriptor number that has been reused.
+pub(crate) struct HelperEnd<T> {
+    end: T,
+    #[cfg(unix)]
+    fd: RawFd,
+}
+
+impl<T> HelperEnd<T> {
+    #[cfg(unix)]
+    pub(crate) fn new(end: T) -> Self
+    where
+        T: AsRawFd,
+    {
+        let fd = end.as_raw_fd();
+        let _ = HELPER_FDS
+            .iter()
+            .find(|slot| slot.compare_exchange(-1, fd, SeqCst, SeqCst).is_ok());
+        Self { end, fd }
+    }
+
+    #[cfg(windows)]
+    pub(crate) fn new(end: T) -> Self {
+        Self { end }
+    }
+}
+
+impl<T> Drop for HelperEnd<T> {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        let _ = HELPER_FDS
+            .iter()
+            .find(|slot| slot.compare_exchange(self.fd, -1, SeqCst, SeqCst).is_ok());
+    }
+}
+
+impl<T> Deref for HelperEnd<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.end
+    }
+}
+
+impl<T> DerefMut for HelperEnd<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.end
+    }
+}
+
+/// Closes the pipe ends of the helper threads, in a process forked from
+/// the shell, where those threads do not exist.
+#[cfg(unix)]
+fn close_helper_ends() {
+    for slot in &HELPER_FDS {
+        let fd = slot.swap(-1, SeqCst);
+        if fd >= 0 {
+            unsafe { libc::close(fd) };
+        }
+    }
+}
+
+/// A pipe end as a `File`, so that it can stand in for a redirection.
+#[cfg(unix)]
+fn pipe_file(end: impl Into<std::os::fd::OwnedFd>) -> File {
+    File::from(end.into())
+}
+
+#[cfg(windows)]
+fn pipe_file(end: impl Into<std::os::windows::io::OwnedHandle>) -> File {
+    File::from(end.into())
+}
+
+/// A new handle on the shell's own `stream`, for a redirection that sends
+/// another stream to it, such as `2>&1` when stdout is not redirected.
+#[cfg(unix)]
+pub(crate) fn standard_file(stream: &StreamType) -> io::Result<File> {
+    use std::os::fd::AsFd;
+
+    let fd = match stream {
+        StreamType::Stdin => io::stdin().as_fd().try_clone_to_owned(),
+        StreamType::Stdout => io::stdout().as_fd().try_clone_to_owned(),
+        StreamType::Stderr => io::stderr().as_fd().try_clone_to_owned(),
+    }?;
+    Ok(File::from(fd))
+}
+
+#[cfg(windows)]
+pub(crate) fn standard_file(stream: &StreamType) -> io::Result<File> {
+    use std::os::windows::io::AsHandle;
+
+    let handle = match stream {
+        StreamType::Stdin => io::stdin().as_handle().try_clone_to_owned(),
+        StreamType::Stdout => io::stdout().as_handle().try_clone_to_owned(),
+        StreamType::Stderr => io::stderr().as_handle().try_clone_to_owned(),
+    }?;
+    Ok(File::from(handle))
+}
@@ -0,0 +1,270 @@
+/// ============================================
+/// PLUGIN BUILTINS
+/// ============================================
+use std::ffi::{CStr, CString, c_char, c_int, c_void};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::Shell;
+use crate::builtins::Builtin;
+use crate::error::ShellError;
+use crate::shell_io::ShellIo;
+use crate::startup::home_dir;
+
+/// Where plugins are looked for, under the home directory.
+const PLUGIN_DIR: &str = ".myshell_plugins";
+
+/// The version of the structures below. A plugin built against another
+/// version is refused rather than called with the wrong layout.
+pub const ABI_VERSION: u32 = 1;
+
+/// The symbol every plugin exports: an `extern "C" fn() -> *const
+/// PluginTable` giving the builtins it provides.
+pub const ENTRY_POINT: &str = "myshell_plugin";
+
+/// `stream` values for `PluginIo::write`.
+pub const STDOUT: c_int = 1;
+pub const STDERR: c_int = 2;
+
+/// What a plugin's entry point returns. It and everything it points to must
+/// stay valid for as long as the library is loaded.
+#[repr(C)]
+pub struct PluginTable {
+    pub abi_version: u32,
+    pub builtins: *const PluginBuiltin,
+    pub count: usize,
+}
+
+/// One builtin a plugin provides. `name` and `synopsis` are NUL-terminated
+/// UTF-8.
+#[repr(C)]
+pub struct PluginBuiltin {
+    pub name: *const c_char,
+    pub synopsis: *const c_char,
+    /// Runs the builtin with `argv[0]` set to its name and returns its exit
+    /// status. All input and output goes through `io`, so redirections
+    /// apply.
+    pub run:
+        unsafe extern "C" fn(argc: usize, argv: *const *const c_char, io: *mut PluginIo) -> c_int,
+}
+
+/// The streams of a running plugin builtin. `read` and `write` return the
+/// number of bytes transferred, or -1 on error.
+#[repr(C)]
+pub struct PluginIo {
+    pub read: unsafe extern "C" fn(io: *mut PluginIo, data: *mut u8, len: usize) -> isize,
+    pub write: unsafe extern "C" fn(
+        io: *mut PluginIo,
+        stream: c_int,
+        data: *const u8,
+        len: usize,
+    ) -> isize,
+    context: *mut c_void,
+}
+
+/// Rust trait objects have no stable layout across separately compiled
+/// libraries, so plugins describe their builtins with the C structures
+/// above and this adapts each of them to `Builtin`.
+struct PluginCommand {
+    name: &'static str,
+    synopsis: &'static str,
+    run: unsafe extern "C" fn(usize, *const *const c_char, *mut PluginIo) -> c_int,
+}
+
+impl Builtin for PluginCommand {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn synopsis(&self) -> &'static str {
+        self.synopsis
+    }
+
+    fn execute(&self, _shell: &mut Shell, args: &[String], io: &mut dyn ShellIo) -> i32 {
+        let args: Vec<CString> = std::iter::once(self.name)
+            .chain(args.iter().map(String::as_str))
+            .map(|arg| {
+                let arg = arg.split('\0').next().unwrap_or_default();
+                CString::new(arg).unwrap_or_default()
+            })
+            .collect();
+        let mut argv: Vec<*const c_char> = args.iter().map(|arg| arg.as_ptr()).collect();
+        argv.push(std::ptr::null());
+
+        let mut io: &mut dyn ShellIo = io;
+        let mut plugin_io = PluginIo {
+            read: plugin_read,
+            write: plugin_write,
+            context: &mut io as *mut &mut dyn ShellIo as *mut c_void,
+        };
+        unsafe { (self.run)(args.len(), argv.as_ptr(), &mut plugin_io) }
+    }
+}
+
+unsafe extern "C" fn plugin_read(io: *mut PluginIo, data: *mut u8, len: usize) -> isize {
+    let io = unsafe { &mut *((*io).context as *mut &mut dyn ShellIo) };
+    let buffer = unsafe { std::slice::from_raw_parts_mut(data, len) };
+    match io.stdin().read(buffer) {
+        Ok(n) => n as isize,
+        Err(_) => -1,
+    }
+}
+
+unsafe extern "C" fn plugin_write(
+    io: *mut PluginIo,
+    stream: c_int,
+    data: *const u8,
+    len: usize,
+) -> isize {
+    let io = unsafe { &mut *((*io).context as *mut &mut dyn ShellIo) };
+    let data = unsafe { std::slice::from_raw_parts(data, len) };
+    let out = match stream {
+        STDOUT => io.stdout(),
+        STDERR => io.stderr(),
+        _ => return -1,
+    };
+    match out.write_all(data) {
+        Ok(()) => len as isize,
+        Err(_) => -1,
+    }
+}
+
+/// Opens the library at `path` and returns its entry point. The library is
+/// never closed, since its builtins stay registered for the life of the
+/// shell.
+#[cfg(unix)]
+fn open_library(path: &Path) -> Result<unsafe extern "C" fn() -> *const PluginTable, String> {
+    use std::os::unix::ffi::OsStrExt;
+
+    // dlerror() names the file itself; the caller already does.
+    let prefix = format!("{}: ", path.display());
+    let last_error = || {
+        let message = unsafe { libc::dlerror() };
+        if message.is_null() {
+            return "cannot load plugin".to_string();
+        }
+        let message = unsafe { CStr::from_ptr(message) }.to_string_lossy();
+        message
+            .strip_prefix(&prefix)
+            .unwrap_or(&message)
+            .to_string()
+    };
+
+    let path = CString::new(path.as_os_str().as_bytes()).map_err(|e| e.to_string())?;
+    let handle = unsafe { libc::dlopen(path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+    if handle.is_null() {
+        return Err(last_error());
+    }
+
+    let symbol = CString::new(ENTRY_POINT).unwrap_or_default();
+    let entry = unsafe { libc::dlsym(handle, symbol.as_ptr()) };
+    if entry.is_null() {
+        return Err(last_error());
+    }
+    Ok(unsafe {
+        std::mem::transmute::<*mut c_void, unsafe extern "C" fn() -> *const PluginTable>(entry)
+    })
+}
+
+#[cfg(windows)]
+fn open_library(path: &Path) -> Result<unsafe extern "C" fn() -> *const PluginTable, String> {
+    use windows::Win32::System::LibraryLoader::{GetProcAddress, LoadLibraryW};
+    use windows::core::{HSTRING, PCSTR};
+
+    let module =
+        unsafe { LoadLibraryW(&HSTRING::from(path.as_os_str())) }.map_err(|e| e.message())?;
+
+    let symbol = CString::new(ENTRY_POINT).unwrap_or_default();
+    let entry = unsafe { GetProcAddress(module, PCSTR(symbol.as_ptr() as *const u8)) }
+        .ok_or_else(|| format!("{}: entry point not found", ENTRY_POINT))?;
+    Ok(unsafe {
+        std::mem::transmute::<
+            unsafe extern "system" fn() -> isize,
+            unsafe extern "C" fn() -> *const PluginTable,
+        >(entry)
+    })
+}
+
+/// A string from a plugin, kept for the life of the shell.
+fn plugin_str(s: *const c_char) -> Option<&'static str> {
+    if s.is_null() {
+        return None;
+    }
+    let s = unsafe { CStr::from_ptr(s) }.to_str().ok()?;
+    Some(Box::leak(s.into()))
+}
+
+/// Loads the library at `path` and returns the builtins it provides.
+fn load_plugin(path: &Path) -> Result<Vec<PluginCommand>, String> {
+    let entry = open_library(path)?;
+    let table = unsafe { entry().as_ref() }.ok_or("plugin returned no builtins")?;
+    if table.abi_version != ABI_VERSION {
+        return Err(format!(
+            "plugin ABI version {} is not supported (expected {})",
+            table.abi_version, ABI_VERSION
+        ));
+    }
+    if table.builtins.is_null() {
+        return Ok(Vec::new());
+    }
+
+    let builtins = unsafe { std::slice::from_raw_parts(table.builtins, table.count) };
+    builtins
+        .iter()
+        .map(|builtin| {
+            let name = plugin_str(builtin.name)
+                .filter(|name| {
+                    !name.is_empty() && !name.contains(|c: char| c.is_whitespace() || c == '/')
+                })
+                .ok_or("plugin builtin has an invalid name")?;
+            Ok(PluginCommand {
+                name,
+                synopsis: plugin_str(builtin.synopsis).unwrap_or(name),
+                run: builtin.run,
+            })
+        })
+        .collect()
+}
+
+impl Shell {
+    /// Adds `builtin`, replacing any builtin of the same name.
+    pub fn register_builtin(&mut self, builtin: Rc<dyn Builtin>) {
+        self.builtins.register(builtin);
+    }
+
+    /// Loads every plugin library in `~/.myshell_plugins`, in name order,
+    /// and registers the builtins they provide. A plugin builtin replaces a
+    /// builtin of the same name. Plugins that fail to load are reported and
+    /// skipped.
+    pub fn load_plugins(&mut self) {
+        let Some(dir) = home_dir().map(|home| home.join(PLUGIN_DIR)) else {
+            return;
+        };
+        let Ok(entries) = fs::read_dir(&dir) else {
+            return;
+        };
+
+        let mut libraries: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension() == Some(std::env::consts::DLL_EXTENSION.as_ref()))
+            .collect();
+        libraries.sort();
+
+        for path in libraries {
+            match load_plugin(&path) {
+                Ok(builtins) => {
+                    for builtin in builtins {
+                        self.register_builtin(Rc::new(builtin));
+                    }
+                }
+                Err(message) => {
+                    self.report(&ShellError::Plugin {
+                        path: path.display().to_string(),
+                        message,
+                    });
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,41 @@
+/// ============================================
+/// PROMPT STRINGS
+/// ============================================
+use std::io::{self, IsTerminal};
+
+use crate::Shell;
+
+/// The primary prompt when `PS1` is unset.
+const DEFAULT_PS1: &str = "$ ";
+
+/// The prompt for continuation lines when `PS2` is unset.
+const DEFAULT_PS2: &str = "> ";
+
+impl Shell {
+    /// The prompt for the first line of a command, from `PS1`. With the
+    /// `prompt-theme` feature its escapes are expanded and the prompt
+    /// segments shown in front of it.
+    pub(crate) fn primary_prompt(&mut self) -> String {
+        // No prompt is shown when input is not a terminal.
+        if !io::stdin().is_terminal() {
+            return DEFAULT_PS1.to_string();
+        }
+        #[cfg(feature = "prompt-theme")]
+        return self.themed_primary_prompt(DEFAULT_PS1);
+        #[cfg(not(feature = "prompt-theme"))]
+        self.get_var("PS1")
+            .unwrap_or_else(|| DEFAULT_PS1.to_string())
+    }
+
+    /// The prompt for the continuation lines of a command, from `PS2`.
+    pub(crate) fn secondary_prompt(&mut self) -> String {
+        if !io::stdin().is_terminal() {
+            return DEFAULT_PS2.to_string();
+        }
+        #[cfg(feature = "prompt-theme")]
+        return self.prompt_string("PS2", DEFAULT_PS2);
+        #[cfg(not(feature = "prompt-theme"))]
+        self.get_var("PS2")
+            .unwrap_or_else(|| DEFAULT_PS2.to_string())
+    }
+}
@@ -0,0 +1,219 @@
+/// ============================================
+/// SESSION RECORDING
+/// ============================================
+#[cfg(unix)]
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+/// A session being recorded with `record start`. Everything written to
+/// stdout and stderr, by the shell and the programs it runs, is copied to a
+/// file as well as the terminal, like util-linux `script`. Keys are
+/// recorded as the shell or terminal echoes them.
+#[cfg(unix)]
+pub struct Recording {
+    /// The real stdout and stderr, put back when the recording stops.
+    saved: [std::os::fd::OwnedFd; 2],
+    stopping: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    pump: Option<std::thread::JoinHandle<()>>,
+}
+
+#[cfg(unix)]
+impl Recording {
+    /// Starts copying stdout and stderr to `file`, which is created or, with
+    /// `append`, added to.
+    ///
+    /// Both are pointed at a pseudo-terminal so that programs still see a
+    /// terminal, and a thread passes what arrives on to the real terminal
+    /// and the file. Anything buffered must be flushed before this.
+    pub fn start(path: &Path, append: bool) -> io::Result<Self> {
+        use std::io::Write;
+        use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+        use std::sync::Arc;
+        use std::sync::atomic::AtomicBool;
+
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(path)?;
+        let _ = writeln!(file, "Script started on {}", crate::audit::timestamp_now());
+
+        let (master, slave) = open_pty()?;
+        let dup = |fd| {
+            let fd = unsafe { libc::dup(fd) };
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+        };
+        let saved = [dup(libc::STDOUT_FILENO)?, dup(libc::STDERR_FILENO)?];
+        let terminal = File::from(dup(libc::STDOUT_FILENO)?);
+
+        for fd in [libc::STDOUT_FILENO, libc::STDERR_FILENO] {
+            if unsafe { libc::dup2(slave.as_raw_fd(), fd) } < 0 {
+                let error = io::Error::last_os_error();
+                restore(&saved);
+                return Err(error);
+            }
+        }
+
+        let stopping = Arc::new(AtomicBool::new(false));
+        let pump = {
+            let stopping = Arc::clone(&stopping);
+            std::thread::Builder::new()
+                .name("record".into())
+                .spawn(move || pump(master, terminal, file, &stopping))
+        };
+        let pump = match pump {
+            Ok(pump) => pump,
+            Err(error) => {
+                restore(&saved);
+                return Err(error);
+            }
+        };
+
+        Ok(Self {
+            saved,
+            stopping,
+            pump: Some(pump),
+        })
+    }
+
+    /// Puts the real stdout and stderr back and waits for everything
+    /// recorded so far to reach the file.
+    pub fn stop(mut self) {
+        self.finish();
+    }
+
+    fn finish(&mut self) {
+        use std::sync::atomic::Ordering;
+
+        restore(&self.saved);
+        self.stopping.store(true, Ordering::Relaxed);
+        if let Some(pump) = self.pump.take() {
+            let _ = pump.join();
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Recording {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+/// Opens a pseudo-terminal sized like the real one, passing output through
+/// unchanged so the real terminal does any newline translation once.
+#[cfg(unix)]
+fn open_pty() -> io::Result<(File, std::os::fd::OwnedFd)> {
+    use std::os::fd::{FromRawFd, OwnedFd};
+
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    let sized = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0;
+
+    let (mut master, mut slave) = (0, 0);
+    let result = unsafe {
+        libc::openpty(
+            &mut master,
+            &mut slave,
+            std::ptr::null_mut(),
+            std::ptr::null(),
+            if sized { &size } else { std::ptr::null() },
+        )
+    };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let (master, slave) = unsafe { (File::from_raw_fd(master), OwnedFd::from_raw_fd(slave)) };
+
+    let mut attributes: libc::termios = unsafe { std::mem::zeroed() };
+    if unsafe { libc::tcgetattr(std::os::fd::AsRawFd::as_raw_fd(&slave), &mut attributes) } == 0 {
+        attributes.c_oflag &= !libc::OPOST;
+        unsafe {
+            libc::tcsetattr(
+                std::os::fd::AsRawFd::as_raw_fd(&slave),
+                libc::TCSANOW,
+                &attributes,
+            );
+        }
+    }
+    Ok((master, slave))
+}
+
+/// Points stdout and stderr back at the descriptors in `saved`.
+#[cfg(unix)]
+fn restore(saved: &[std::os::fd::OwnedFd; 2]) {
+    use std::os::fd::AsRawFd;
+
+    unsafe {
+        libc::dup2(saved[0].as_raw_fd(), libc::STDOUT_FILENO);
+        libc::dup2(saved[1].as_raw_fd(), libc::STDERR_FILENO);
+    }
+}
+
+/// How long the pump waits for output before checking whether the
+/// recording has stopped.
+#[cfg(unix)]
+const POLL_INTERVAL_MS: libc::c_int = 50;
+
+/// Copies output from the pseudo-terminal to the terminal and the file
+/// until the recording stops and no more output is waiting. A program
+/// left running in the background may still hold the pseudo-terminal, so
+/// the end of its output cannot be waited for.
+#[cfg(unix)]
+fn pump(
+    mut master: File,
+    mut terminal: File,
+    mut file: File,
+    stopping: &std::sync::atomic::AtomicBool,
+) {
+    use std::io::{Read, Write};
+    use std::os::fd::AsRawFd;
+    use std::sync::atomic::Ordering;
+
+    let mut buffer = [0u8; 4096];
+    loop {
+        let mut poll = libc::pollfd {
+            fd: master.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let ready = unsafe { libc::poll(&mut poll, 1, POLL_INTERVAL_MS) };
+        if ready <= 0 {
+            if stopping.load(Ordering::Relaxed) {
+                break;
+            }
+            continue;
+        }
+
+        match master.read(&mut buffer) {
+            Ok(n) if n > 0 => {
+                let _ = terminal.write_all(&buffer[..n]);
+                let _ = file.write_all(&buffer[..n]);
+            }
+            // EIO once nothing has the other end open.
+            _ => break,
+        }
+    }
+    let _ = writeln!(file, "\nScript done on {}", crate::audit::timestamp_now());
+}
+
+/// Session recording needs a pseudo-terminal, which this platform does not
+/// offer in the same way.
+#[cfg(windows)]
+pub struct Recording;
+
+#[cfg(windows)]
+impl Recording {
+    pub fn start(_path: &Path, _append: bool) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "session recording is not supported on this platform",
+        ))
+    }
+
+    pub fn stop(self) {}
+}
@@ -0,0 +1,105 @@
+/// ============================================
+/// COMMAND I/O STREAMS
+/// ============================================
+use std::cell::RefCell;
+use std::io::{self, BufWriter, Read, StdoutLock, Write};
+use std::rc::Rc;
+
+/// The standard streams a builtin reads and writes. Redirections are
+/// applied by handing the builtin different handles, so it never needs to
+/// know where its output goes.
+pub trait ShellIo {
+    fn stdin(&mut self) -> &mut dyn Read;
+    fn stdout(&mut self) -> &mut dyn Write;
+    fn stderr(&mut self) -> &mut dyn Write;
+}
+
+/// The shell's buffered stdout, shared by every builtin that writes to the
+/// terminal. It is written out before prompts, errors and child processes.
+#[derive(Clone)]
+pub struct SharedStdout(Rc<RefCell<BufWriter<StdoutLock<'static>>>>);
+
+impl Default for SharedStdout {
+    fn default() -> Self {
+        Self(Rc::new(RefCell::new(BufWriter::new(io::stdout().lock()))))
+    }
+}
+
+impl Write for SharedStdout {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.borrow_mut().flush()
+    }
+}
+
+/// Command output collected in memory instead of written to the terminal.
+/// Clones share the same buffer.
+#[derive(Clone, Default)]
+pub struct Capture(Rc<RefCell<Vec<u8>>>);
+
+impl Capture {
+    /// Everything written so far, emptying the buffer.
+    pub fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut self.0.borrow_mut())
+    }
+}
+
+impl Write for Capture {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// stderr, written after anything still buffered for stdout so the two
+/// appear in the order they were produced.
+pub struct Stderr(SharedStdout);
+
+impl Write for Stderr {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.flush()?;
+        io::stderr().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
+
+/// The streams one command runs with: the terminal, unless redirected.
+pub struct Streams {
+    pub stdin: Box<dyn Read>,
+    pub stdout: Box<dyn Write>,
+    pub stderr: Box<dyn Write>,
+}
+
+impl Streams {
+    pub fn terminal(stdout: &SharedStdout) -> Self {
+        Self {
+            stdin: Box::new(io::stdin()),
+            stdout: Box::new(stdout.clone()),
+            stderr: Box::new(Stderr(stdout.clone())),
+        }
+    }
+}
+
+impl ShellIo for Streams {
+    fn stdin(&mut self) -> &mut dyn Read {
+        &mut self.stdin
+    }
+
+    fn stdout(&mut self) -> &mut dyn Write {
+        &mut self.stdout
+    }
+
+    fn stderr(&mut self) -> &mut dyn Write {
+        &mut self.stderr
+    }
+}
@@ -0,0 +1,153 @@
+/// ============================================
+/// SIGNAL HANDLING
+/// ============================================
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+#[cfg(windows)]
+static BREAK: AtomicBool = AtomicBool::new(false);
+
+/// Installs the shell's SIGINT handler. The shell itself survives Ctrl-C;
+/// the handler only records it so running loops and lists can stop early.
+/// Child processes get the default disposition back when they exec.
+#[cfg(unix)]
+pub fn install() {
+    extern "C" fn on_sigint(_: libc::c_int) {
+        INTERRUPTED.store(true, Ordering::SeqCst);
+    }
+
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = on_sigint as *const () as libc::sighandler_t;
+        action.sa_flags = libc::SA_RESTART;
+        libc::sigemptyset(&mut action.sa_mask);
+        libc::sigaction(libc::SIGINT, &action, std::ptr::null_mut());
+    }
+}
+
+/// The signals that stop a process: Ctrl-Z, and reading or writing the
+/// terminal from a background job.
+#[cfg(unix)]
+const JOB_SIGNALS: [libc::c_int; 3] = [libc::SIGTSTP, libc::SIGTTIN, libc::SIGTTOU];
+
+/// Makes an interactive shell ignore the signals that stop a process, so
+/// that they stop only the foreground job, which has a process group of
+/// its own, and not the shell.
+#[cfg(unix)]
+pub fn ignore_job_signals() {
+    for signal in JOB_SIGNALS {
+        unsafe { libc::signal(signal, libc::SIG_IGN) };
+    }
+}
+
+/// Gives the signals that stop a process their default dispositions back,
+/// in a program about to be run: unlike handlers, ignored signals stay
+/// ignored across exec. Only async-signal-safe calls are made.
+#[cfg(unix)]
+pub fn default_job_signals() {
+    for signal in JOB_SIGNALS {
+        unsafe { libc::signal(signal, libc::SIG_DFL) };
+    }
+}
+
+/// Gives a copy of the shell forked for a pipeline stage the default
+/// dispositions back, as programs get them when they exec: Ctrl-C ends it,
+/// Ctrl-Z stops it, and writing to a pipe nobody reads any more ends it.
+#[cfg(unix)]
+pub fn reset() {
+    INTERRUPTED.store(false, Ordering::SeqCst);
+    unsafe {
+        libc::signal(libc::SIGINT, libc::SIG_DFL);
+        libc::signal(libc::SIGPIPE, libc::SIG_DFL);
+    }
+    default_job_signals();
+}
+
+/// Installs the shell's console control handler, the Windows counterpart
+/// of the SIGINT handler. Ctrl-C is recorded like SIGINT. Ctrl-Break only
+/// asks for the foreground command to be terminated: it does not stop the
+/// shell's own loops. At the prompt, raw mode turns Ctrl-C into an ordinary
+/// key press instead, which cancels the line being edited.
+#[cfg(windows)]
+pub fn install() {
+    use windows::Win32::System::Console::{CTRL_BREAK_EVENT, CTRL_C_EVENT, SetConsoleCtrlHandler};
+    use windows::core::BOOL;
+
+    unsafe extern "system" fn on_ctrl(ctrl_type: u32) -> BOOL {
+        match ctrl_type {
+            CTRL_C_EVENT => INTERRUPTED.store(true, Ordering::SeqCst),
+            CTRL_BREAK_EVENT => BREAK.store(true, Ordering::SeqCst),
+            // Closing the console, logoff and shutdown end the shell.
+            _ => return false.into(),
+        }
+        true.into()
+    }
+
+    unsafe {
+        let _ = SetConsoleCtrlHandler(Some(on_ctrl), true);
+    }
+}
+
+/// Whether Ctrl-C has been pressed since the flag was last cleared.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Records Ctrl-C as the SIGINT handler does, for a foreground job that
+/// Ctrl-C ended while it had the terminal to itself.
+#[cfg(unix)]
+pub fn interrupt() {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// Clears the interrupt flag, returning whether it was set.
+pub fn take_interrupt() -> bool {
+    INTERRUPTED.swap(false, Ordering::SeqCst)
+}
+
+/// Clears the Ctrl-Break flag, returning whether it was set.
+#[cfg(windows)]
+pub fn take_break() -> bool {
+    BREAK.swap(false, Ordering::SeqCst)
+}
+
+/// The signals `kill` knows by name.
+#[cfg(unix)]
+pub const SIGNALS: &[(&str, i32)] = &[
+    ("HUP", libc::SIGHUP),
+    ("INT", libc::SIGINT),
+    ("QUIT", libc::SIGQUIT),
+    ("KILL", libc::SIGKILL),
+    ("USR1", libc::SIGUSR1),
+    ("USR2", libc::SIGUSR2),
+    ("PIPE", libc::SIGPIPE),
+    ("ALRM", libc::SIGALRM),
+    ("TERM", libc::SIGTERM),
+    ("CONT", libc::SIGCONT),
+    ("STOP", libc::SIGSTOP),
+    ("TSTP", libc::SIGTSTP),
+    ("TTIN", libc::SIGTTIN),
+    ("TTOU", libc::SIGTTOU),
+    ("WINCH", libc::SIGWINCH),
+];
+
+/// On Windows every signal ends the process; these are the names that
+/// mean that.
+#[cfg(windows)]
+pub const SIGNALS: &[(&str, i32)] = &[("INT", 2), ("KILL", 9), ("TERM", 15)];
+
+/// The number of the signal `name`, given with or without `SIG` and in
+/// any case, or as a number.
+pub fn signal_number(name: &str) -> Option<i32> {
+    if let Ok(number) = name.parse() {
+        // Signal 0 only checks that the process exists.
+        let known = number == 0 || SIGNALS.iter().any(|&(_, known)| known == number);
+        return known.then_some(number);
+    }
+    let upper = name.to_ascii_uppercase();
+    let name = upper.strip_prefix("SIG").unwrap_or(&upper);
+    SIGNALS
+        .iter()
+        .find(|&&(known, _)| known == name)
+        .map(|&(_, number)| number)
+}
@@ -0,0 +1,154 @@
+/// ============================================
+/// STARTUP FILES
+/// ============================================
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::ShellError;
+use crate::options::RcFile;
+use crate::osstr;
+use crate::{Flow, Shell};
+
+/// The rc files looked for in the home directory; only the first that
+/// exists is sourced.
+const RC_FILES: &[&str] = &[".myshellrc", ".shellrc"];
+const LOGOUT_FILE: &str = ".myshell_logout";
+
+/// The user's home directory, from `HOME` or, on Windows, `USERPROFILE`.
+pub(crate) fn home_dir() -> Option<PathBuf> {
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
+
+impl Shell {
+    /// Increments `SHLVL`, sets `SHELL` if it is unset and makes `PWD` match
+    /// the working directory, exporting them so nested shells and other
+    /// programs see them. `POSIXLY_CORRECT` in the environment turns on
+    /// POSIX mode.
+    pub fn init_environment(&mut self) {
+        if self.get_var("POSIXLY_CORRECT").is_some() {
+            self.posix = true;
+        }
+
+        let level = self
+            .get_var("SHLVL")
+            .and_then(|level| level.trim().parse::<i64>().ok())
+            .filter(|&level| level >= 0)
+            .unwrap_or(0);
+        self.export_var("SHLVL", (level + 1).to_string());
+
+        let shell = match self.get_var("SHELL") {
+            Some(shell) if !shell.is_empty() => shell,
+            _ => env::current_exe()
+                .map(|path| osstr::from_os(path.as_os_str()))
+                .unwrap_or_else(|_| self.shell_name.clone()),
+        };
+        self.export_var("SHELL", shell);
+
+        if let Ok(pwd) = self.logical_cwd() {
+            self.export_var("PWD", osstr::from_os(pwd.as_os_str()));
+        }
+    }
+
+    /// Reads and runs the commands in `path` in the current shell. A syntax
+    /// error anywhere in the file means none of it is run, and `return` at
+    /// its top level stops the file early.
+    pub(crate) fn source_file(&mut self, path: &Path) -> Result<i32, ShellError> {
+        let origin = || path.display().to_string();
+        let source = fs::read(path).map_err(|source| ShellError::Io {
+            context: origin(),
+            source,
+        })?;
+        let source = osstr::from_bytes(&source);
+        let program = self
+            .parse(&source)
+            .map_err(|error| ShellError::syntax(&origin(), 0, &source, error))?;
+
+        self.source_depth += 1;
+        self.push_frame("source", origin());
+        let line_base = std::mem::take(&mut self.line_base);
+        let status = self.execute(&program);
+        self.line_base = line_base;
+        self.pop_frame();
+        self.source_depth -= 1;
+        if self.flow == Flow::Return {
+            self.flow = Flow::Normal;
+        }
+
+        Ok(status)
+    }
+
+    /// Sources the file named by `$ENV`, after parameter expansion, whether
+    /// or not the shell is interactive.
+    pub fn load_env_file(&mut self) {
+        let Some(env_file) = self.get_var("ENV").filter(|value| !value.is_empty()) else {
+            return;
+        };
+        let path = PathBuf::from(osstr::to_os(&self.expand_string(&env_file)));
+
+        if let Err(e) = self.source_file(&path) {
+            self.report(&e);
+        }
+    }
+
+    /// Sources the rc file chosen on the command line, `~/.myshellrc` or
+    /// else `~/.shellrc` by default, when the shell is interactive. A
+    /// missing default rc file is not an error; a missing `--rcfile` is
+    /// reported.
+    pub fn load_rc(&mut self, rc_file: &RcFile) {
+        if !self.interactive {
+            return;
+        }
+        let path = match rc_file {
+            RcFile::Skip => return,
+            RcFile::Custom(path) => path.clone(),
+            RcFile::Default => {
+                let Some(home) = home_dir() else {
+                    return;
+                };
+                match RC_FILES
+                    .iter()
+                    .map(|name| home.join(name))
+                    .find(|path| path.is_file())
+                {
+                    Some(path) => path,
+                    None => return,
+                }
+            }
+        };
+
+        if let Err(e) = self.source_file(&path) {
+            self.report(&e);
+        }
+    }
+
+    /// Exits the shell, first sourcing `~/.myshell_logout` if this is a
+    /// login shell. A copy of the shell forked for a pipeline stage just
+    /// ends.
+    pub fn exit(&mut self, code: i32) -> ! {
+        #[cfg(unix)]
+        if self.subshell {
+            self.flush_output();
+            unsafe { libc::_exit(code) };
+        }
+        if std::mem::take(&mut self.login)
+            && let Some(path) = home_dir().map(|home| home.join(LOGOUT_FILE))
+            && path.is_file()
+            && let Err(e) = self.source_file(&path)
+        {
+            self.report(&e);
+        }
+        #[cfg(feature = "history")]
+        self.save_history();
+        #[cfg(feature = "line-editor")]
+        self.report_focus(false);
+        self.flush_output();
+        if let Some(recording) = self.recording.take() {
+            recording.stop();
+        }
+        std::process::exit(code);
+    }
+}
@@ -7,3 +7,20 @@ pub mod windows;
 pub use unix::RawMode;
 #[cfg(windows)]
 pub use windows::RawMode;
+
+/// The settings `stty` shows and changes, as the terminal has them while
+/// commands run. The line editor's raw mode starts from them, so they
+/// apply while a line is edited too, except `ixon`: Ctrl-Q is an editor
+/// key.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Settings {
+    /// Ctrl-S stops output and Ctrl-Q restarts it.
+    pub ixon: bool,
+    /// The terminal is sent Ctrl-S and Ctrl-Q as its input fills up and
+    /// drains.
+    pub ixoff: bool,
+    /// Typed characters are echoed.
+    pub echo: bool,
+    /// The character that erases the one before it.
+    pub erase: u8,
+}
@@ -1,10 +1,12 @@
 /// ============================================
 /// TERMINAL RAW MODE - UNIX
 /// ============================================
-use libc::{ECHO, ICANON, TCSANOW, VMIN, VTIME, c_int, termios};
+use libc::{ECHO, ICANON, ISIG, IXOFF, IXON, TCSANOW, VERASE, VMIN, VTIME, c_int, termios};
 use std::io;
 use std::os::unix::io::AsRawFd;
 
+use super::Settings;
+
 pub struct RawMode {
     fd: c_int,
     original: termios,
@@ -13,14 +15,15 @@ pub struct RawMode {
 impl RawMode {
     pub fn enable() -> io::Result<Self> {
         let fd = io::stdin().as_raw_fd();
-        let mut original = unsafe { std::mem::zeroed() };
-
-        if unsafe { libc::tcgetattr(fd, &mut original) } != 0 {
-            return Err(io::Error::last_os_error());
-        }
+        let original = get_attributes(fd)?;
 
         let mut raw = original;
-        raw.c_lflag &= !(ICANON | ECHO);
+        // Ctrl-C and Ctrl-Z arrive as key presses rather than signals, so
+        // a Ctrl-C typed ahead is not lost and Ctrl-Z cannot stop the shell.
+        raw.c_lflag &= !(ICANON | ECHO | ISIG);
+        // Ctrl-Q is the push-line key, which flow control would swallow;
+        // `stty ixon` still applies while commands run.
+        raw.c_iflag &= !IXON;
         raw.c_cc[VMIN] = 0;
         raw.c_cc[VTIME] = 1;
 
@@ -30,6 +33,28 @@ impl RawMode {
 
         Ok(RawMode { fd, original })
     }
+
+    /// The erase character, as `stty erase` set it.
+    pub fn erase(&self) -> u8 {
+        self.original.c_cc[VERASE]
+    }
+
+    /// Whether typed characters are echoed, as `stty echo` set it; the
+    /// line editor does the echoing in raw mode.
+    pub fn echo(&self) -> bool {
+        self.original.c_lflag & ECHO != 0
+    }
+
+    /// The terminal's rows and columns, or 24 by 80 if it does not say.
+    pub fn size(&self) -> (usize, usize) {
+        let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+        // SAFETY: TIOCGWINSZ fills in the winsize it is given.
+        let sized = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0;
+        if !sized || size.ws_row == 0 || size.ws_col == 0 {
+            return (24, 80);
+        }
+        (size.ws_row.into(), size.ws_col.into())
+    }
 }
 
 impl Drop for RawMode {
@@ -39,3 +64,48 @@ impl Drop for RawMode {
         }
     }
 }
+
+impl Settings {
+    /// Reads the settings of the terminal on stdin.
+    pub fn get() -> io::Result<Self> {
+        let attributes = get_attributes(io::stdin().as_raw_fd())?;
+        Ok(Settings {
+            ixon: attributes.c_iflag & IXON != 0,
+            ixoff: attributes.c_iflag & IXOFF != 0,
+            echo: attributes.c_lflag & ECHO != 0,
+            erase: attributes.c_cc[VERASE],
+        })
+    }
+
+    /// Applies the settings to the terminal on stdin.
+    pub fn set(&self) -> io::Result<()> {
+        let fd = io::stdin().as_raw_fd();
+        let mut attributes = get_attributes(fd)?;
+        for (flag, on) in [(IXON, self.ixon), (IXOFF, self.ixoff)] {
+            if on {
+                attributes.c_iflag |= flag;
+            } else {
+                attributes.c_iflag &= !flag;
+            }
+        }
+        if self.echo {
+            attributes.c_lflag |= ECHO;
+        } else {
+            attributes.c_lflag &= !ECHO;
+        }
+        attributes.c_cc[VERASE] = self.erase;
+
+        if unsafe { libc::tcsetattr(fd, TCSANOW, &attributes) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+fn get_attributes(fd: c_int) -> io::Result<termios> {
+    let mut attributes = unsafe { std::mem::zeroed() };
+    if unsafe { libc::tcgetattr(fd, &mut attributes) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(attributes)
+}
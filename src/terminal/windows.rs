@@ -3,14 +3,21 @@
 /// ============================================
 use std::io;
 use windows::Win32::Foundation::HANDLE;
+
+use super::Settings;
 use windows::Win32::System::Console::{
-    CONSOLE_MODE, ENABLE_ECHO_INPUT, ENABLE_LINE_INPUT, ENABLE_PROCESSED_INPUT, GetConsoleMode,
-    GetStdHandle, STD_INPUT_HANDLE, SetConsoleMode,
+    CONSOLE_MODE, CONSOLE_SCREEN_BUFFER_INFO, ENABLE_ECHO_INPUT, ENABLE_LINE_INPUT,
+    ENABLE_PROCESSED_INPUT, ENABLE_PROCESSED_OUTPUT, ENABLE_VIRTUAL_TERMINAL_INPUT,
+    ENABLE_VIRTUAL_TERMINAL_PROCESSING, GetConsoleMode, GetConsoleScreenBufferInfo, GetStdHandle,
+    STD_INPUT_HANDLE, STD_OUTPUT_HANDLE, SetConsoleMode,
 };
 
 pub struct RawMode {
     handle: HANDLE,
     original_mode: CONSOLE_MODE,
+    /// The output handle and its mode before VT processing was turned on,
+    /// if it is a console.
+    output: Option<(HANDLE, CONSOLE_MODE)>,
 }
 
 impl RawMode {
@@ -22,23 +29,126 @@ impl RawMode {
             GetConsoleMode(handle, &mut original_mode).map_err(io::Error::other)?;
 
             let mut new_mode = original_mode;
-            new_mode &= !(ENABLE_LINE_INPUT | ENABLE_ECHO_INPUT);
-            new_mode |= ENABLE_PROCESSED_INPUT;
+            // Without processed input Ctrl-C arrives as a key press, so the
+            // line editor can cancel the line instead of the control handler
+            // running behind a blocked read.
+            new_mode &= !(ENABLE_LINE_INPUT | ENABLE_ECHO_INPUT | ENABLE_PROCESSED_INPUT);
 
-            SetConsoleMode(handle, new_mode).map_err(io::Error::other)?;
+            // Keys then arrive as the same escape sequences a Unix terminal
+            // sends. Consoles older than Windows 10 reject the flag, in which
+            // case the plain raw mode is used.
+            if SetConsoleMode(handle, new_mode | ENABLE_VIRTUAL_TERMINAL_INPUT).is_err() {
+                SetConsoleMode(handle, new_mode).map_err(io::Error::other)?;
+            }
 
             Ok(RawMode {
                 handle,
                 original_mode,
+                output: Self::enable_vt_output(),
             })
         }
     }
+
+    /// The erase character: Backspace, which the console cannot change.
+    pub fn erase(&self) -> u8 {
+        0x08
+    }
+
+    /// Whether typed characters are echoed, as `stty echo` set it; the
+    /// line editor does the echoing in raw mode.
+    pub fn echo(&self) -> bool {
+        self.original_mode.contains(ENABLE_ECHO_INPUT)
+    }
+
+    /// The console window's rows and columns, or 24 by 80 if stdout is
+    /// not a console.
+    pub fn size(&self) -> (usize, usize) {
+        let mut info = CONSOLE_SCREEN_BUFFER_INFO::default();
+        let window = unsafe {
+            GetStdHandle(STD_OUTPUT_HANDLE)
+                .and_then(|handle| GetConsoleScreenBufferInfo(handle, &mut info))
+                .map(|()| info.srWindow)
+        };
+        match window {
+            Ok(window) => (
+                (window.Bottom - window.Top + 1).max(1) as usize,
+                (window.Right - window.Left + 1).max(1) as usize,
+            ),
+            Err(_) => (24, 80),
+        }
+    }
+
+    /// Turns on ANSI escape sequence handling for stdout, which conhost
+    /// leaves off by default. Fails quietly when stdout is not a console
+    /// or the console is too old to support it.
+    fn enable_vt_output() -> Option<(HANDLE, CONSOLE_MODE)> {
+        unsafe {
+            let handle = GetStdHandle(STD_OUTPUT_HANDLE).ok()?;
+
+            let mut original_mode = CONSOLE_MODE::default();
+            GetConsoleMode(handle, &mut original_mode).ok()?;
+
+            let new_mode =
+                original_mode | ENABLE_PROCESSED_OUTPUT | ENABLE_VIRTUAL_TERMINAL_PROCESSING;
+            SetConsoleMode(handle, new_mode).ok()?;
+
+            Some((handle, original_mode))
+        }
+    }
 }
 
 impl Drop for RawMode {
     fn drop(&mut self) {
         unsafe {
             let _ = SetConsoleMode(self.handle, self.original_mode);
+            if let Some((handle, mode)) = self.output {
+                let _ = SetConsoleMode(handle, mode);
+            }
         }
     }
 }
+
+impl Settings {
+    /// Reads the settings of the console on stdin. Consoles have no flow
+    /// control, and Backspace always erases.
+    pub fn get() -> io::Result<Self> {
+        let (_, mode) = input_mode()?;
+        Ok(Settings {
+            ixon: false,
+            ixoff: false,
+            echo: mode.contains(ENABLE_ECHO_INPUT),
+            erase: 0x08,
+        })
+    }
+
+    /// Applies the settings to the console on stdin. Only echo can be
+    /// changed.
+    pub fn set(&self) -> io::Result<()> {
+        let current = Self::get()?;
+        if (self.ixon, self.ixoff, self.erase) != (current.ixon, current.ixoff, current.erase) {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "only echo can be changed on the Windows console",
+            ));
+        }
+
+        let (handle, mut mode) = input_mode()?;
+        if self.echo {
+            // The console only echoes in line input mode.
+            mode |= ENABLE_ECHO_INPUT | ENABLE_LINE_INPUT;
+        } else {
+            mode &= !ENABLE_ECHO_INPUT;
+        }
+        unsafe { SetConsoleMode(handle, mode).map_err(io::Error::other) }
+    }
+}
+
+/// The console input handle and its mode.
+fn input_mode() -> io::Result<(HANDLE, CONSOLE_MODE)> {
+    unsafe {
+        let handle = GetStdHandle(STD_INPUT_HANDLE).map_err(io::Error::other)?;
+        let mut mode = CONSOLE_MODE::default();
+        GetConsoleMode(handle, &mut mode).map_err(io::Error::other)?;
+        Ok((handle, mode))
+    }
+}
@@ -0,0 +1,336 @@
+/// ============================================
+/// PROMPT THEMING - ESCAPES AND ASYNCHRONOUS SEGMENTS
+/// ============================================
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use crate::{Shell, osstr};
+
+/// Computes a piece of the prompt, such as the git branch, for the working
+/// directory it is given. The text is shown as is, in front of `$ `, so it
+/// should carry its own separator; `None` shows nothing.
+pub type Render = dyn Fn(&Path) -> Option<String> + Send + Sync;
+
+/// The variable listing built-in segments to show, separated by spaces.
+const SEGMENTS_VARIABLE: &str = "MYSHELL_PROMPT_SEGMENTS";
+
+/// One segment and what is known of its text.
+struct Segment {
+    /// Identifies the segment's results, which may arrive after it has
+    /// been removed.
+    id: u64,
+    render: Arc<Render>,
+    /// Whether it was added from `$MYSHELL_PROMPT_SEGMENTS`.
+    builtin: bool,
+    /// The last text computed and the directory it was computed for.
+    value: Option<(PathBuf, String)>,
+    /// Whether a worker thread is computing it now.
+    busy: bool,
+    /// Whether it should be computed again once the worker is done.
+    stale: bool,
+}
+
+/// The prompt's segments. Each is computed on a worker thread at every
+/// prompt, so a slow one never delays the prompt: until it is done, the
+/// text it last gave for the same directory is shown, and the prompt is
+/// drawn again if the new text differs.
+pub(crate) struct PromptSegments {
+    segments: Vec<Segment>,
+    next_id: u64,
+    sender: Sender<(u64, PathBuf, String)>,
+    results: Receiver<(u64, PathBuf, String)>,
+    /// The value of `$MYSHELL_PROMPT_SEGMENTS` the built-in segments were
+    /// added for.
+    builtin: String,
+    /// How many worker threads have not sent their result yet, those of
+    /// segments since removed included.
+    running: usize,
+}
+
+impl Default for PromptSegments {
+    fn default() -> Self {
+        let (sender, results) = mpsc::channel();
+        Self {
+            segments: Vec::new(),
+            next_id: 0,
+            sender,
+            results,
+            builtin: String::new(),
+            running: 0,
+        }
+    }
+}
+
+impl PromptSegments {
+    fn add(&mut self, render: Arc<Render>, builtin: bool) {
+        self.next_id += 1;
+        self.segments.push(Segment {
+            id: self.next_id,
+            render,
+            builtin,
+            value: None,
+            busy: false,
+            stale: true,
+        });
+    }
+
+    /// Starts computing every segment for `cwd`.
+    fn start(&mut self, cwd: &Path) {
+        for segment in &mut self.segments {
+            segment.stale = true;
+        }
+        self.poll(cwd);
+    }
+
+    /// Collects the segments computed so far and starts those due again.
+    /// Returns whether the text for `cwd` may have changed.
+    fn poll(&mut self, cwd: &Path) -> bool {
+        let mut changed = false;
+        while let Ok((id, dir, text)) = self.results.try_recv() {
+            changed |= dir == cwd && self.store(id, dir.clone(), text);
+        }
+
+        for segment in &mut self.segments {
+            if !segment.stale || segment.busy {
+                continue;
+            }
+            let id = segment.id;
+            let render = Arc::clone(&segment.render);
+            let sender = self.sender.clone();
+            let dir = cwd.to_path_buf();
+            let spawned = std::thread::Builder::new()
+                .name("prompt-segment".into())
+                .spawn(move || {
+                    // A result is always sent, as `wait` counts on it.
+                    let text = panic::catch_unwind(AssertUnwindSafe(|| render(&dir)))
+                        .ok()
+                        .flatten()
+                        .unwrap_or_default();
+                    let _ = sender.send((id, dir, text));
+                });
+            segment.stale = false;
+            segment.busy = spawned.is_ok();
+            self.running += usize::from(spawned.is_ok());
+        }
+        changed
+    }
+
+    /// Keeps the text a worker computed for segment `id`, and returns
+    /// whether it differs from what the segment had.
+    fn store(&mut self, id: u64, dir: PathBuf, text: String) -> bool {
+        self.running -= 1;
+        let Some(segment) = self.segments.iter_mut().find(|segment| segment.id == id) else {
+            return false;
+        };
+        segment.busy = false;
+        let changed = segment
+            .value
+            .as_ref()
+            .is_none_or(|(old_dir, old)| old_dir != &dir || *old != text);
+        segment.value = Some((dir, text));
+        changed
+    }
+
+    /// Waits for the worker threads still running, keeping what they
+    /// computed. A render function may take any lock, the one on the
+    /// environment `Command` takes included, so the shell must not fork
+    /// while one runs; see `Shell::run_stage`.
+    #[cfg(unix)]
+    pub(crate) fn wait(&mut self) {
+        while self.running > 0 {
+            let Ok((id, dir, text)) = self.results.recv() else {
+                return;
+            };
+            self.store(id, dir, text);
+        }
+    }
+
+    /// The segments' texts for `cwd`, as far as they are known.
+    fn text(&self, cwd: &Path) -> String {
+        self.segments
+            .iter()
+            .filter_map(|segment| segment.value.as_ref())
+            .filter(|(dir, _)| dir == cwd)
+            .map(|(_, text)| text.as_str())
+            .collect()
+    }
+}
+
+/// The user the shell runs as, for `\u`.
+fn user_name() -> String {
+    if let Some(user) = ["USER", "LOGNAME", "USERNAME"]
+        .iter()
+        .find_map(|name| std::env::var(name).ok().filter(|user| !user.is_empty()))
+    {
+        return user;
+    }
+    #[cfg(unix)]
+    // SAFETY: getpwuid returns null or a record valid until the next call.
+    unsafe {
+        let entry = libc::getpwuid(libc::geteuid());
+        if !entry.is_null() {
+            return std::ffi::CStr::from_ptr((*entry).pw_name)
+                .to_string_lossy()
+                .into_owned();
+        }
+    }
+    String::new()
+}
+
+/// The machine's host name, for `\H`.
+fn host_name() -> String {
+    #[cfg(unix)]
+    {
+        let mut name = [0u8; 256];
+        // SAFETY: the buffer is as long as the length given.
+        if unsafe { libc::gethostname(name.as_mut_ptr().cast(), name.len()) } == 0 {
+            let end = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+            return String::from_utf8_lossy(&name[..end]).into_owned();
+        }
+        String::new()
+    }
+    #[cfg(windows)]
+    std::env::var("COMPUTERNAME").unwrap_or_default()
+}
+
+/// Whether the shell runs as the superuser, for `\$`.
+fn is_root() -> bool {
+    #[cfg(unix)]
+    return unsafe { libc::geteuid() } == 0;
+    #[cfg(windows)]
+    false
+}
+
+/// The current git branch, as `(branch) `.
+fn git_branch(dir: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .args(["symbolic-ref", "--short", "-q", "HEAD"])
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    let branch = String::from_utf8(output.stdout).ok()?;
+    let branch = branch.trim();
+    (output.status.success() && !branch.is_empty()).then(|| format!("({}) ", branch))
+}
+
+impl Shell {
+    /// Adds a segment to the front of the primary prompt, after any added
+    /// before it. `render` runs on a worker thread with the working
+    /// directory each time the prompt is shown.
+    pub fn add_prompt_segment(
+        &mut self,
+        render: impl Fn(&Path) -> Option<String> + Send + Sync + 'static,
+    ) {
+        self.prompt_segments.add(Arc::new(render), false);
+    }
+
+    /// Starts computing the prompt segments and returns the primary prompt
+    /// with the segments known so far. `MYSHELL_PROMPT_SEGMENTS=git` adds
+    /// the built-in git branch segment.
+    pub(crate) fn themed_primary_prompt(&mut self, default: &str) -> String {
+        let builtin = self.get_var(SEGMENTS_VARIABLE).unwrap_or_default();
+        let segments = &mut self.prompt_segments;
+        if builtin != segments.builtin {
+            segments.segments.retain(|segment| !segment.builtin);
+            if builtin.split_whitespace().any(|name| name == "git") {
+                segments.add(Arc::new(git_branch), true);
+            }
+            segments.builtin = builtin;
+        }
+
+        let Ok(cwd) = self.logical_cwd() else {
+            return default.to_string();
+        };
+        self.prompt_segments.start(&cwd);
+        self.prompt = self.prompt_string("PS1", default);
+        format!("{}{}", self.prompt_segments.text(&cwd), self.prompt)
+    }
+
+    /// The primary prompt again if a segment has finished since it was
+    /// drawn and changed it.
+    #[cfg(feature = "line-editor")]
+    pub(crate) fn updated_prompt(&mut self) -> Option<String> {
+        let cwd = self.logical_cwd().ok()?;
+        self.prompt_segments
+            .poll(&cwd)
+            .then(|| format!("{}{}", self.prompt_segments.text(&cwd), self.prompt))
+    }
+
+    /// The prompt string in `variable`, or `default` when it is unset, with
+    /// its escapes and expansions done. `\u`, `\h`, `\H`, `\w`, `\W` and
+    /// `\$` give the user, the host name up to the first dot and in full,
+    /// the working directory with `~` for the home directory and its last
+    /// component, and `#` for the superuser or else `$`. `\n`, `\e`, `\a`
+    /// and `\\` give a newline, escape, bell and backslash, and `\[` and
+    /// `\]`, which mark terminal control sequences in bash, are dropped.
+    /// Then parameters, command substitutions and arithmetic are expanded
+    /// as in double quotes, leaving `$?` as it was.
+    pub(crate) fn prompt_string(&mut self, variable: &str, default: &str) -> String {
+        let template = self
+            .get_var(variable)
+            .unwrap_or_else(|| default.to_string());
+
+        let mut word = String::from("\"");
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            if c == '"' {
+                word.push_str("\\\"");
+                continue;
+            } else if c != '\\' {
+                word.push(c);
+                continue;
+            }
+            let text = match chars.next() {
+                Some('u') => user_name(),
+                Some('h') => host_name()
+                    .split('.')
+                    .next()
+                    .unwrap_or_default()
+                    .to_string(),
+                Some('H') => host_name(),
+                Some('w') => self.prompt_dir(false),
+                Some('W') => self.prompt_dir(true),
+                Some('$') if is_root() => "#".to_string(),
+                Some('$') => "$".to_string(),
+                Some('n') => "\n".to_string(),
+                Some('e') => "\x1b".to_string(),
+                Some('a') => "\x07".to_string(),
+                Some('\\') => "\\".to_string(),
+                Some('[' | ']') => String::new(),
+                Some(other) => format!("\\{}", other),
+                None => "\\".to_string(),
+            };
+            // What an escape gives is not expanded further.
+            for c in text.chars() {
+                if matches!(c, '$' | '`' | '"' | '\\') {
+                    word.push('\\');
+                }
+                word.push(c);
+            }
+        }
+        word.push('"');
+
+        let status = self.last_status;
+        let prompt = self.expand_string(&word);
+        self.last_status = status;
+        prompt
+    }
+
+    /// The working directory for `\w`, with `~` for the home directory,
+    /// or for `\W` only its last component.
+    fn prompt_dir(&self, last: bool) -> String {
+        let Ok(cwd) = self.logical_cwd() else {
+            return String::new();
+        };
+        let path = osstr::from_os(cwd.as_os_str());
+        if !last || self.home().as_deref() == Some(path.as_str()) {
+            return self.tilde_path(&path);
+        }
+        cwd.file_name().map(osstr::from_os).unwrap_or(path)
+    }
+}
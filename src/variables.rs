@@ -0,0 +1,368 @@
+/// ============================================
+/// SHELL VARIABLES
+/// ============================================
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::arith;
+use crate::osstr;
+use crate::{Frame, Shell};
+
+/// Namerefs are followed at most this many times, which also stops cycles.
+const MAX_NAMEREF_DEPTH: usize = 8;
+
+/// The value of a shell variable. Indexed arrays are sparse, as in bash.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Scalar(String),
+    Array(BTreeMap<usize, String>),
+    /// A nameref (`declare -n`): the name of the variable it stands for.
+    /// Seen directly, it behaves as a scalar holding that name.
+    Ref(String),
+}
+
+impl Value {
+    /// The value seen by `$name`: the scalar itself or element 0 of an array.
+    pub fn scalar(&self) -> Option<&str> {
+        self.element(0)
+    }
+
+    pub fn element(&self, index: usize) -> Option<&str> {
+        match self {
+            Value::Scalar(value) | Value::Ref(value) => (index == 0).then_some(value.as_str()),
+            Value::Array(items) => items.get(&index).map(String::as_str),
+        }
+    }
+
+    pub fn elements(&self) -> Vec<String> {
+        match self {
+            Value::Scalar(value) | Value::Ref(value) => vec![value.clone()],
+            Value::Array(items) => items.values().cloned().collect(),
+        }
+    }
+
+    /// The set elements with their indices, in index order.
+    pub fn entries(&self) -> Vec<(usize, String)> {
+        match self {
+            Value::Scalar(value) | Value::Ref(value) => vec![(0, value.clone())],
+            Value::Array(items) => items.iter().map(|(i, v)| (*i, v.clone())).collect(),
+        }
+    }
+
+    /// Resolves a possibly negative subscript; negative values count back
+    /// from one past the highest index.
+    pub fn resolve_index(&self, index: i64) -> Option<usize> {
+        if index >= 0 {
+            return Some(index as usize);
+        }
+        let end = match self {
+            Value::Scalar(_) | Value::Ref(_) => 1,
+            Value::Array(items) => items.keys().next_back().map_or(0, |last| last + 1),
+        };
+        usize::try_from(end as i64 + index).ok()
+    }
+
+    pub fn set_element(&mut self, index: usize, value: String) {
+        match self {
+            Value::Ref(_) => {
+                *self = Value::Scalar(String::new());
+                self.set_element(index, value);
+            }
+            Value::Scalar(current) if index == 0 => *current = value,
+            Value::Scalar(current) => {
+                let mut items = BTreeMap::from([(0, std::mem::take(current))]);
+                items.insert(index, value);
+                *self = Value::Array(items);
+            }
+            Value::Array(items) => {
+                items.insert(index, value);
+            }
+        }
+    }
+}
+
+/// A nonzero seed for `$RANDOM` that differs from run to run.
+pub(crate) fn random_seed() -> u32 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.subsec_nanos() ^ since.as_secs() as u32);
+    (nanos ^ std::process::id().rotate_left(16)) | 1
+}
+
+impl Shell {
+    /// The value of a variable computed each time it is read: `RANDOM`, a
+    /// number from 0 to 32767; `SECONDS` since the shell started or since
+    /// it was assigned, counting up from the value assigned; `LINENO`, the
+    /// line of the current command; and `EPOCHSECONDS`, the Unix time.
+    fn dynamic_var(&self, name: &str) -> Option<String> {
+        Some(match name {
+            "RANDOM" => {
+                // xorshift32; the high bits are the better ones.
+                let mut state = self.random.get();
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                self.random.set(state);
+                ((state >> 16) & 0x7fff).to_string()
+            }
+            "SECONDS" => {
+                let (since, start) = self.seconds_base;
+                (start + since.elapsed().as_secs() as i64).to_string()
+            }
+            "LINENO" => self.lineno.to_string(),
+            "EPOCHSECONDS" => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_secs())
+                .to_string(),
+            _ => return None,
+        })
+    }
+
+    /// Handles an assignment to a dynamic variable: `RANDOM=n` seeds the
+    /// generator and `SECONDS=n` restarts the count from n. Assigning to
+    /// `LINENO` or `EPOCHSECONDS` has no effect. Returns false for other
+    /// names.
+    fn assign_dynamic(&mut self, name: &str, value: &str) -> bool {
+        match name {
+            "RANDOM" => {
+                let seed = value.trim().parse::<i64>().unwrap_or(0) as u32;
+                self.random.set(seed.wrapping_mul(2_654_435_761) | 1);
+            }
+            "SECONDS" => {
+                let start = value.trim().parse().unwrap_or(0);
+                self.seconds_base = (Instant::now(), start);
+            }
+            "LINENO" | "EPOCHSECONDS" => {}
+            _ => return false,
+        }
+        true
+    }
+
+    /// Follows namerefs from `name` to the variable they finally refer to.
+    pub(crate) fn resolve_name<'a>(&'a self, name: &'a str) -> &'a str {
+        let mut name = name;
+        for _ in 0..MAX_NAMEREF_DEPTH {
+            match self.vars.get(name) {
+                Some(Value::Ref(target)) => name = target,
+                _ => break,
+            }
+        }
+        name
+    }
+
+    /// A variable of the environment the shell started with, unless it
+    /// has been unset since.
+    pub(crate) fn env_var(&self, name: &str) -> Option<String> {
+        if self.unset_env.contains(name) {
+            return None;
+        }
+        osstr::env_var(name)
+    }
+
+    /// Looks a variable up as a scalar, falling back to the environment.
+    pub(crate) fn get_var(&self, name: &str) -> Option<String> {
+        let name = self.resolve_name(name);
+        if let Some(value) = self.dynamic_var(name) {
+            return Some(value);
+        }
+        match self.vars.get(name) {
+            Some(value) => value.scalar().map(String::from),
+            None => self.env_var(name),
+        }
+    }
+
+    /// Looks a variable up with its full (possibly array) value.
+    pub(crate) fn get_value(&self, name: &str) -> Option<Value> {
+        let name = self.resolve_name(name);
+        if let Some(value) = self.dynamic_var(name) {
+            return Some(Value::Scalar(value));
+        }
+        match self.vars.get(name) {
+            Some(value) => Some(value.clone()),
+            None => self.env_var(name).map(Value::Scalar),
+        }
+    }
+
+    /// Assigns a scalar; assigning to an array name sets element 0.
+    pub(crate) fn set_var(&mut self, name: &str, value: String) {
+        self.set_element(name, 0, value);
+    }
+
+    pub(crate) fn set_element(&mut self, name: &str, index: usize, value: String) {
+        let name = self.resolve_name(name).to_string();
+        if index == 0 && self.assign_dynamic(&name, &value) {
+            return;
+        }
+        match self.vars.get_mut(&name) {
+            Some(current) => current.set_element(index, value),
+            None if index == 0 => {
+                self.vars.insert(name, Value::Scalar(value));
+            }
+            None => {
+                let mut current = Value::Scalar(self.env_var(&name).unwrap_or_default());
+                current.set_element(index, value);
+                self.vars.insert(name, current);
+            }
+        }
+    }
+
+    pub(crate) fn set_array(&mut self, name: &str, value: Value) {
+        let name = self.resolve_name(name).to_string();
+        self.vars.insert(name, value);
+    }
+
+    /// Sets the `PIPESTATUS` array to the exit status of each command of
+    /// the pipeline just run.
+    pub(crate) fn set_pipestatus(&mut self, statuses: &[i32]) {
+        let items = statuses
+            .iter()
+            .enumerate()
+            .map(|(index, status)| (index, status.to_string()))
+            .collect();
+        self.vars
+            .insert("PIPESTATUS".to_string(), Value::Array(items));
+    }
+
+    /// The file the code being run comes from, or `main` for the shell's
+    /// own input.
+    pub(crate) fn current_source(&self) -> &str {
+        self.call_stack.last().map_or("main", |frame| &frame.source)
+    }
+
+    /// Enters a function call or sourced file, called from the current
+    /// line, and updates `FUNCNAME`, `BASH_SOURCE` and `BASH_LINENO`.
+    pub(crate) fn push_frame(&mut self, name: &str, source: String) {
+        self.call_stack.push(Frame {
+            name: name.to_string(),
+            source,
+            line: self.lineno,
+        });
+        self.set_call_stack_vars();
+    }
+
+    pub(crate) fn pop_frame(&mut self) {
+        self.call_stack.pop();
+        self.set_call_stack_vars();
+    }
+
+    /// Sets the call stack arrays, innermost frame first: each frame's
+    /// name, its file and the line it was called from. They are unset
+    /// outside any function or sourced file.
+    fn set_call_stack_vars(&mut self) {
+        let names = ["FUNCNAME", "BASH_SOURCE", "BASH_LINENO"];
+        if self.call_stack.is_empty() {
+            for name in names {
+                self.vars.remove(name);
+            }
+            return;
+        }
+
+        let frames = || self.call_stack.iter().rev().enumerate();
+        let arrays = [
+            frames().map(|(i, frame)| (i, frame.name.clone())).collect(),
+            frames()
+                .map(|(i, frame)| (i, frame.source.clone()))
+                .collect(),
+            frames()
+                .map(|(i, frame)| (i, frame.line.to_string()))
+                .collect(),
+        ];
+        for (name, items) in names.into_iter().zip(arrays) {
+            self.vars.insert(name.to_string(), Value::Array(items));
+        }
+    }
+
+    /// Makes `name` a nameref to `target`, replacing any previous value.
+    pub(crate) fn set_ref(&mut self, name: &str, target: &str) {
+        self.vars
+            .insert(name.to_string(), Value::Ref(target.to_string()));
+    }
+
+    /// Assigns a scalar and marks it to be passed to child processes.
+    pub(crate) fn export_var(&mut self, name: &str, value: String) {
+        self.set_var(name, value);
+        let name = self.resolve_name(name).to_string();
+        self.exported.insert(name);
+    }
+
+    /// The exported variables that have a value, as `(name, value)` pairs
+    /// for a child's environment. Variables that came from the environment
+    /// are exported too, so assigning to one changes what children see.
+    pub(crate) fn exported_env(&self) -> Vec<(String, String)> {
+        self.vars
+            .iter()
+            .filter(|(name, _)| self.exported.contains(*name) || self.env_var(name).is_some())
+            .filter_map(|(name, value)| Some((name.clone(), value.scalar()?.to_string())))
+            .collect()
+    }
+
+    /// Removes a variable, including one from the environment, so that
+    /// neither the shell nor its children see it any more.
+    pub(crate) fn unset_var(&mut self, name: &str) {
+        let name = self.resolve_name(name).to_string();
+        self.vars.remove(&name);
+        self.exported.remove(&name);
+        if osstr::env_var(&name).is_some() {
+            self.unset_env.insert(name);
+        }
+    }
+
+    /// Starts a new scope for variables declared local to a function call.
+    pub(crate) fn push_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    /// Ends the innermost scope, restoring the variables it shadowed.
+    pub(crate) fn pop_scope(&mut self) {
+        let Some(scope) = self.scopes.pop() else {
+            return;
+        };
+        for (name, saved) in scope {
+            match saved {
+                Some(value) => self.vars.insert(name, value),
+                None => self.vars.remove(&name),
+            };
+        }
+    }
+
+    /// Makes `name` local to the innermost scope, starting out unset. Returns
+    /// false outside of any function call.
+    pub(crate) fn make_local(&mut self, name: &str) -> bool {
+        let Some(scope) = self.scopes.last_mut() else {
+            return false;
+        };
+        if !scope.contains_key(name) {
+            scope.insert(name.to_string(), self.vars.remove(name));
+        }
+        true
+    }
+}
+
+impl arith::Variables for Shell {
+    fn get(&mut self, name: &str, index: Option<i64>) -> Option<String> {
+        match index {
+            None => self.get_var(name),
+            Some(index) => {
+                let value = self.get_value(name)?;
+                let index = value.resolve_index(index)?;
+                value.element(index).map(String::from)
+            }
+        }
+    }
+
+    fn set(&mut self, name: &str, index: Option<i64>, value: i64) {
+        let index = match index {
+            None => 0,
+            Some(index) => {
+                let current = self
+                    .get_value(name)
+                    .unwrap_or(Value::Array(Default::default()));
+                match current.resolve_index(index) {
+                    Some(index) => index,
+                    None => return,
+                }
+            }
+        };
+        self.set_element(name, index, value.to_string());
+    }
+}
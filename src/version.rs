@@ -0,0 +1,47 @@
+/// ============================================
+/// BUILD INFORMATION
+/// ============================================
+use crate::json::Json;
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// The git commit built from, or `unknown` outside a git checkout.
+pub const COMMIT: &str = env!("MYSHELL_GIT_COMMIT");
+pub const TARGET: &str = env!("MYSHELL_TARGET");
+
+/// The cargo features this build was compiled with.
+pub fn features() -> Vec<&'static str> {
+    let mut features = Vec::new();
+    if cfg!(feature = "completion") {
+        features.push("completion");
+    }
+    if cfg!(feature = "line-editor") {
+        features.push("line-editor");
+    }
+    if cfg!(feature = "tracing") {
+        features.push("tracing");
+    }
+    features
+}
+
+/// The report `--version` and `version` print, one fact per line.
+pub fn describe() -> String {
+    format!(
+        "{} {}\ncommit: {}\ntarget: {}\nfeatures: {}",
+        env!("CARGO_PKG_NAME"),
+        VERSION,
+        COMMIT,
+        TARGET,
+        features().join(" ")
+    )
+}
+
+/// The same facts as a JSON object, for scripts.
+pub(crate) fn to_json() -> Json {
+    Json::Object(vec![
+        ("name", env!("CARGO_PKG_NAME").into()),
+        ("version", VERSION.into()),
+        ("commit", COMMIT.into()),
+        ("target", TARGET.into()),
+        ("features", features().into()),
+    ])
+}
@@ -0,0 +1,85 @@
+/// ============================================
+/// WINDOWS JOB OBJECTS
+/// ============================================
+use std::io;
+use std::os::windows::io::AsRawHandle;
+use std::process::{Child, Command, ExitStatus};
+
+use windows::Win32::Foundation::{CloseHandle, HANDLE, WAIT_OBJECT_0};
+use windows::Win32::System::JobObjects::{
+    AssignProcessToJobObject, CreateJobObjectW, TerminateJobObject,
+};
+use windows::Win32::System::Threading::WaitForSingleObject;
+use windows::core::PCWSTR;
+
+use crate::signals;
+
+/// How often a waiting shell checks whether Ctrl-C was pressed.
+const POLL_INTERVAL_MS: u32 = 50;
+
+/// Exit code given to processes killed by Ctrl-C, matching `$?` after a
+/// SIGINT on Unix.
+const INTERRUPTED_EXIT_CODE: u32 = 130;
+
+/// A Job Object grouping a command with every process it starts, the
+/// Windows counterpart of a Unix process group.
+pub struct Job {
+    handle: HANDLE,
+}
+
+impl Job {
+    pub fn new() -> io::Result<Self> {
+        let handle = unsafe { CreateJobObjectW(None, PCWSTR::null()) }.map_err(io::Error::other)?;
+        Ok(Job { handle })
+    }
+
+    /// Adds `child` to the job. Processes it starts afterwards join too.
+    pub fn assign(&self, child: &Child) -> io::Result<()> {
+        let process = HANDLE(child.as_raw_handle());
+        unsafe { AssignProcessToJobObject(self.handle, process) }.map_err(io::Error::other)
+    }
+
+    /// Kills every process in the job.
+    pub fn terminate(&self, exit_code: u32) {
+        unsafe {
+            let _ = TerminateJobObject(self.handle, exit_code);
+        }
+    }
+}
+
+impl Drop for Job {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = CloseHandle(self.handle);
+        }
+    }
+}
+
+/// Runs `command` in the foreground inside its own job and waits for it.
+/// If Ctrl-C or Ctrl-Break is pressed meanwhile the whole job is terminated, so
+/// grandchildren are not left running behind the shell. When a job cannot
+/// be created the child is simply waited for.
+pub fn run(command: &mut Command) -> io::Result<ExitStatus> {
+    // A Ctrl-Break pressed at the prompt has nothing to terminate.
+    signals::take_break();
+
+    let mut child = {
+        trace_span!("spawn");
+        command.spawn()?
+    };
+    trace_span!("wait", pid = child.id());
+    let job = Job::new().and_then(|job| job.assign(&child).map(|()| job));
+    let Ok(job) = job else {
+        return child.wait();
+    };
+
+    let process = HANDLE(child.as_raw_handle());
+    loop {
+        if unsafe { WaitForSingleObject(process, POLL_INTERVAL_MS) } == WAIT_OBJECT_0 {
+            return child.wait();
+        }
+        if signals::interrupted() || signals::take_break() {
+            job.terminate(INTERRUPTED_EXIT_CODE);
+        }
+    }
+}
@@ -0,0 +1,115 @@
+/// ============================================
+/// WINDOWS COMMAND LINES
+/// ============================================
+use std::env;
+use std::io;
+use std::os::windows::process::CommandExt;
+use std::path::Path;
+use std::process::Command;
+
+use crate::osstr;
+
+/// Characters that make cmd.exe split or reinterpret an unquoted argument.
+const CMD_SPECIAL: &[char] = &[
+    ' ', '\t', '"', '&', '|', '<', '>', '(', ')', '^', '%', '!', ',', ';', '=',
+];
+
+/// Builds the command that runs `program` with `args`.
+///
+/// Windows passes a child one command line that the child splits itself.
+/// Ordinary programs split it by the MSVC C runtime rules, which the
+/// standard library already quotes for. cmd.exe and the batch files it runs
+/// parse the line differently, so for those the line is quoted for cmd and
+/// passed through untouched with `raw_arg`. PowerShell scripts are not
+/// executable themselves and are run with `powershell -File`.
+pub fn command(program: &Path, args: &[String]) -> io::Result<Command> {
+    if is_cmd(program) {
+        let mut command = Command::new(program);
+        for arg in args {
+            command.raw_arg(osstr::to_os(&quote_for_cmd(arg)?));
+        }
+        return Ok(command);
+    }
+
+    if has_extension(program, &["ps1"]) {
+        let mut command = Command::new("powershell.exe");
+        command
+            .args(["-NoLogo", "-NoProfile", "-File"])
+            .arg(program)
+            .args(args.iter().map(|arg| osstr::to_os(arg)));
+        return Ok(command);
+    }
+
+    if is_batch_file(program) {
+        let mut line = quote_for_cmd(&osstr::from_os(program.as_os_str()))?;
+        for arg in args {
+            line.push(' ');
+            line.push_str(&quote_for_cmd(arg)?);
+        }
+
+        let comspec = env::var_os("ComSpec").unwrap_or_else(|| "cmd.exe".into());
+        let mut command = Command::new(comspec);
+        command.raw_arg(osstr::to_os(&format!("/d /e:ON /v:OFF /s /c \"{}\"", line)));
+        return Ok(command);
+    }
+
+    let mut command = Command::new(program);
+    command.args(args.iter().map(|arg| osstr::to_os(arg)));
+    Ok(command)
+}
+
+fn has_extension(path: &Path, wanted: &[&str]) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| wanted.iter().any(|w| ext.eq_ignore_ascii_case(w)))
+}
+
+fn is_batch_file(path: &Path) -> bool {
+    has_extension(path, &["bat", "cmd"])
+}
+
+fn is_cmd(path: &Path) -> bool {
+    has_extension(path, &["exe"])
+        && path
+            .file_stem()
+            .is_some_and(|stem| stem.eq_ignore_ascii_case("cmd"))
+}
+
+/// Quotes one argument so that cmd.exe passes it on, and a batch file's
+/// `%~1` sees it, unchanged. Inside the quotes `"` is doubled and `%`
+/// becomes `%%cd:~,%`, which cmd expands back to a lone `%` instead of
+/// treating it as the start of a variable. Line breaks cannot be passed.
+fn quote_for_cmd(arg: &str) -> io::Result<String> {
+    if arg.contains(['\r', '\n', '\0']) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "argument contains a line break, which cmd.exe cannot pass",
+        ));
+    }
+    if !arg.is_empty() && !arg.contains(CMD_SPECIAL) {
+        return Ok(arg.to_string());
+    }
+
+    let mut quoted = String::from('"');
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                // Backslashes before a quote are doubled, as the C runtime
+                // expects of the program the batch file goes on to run.
+                quoted.extend(std::iter::repeat_n('\\', backslashes));
+                backslashes = 0;
+            }
+            _ => backslashes = 0,
+        }
+        match c {
+            '"' => quoted.push_str("\"\""),
+            '%' => quoted.push_str("%%cd:~,%"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.extend(std::iter::repeat_n('\\', backslashes));
+    quoted.push('"');
+    Ok(quoted)
+}
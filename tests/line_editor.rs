@@ -0,0 +1,409 @@
+#![cfg(all(unix, feature = "line-editor"))]
+
+// Each test binary uses only some of the helpers.
+#[allow(dead_code)]
+mod support;
+
+use std::time::Duration;
+
+use support::{
+    ALT_Q, ALT_R, BACKSPACE, CTRL_A, CTRL_C, CTRL_E, CTRL_N, CTRL_Q, CTRL_U, CTRL_W, CTRL_Z,
+    DELETE, DOWN, FOCUS_IN, FOCUS_OUT, LEFT, PtyShell, UP,
+};
+
+#[test]
+fn runs_a_command_and_prompts_again() {
+    let mut shell = PtyShell::spawn(&["--norc"]);
+    shell.send_line("echo hello");
+    shell.expect("hello\n$ ");
+    assert_eq!(shell.finish(), 0);
+}
+
+#[test]
+fn expands_ps1_and_ps2() {
+    let mut shell = PtyShell::spawn(&["--norc"]);
+    shell.send_line(r"PS1='[\w] $((6 * 7)) '; PS2='more> '");
+    shell.expect("[~] 42 ");
+    shell.send_line("mkdir dir; cd dir");
+    shell.expect("[~/dir] 42 ");
+    shell.send_line("echo 'two");
+    shell.expect("more> ");
+    shell.send_line("lines'");
+    shell.expect("two\nlines\n[~/dir] 42 ");
+}
+
+#[test]
+fn joins_a_line_continuation_with_the_next_line() {
+    let mut shell = PtyShell::spawn(&["--norc"]);
+    shell.send_line("echo one \\");
+    shell.expect_line("> ");
+    shell.send_line("two");
+    shell.expect("one two\n$ ");
+}
+
+#[test]
+fn reads_a_here_document_after_the_command_line() {
+    let mut shell = PtyShell::spawn(&["--norc"]);
+    shell.send_line("name=doc; cat <<EOF");
+    shell.expect("> ");
+    shell.send_line("from the $name");
+    shell.expect("> ");
+    shell.send_line("EOF");
+    shell.expect("from the doc\n$ ");
+}
+
+#[test]
+fn edits_in_the_middle_of_the_line() {
+    let mut shell = PtyShell::spawn(&["--norc"]);
+    shell.send("echo wrld");
+    shell.send(&LEFT.repeat(3));
+    shell.send("o");
+    shell.expect_line("$ echo world");
+    assert_eq!(shell.cursor_column(), "$ echo wo".len());
+
+    shell.send(CTRL_A);
+    shell.send(DELETE);
+    shell.send("E");
+    shell.send(CTRL_E);
+    shell.send(BACKSPACE);
+    shell.expect_line("$ Echo worl");
+    assert_eq!(shell.cursor_column(), "$ Echo worl".len());
+}
+
+#[test]
+fn kills_words_and_the_start_of_the_line() {
+    let mut shell = PtyShell::spawn(&["--norc"]);
+    shell.send("echo one two  ");
+    shell.send(CTRL_W);
+    shell.expect_line("$ echo one ");
+    shell.send("three");
+    shell.send(&LEFT.repeat(5));
+    shell.send(CTRL_U);
+    shell.expect_line("$ three");
+    assert_eq!(shell.cursor_column(), "$ ".len());
+    shell.send("echo ");
+    shell.send_line("");
+    shell.expect("three\n$ ");
+}
+
+#[test]
+fn recalls_and_saves_history() {
+    let mut shell = PtyShell::spawn(&["--norc"]);
+    shell.send_line("echo one");
+    shell.expect("one\n$ ");
+    shell.send_line("echo two");
+    shell.expect("two\n$ ");
+    shell.send_line("echo two");
+    shell.expect("two\n$ ");
+
+    shell.send("ec");
+    shell.send(UP);
+    shell.expect_line("$ echo two");
+    shell.send(UP);
+    shell.expect_line("$ echo one");
+    shell.send(UP);
+    shell.expect_line("$ echo one");
+    shell.send(DOWN);
+    shell.send(DOWN);
+    shell.expect_line("$ ec");
+    shell.send(CTRL_U);
+    shell.send_line("history");
+    shell.expect("    1  echo one\n    2  echo two\n    3  history\n$ ");
+
+    let history = shell.home().join(".shell_history");
+    shell.send_line("exit");
+    assert_eq!(shell.wait(), 0);
+    let saved = std::fs::read_to_string(history).unwrap();
+    assert_eq!(saved, "echo one\necho two\nhistory\nexit\n");
+}
+
+#[test]
+fn picks_a_line_from_the_history() {
+    let mut shell = PtyShell::spawn(&["--norc"]);
+    for word in ["alpha", "beta", "gamma", "bravo"] {
+        shell.send_line(&format!("echo {}", word));
+        shell.expect(&format!("{}\n$ ", word));
+    }
+    shell.send("x ");
+    shell.send(ALT_R);
+    shell.expect("history> ");
+    shell.send("ba");
+    shell.expect("> echo bravo");
+    shell.send(CTRL_N);
+    shell.expect("> echo beta");
+    shell.send("\r");
+    shell.expect_line("$ x echo beta");
+
+    shell.send(ALT_R);
+    shell.expect("history> ");
+    shell.send(CTRL_C);
+    shell.expect_line("$ x echo beta");
+}
+
+#[test]
+fn reads_older_history_when_it_is_needed() {
+    let dir = std::env::temp_dir().join(format!("myshell-history-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let history: String = (1..=500).map(|n| format!("echo {}\n", n)).collect();
+    std::fs::write(dir.join("history"), history).unwrap();
+    let rc = dir.join("rc");
+    std::fs::write(&rc, format!("HISTFILE={}\n", dir.join("history").display())).unwrap();
+
+    let mut shell = PtyShell::spawn(&["--rcfile", rc.to_str().unwrap()]);
+    shell.send_line("history 2");
+    shell.expect("  500  echo 500\n  501  history 2\n$ ");
+    shell.send(&UP.repeat(202));
+    shell.expect_line("$ echo 300");
+    shell.send(CTRL_U);
+    shell.send_line("history | head -1");
+    shell.expect("    1  echo 1\n$ ");
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn push_line_restores_the_line_after_one_command() {
+    let mut shell = PtyShell::spawn(&["--norc"]);
+    shell.send("echo later");
+    shell.send(ALT_Q);
+    shell.expect_line("$ ");
+    shell.send_line("echo now");
+    shell.expect("now\n$ echo later");
+    assert_eq!(shell.cursor_column(), "$ echo later".len());
+    shell.send_line("");
+    shell.expect("later\n$ ");
+}
+
+#[test]
+fn ctrl_q_pushes_the_line_despite_flow_control() {
+    let mut shell = PtyShell::spawn(&["--norc"]);
+    shell.send_line("stty ixon");
+    shell.expect("$ ");
+    shell.send("echo later");
+    shell.send(CTRL_Q);
+    shell.expect_line("$ ");
+    shell.send_line("echo now");
+    shell.expect("now\n$ echo later");
+    shell.send_line("");
+    shell.expect("later\n$ ");
+}
+
+#[test]
+fn follows_stty_erase_and_echo() {
+    let mut shell = PtyShell::spawn(&["--norc"]);
+    shell.send_line("stty erase ^X");
+    shell.expect("$ ");
+    shell.send("echo abcd\x18\x18");
+    shell.send_line("x");
+    shell.expect("abx\n$ ");
+    shell.send_line("stty -echo");
+    shell.expect("$ ");
+    shell.send_line("echo hid''den");
+    shell.expect("hidden\n");
+    assert!(!shell.screen().contains("hid''den"));
+}
+
+#[test]
+#[cfg(feature = "completion")]
+fn completes_builtin_names() {
+    let mut shell = PtyShell::spawn(&["--norc"]);
+    shell.send("ech");
+    shell.send(support::TAB);
+    shell.expect("$ echo ");
+    shell.send_line("done");
+    shell.expect("done\n$ ");
+}
+
+#[test]
+#[cfg(feature = "completion")]
+fn loads_completion_files_on_first_tab() {
+    let mut shell = PtyShell::spawn(&["--norc"]);
+    let completions = shell.home().join(".myshell_completions");
+    std::fs::create_dir(&completions).unwrap();
+    std::fs::write(completions.join("echo"), "complete -W 'start stop' echo\n").unwrap();
+
+    shell.send("echo sto");
+    shell.send(support::TAB);
+    shell.expect("$ echo stop ");
+    shell.send_line("");
+    shell.expect("stop\n$ ");
+    shell.send_line("complete -p");
+    shell.expect("complete -W 'start stop' 'echo'\n$ ");
+}
+
+#[test]
+fn ctrl_c_discards_the_line() {
+    let mut shell = PtyShell::spawn(&["--norc"]);
+    shell.send("echo never");
+    shell.send(CTRL_C);
+    shell.expect("^C\n$ ");
+    shell.send_line("echo after");
+    shell.expect("after\n$ ");
+    assert!(!shell.screen().contains("\nnever\n"));
+}
+
+#[test]
+fn stops_and_resumes_a_background_job() {
+    let mut shell = PtyShell::spawn(&["--norc"]);
+    shell.send_line("sleep 30 &");
+    shell.expect("[1] ");
+    shell.send_line("fg %sleep");
+    shell.expect("sleep 30\n");
+    // Give `fg` time to hand the terminal to the job.
+    shell.settle(Duration::from_millis(200));
+    shell.send(CTRL_Z);
+    shell.expect("[1]+  Stopped                 sleep 30\n$ ");
+    shell.send_line("bg");
+    shell.expect("[1]+ sleep 30 &\n$ ");
+    shell.send_line("kill %1");
+    shell.expect("[1]+  Exit 143                sleep 30");
+}
+
+#[test]
+fn ctrl_c_interrupts_the_running_command() {
+    let mut shell = PtyShell::spawn(&["--norc"]);
+    shell.send_line("sleep 30; echo never");
+    shell.settle(Duration::from_millis(200));
+    shell.send(CTRL_C);
+    shell.expect("^C\n$ ");
+    shell.send_line("echo $?");
+    shell.expect("130\n$ ");
+    assert!(!shell.screen().contains("\nnever\n"));
+}
+
+#[test]
+fn exits_with_the_given_status() {
+    let mut shell = PtyShell::spawn(&["--norc"]);
+    shell.send_line("exit 3");
+    assert_eq!(shell.wait(), 3);
+}
+
+#[test]
+fn exit_without_a_status_uses_the_last_one() {
+    let mut shell = PtyShell::spawn(&["--norc"]);
+    shell.send_line("false; exit");
+    assert_eq!(shell.wait(), 1);
+}
+
+#[test]
+fn duplicates_one_stream_onto_another() {
+    let mut shell = PtyShell::spawn(&["--norc"]);
+    shell.send_line("ls /nonexistent > /dev/null 2>&1; echo status $?");
+    shell.expect("status 2\n$ ");
+    shell.send_line("ls /nonexistent 2>&1 > /dev/null | wc -l");
+    shell.expect("\n1\n$ ");
+    shell.send_line("echo hi >&5");
+    shell.expect("5: bad file descriptor\n$ ");
+}
+
+#[test]
+fn pipeline_stages_run_apart_from_the_shell() {
+    let mut shell = PtyShell::spawn(&["--norc"]);
+    shell.send_line("while true; do echo y; done | head -1");
+    shell.expect("\ny\n$ ");
+    shell.send_line("cd / | cat; echo | x=2; echo \"[$x]\" $(pwd)");
+    shell.expect("\n[] /");
+    assert!(!shell.screen().contains("\n[] /\n"));
+    shell.send_line("exit 3 | cat; echo still here");
+    shell.expect("\nstill here\n$ ");
+}
+
+#[test]
+fn forked_stages_do_not_hold_here_documents_open() {
+    let mut shell = PtyShell::spawn(&["--norc"]);
+    // More than a pipe holds, so its writer is still at work when the
+    // second stage is forked.
+    let body = "line\n".repeat(30000);
+    let script = format!("cat <<EOF | {{ sleep 0.2; wc -l; }}\n{body}EOF\necho done\n");
+    std::fs::write(shell.home().join("here.sh"), script).expect("write script");
+    shell.send_line("source here.sh");
+    shell.expect("\n30000\ndone\n$ ");
+}
+
+#[test]
+fn ctrl_z_stops_the_foreground_command() {
+    let mut shell = PtyShell::spawn(&["--norc"]);
+    shell.send_line("sleep 30");
+    shell.settle(Duration::from_millis(200));
+    shell.send(CTRL_Z);
+    shell.expect("[1]+  Stopped                 sleep 30\n$ ");
+    shell.send_line("echo $?");
+    shell.expect("148\n$ ");
+    shell.send_line("sleep 30 | cat");
+    shell.settle(Duration::from_millis(200));
+    shell.send(CTRL_Z);
+    shell.expect("[2]+  Stopped                 sleep 30 | cat\n$ ");
+    shell.send_line("jobs");
+    shell.expect(
+        "[1]-  Stopped                 sleep 30\n[2]+  Stopped                 sleep 30 | cat\n$ ",
+    );
+    shell.send_line("kill %1");
+    shell.expect("[1]-  Exit 143                sleep 30");
+}
+
+#[test]
+fn watches_jobs_and_acts_on_them() {
+    let mut shell = PtyShell::spawn(&["--norc"]);
+    shell.send_line("sleep 30 &");
+    shell.expect("[1] ");
+    shell.send_line("sleep 40 &");
+    shell.expect("[2] ");
+    shell.send_line("jobs --watch");
+    shell.expect("Jobs  (f: foreground, k: kill, q: quit)");
+    shell.expect("> [1]-  Running");
+    shell.send(DOWN);
+    shell.expect("> [2]+  Running");
+    shell.send("k");
+    shell.expect("> [2]+  Exit 143");
+    shell.send(UP);
+    shell.expect("> [1]");
+    shell.send("f");
+    shell.expect("sleep 30\n");
+    shell.settle(Duration::from_millis(200));
+    shell.send(CTRL_Z);
+    shell.expect("[1]+  Stopped                 sleep 30\n$ ");
+    shell.send_line("jobs");
+    shell.expect("jobs\n[1]+  Stopped                 sleep 30\n$ ");
+}
+
+#[test]
+fn notifies_of_slow_commands_only_while_unfocused() {
+    let mut shell = PtyShell::spawn(&["--norc"]);
+    shell.send_line("set -o notifyafter=1");
+    shell.expect("$ set -o notifyafter=1\n$ ");
+    assert_eq!(shell.count("\x1b[?1004h"), 1);
+    shell.send_line("sleep 1");
+    shell.expect("sleep 1\n$ ");
+    shell.settle(Duration::from_millis(300));
+    assert_eq!(shell.count("\x07"), 0);
+    shell.send(FOCUS_OUT);
+    shell.send_line("sleep 1");
+    shell.settle(Duration::from_millis(1500));
+    assert_eq!(shell.count("\x07"), 1);
+    shell.send(FOCUS_IN);
+    shell.send_line("set +o notifyafter");
+    shell.expect("set +o notifyafter\n$ ");
+    assert_eq!(shell.count("\x1b[?1004l"), 1);
+}
+
+#[test]
+fn double_star_matches_files_in_every_directory() {
+    let mut shell = PtyShell::spawn(&["--norc"]);
+    for dir in ["src/a/b", "src/.hidden", "docs"] {
+        std::fs::create_dir_all(shell.home().join(dir)).expect("create test dir");
+    }
+    for file in [
+        "top.rs",
+        "src/a/one.rs",
+        "src/a/b/two.rs",
+        "src/.hidden/no.rs",
+        "docs/x.md",
+    ] {
+        std::fs::write(shell.home().join(file), "").expect("create test file");
+    }
+    shell.send_line("echo **/*.rs");
+    shell.expect("\nsrc/a/b/two.rs src/a/one.rs top.rs\n$ ");
+    shell.send_line("echo src/**/");
+    shell.expect("\nsrc/ src/a/ src/a/b/\n$ ");
+    shell.send_line("set -o posix; echo **/*.rs");
+    shell.expect("\n**/*.rs\n$ ");
+}
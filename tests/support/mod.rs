@@ -0,0 +1,318 @@
+/// ============================================
+/// PTY TEST HARNESS
+/// ============================================
+use std::fs::File;
+use std::io::{Read, Write};
+use std::os::fd::{FromRawFd, OwnedFd};
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const UP: &str = "\x1b[A";
+pub const DOWN: &str = "\x1b[B";
+pub const RIGHT: &str = "\x1b[C";
+pub const LEFT: &str = "\x1b[D";
+pub const HOME: &str = "\x1b[H";
+pub const END: &str = "\x1b[F";
+pub const DELETE: &str = "\x1b[3~";
+pub const BACKSPACE: &str = "\x7f";
+pub const TAB: &str = "\t";
+pub const CTRL_A: &str = "\x01";
+pub const CTRL_C: &str = "\x03";
+pub const CTRL_D: &str = "\x04";
+pub const CTRL_E: &str = "\x05";
+pub const CTRL_N: &str = "\x0e";
+pub const CTRL_Q: &str = "\x11";
+pub const CTRL_U: &str = "\x15";
+pub const CTRL_W: &str = "\x17";
+pub const CTRL_Z: &str = "\x1a";
+pub const ALT_Q: &str = "\x1bq";
+pub const ALT_R: &str = "\x1br";
+pub const FOCUS_IN: &str = "\x1b[I";
+pub const FOCUS_OUT: &str = "\x1b[O";
+
+/// How long `expect` waits for output before failing the test.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The shell running on a pseudo-terminal, as it would under a terminal
+/// emulator: in raw mode, with its own session and controlling terminal.
+pub struct PtyShell {
+    master: File,
+    child: Child,
+    output: Receiver<Vec<u8>>,
+    /// Everything the shell has written so far.
+    raw: Vec<u8>,
+    home: PathBuf,
+}
+
+impl PtyShell {
+    /// Starts the shell with `args` and an empty home directory, and waits
+    /// for the first prompt.
+    pub fn spawn(args: &[&str]) -> Self {
+        let home = std::env::temp_dir().join(format!(
+            "myshell-test-{}-{:?}",
+            std::process::id(),
+            thread::current().id()
+        ));
+        let _ = std::fs::remove_dir_all(&home);
+        std::fs::create_dir_all(&home).expect("create test home");
+
+        let (master, slave) = open_pty();
+        let mut command = Command::new(env!("CARGO_BIN_EXE_codecrafters-shell"));
+        command
+            .args(args)
+            .env("HOME", &home)
+            .env("TERM", "xterm")
+            .env_remove("ENV")
+            .current_dir(&home)
+            .stdin(Stdio::from(slave.try_clone().expect("dup pty")))
+            .stdout(Stdio::from(slave.try_clone().expect("dup pty")))
+            .stderr(Stdio::from(slave));
+        // SAFETY: only async-signal-safe calls between fork and exec.
+        unsafe {
+            command.pre_exec(|| {
+                if libc::setsid() < 0 || libc::ioctl(0, libc::TIOCSCTTY as _, 0) < 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        let child = command.spawn().expect("spawn shell");
+
+        let mut reader = master.try_clone().expect("dup pty master");
+        let (sender, output) = mpsc::channel();
+        thread::spawn(move || {
+            let mut buf = [0u8; 4096];
+            while let Ok(n) = reader.read(&mut buf) {
+                if n == 0 || sender.send(buf[..n].to_vec()).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut shell = Self {
+            master,
+            child,
+            output,
+            raw: Vec::new(),
+            home,
+        };
+        shell.expect("$ ");
+        shell
+    }
+
+    /// The shell's home and starting directory.
+    pub fn home(&self) -> &PathBuf {
+        &self.home
+    }
+
+    /// Types `keys`, which may include escape sequences such as `LEFT`.
+    pub fn send(&mut self, keys: &str) {
+        self.master
+            .write_all(keys.as_bytes())
+            .expect("write to pty");
+        self.master.flush().expect("flush pty");
+    }
+
+    /// Types `line` and presses Enter.
+    pub fn send_line(&mut self, line: &str) {
+        self.send(line);
+        self.send("\r");
+    }
+
+    /// Waits until the rendered screen contains `text`, failing the test
+    /// with the screen contents if it does not appear in time.
+    pub fn expect(&mut self, text: &str) {
+        self.wait_for(text, |shell| shell.screen().contains(text));
+    }
+
+    /// Waits until the line the cursor is on reads exactly `text`.
+    pub fn expect_line(&mut self, text: &str) {
+        self.wait_for(text, |shell| shell.current_line() == text);
+    }
+
+    fn wait_for(&mut self, what: &str, done: impl Fn(&Self) -> bool) {
+        let deadline = Instant::now() + TIMEOUT;
+        loop {
+            if done(self) {
+                return;
+            }
+            let left = deadline.saturating_duration_since(Instant::now());
+            match self.output.recv_timeout(left) {
+                Ok(bytes) => self.raw.extend(bytes),
+                Err(_) => panic!("timed out waiting for {what:?}; screen:\n{}", self.screen()),
+            }
+        }
+    }
+
+    /// Reads whatever the shell writes within `wait`.
+    pub fn settle(&mut self, wait: Duration) {
+        let deadline = Instant::now() + wait;
+        while let Ok(bytes) = self
+            .output
+            .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+        {
+            self.raw.extend(bytes);
+        }
+    }
+
+    /// The output as a terminal would show it, one string per line.
+    pub fn screen(&self) -> String {
+        Screen::render(&String::from_utf8_lossy(&self.raw)).join("\n")
+    }
+
+    /// How many times the shell has written `text`, control sequences
+    /// included.
+    pub fn count(&self, text: &str) -> usize {
+        String::from_utf8_lossy(&self.raw).matches(text).count()
+    }
+
+    /// The line the cursor is on, as shown.
+    pub fn current_line(&self) -> String {
+        let lines = Screen::render(&String::from_utf8_lossy(&self.raw));
+        lines.last().cloned().unwrap_or_default()
+    }
+
+    /// The column of the cursor on the current line.
+    pub fn cursor_column(&self) -> usize {
+        let mut screen = Screen::default();
+        screen.feed(&String::from_utf8_lossy(&self.raw));
+        screen.col
+    }
+
+    /// Sends end-of-input and returns the shell's exit status.
+    pub fn finish(mut self) -> i32 {
+        self.send(CTRL_D);
+        self.wait()
+    }
+
+    /// Waits for the shell to exit and returns its exit status.
+    pub fn wait(&mut self) -> i32 {
+        let deadline = Instant::now() + TIMEOUT;
+        loop {
+            if let Some(status) = self.child.try_wait().expect("wait for shell") {
+                return status.code().unwrap_or(-1);
+            }
+            if Instant::now() > deadline {
+                panic!("shell did not exit; screen:\n{}", self.screen());
+            }
+            self.settle(Duration::from_millis(20));
+        }
+    }
+}
+
+impl Drop for PtyShell {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = std::fs::remove_dir_all(&self.home);
+    }
+}
+
+fn open_pty() -> (File, OwnedFd) {
+    let (mut master, mut slave) = (0, 0);
+    // SAFETY: openpty fills in two new descriptors, which we take ownership
+    // of; the name, termios and window size arguments may be null.
+    unsafe {
+        let result = libc::openpty(
+            &mut master,
+            &mut slave,
+            std::ptr::null_mut(),
+            std::ptr::null(),
+            std::ptr::null(),
+        );
+        assert_eq!(result, 0, "openpty: {}", std::io::Error::last_os_error());
+        (File::from_raw_fd(master), OwnedFd::from_raw_fd(slave))
+    }
+}
+
+/// Just enough of a terminal to replay the shell's output: printable text,
+/// carriage return, newline, cursor movement, erasing and the alternate
+/// screen.
+#[derive(Default)]
+struct Screen {
+    lines: Vec<Vec<char>>,
+    row: usize,
+    col: usize,
+    /// The main screen while the alternate screen is shown.
+    saved: Option<(Vec<Vec<char>>, usize, usize)>,
+}
+
+impl Screen {
+    fn render(output: &str) -> Vec<String> {
+        let mut screen = Screen::default();
+        screen.feed(output);
+        screen
+            .lines
+            .iter()
+            .map(|line| line.iter().collect())
+            .collect()
+    }
+
+    fn feed(&mut self, output: &str) {
+        self.lines
+            .resize(self.lines.len().max(self.row + 1), Vec::new());
+        let mut chars = output.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\r' => self.col = 0,
+                '\n' => {
+                    self.row += 1;
+                    self.col = 0;
+                    self.lines
+                        .resize(self.lines.len().max(self.row + 1), Vec::new());
+                }
+                '\x07' => {}
+                '\x1b' if chars.peek() == Some(&'[') => {
+                    chars.next();
+                    let mut param = String::new();
+                    let mut command = None;
+                    for c in chars.by_ref() {
+                        if c.is_ascii_digit() || c == ';' || c == '?' {
+                            param.push(c);
+                        } else {
+                            command = Some(c);
+                            break;
+                        }
+                    }
+                    let n = param.parse::<usize>().unwrap_or(1);
+                    match (command, param.as_str()) {
+                        (Some('h'), "?1049") => {
+                            let main = std::mem::take(&mut self.lines);
+                            self.saved = Some((main, self.row, self.col));
+                        }
+                        (Some('l'), "?1049") => {
+                            if let Some((lines, row, col)) = self.saved.take() {
+                                (self.lines, self.row, self.col) = (lines, row, col);
+                            }
+                        }
+                        (Some('C'), _) => self.col += n,
+                        (Some('D'), _) => self.col = self.col.saturating_sub(n),
+                        (Some('K'), _) => self.lines[self.row].truncate(self.col),
+                        (Some('J'), "2") => self.lines.clear(),
+                        (Some('H'), _) => {
+                            let mut position = param.split(';').map(|n| n.parse().unwrap_or(1));
+                            self.row = position.next().unwrap_or(1).max(1) - 1;
+                            self.col = position.next().unwrap_or(1).max(1) - 1;
+                        }
+                        _ => {}
+                    }
+                    self.lines
+                        .resize(self.lines.len().max(self.row + 1), Vec::new());
+                }
+                c => {
+                    let line = &mut self.lines[self.row];
+                    if line.len() <= self.col {
+                        line.resize(self.col + 1, ' ');
+                    }
+                    line[self.col] = c;
+                    self.col += 1;
+                }
+            }
+        }
+    }
+}